@@ -3,10 +3,12 @@
 
 use crate::{
 	appservice::{self, AppserviceCommand},
+	audit::{self, AuditCommand},
 	context::Context,
 	debug::{self, DebugCommand},
 	federation::{self, FederationCommand},
 	media::{self, MediaCommand},
+	notices::{self, NoticesCommand},
 	query::{self, QueryCommand},
 	room::{self, RoomCommand},
 	server::{self, ServerCommand},
@@ -41,6 +43,10 @@ pub(super) enum AdminCommand {
 	/// - Commands for managing media
 	Media(MediaCommand),
 
+	#[command(subcommand)]
+	/// - Commands for sending server notices to users
+	Notices(NoticesCommand),
+
 	#[command(subcommand)]
 	/// - Commands for debugging things
 	Debug(DebugCommand),
@@ -52,6 +58,10 @@ pub(super) enum AdminCommand {
 	#[command(subcommand)]
 	/// - Commands for managing registration tokens
 	Token(TokenCommand),
+
+	#[command(subcommand)]
+	/// - Commands for querying the log of administrative actions
+	Audit(AuditCommand),
 }
 
 #[tracing::instrument(skip_all, name = "command")]
@@ -61,6 +71,7 @@ pub(super) async fn process(command: AdminCommand, context: &Context<'_>) -> Res
 	match command {
 		| Appservices(command) => appservice::process(command, context).await,
 		| Media(command) => media::process(command, context).await,
+		| Notices(command) => notices::process(command, context).await,
 		| Users(command) => user::process(command, context).await,
 		| Rooms(command) => room::process(command, context).await,
 		| Federation(command) => federation::process(command, context).await,
@@ -68,5 +79,6 @@ pub(super) async fn process(command: AdminCommand, context: &Context<'_>) -> Res
 		| Debug(command) => debug::process(command, context).await,
 		| Query(command) => query::process(command, context).await,
 		| Token(command) => token::process(command, context).await,
+		| Audit(command) => audit::process(command, context).await,
 	}
 }
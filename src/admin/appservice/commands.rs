@@ -78,3 +78,22 @@ pub(super) async fn list_registered(&self) -> Result {
 		})
 		.await
 }
+
+#[admin_command]
+pub(super) async fn check(&self) -> Result {
+	let collisions = self.services.appservice.collisions().await;
+	if collisions.is_empty() {
+		return self
+			.write_str("No overlapping exclusive namespaces found.")
+			.await;
+	}
+
+	let len = collisions.len();
+	let list: Vec<_> = collisions
+		.iter()
+		.map(|collision| format!("- {collision}"))
+		.collect();
+
+	let list = list.join("\n");
+	write!(self, "Overlapping exclusive namespaces ({len}):\n{list}").await
+}
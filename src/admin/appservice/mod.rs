@@ -37,4 +37,7 @@ pub(super) enum AppserviceCommand {
 	/// - List all the currently registered appservices
 	#[clap(alias("list"))]
 	ListRegistered,
+
+	/// - Check the registered appservices for overlapping exclusive namespaces
+	Check,
 }
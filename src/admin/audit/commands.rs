@@ -0,0 +1,158 @@
+use std::{
+	fmt::Write,
+	path::PathBuf,
+	time::{Duration, UNIX_EPOCH},
+};
+
+use futures::{Stream, StreamExt};
+use ruma::{OwnedUserId, UserId};
+use tuwunel_core::{
+	Result, err,
+	utils::{self, ReadyExt, time::parse_timepoint_ago},
+};
+use tuwunel_macros::admin_command;
+use tuwunel_service::{Services, audit::Entry};
+
+use crate::utils::parse_user_id;
+
+/// Criteria selecting entries of the audit log.
+struct Filter {
+	actor: Option<OwnedUserId>,
+	action: Option<String>,
+	target: Option<String>,
+	since: u64,
+	failed: bool,
+}
+
+impl Filter {
+	fn new(
+		services: &Services,
+		actor: Option<String>,
+		action: Option<String>,
+		target: Option<String>,
+		since: Option<String>,
+		failed: bool,
+	) -> Result<Self> {
+		let since = since
+			.as_deref()
+			.map(parse_timepoint_ago)
+			.transpose()?
+			.map(utils::time::duration_since_epoch)
+			.map(|since| u64::try_from(since.as_millis()))
+			.transpose()?
+			.unwrap_or(0);
+
+		Ok(Self {
+			actor: actor
+				.as_deref()
+				.map(|actor| parse_user_id(services, actor))
+				.transpose()?,
+			action,
+			target,
+			since,
+			failed,
+		})
+	}
+
+	fn matches(&self, entry: &Entry) -> bool {
+		self.actor
+			.as_ref()
+			.is_none_or(|actor| entry.actor.as_ref() == Some(actor))
+			&& self
+				.action
+				.as_deref()
+				.is_none_or(|action| entry.action.contains(action))
+			&& self
+				.target
+				.as_deref()
+				.is_none_or(|target| entry.target.as_deref() == Some(target))
+			&& (!self.failed || entry.error.is_some())
+	}
+
+	/// Matching entries from the newest to the oldest.
+	fn entries<'a>(&'a self, services: &'a Services) -> impl Stream<Item = Entry> + Send + 'a {
+		services
+			.audit
+			.entries()
+			.ready_take_while(|entry| entry.ts >= self.since)
+			.ready_filter(|entry| self.matches(entry))
+	}
+}
+
+#[admin_command]
+pub(super) async fn list(
+	&self,
+	actor: Option<String>,
+	action: Option<String>,
+	target: Option<String>,
+	since: Option<String>,
+	failed: bool,
+	limit: usize,
+) -> Result {
+	let filter = Filter::new(self.services, actor, action, target, since, failed)?;
+	let entries: Vec<_> = filter
+		.entries(self.services)
+		.take(limit)
+		.collect()
+		.await;
+
+	if entries.is_empty() {
+		return self.write_str("No recorded actions match.").await;
+	}
+
+	let mut out = String::from("| Time | Actor | Source | Action | Target | Result |\n");
+	out.push_str("| --- | --- | --- | --- | --- | --- |\n");
+	for entry in entries {
+		let time = UNIX_EPOCH
+			.checked_add(Duration::from_millis(entry.ts))
+			.map(|ts| utils::time::format(ts, "%+"))
+			.unwrap_or_default();
+
+		writeln!(
+			out,
+			"| {time} | {} | {} | `{}` | {} | {} |",
+			entry.actor.as_deref().map_or("-", UserId::as_str),
+			entry.source,
+			entry.action,
+			entry.target.as_deref().unwrap_or("-"),
+			entry.error.as_deref().unwrap_or("ok"),
+		)?;
+	}
+
+	self.write_str(&out).await
+}
+
+#[admin_command]
+pub(super) async fn export(
+	&self,
+	actor: Option<String>,
+	action: Option<String>,
+	target: Option<String>,
+	since: Option<String>,
+	failed: bool,
+	output: Option<PathBuf>,
+) -> Result {
+	let filter = Filter::new(self.services, actor, action, target, since, failed)?;
+	let mut entries: Vec<_> = filter.entries(self.services).collect().await;
+	entries.reverse();
+
+	let mut lines = String::new();
+	for entry in &entries {
+		lines.push_str(&serde_json::to_string(entry)?);
+		lines.push('\n');
+	}
+
+	match output {
+		| Some(path) => {
+			tokio::fs::write(&path, lines)
+				.await
+				.map_err(|e| err!("Failed to write {}: {e}", path.display()))?;
+
+			self.write_str(&format!("Exported {} actions to {}.", entries.len(), path.display()))
+				.await
+		},
+		| None =>
+			self.write_str(&format!("```json\n{lines}```"))
+				.await,
+	}
+}
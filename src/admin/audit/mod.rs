@@ -0,0 +1,70 @@
+mod commands;
+
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use tuwunel_core::Result;
+
+use crate::admin_command_dispatch;
+
+#[admin_command_dispatch]
+#[derive(Debug, Subcommand)]
+pub(crate) enum AuditCommand {
+	/// - List recorded administrative actions, newest first
+	List {
+		/// Only actions performed by this user.
+		#[arg(long)]
+		actor: Option<String>,
+
+		/// Only actions whose command line or endpoint contains this text.
+		#[arg(long)]
+		action: Option<String>,
+
+		/// Only actions applied to this user, room, event or media.
+		#[arg(long)]
+		target: Option<String>,
+
+		/// Only actions more recent than this (e.g. 30m, 12h, 7d).
+		#[arg(long)]
+		since: Option<String>,
+
+		/// Only actions which failed.
+		#[arg(long)]
+		failed: bool,
+
+		/// Maximum number of actions to list.
+		#[arg(long, default_value("50"))]
+		limit: usize,
+	},
+
+	/// - Export recorded administrative actions as JSON lines, oldest first
+	///
+	/// Each line is one self-contained JSON object, suitable for ingestion by
+	/// a SIEM.
+	Export {
+		/// Only actions performed by this user.
+		#[arg(long)]
+		actor: Option<String>,
+
+		/// Only actions whose command line or endpoint contains this text.
+		#[arg(long)]
+		action: Option<String>,
+
+		/// Only actions applied to this user, room, event or media.
+		#[arg(long)]
+		target: Option<String>,
+
+		/// Only actions more recent than this (e.g. 30m, 12h, 7d).
+		#[arg(long)]
+		since: Option<String>,
+
+		/// Only actions which failed.
+		#[arg(long)]
+		failed: bool,
+
+		/// File on the server to write the export to instead of replying
+		/// with it.
+		#[arg(long)]
+		output: Option<PathBuf>,
+	},
+}
@@ -5,7 +5,7 @@
 	io::{AsyncWriteExt, BufWriter},
 	lock::Mutex,
 };
-use ruma::EventId;
+use ruma::{EventId, RoomId, UserId};
 use tuwunel_core::Result;
 use tuwunel_service::Services;
 
@@ -14,10 +14,49 @@ pub(crate) struct Context<'a> {
 	pub(crate) body: &'a [&'a str],
 	pub(crate) timer: SystemTime,
 	pub(crate) reply_id: Option<&'a EventId>,
+
+	/// The admin who sent the command; none for the console and startup
+	/// commands.
+	pub(crate) sender: Option<&'a UserId>,
 	pub(crate) output: Mutex<BufWriter<Vec<u8>>>,
 }
 
 impl Context<'_> {
+	/// Checks the sender may act on the account of the target.
+	pub(crate) async fn check_target(&self, target: &UserId) -> Result {
+		let Some(sender) = self.sender else {
+			return Ok(());
+		};
+
+		self.services
+			.admin
+			.check_admin_target(sender, target)
+			.await
+	}
+
+	/// Checks the sender may join users to the room.
+	pub(crate) async fn check_join(&self, room_id: &RoomId) -> Result {
+		let Some(sender) = self.sender else {
+			return Ok(());
+		};
+
+		self.services
+			.admin
+			.check_admin_room_join(sender, room_id)
+			.await
+	}
+
+	/// Assigns the user joined to the room its admin role when the room is the
+	/// admin room.
+	pub(crate) async fn joined(&self, user_id: &UserId, room_id: &RoomId) {
+		if self.services.admin.is_admin_room(room_id).await {
+			self.services
+				.admin
+				.set_joined_admin_role(user_id)
+				.await;
+		}
+	}
+
 	pub(crate) fn write_fmt(
 		&self,
 		arguments: fmt::Arguments<'_>,
@@ -19,6 +19,7 @@
 	matrix::{
 		Event,
 		pdu::{PduEvent, PduId, RawPduId},
+		room_version,
 	},
 	trace, utils,
 	utils::{
@@ -88,9 +89,7 @@ pub(super) async fn parse_pdu(&self) -> Result {
 	}
 
 	let string = self.body[1..self.body.len().saturating_sub(1)].join("\n");
-	let rules = RoomVersionId::V6
-		.rules()
-		.expect("rules for V6 rooms");
+	let rules = room_version::rules(&RoomVersionId::V6)?;
 	match serde_json::from_str(&string) {
 		| Err(e) => return Err!("Invalid json in command body: {e}"),
 		| Ok(value) => match ruma::signatures::reference_hash(&value, &rules) {
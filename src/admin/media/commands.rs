@@ -1,11 +1,13 @@
 use std::time::Duration;
 
-use ruma::{Mxc, OwnedEventId, OwnedMxcUri, OwnedServerName};
+use futures::StreamExt;
+use ruma::{Mxc, OwnedEventId, OwnedMxcUri, OwnedRoomId, OwnedServerName};
 use tuwunel_core::{
 	Err, Result, debug, debug_info, debug_warn, error, info, trace,
-	utils::time::parse_timepoint_ago, warn,
+	utils::{self, time::parse_timepoint_ago},
+	warn,
 };
-use tuwunel_service::media::Dim;
+use tuwunel_service::media::{Dim, StatsGroup, parse_sha256, scan::Verdict};
 
 use crate::{admin_command, utils::parse_local_user_id};
 
@@ -387,3 +389,329 @@ pub(super) async fn get_remote_thumbnail(
 	self.write_str(&format!("```\n{result:#?}\nreceived {len} bytes for file content.\n```"))
 		.await
 }
+
+#[admin_command]
+pub(super) async fn quarantine(
+	&self,
+	mxc: OwnedMxcUri,
+	reason: Option<String>,
+	block: bool,
+) -> Result {
+	let mxc: Mxc<'_> = mxc.as_str().try_into()?;
+	self.services
+		.media
+		.quarantine(&mxc, None, reason.as_deref(), block)
+		.await?;
+
+	self.write_str(&format!("Quarantined {mxc}."))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn quarantine_room(
+	&self,
+	room_id: OwnedRoomId,
+	reason: Option<String>,
+	block: bool,
+) -> Result {
+	let count = self
+		.services
+		.media
+		.quarantine_from_room(&room_id, None, reason.as_deref(), block)
+		.await?;
+
+	self.write_str(&format!("Quarantined {count} files referenced in {room_id}."))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn quarantine_user(
+	&self,
+	username: String,
+	reason: Option<String>,
+	block: bool,
+) -> Result {
+	let user_id = parse_local_user_id(self.services, &username)?;
+	let count = self
+		.services
+		.media
+		.quarantine_from_user(&user_id, None, reason.as_deref(), block)
+		.await?;
+
+	self.write_str(&format!("Quarantined {count} files uploaded by {user_id}."))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn release(&self, mxc: OwnedMxcUri) -> Result {
+	let mxc: Mxc<'_> = mxc.as_str().try_into()?;
+	self.services.media.release(&mxc).await?;
+
+	self.write_str(&format!("Released {mxc}.")).await
+}
+
+#[admin_command]
+pub(super) async fn list_quarantined(&self) -> Result {
+	let quarantined: Vec<_> = self.services.media.quarantined().collect().await;
+	if quarantined.is_empty() {
+		return self.write_str("No media is quarantined.").await;
+	}
+
+	let list = quarantined
+		.iter()
+		.map(|(mxc, quarantine)| {
+			let reason = quarantine
+				.reason
+				.as_deref()
+				.unwrap_or("no reason");
+			let blocked = if quarantine.blocked_sha256.is_some() {
+				", blocked"
+			} else {
+				""
+			};
+
+			format!("- {mxc} ({reason}{blocked})")
+		})
+		.collect::<Vec<_>>()
+		.join("\n");
+
+	self.write_str(&format!("Quarantined media ({}):\n{list}", quarantined.len()))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn block_hash(&self, sha256: String, reason: Option<String>) -> Result {
+	let hash = parse_sha256(&sha256)?;
+	self.services
+		.media
+		.block_sha256(&hash, reason.as_deref());
+
+	self.write_str(&format!("Blocked content with SHA-256 hash {sha256}."))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn unblock_hash(&self, sha256: String) -> Result {
+	let hash = parse_sha256(&sha256)?;
+	if !self.services.media.is_blocked_sha256(&hash).await {
+		return Err!("The SHA-256 hash {sha256} is not blocked.");
+	}
+
+	self.services.media.unblock_sha256(&hash);
+
+	self.write_str(&format!("Unblocked content with SHA-256 hash {sha256}."))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn list_blocked_hashes(&self) -> Result {
+	let blocked: Vec<_> = self
+		.services
+		.media
+		.blocked_sha256s()
+		.collect()
+		.await;
+	if blocked.is_empty() {
+		return self
+			.write_str("No content hashes are blocked.")
+			.await;
+	}
+
+	let list = blocked
+		.iter()
+		.map(|(hash, blocked)| {
+			let reason = blocked.reason.as_deref().unwrap_or("no reason");
+
+			format!("- {hash} ({reason})")
+		})
+		.collect::<Vec<_>>()
+		.join("\n");
+
+	self.write_str(&format!("Blocked content hashes ({}):\n{list}", blocked.len()))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn scan(&self, mxc: OwnedMxcUri) -> Result {
+	let mxc: Mxc<'_> = mxc.as_str().try_into()?;
+	match self.services.media.rescan(&mxc).await? {
+		| Verdict::Clean => self.write_str(&format!("{mxc} is clean.")).await,
+		| Verdict::Infected(threat) =>
+			self.write_str(&format!("{mxc} is infected with {threat} and was quarantined."))
+				.await,
+	}
+}
+
+#[admin_command]
+pub(super) async fn clear_scan_results(&self) -> Result {
+	self.services.media.clear_scan_results().await;
+
+	self.write_str("Cleared the cached malware scan verdicts.")
+		.await
+}
+
+#[admin_command]
+pub(super) async fn retention_report(&self) -> Result {
+	if !self
+		.services
+		.server
+		.config
+		.media_retention
+		.is_enabled()
+	{
+		return Err!("No media retention policies are configured.");
+	}
+
+	let expired = self.services.media.expired_media().await?;
+	if expired.is_empty() {
+		return self.write_str("No media has expired.").await;
+	}
+
+	let list = expired
+		.iter()
+		.map(|expired| format!("- {} ({})", expired.mxc, expired.expiry))
+		.collect::<Vec<_>>()
+		.join("\n");
+
+	self.write_str(&format!("Media which would be deleted ({}):\n{list}", expired.len()))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn apply_retention(&self) -> Result {
+	if !self
+		.services
+		.server
+		.config
+		.media_retention
+		.is_enabled()
+	{
+		return Err!("No media retention policies are configured.");
+	}
+
+	let count = self.services.media.apply_retention().await?;
+
+	self.write_str(&format!("Deleted {count} expired media files."))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn stats(&self) -> Result {
+	let summary = self.services.media.stats_summary().await;
+
+	self.write_str(&summary).await
+}
+
+#[admin_command]
+pub(super) async fn top_uploaders(&self, limit: usize) -> Result {
+	let top = self
+		.services
+		.media
+		.top_stats(StatsGroup::Uploader, limit)
+		.await;
+
+	if top.is_empty() {
+		return self.write_str("No uploads are recorded.").await;
+	}
+
+	let list = top
+		.iter()
+		.map(|(user_id, totals)| format!("- {user_id}: {totals}"))
+		.collect::<Vec<_>>()
+		.join("\n");
+
+	self.write_str(&format!("Top uploaders:\n{list}"))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn largest_files(&self, limit: usize) -> Result {
+	let largest = self.services.media.largest_media(limit).await;
+	if largest.is_empty() {
+		return self.write_str("No media is recorded.").await;
+	}
+
+	let list = largest
+		.iter()
+		.map(|(mxc, info)| {
+			let size = usize::try_from(info.size).unwrap_or(usize::MAX);
+			let content_type = info
+				.content_type
+				.as_deref()
+				.unwrap_or("unknown type");
+
+			format!("- {mxc}: {} ({content_type})", utils::bytes::pretty(size))
+		})
+		.collect::<Vec<_>>()
+		.join("\n");
+
+	self.write_str(&format!("Largest media files:\n{list}"))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn growth(&self, days: usize) -> Result {
+	let mut stats = self.services.media.stats(StatsGroup::Day).await;
+	if stats.is_empty() {
+		return self.write_str("No uploads are recorded.").await;
+	}
+
+	stats.sort_unstable_by(|(a, _), (b, _)| b.cmp(a));
+	stats.truncate(days);
+
+	let list = stats
+		.iter()
+		.map(|(day, totals)| format!("- {day}: {totals}"))
+		.collect::<Vec<_>>()
+		.join("\n");
+
+	self.write_str(&format!("Media stored by day:\n{list}"))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn room_stats(&self, room_id: OwnedRoomId) -> Result {
+	let totals = self.services.media.room_stats(&room_id).await;
+
+	self.write_str(&format!("Media referenced in {room_id}: {totals}"))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn workspace_stats(&self, workspace_id: String) -> Result {
+	let totals = self
+		.services
+		.media
+		.workspace_stats(&workspace_id)
+		.await?;
+
+	self.write_str(&format!("Media referenced in workspace {workspace_id}: {totals}"))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn rebuild_stats(&self) -> Result {
+	let count = self.services.media.rebuild_stats().await?;
+
+	self.write_str(&format!("Rebuilt the media statistics from {count} media files."))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn rewrap_media(&self) -> Result {
+	if self
+		.services
+		.server
+		.config
+		.media_storage
+		.encryption
+		.is_none()
+	{
+		return Err!("Media encryption at rest is not configured.");
+	}
+
+	let (rewrapped, failed) = self.services.media.rewrap_media().await;
+
+	self.write_str(&format!("Re-wrapped {rewrapped} media files; {failed} failed."))
+		.await
+}
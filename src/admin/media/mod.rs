@@ -2,7 +2,7 @@
 mod commands;
 
 use clap::Subcommand;
-use ruma::{OwnedEventId, OwnedMxcUri, OwnedServerName};
+use ruma::{OwnedEventId, OwnedMxcUri, OwnedRoomId, OwnedServerName};
 use tuwunel_core::Result;
 
 use crate::admin_command_dispatch;
@@ -96,4 +96,136 @@ pub(super) enum MediaCommand {
 		#[arg(long, default_value("800"))]
 		height: u32,
 	},
+
+	/// - Quarantines a media file: it is hidden from clients and federation but
+	///   kept for review. Optionally blocks its content hash so identical
+	///   content can not be uploaded or fetched again.
+	Quarantine {
+		/// The MXC URL to quarantine
+		mxc: OwnedMxcUri,
+
+		/// Reason recorded with the quarantine
+		#[arg(long)]
+		reason: Option<String>,
+
+		/// Also put the content hash on the blocklist
+		#[arg(long)]
+		block: bool,
+	},
+
+	/// - Quarantines all media referenced by the events of a room.
+	QuarantineRoom {
+		room_id: OwnedRoomId,
+
+		/// Reason recorded with the quarantine
+		#[arg(long)]
+		reason: Option<String>,
+
+		/// Also put the content hashes on the blocklist
+		#[arg(long)]
+		block: bool,
+	},
+
+	/// - Quarantines all the local media uploaded by a local user.
+	QuarantineUser {
+		username: String,
+
+		/// Reason recorded with the quarantine
+		#[arg(long)]
+		reason: Option<String>,
+
+		/// Also put the content hashes on the blocklist
+		#[arg(long)]
+		block: bool,
+	},
+
+	/// - Releases quarantined media, removing the content hash blocked along
+	///   with it from the blocklist.
+	Release {
+		/// The MXC URL to release
+		mxc: OwnedMxcUri,
+	},
+
+	/// - Lists the quarantined media.
+	ListQuarantined,
+
+	/// - Adds a SHA-256 content hash to the blocklist, refusing uploads and
+	///   remote fetches of identical content.
+	BlockHash {
+		/// The hex-encoded SHA-256 hash
+		sha256: String,
+
+		/// Reason recorded with the block
+		#[arg(long)]
+		reason: Option<String>,
+	},
+
+	/// - Removes a SHA-256 content hash from the blocklist.
+	UnblockHash {
+		/// The hex-encoded SHA-256 hash
+		sha256: String,
+	},
+
+	/// - Lists the SHA-256 content hashes on the blocklist.
+	ListBlockedHashes,
+
+	/// - Scans a media file for malware with the configured scanner, ignoring
+	///   the cached verdict. Infected media is quarantined.
+	Scan {
+		/// The MXC URL to scan
+		mxc: OwnedMxcUri,
+	},
+
+	/// - Drops the cached malware scan verdicts, e.g. after the signatures of
+	///   the scanner were updated. Media is scanned again when next served.
+	ClearScanResults,
+
+	/// - Lists the media which the policies of `[global.media_retention]` would
+	///   delete, without deleting it.
+	RetentionReport,
+
+	/// - Deletes the media expired under the policies of
+	///   `[global.media_retention]` now, instead of waiting for the next run of
+	///   the cleanup.
+	ApplyRetention,
+
+	/// - Shows the totals of local and remote media, the uploads of the day and
+	///   the top uploaders, content types and remote servers.
+	Stats,
+
+	/// - Lists the local users whose uploads take the most space.
+	TopUploaders {
+		#[arg(short, long, default_value("10"))]
+		limit: usize,
+	},
+
+	/// - Lists the largest media files.
+	LargestFiles {
+		#[arg(short, long, default_value("10"))]
+		limit: usize,
+	},
+
+	/// - Shows the media uploaded or fetched on each of the last days.
+	Growth {
+		#[arg(short, long, default_value("30"))]
+		days: usize,
+	},
+
+	/// - Shows the media referenced by the events of a room.
+	RoomStats {
+		room_id: OwnedRoomId,
+	},
+
+	/// - Shows the media referenced by the events of the rooms of a workspace.
+	WorkspaceStats {
+		workspace_id: String,
+	},
+
+	/// - Rebuilds the media statistics from the media in the database, e.g. for
+	///   media from before they were kept. The uploads by day are kept.
+	RebuildStats,
+
+	/// - Re-wraps the media encrypted under previous master keys by the current
+	///   one, and encrypts media stored before encryption was enabled.
+	RewrapMedia,
 }
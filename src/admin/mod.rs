@@ -7,9 +7,11 @@
 pub(crate) mod utils;
 
 pub(crate) mod appservice;
+pub(crate) mod audit;
 pub(crate) mod debug;
 pub(crate) mod federation;
 pub(crate) mod media;
+pub(crate) mod notices;
 pub(crate) mod query;
 pub(crate) mod room;
 pub(crate) mod server;
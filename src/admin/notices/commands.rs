@@ -0,0 +1,154 @@
+use futures::{StreamExt, stream};
+use ruma::OwnedUserId;
+use tuwunel_core::{Err, Result, info, utils::ReadyExt, warn};
+
+use crate::{Context, admin_command, utils::parse_local_user_id};
+
+#[admin_command]
+pub(super) async fn send_user(&self, user_id: String, message: Vec<String>) -> Result {
+	let message = notice_message(self, &message)?;
+	let user_id = parse_local_user_id(self.services, &user_id)?;
+
+	self.services
+		.server_notices
+		.send_text(&user_id, &message)
+		.await?;
+
+	self.write_str(&format!("Sent server notice to {user_id}."))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn send_workspace(&self, workspace_id: String, message: Vec<String>) -> Result {
+	let message = notice_message(self, &message)?;
+	let space_room_id = self
+		.services
+		.workspace
+		.get_space_room_id(&workspace_id)
+		.await?;
+
+	let users: Vec<OwnedUserId> = self
+		.services
+		.state_cache
+		.local_users_in_room(&space_room_id)
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	self.send_to_users(users, &message).await
+}
+
+#[admin_command]
+pub(super) async fn send_all(&self, message: Vec<String>) -> Result {
+	let message = notice_message(self, &message)?;
+	let server_user = &self.services.globals.server_user;
+	let system_user = self.services.server_notices.system_user();
+
+	let users: Vec<OwnedUserId> = self
+		.services
+		.users
+		.stream()
+		.ready_filter(|user_id| self.services.globals.user_is_local(user_id))
+		.ready_filter(|&user_id| user_id != server_user.as_ref())
+		.ready_filter(|&user_id| system_user.as_deref() != Some(user_id))
+		.filter(|user_id| self.services.users.is_active(user_id))
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	self.send_to_users(users, &message).await
+}
+
+#[admin_command]
+pub(super) async fn usage_limit(
+	&self,
+	user_id: String,
+	limit_type: String,
+	admin_contact: Option<String>,
+	message: Vec<String>,
+) -> Result {
+	let message = notice_message(self, &message)?;
+	let user_id = parse_local_user_id(self.services, &user_id)?;
+
+	self.services
+		.server_notices
+		.send_usage_limit_reached(&user_id, &message, &limit_type, admin_contact.as_deref())
+		.await?;
+
+	self.write_str(&format!("Sent usage limit notice to {user_id}."))
+		.await
+}
+
+/// Number of notices sent at the same time.
+const CONCURRENCY: usize = 8;
+
+/// Number of users after which the progress is reported.
+const PROGRESS_INTERVAL: usize = 500;
+
+impl Context<'_> {
+	/// Sends the notice to the users a few at a time, reporting the progress of
+	/// large batches to the admin room.
+	async fn send_to_users(&self, users: Vec<OwnedUserId>, message: &str) -> Result {
+		let total = users.len();
+		let mut results = stream::iter(users)
+			.map(async |user_id| {
+				let result = self
+					.services
+					.server_notices
+					.send_text(&user_id, message)
+					.await;
+
+				(user_id, result)
+			})
+			.buffer_unordered(CONCURRENCY);
+
+		let (mut sent, mut failed) = (0_usize, 0_usize);
+		while let Some((user_id, result)) = results.next().await {
+			match result {
+				| Ok(_) => sent = sent.saturating_add(1),
+				| Err(e) => {
+					warn!(%user_id, "Failed to send server notice: {e}");
+					failed = failed.saturating_add(1);
+				},
+			}
+
+			let done = sent.saturating_add(failed);
+			if done.is_multiple_of(PROGRESS_INTERVAL) && done < total {
+				info!(%done, %total, "Sending server notices");
+				self.services
+					.admin
+					.send_text(&format!("Sent server notice to {done} of {total} users..."))
+					.await;
+			}
+		}
+
+		self.write_str(&format!("Sent server notice to {sent} users; {failed} failed."))
+			.await
+	}
+}
+
+/// The message of the command: its arguments or the code block below it.
+fn notice_message(context: &Context<'_>, message: &[String]) -> Result<String> {
+	if !context.services.server_notices.is_enabled() {
+		return Err!("Server notices are not enabled; set server_notices.system_localpart.");
+	}
+
+	if !message.is_empty() {
+		return Ok(message.join(" "));
+	}
+
+	let body = context.body;
+	if body.len() >= 2
+		&& body[0].trim().starts_with("```")
+		&& body
+			.last()
+			.is_some_and(|line| line.trim() == "```")
+	{
+		let message = body[1..body.len().saturating_sub(1)].join("\n");
+		if !message.trim().is_empty() {
+			return Ok(message);
+		}
+	}
+
+	Err!("Expected a message as arguments or in a code block below the command.")
+}
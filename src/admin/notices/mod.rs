@@ -0,0 +1,58 @@
+mod commands;
+
+use clap::Subcommand;
+use tuwunel_core::Result;
+
+use crate::admin_command_dispatch;
+
+#[admin_command_dispatch]
+#[derive(Debug, Subcommand)]
+pub(crate) enum NoticesCommand {
+	/// - Send a server notice to a local user
+	///
+	/// The notice is the Markdown message given as arguments or in a code
+	/// block below the command.
+	SendUser {
+		/// The user to notify
+		user_id: String,
+
+		/// The message
+		message: Vec<String>,
+	},
+
+	/// - Send a server notice to the local members of a workspace
+	SendWorkspace {
+		/// The workspace whose members to notify
+		workspace_id: String,
+
+		/// The message
+		message: Vec<String>,
+	},
+
+	/// - Send a server notice to all local users
+	SendAll {
+		/// The message
+		message: Vec<String>,
+	},
+
+	/// - Notify a local user that a usage limit of the server was reached
+	///
+	/// Clients show `m.server_notice.usage_limit_reached` notices as a banner
+	/// until they are replaced.
+	UsageLimit {
+		/// The user to notify
+		user_id: String,
+
+		/// The kind of limit that was reached
+		#[arg(long, default_value = "monthly_active_user")]
+		limit_type: String,
+
+		/// How to contact the admins, e.g. a mailto: link; defaults to the
+		/// support contact of the server
+		#[arg(long)]
+		admin_contact: Option<String>,
+
+		/// The message
+		message: Vec<String>,
+	},
+}
@@ -9,7 +9,7 @@
 use clap::{CommandFactory, Parser};
 use futures::{AsyncWriteExt, future::FutureExt, io::BufWriter};
 use ruma::{
-	EventId,
+	EventId, OwnedUserId, UserId,
 	events::{
 		relation::InReplyTo,
 		room::message::{Relation::Reply, RoomMessageEventContent},
@@ -18,7 +18,7 @@
 use tracing::Level;
 use tracing_subscriber::{EnvFilter, filter::LevelFilter};
 use tuwunel_core::{
-	Error, Result, debug, error,
+	Error, Event, Result, debug, err, error,
 	log::{
 		capture,
 		capture::Capture,
@@ -31,6 +31,7 @@
 use tuwunel_service::{
 	Services,
 	admin::{CommandInput, CommandOutput, ProcessorFuture, ProcessorResult},
+	audit,
 };
 
 use crate::{admin, admin::AdminCommand, context::Context};
@@ -58,15 +59,42 @@ async fn process_command(services: Arc<Services>, input: &CommandInput) -> Proce
 		| Ok(parsed) => parsed,
 	};
 
+	let sender = match command_sender(&services, input).await {
+		| Ok(sender) => sender,
+		| Err(error) => {
+			let message = error.to_string();
+			warn!(command = ?redact_secrets(&args), "{message}");
+
+			return Err(Box::new(reply(
+				RoomMessageEventContent::notice_plain(message),
+				input.reply_id.as_deref(),
+			)));
+		},
+	};
+
+	if let Some(sender) = &sender
+		&& let Err(error) = check_permission(&services, sender, &args).await
+	{
+		let message = error.to_string();
+		record_audit(&services, input, Some(sender.clone()), &args, &Err(error));
+
+		return Err(Box::new(reply(
+			RoomMessageEventContent::notice_plain(message),
+			input.reply_id.as_deref(),
+		)));
+	}
+
 	let context = Context {
 		services: &services,
 		body: &body,
 		timer: SystemTime::now(),
 		reply_id: input.reply_id.as_deref(),
+		sender: sender.as_deref(),
 		output: BufWriter::new(Vec::new()).into(),
 	};
 
 	let (result, mut logs) = process(&context, command, &args).await;
+	record_audit(&services, input, sender, &args, &result);
 
 	let output = &mut context.output.lock().await;
 	output
@@ -139,6 +167,145 @@ async fn process(
 	(result, output)
 }
 
+/// The user who sent the command to the admin room; none for the server
+/// console and startup commands, which reply to no event. Fails when the
+/// sender of a command from the admin room cannot be found, rather than run
+/// it as a console command.
+async fn command_sender(
+	services: &Services,
+	input: &CommandInput,
+) -> Result<Option<OwnedUserId>> {
+	let Some(reply_id) = input.reply_id.as_deref() else {
+		return Ok(None);
+	};
+
+	services
+		.timeline
+		.get_pdu(reply_id)
+		.await
+		.map(|pdu| Some(pdu.sender().to_owned()))
+		.map_err(|e| err!("Permission denied: the sender of the command was not found: {e}"))
+}
+
+/// Check the admin role of the sender permits the command
+async fn check_permission(services: &Services, sender: &UserId, args: &[String]) -> Result {
+	let command = command_path(args);
+	let command: Vec<_> = command.iter().map(String::as_str).collect();
+
+	services
+		.admin
+		.check_admin_permission(sender, &command)
+		.await
+}
+
+/// Names of the (sub)commands selected by the arguments, e.g. `["users",
+/// "list-users"]`
+fn command_path(argv: &[String]) -> Vec<String> {
+	let Ok(matches) = AdminCommand::command().try_get_matches_from(argv) else {
+		return Vec::new();
+	};
+
+	let mut path = Vec::new();
+	let mut matches = &matches;
+	while let Some((name, sub)) = matches.subcommand() {
+		path.push(name.to_owned());
+		matches = sub;
+	}
+
+	path
+}
+
+/// Arguments never kept in the audit log, by the path of their command and
+/// their id
+const SECRET_ARGS: &[(&[&str], &str)] = &[
+	(&["users", "create-user"], "password"),
+	(&["users", "reset-password"], "password"),
+	(&["token", "revoke"], "token"),
+];
+
+/// The arguments with the values of the secret arguments of the command
+/// replaced, wherever they were given on the command line
+pub(super) fn redact_secrets(argv: &[String]) -> Vec<String> {
+	const REDACTED: &str = "<redacted>";
+
+	let Ok(matches) = AdminCommand::command().try_get_matches_from(argv) else {
+		// Nothing is known of the arguments of an invalid command
+		return argv
+			.iter()
+			.enumerate()
+			.map(|(i, arg)| if i > 2 { REDACTED } else { arg.as_str() })
+			.map(ToOwned::to_owned)
+			.collect();
+	};
+
+	let mut path = Vec::new();
+	let mut matches = &matches;
+	while let Some((name, sub)) = matches.subcommand() {
+		path.push(name);
+		matches = sub;
+	}
+
+	let secrets: Vec<&str> = SECRET_ARGS
+		.iter()
+		.filter(|(command, _)| *command == path.as_slice())
+		.filter_map(|(_, id)| matches.try_get_raw(id).ok().flatten())
+		.flatten()
+		.filter_map(|value| value.to_str())
+		.collect();
+
+	argv.iter()
+		.map(|arg| {
+			if secrets.contains(&arg.as_str()) {
+				REDACTED.to_owned()
+			} else if let Some((flag, value)) = arg.split_once('=')
+				&& secrets.contains(&value)
+			{
+				format!("{flag}={REDACTED}")
+			} else {
+				arg.clone()
+			}
+		})
+		.collect()
+}
+
+/// Record the command in the audit log
+fn record_audit(
+	services: &Services,
+	input: &CommandInput,
+	actor: Option<OwnedUserId>,
+	args: &[String],
+	result: &Result,
+) {
+	let args = redact_secrets(args);
+	let action = args
+		.iter()
+		.skip(1)
+		.map(String::as_str)
+		.collect::<Vec<_>>()
+		.join(" ");
+
+	let target = args
+		.iter()
+		.skip(2)
+		.find(|arg| arg.starts_with(['@', '!', '#', '$']) || arg.starts_with("mxc://"))
+		.cloned();
+
+	let source = if input.reply_id.is_some() {
+		"admin_room"
+	} else {
+		"console"
+	};
+
+	services.audit.record(audit::Entry {
+		actor,
+		source: source.to_owned(),
+		action,
+		target,
+		error: result.as_ref().err().map(ToString::to_string),
+		..Default::default()
+	});
+}
+
 fn capture_create(context: &Context<'_>) -> (Arc<Capture>, Arc<Mutex<String>>) {
 	let env_config = &context.services.server.config.admin_log_capture;
 	let env_filter = EnvFilter::try_new(env_config).unwrap_or_else(|e| {
@@ -1,5 +1,8 @@
 use futures::StreamExt;
-use ruma::OwnedRoomId;
+use ruma::{
+	OwnedRoomId, OwnedUserId,
+	events::{StateEventType, room::create::RoomCreateEventContent},
+};
 use tuwunel_core::{Err, Result};
 
 use crate::{PAGE_SIZE, admin_command, get_room_info};
@@ -66,6 +69,55 @@ pub(super) async fn exists(&self, room_id: OwnedRoomId) -> Result {
 	self.write_str(&format!("{result}")).await
 }
 
+#[admin_command]
+pub(super) async fn copy_upgrade_data(&self, room_id: Option<OwnedRoomId>) -> Result {
+	let room_ids: Vec<OwnedRoomId> = match room_id {
+		| Some(room_id) => vec![room_id],
+		| None =>
+			self.services
+				.metadata
+				.iter_ids()
+				.map(ToOwned::to_owned)
+				.collect()
+				.await,
+	};
+
+	let mut rooms = 0_usize;
+	let mut members = 0_usize;
+	for room_id in &room_ids {
+		let Ok(Some(predecessor)) = self
+			.services
+			.state_accessor
+			.room_state_get_content(room_id, &StateEventType::RoomCreate, "")
+			.await
+			.map(|content: RoomCreateEventContent| content.predecessor)
+		else {
+			continue;
+		};
+
+		let user_ids: Vec<OwnedUserId> = self
+			.services
+			.state_cache
+			.local_users_in_room(room_id)
+			.map(ToOwned::to_owned)
+			.collect()
+			.await;
+
+		for user_id in &user_ids {
+			self.services
+				.account_data
+				.copy_room_data(user_id, &predecessor.room_id, room_id)
+				.await?;
+		}
+
+		rooms = rooms.saturating_add(1);
+		members = members.saturating_add(user_ids.len());
+	}
+
+	self.write_str(&format!("Copied data of {members} members across {rooms} upgraded rooms."))
+		.await
+}
+
 #[admin_command]
 pub(super) async fn delete_room(&self, room_id: OwnedRoomId, force: bool) -> Result {
 	if self.services.admin.is_admin_room(&room_id).await {
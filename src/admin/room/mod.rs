@@ -57,6 +57,16 @@ pub(super) enum RoomCommand {
 		room_id: OwnedRoomId,
 	},
 
+	/// - Copy the data local members attached to upgraded rooms over to the
+	///   replacement rooms
+	///
+	/// Room-specific push rules, room tags, `m.direct` entries and room
+	/// account data are copied for rooms upgraded before this happened on
+	/// join. Without a room ID, every room replacing another one is processed.
+	CopyUpgradeData {
+		room_id: Option<OwnedRoomId>,
+	},
+
 	/// - Delete room
 	DeleteRoom {
 		room_id: OwnedRoomId,
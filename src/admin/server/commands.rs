@@ -32,6 +32,15 @@ pub(super) async fn show_config(&self) -> Result {
 pub(super) async fn reload_config(&self, path: Option<PathBuf>) -> Result {
 	let path = path.as_deref().into_iter();
 	self.services.config.reload(path)?;
+	if let Err(e) = self
+		.services
+		.appservice
+		.reload_config_registrations()
+		.await
+	{
+		return write!(self, "Reconfigured, but kept the previous appservice registrations: {e}")
+			.await;
+	}
 
 	self.write_str("Successfully reconfigured.").await
 }
@@ -24,3 +24,39 @@ fn get_help_inner(input: &str) {
 	assert!(error.contains("Commands:"));
 	assert!(error.contains("Options:"));
 }
+
+fn redact(line: &str) -> String {
+	let argv: Vec<String> = line.split(' ').map(ToOwned::to_owned).collect();
+
+	crate::processor::redact_secrets(&argv).join(" ")
+}
+
+#[test]
+fn redact_positional_secrets() {
+	assert_eq!(
+		redact("admin users reset-password @alice:example.org hunter2"),
+		"admin users reset-password @alice:example.org <redacted>"
+	);
+	assert_eq!(
+		redact("admin users create-user alice hunter2"),
+		"admin users create-user alice <redacted>"
+	);
+	assert_eq!(redact("admin token revoke abcdef"), "admin token revoke <redacted>");
+}
+
+#[test]
+fn redact_keeps_other_arguments() {
+	assert_eq!(redact("admin users reset-password alice"), "admin users reset-password alice");
+	assert_eq!(
+		redact("admin users deactivate @alice:example.org"),
+		"admin users deactivate @alice:example.org"
+	);
+}
+
+#[test]
+fn redact_invalid_commands() {
+	assert_eq!(
+		redact("admin users reset-password alice hunter2 extra"),
+		"admin users reset-password <redacted> <redacted> <redacted>"
+	);
+}
@@ -1,4 +1,9 @@
-use std::{cmp, collections::BTreeMap};
+use std::{
+	cmp,
+	collections::BTreeMap,
+	fmt::Write,
+	time::{Duration, UNIX_EPOCH},
+};
 
 use futures::{FutureExt, StreamExt, TryStreamExt};
 use ruma::{
@@ -86,6 +91,7 @@ pub(super) async fn deactivate(&self, no_leave_rooms: bool, user_id: String) ->
 		return Err!("Not allowed to deactivate the server service account.",);
 	}
 
+	self.check_target(&user_id).await?;
 	deactivate_user(self.services, &user_id, no_leave_rooms).await?;
 
 	self.write_str(&format!("User {user_id} has been deactivated"))
@@ -122,6 +128,8 @@ pub(super) async fn reset_password(&self, username: String, password: Option<Str
 		);
 	}
 
+	self.check_target(&user_id).await?;
+
 	let new_password = password.unwrap_or_else(|| utils::random_string(AUTO_GEN_PASSWORD_LENGTH));
 
 	match self
@@ -191,6 +199,15 @@ pub(super) async fn deactivate_all(&self, no_leave_rooms: bool, force: bool) ->
 					continue;
 				}
 
+				if let Err(e) = self.check_target(&user_id).await {
+					self.services
+						.admin
+						.send_text(&format!("{username} is skipped over: {e}"))
+						.await;
+
+					continue;
+				}
+
 				user_ids.push(user_id);
 			},
 		}
@@ -239,6 +256,59 @@ async fn deactivate_user(services: &Services, user_id: &UserId, no_leave_rooms:
 	Ok(())
 }
 
+#[admin_command]
+pub(super) async fn list_without_consent(&self) -> Result {
+	if !self.services.terms.is_enabled() {
+		return Err!("No terms of service are configured.");
+	}
+
+	let users: Vec<_> = self
+		.services
+		.terms
+		.users_without_consent()
+		.map(ToString::to_string)
+		.collect()
+		.await;
+
+	let version = self.services.terms.version();
+	let mut plain_msg = format!(
+		"Found {} local user account(s) without consent to {version}:\n```\n",
+		users.len()
+	);
+	plain_msg += users.join("\n").as_str();
+	plain_msg += "\n```";
+
+	self.write_str(&plain_msg).await
+}
+
+#[admin_command]
+pub(super) async fn consent_status(&self, user_id: String) -> Result {
+	let user_id = parse_local_user_id(self.services, &user_id)?;
+	let current = self.services.terms.version();
+
+	match self.services.terms.consent(&user_id).await {
+		| Ok(consent) => {
+			let accepted_at = UNIX_EPOCH
+				.checked_add(Duration::from_millis(consent.accepted_at))
+				.map(|ts| utils::time::format(ts, "%+"))
+				.unwrap_or_default();
+
+			let outdated = if consent.version == current { "" } else { " (outdated)" };
+
+			self.write_str(&format!(
+				"{user_id} accepted {}{outdated} at {accepted_at}. Current version: {current}",
+				consent.version
+			))
+			.await
+		},
+		| Err(_) =>
+			self.write_str(&format!(
+				"{user_id} has not accepted any terms of service. Current version: {current}"
+			))
+			.await,
+	}
+}
+
 #[admin_command]
 pub(super) async fn list_joined_rooms(&self, user_id: String) -> Result {
 	// Validate user id
@@ -299,6 +369,8 @@ pub(super) async fn force_join_list_of_local_users(
 		.maybe_resolve_with_servers(&room, None)
 		.await?;
 
+	self.check_join(&room_id).await?;
+
 	if !self
 		.services
 		.state_cache
@@ -383,6 +455,7 @@ pub(super) async fn force_join_list_of_local_users(
 			.await
 		{
 			| Ok(_res) => {
+				self.joined(&user_id, &room_id).await;
 				successful_joins = successful_joins.saturating_add(1);
 			},
 			| Err(e) => {
@@ -424,6 +497,8 @@ pub(super) async fn force_join_all_local_users(
 		.maybe_resolve_with_servers(&room, None)
 		.await?;
 
+	self.check_join(&room_id).await?;
+
 	if !self
 		.services
 		.state_cache
@@ -479,6 +554,7 @@ pub(super) async fn force_join_all_local_users(
 			.await
 		{
 			| Ok(_res) => {
+				self.joined(user_id, &room_id).await;
 				successful_joins = successful_joins.saturating_add(1);
 			},
 			| Err(e) => {
@@ -511,6 +587,7 @@ pub(super) async fn force_join_room(&self, user_id: String, room: OwnedRoomOrAli
 		"Parsed user_id must be a local user"
 	);
 
+	self.check_join(&room_id).await?;
 	let state_lock = self.services.state.mutex.lock(&room_id).await;
 
 	self.services
@@ -519,6 +596,7 @@ pub(super) async fn force_join_room(&self, user_id: String, room: OwnedRoomOrAli
 		.await?;
 
 	drop(state_lock);
+	self.joined(&user_id, &room_id).await;
 
 	self.write_str(&format!("{user_id} has been joined to {room_id}.",))
 		.await
@@ -531,6 +609,8 @@ pub(super) async fn force_leave_room(
 	room_id: OwnedRoomOrAliasId,
 ) -> Result {
 	let user_id = parse_local_user_id(self.services, &user_id)?;
+	self.check_target(&user_id).await?;
+
 	let room_id = self
 		.services
 		.alias
@@ -568,6 +648,8 @@ pub(super) async fn force_leave_room(
 #[admin_command]
 pub(super) async fn force_demote(&self, user_id: String, room_id: OwnedRoomOrAliasId) -> Result {
 	let user_id = parse_local_user_id(self.services, &user_id)?;
+	self.check_target(&user_id).await?;
+
 	let room_id = self
 		.services
 		.alias
@@ -638,6 +720,8 @@ pub(super) async fn force_promote(
 	room_id: OwnedRoomOrAliasId,
 ) -> Result {
 	let target_id = parse_user_id(self.services, &target_id)?;
+	self.check_target(&target_id).await?;
+
 	let room_id = self
 		.services
 		.alias
@@ -732,6 +816,74 @@ pub(super) async fn make_user_admin(&self, user_id: String) -> Result {
 		.await
 }
 
+#[admin_command]
+pub(super) async fn set_admin_role(&self, user_id: String, role: String) -> Result {
+	let user_id = parse_local_user_id(self.services, &user_id)?;
+	if user_id == self.services.globals.server_user {
+		return Err!("Not allowed to change the admin role of the server service account.");
+	}
+
+	self.services
+		.admin
+		.set_admin_role(&user_id, &role)?;
+
+	let note = if self.services.admin.user_is_admin(&user_id).await {
+		""
+	} else {
+		" It applies once they are granted admin privileges."
+	};
+
+	self.write_str(&format!("{user_id} has been assigned the admin role {role:?}.{note}"))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn unset_admin_role(&self, user_id: String) -> Result {
+	let user_id = parse_local_user_id(self.services, &user_id)?;
+	self.services.admin.unset_admin_role(&user_id);
+
+	let role = self.services.admin.admin_role(&user_id).await;
+	self.write_str(&format!("{user_id} has the default admin role {role:?} again."))
+		.await
+}
+
+#[admin_command]
+pub(super) async fn list_admin_roles(&self) -> Result {
+	let mut assigned: BTreeMap<String, Vec<String>> = BTreeMap::new();
+	self.services
+		.admin
+		.admin_role_assignments()
+		.ready_for_each(|(user_id, role)| {
+			assigned
+				.entry(role.to_owned())
+				.or_default()
+				.push(user_id.to_string());
+		})
+		.await;
+
+	let default_role = &self.services.config.admin_roles.default_role;
+	let mut out = String::new();
+	for (role, grants) in self.services.admin.admin_roles() {
+		let default = if role == *default_role { " (default)" } else { "" };
+		let users = assigned.remove(&role).unwrap_or_default();
+
+		writeln!(out, "- **{role}**{default}: `{}`", grants.join("`, `"))?;
+		if !users.is_empty() {
+			writeln!(out, "  - assigned to {}", users.join(", "))?;
+		}
+	}
+
+	for (role, users) in assigned {
+		writeln!(
+			out,
+			"- **{role}** (not configured; permits nothing): assigned to {}",
+			users.join(", ")
+		)?;
+	}
+
+	self.write_str(&out).await
+}
+
 #[admin_command]
 pub(super) async fn put_room_tag(
 	&self,
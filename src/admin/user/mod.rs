@@ -75,6 +75,14 @@ pub(super) enum UserCommand {
 	#[clap(alias = "list")]
 	ListUsers,
 
+	/// - List local users who have not accepted the current terms of service
+	ListWithoutConsent,
+
+	/// - Shows which version of the terms of service a user accepted, and when
+	ConsentStatus {
+		user_id: String,
+	},
+
 	/// - Lists all the rooms (local and remote) that the specified user is
 	///   joined in
 	ListJoinedRooms {
@@ -111,6 +119,22 @@ pub(super) enum UserCommand {
 		user_id: String,
 	},
 
+	/// - Assign an admin role to a user, limiting the commands they may run.
+	SetAdminRole {
+		user_id: String,
+		role: String,
+	},
+
+	/// - Remove the admin role assigned to a user, who gets the default role
+	///   back.
+	UnsetAdminRole {
+		user_id: String,
+	},
+
+	/// - List the admin roles, the commands they permit and the users assigned
+	///   to them.
+	ListAdminRoles,
+
 	/// - Puts a room tag for the specified user and room ID.
 	///
 	/// This is primarily useful if you'd like to set your admin room
@@ -7,7 +7,7 @@
 };
 use tuwunel_core::{Err, Result, err, info, utils::ReadyExt};
 
-use crate::{Ruma, router::auth_uiaa};
+use crate::{Ruma, client::utils::audit, router::auth_uiaa};
 
 /// # `POST /_matrix/client/r0/account/password`
 ///
@@ -50,6 +50,7 @@ pub(crate) async fn change_password_route(
 	}
 
 	info!("User {sender_user} changed their password.");
+	audit(&services, Some(sender_user), "account password", sender_user.as_str());
 
 	if services.server.config.admin_room_notices {
 		services
@@ -108,6 +109,8 @@ pub(crate) async fn deactivate_route(
 		.await?;
 
 	info!("User {sender_user} deactivated their account.");
+	audit(&services, Some(sender_user), "account deactivate", sender_user.as_str());
+
 	if services.server.config.admin_room_notices {
 		services
 			.admin
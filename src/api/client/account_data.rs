@@ -112,6 +112,62 @@ pub(crate) async fn get_room_account_data_route(
 	Ok(get_room_account_data::v3::Response { account_data: account_data.content })
 }
 
+/// # `DELETE /_matrix/client/unstable/org.matrix.msc3391/user/{userId}/account_data/{type}`
+///
+/// Deletes some account data for the sender user (MSC3391).
+pub(crate) async fn delete_global_account_data_route(
+	State(services): State<crate::State>,
+	body: Ruma<delete_global_account_data::Request>,
+) -> Result<delete_global_account_data::Response> {
+	let sender_user = body.sender_user();
+
+	if sender_user != body.user_id && body.appservice_info.is_none() {
+		return Err!(Request(Forbidden("You cannot delete account data of other users.")));
+	}
+
+	delete_account_data(&services, None, &body.user_id, &body.event_type).await?;
+
+	Ok(delete_global_account_data::Response {})
+}
+
+/// # `DELETE /_matrix/client/unstable/org.matrix.msc3391/user/{userId}/rooms/{roomId}/account_data/{type}`
+///
+/// Deletes some room account data for the sender user (MSC3391).
+pub(crate) async fn delete_room_account_data_route(
+	State(services): State<crate::State>,
+	body: Ruma<delete_room_account_data::Request>,
+) -> Result<delete_room_account_data::Response> {
+	let sender_user = body.sender_user();
+
+	if sender_user != body.user_id && body.appservice_info.is_none() {
+		return Err!(Request(Forbidden("You cannot delete account data of other users.")));
+	}
+
+	delete_account_data(&services, Some(&body.room_id), &body.user_id, &body.event_type).await?;
+
+	Ok(delete_room_account_data::Response {})
+}
+
+async fn delete_account_data(
+	services: &Services,
+	room_id: Option<&RoomId>,
+	user_id: &UserId,
+	event_type_s: &str,
+) -> Result {
+	if event_type_s == RoomAccountDataEventType::FullyRead.to_cow_str()
+		|| event_type_s == GlobalAccountDataEventType::PushRules.to_cow_str()
+	{
+		return Err!(Request(BadJson(
+			"This endpoint cannot be used for deleting {event_type_s}."
+		)));
+	}
+
+	services
+		.account_data
+		.delete(room_id, user_id, event_type_s.into())
+		.await
+}
+
 async fn set_account_data(
 	services: &Services,
 	room_id: Option<&RoomId>,
@@ -158,3 +214,66 @@ struct ExtractRoomEventContent {
 struct ExtractGlobalEventContent {
 	content: Raw<AnyGlobalAccountDataEventContent>,
 }
+
+/// `DELETE /_matrix/client/unstable/org.matrix.msc3391/user/{userId}/
+/// account_data/{type}`
+pub(crate) mod delete_global_account_data {
+	use ruma::{
+		OwnedUserId,
+		api::{Metadata, metadata, request, response},
+	};
+
+	const METADATA: Metadata = metadata! {
+		method: DELETE,
+		rate_limited: false,
+		authentication: AccessToken,
+		history: {
+			unstable => "/_matrix/client/unstable/org.matrix.msc3391/user/{user_id}/account_data/{event_type}",
+		}
+	};
+
+	#[request]
+	pub(crate) struct Request {
+		#[ruma_api(path)]
+		pub(crate) user_id: OwnedUserId,
+
+		#[ruma_api(path)]
+		pub(crate) event_type: String,
+	}
+
+	#[response]
+	pub(crate) struct Response {}
+}
+
+/// `DELETE /_matrix/client/unstable/org.matrix.msc3391/user/{userId}/rooms/
+/// {roomId}/account_data/{type}`
+pub(crate) mod delete_room_account_data {
+	use ruma::{
+		OwnedRoomId, OwnedUserId,
+		api::{Metadata, metadata, request, response},
+	};
+
+	const METADATA: Metadata = metadata! {
+		method: DELETE,
+		rate_limited: false,
+		authentication: AccessToken,
+		history: {
+			unstable => "/_matrix/client/unstable/org.matrix.msc3391/user/{user_id}/rooms/{room_id}/account_data/{event_type}",
+		}
+	};
+
+	#[request]
+	pub(crate) struct Request {
+		#[ruma_api(path)]
+		pub(crate) user_id: OwnedUserId,
+
+		#[ruma_api(path)]
+		pub(crate) room_id: OwnedRoomId,
+
+		#[ruma_api(path)]
+		pub(crate) event_type: String,
+	}
+
+	#[response]
+	pub(crate) struct Response {}
+}
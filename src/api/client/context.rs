@@ -17,7 +17,9 @@
 
 use crate::{
 	Ruma,
-	client::message::{event_filter, ignored_filter, lazy_loading_witness, visibility_filter},
+	client::message::{
+		event_filter, history_horizon, ignored_filter, lazy_loading_witness, visibility_filter,
+	},
 };
 
 const LIMIT_MAX: usize = 100;
@@ -27,8 +29,8 @@
 ///
 /// Allows loading room history around an event.
 ///
-/// - Only works if the user is joined (TODO: always allow, but only show events
-///   if the user was joined, depending on history_visibility)
+/// - The base event must be visible to the user and only events the user can
+///   see according to `history_visibility` are included around it
 pub(crate) async fn get_context_route(
 	State(services): State<crate::State>,
 	body: Ruma<get_context::v3::Request>,
@@ -94,10 +96,16 @@ pub(crate) async fn get_context_route(
 		.take(limit / 2)
 		.collect();
 
+	let horizon = history_horizon(&services, room_id, sender_user)
+		.await
+		.ok()
+		.flatten();
+
 	let events_after = services
 		.timeline
 		.pdus(Some(sender_user), room_id, Some(base_count))
 		.ignore_err()
+		.ready_take_while(|(count, _)| horizon.is_none_or(|horizon| *count <= horizon))
 		.ready_filter_map(|item| event_filter(item, filter))
 		.wide_filter_map(|item| ignored_filter(&services, item, sender_user))
 		.wide_filter_map(|item| visibility_filter(&services, item, sender_user))
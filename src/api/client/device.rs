@@ -98,10 +98,10 @@ pub(crate) async fn update_device_route(
 				.users
 				.create_device(
 					sender_user,
+					Some(&body.device_id),
+					(None, None),
 					None,
-					(Some(&appservice.registration.as_token), None),
-					None,
-					None,
+					body.display_name.as_deref(),
 					Some(client.to_string()),
 				)
 				.await?;
@@ -33,7 +33,7 @@
 };
 use tuwunel_service::Services;
 
-use crate::Ruma;
+use crate::{Ruma, client::utils::audit};
 
 /// # `POST /_matrix/client/v3/publicRooms`
 ///
@@ -160,6 +160,7 @@ pub(crate) async fn set_room_visibility_route(
 			}
 
 			services.directory.set_public(&body.room_id);
+			audit(&services, Some(sender_user), "directory publish", body.room_id.as_str());
 
 			if services.server.config.admin_room_notices {
 				services
@@ -172,7 +173,10 @@ pub(crate) async fn set_room_visibility_route(
 			}
 			info!("{sender_user} made {0} public to the room directory", body.room_id);
 		},
-		| room::Visibility::Private => services.directory.set_not_public(&body.room_id),
+		| room::Visibility::Private => {
+			services.directory.set_not_public(&body.room_id);
+			audit(&services, Some(sender_user), "directory unpublish", body.room_id.as_str());
+		},
 		| _ => {
 			return Err!(Request(InvalidParam("Room visibility type is not supported.",)));
 		},
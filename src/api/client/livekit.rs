@@ -0,0 +1,43 @@
+use axum::{Json, body::Bytes, extract::State};
+use ruma::{OwnedRoomId, OwnedServerName};
+use serde::Deserialize;
+use serde_json::{Value as JsonValue, json};
+use tuwunel_core::Result;
+
+#[derive(Deserialize)]
+struct SfuRequest {
+	room: OwnedRoomId,
+	openid_token: OpenIdToken,
+	device_id: String,
+}
+
+#[derive(Deserialize)]
+struct OpenIdToken {
+	access_token: String,
+	matrix_server_name: OwnedServerName,
+}
+
+/// # `POST /_tuwunel/livekit/sfu/get`
+///
+/// Exchanges a Matrix OpenID token for a LiveKit access token to the call of
+/// a room, compatible with lk-jwt-service.
+pub(crate) async fn livekit_sfu_route(
+	State(services): State<crate::State>,
+	body: Bytes,
+) -> Result<Json<JsonValue>> {
+	let request: SfuRequest = serde_json::from_slice(&body)?;
+	let access = services
+		.livekit
+		.authorize(
+			&request.room,
+			&request.openid_token.access_token,
+			&request.openid_token.matrix_server_name,
+			&request.device_id,
+		)
+		.await?;
+
+	Ok(Json(json!({
+		"url": access.url,
+		"jwt": access.jwt,
+	})))
+}
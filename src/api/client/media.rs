@@ -61,15 +61,16 @@ pub(crate) async fn create_content_route(
 		media_id: &utils::random_string(MXC_LENGTH),
 	};
 
+	let file = services.media.sanitize(&body.file, content_type);
 	services
 		.media
-		.create(mxc, Some(user), Some(&content_disposition), content_type, &body.file)
+		.create(mxc, Some(user), Some(&content_disposition), content_type, &file)
 		.await?;
 
 	let blurhash = body.generate_blurhash.then(|| {
 		services
 			.media
-			.create_blurhash(&body.file, content_type, filename)
+			.create_blurhash(&file, content_type, filename)
 			.ok()
 			.flatten()
 	});
@@ -96,7 +97,8 @@ pub(crate) async fn get_content_thumbnail_route(
 ) -> Result<get_content_thumbnail::v1::Response> {
 	let user = body.sender_user();
 
-	let dim = Dim::from_ruma(body.width, body.height, body.method.clone())?;
+	let dim = Dim::from_ruma(body.width, body.height, body.method.clone())?
+		.with_animated(body.animated);
 	let mxc = Mxc {
 		server_name: &body.server_name,
 		media_id: &body.media_id,
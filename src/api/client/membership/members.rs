@@ -1,6 +1,7 @@
 use axum::extract::State;
 use futures::{FutureExt, StreamExt, pin_mut};
 use ruma::{
+	RoomId,
 	api::client::membership::{
 		get_member_events::{self},
 		joined_members::{self, v3::RoomMember},
@@ -14,29 +15,32 @@
 	},
 };
 use tuwunel_core::{
-	Err, Result, at,
-	matrix::Event,
+	Err, Result, at, err,
+	matrix::{Event, PduCount},
 	utils::{
 		future::{BoolExt, TryExtExt},
-		stream::ReadyExt,
+		stream::{ReadyExt, TryIgnore},
 	},
 };
+use tuwunel_service::{Services, rooms::short::ShortStateHash};
 
 use crate::Ruma;
 
 /// # `POST /_matrix/client/r0/rooms/{roomId}/members`
 ///
-/// Lists all joined users in a room (TODO: at a specific point in time, with a
-/// specific membership).
+/// Lists the members of a room, optionally at the point in time given by the
+/// `at` pagination token and filtered by membership.
 ///
-/// - Only works if the user is currently joined
+/// - Only works if the user may see the state of the room: they are joined, or
+///   the history visibility of the room lets them see it
 pub(crate) async fn get_member_events_route(
 	State(services): State<crate::State>,
 	body: Ruma<get_member_events::v3::Request>,
 ) -> Result<get_member_events::v3::Response> {
+	let room_id = &body.room_id;
 	if !services
 		.state_accessor
-		.user_can_see_state_events(body.sender_user(), &body.room_id)
+		.user_can_see_state_events(body.sender_user(), room_id)
 		.await
 	{
 		return Err!(Request(Forbidden(
@@ -44,15 +48,24 @@ pub(crate) async fn get_member_events_route(
 		)));
 	}
 
+	let at: Option<PduCount> = body.at.as_deref().map(str::parse).transpose()?;
+
+	let shortstatehash = match at {
+		| Some(at) => shortstatehash_at(&services, room_id, at).await,
+		| None =>
+			services
+				.state
+				.get_room_shortstatehash(room_id)
+				.await,
+	}
+	.map_err(|e| err!(Request(NotFound("Room state not found: {e}"))))?;
+
 	let membership = body.membership.as_ref();
 	let not_membership = body.not_membership.as_ref();
 	Ok(get_member_events::v3::Response {
 		chunk: services
 			.state_accessor
-			.room_state_full(&body.room_id)
-			.ready_filter_map(Result::ok)
-			.ready_filter(|((ty, _), _)| *ty == StateEventType::RoomMember)
-			.map(at!(1))
+			.state_type_pdus(shortstatehash, &StateEventType::RoomMember)
 			.ready_filter_map(|pdu| membership_filter(pdu, membership, not_membership))
 			.map(Event::into_format)
 			.collect()
@@ -61,6 +74,34 @@ pub(crate) async fn get_member_events_route(
 	})
 }
 
+/// Resolves a pagination token to the state of the room at that point, which
+/// is the state before the first event after the token; the event at the token
+/// itself has already happened. When the token is past the end of the timeline
+/// the current state is returned.
+async fn shortstatehash_at(
+	services: &Services,
+	room_id: &RoomId,
+	at: PduCount,
+) -> Result<ShortStateHash> {
+	let next_event_id = services
+		.timeline
+		.pdus(None, room_id, Some(at))
+		.ignore_err()
+		.map(|(_, pdu)| pdu.event_id)
+		.boxed()
+		.next()
+		.await;
+
+	match next_event_id {
+		| Some(event_id) => services.state.pdu_shortstatehash(&event_id).await,
+		| None =>
+			services
+				.state
+				.get_room_shortstatehash(room_id)
+				.await,
+	}
+}
+
 /// # `POST /_matrix/client/r0/rooms/{roomId}/joined_members`
 ///
 /// Lists all members of a room.
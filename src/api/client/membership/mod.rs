@@ -28,7 +28,7 @@
 	members::{get_member_events_route, joined_members_route},
 	unban::unban_user_route,
 };
-use crate::{Ruma, RumaResponse};
+use crate::{Ruma, RumaResponse, client::utils::audit};
 
 
 
@@ -151,6 +151,8 @@ async fn maybe_deactivate(services: &Services, user_id: &UserId, client_ip: IpAd
 			.full_deactivate(user_id)
 			.boxed()
 			.await?;
+
+		audit(services, None, "account deactivate banned room join", user_id.as_str());
 	}
 
 	Ok(())
@@ -17,7 +17,7 @@
 	},
 	ref_at,
 	utils::{
-		BoolExt, IterStream, ReadyExt,
+		BoolExt, FutureBoolExt, IterStream, ReadyExt,
 		result::{FlatOk, LogErr},
 		stream::{BroadbandExt, TryIgnore, WidebandExt},
 	},
@@ -61,8 +61,8 @@
 ///
 /// Allows paginating through room history.
 ///
-/// - Only works if the user is joined (TODO: always allow, but only show events
-///   where the user was joined, depending on `history_visibility`)
+/// - Only events the user can see according to `history_visibility` are
+///   returned; users who left the room only see history up to their departure.
 pub(crate) async fn get_message_events_route(
 	State(services): State<crate::State>,
 	body: Ruma<get_message_events::v3::Request>,
@@ -77,6 +77,8 @@ pub(crate) async fn get_message_events_route(
 		return Err!(Request(Forbidden("Room does not exist to this server")));
 	}
 
+	let horizon = history_horizon(&services, room_id, sender_user).await?;
+
 	let from: PduCount = body
 		.from
 		.as_deref()
@@ -87,6 +89,14 @@ pub(crate) async fn get_message_events_route(
 			| Direction::Backward => PduCount::max(),
 		});
 
+	// Users who left can only paginate backwards from the point they left.
+	let from = match body.dir {
+		| Direction::Backward => horizon
+			.map(|horizon| horizon.saturating_inc(Direction::Forward))
+			.map_or(from, |horizon| from.min(horizon)),
+		| Direction::Forward => from,
+	};
+
 	let to: Option<PduCount> = body.to.as_deref().map(str::parse).flat_ok();
 
 	let limit: usize = body
@@ -121,6 +131,7 @@ pub(crate) async fn get_message_events_route(
 
 	let events: Vec<_> = it
 		.ready_take_while(|(count, _)| Some(*count) != to)
+		.ready_take_while(|(count, _)| horizon.is_none_or(|horizon| *count <= horizon))
 		.ready_filter_map(|item| event_filter(item, filter))
 		.wide_filter_map(|item| ignored_filter(&services, item, sender_user))
 		.wide_filter_map(|item| visibility_filter(&services, item, sender_user))
@@ -167,6 +178,44 @@ pub(crate) async fn get_message_events_route(
 	})
 }
 
+/// Determines how much of the room's history the user may paginate through.
+/// Joined users, and anyone in a world_readable room, are unbounded. Users who
+/// left the room are bounded by their departure; the events themselves are
+/// still subject to `visibility_filter`. Users who never had any membership in
+/// the room are refused.
+pub(crate) async fn history_horizon(
+	services: &Services,
+	room_id: &RoomId,
+	user_id: &UserId,
+) -> Result<Option<PduCount>> {
+	let is_joined = services.state_cache.is_joined(user_id, room_id);
+
+	let is_world_readable = services.state_accessor.is_world_readable(room_id);
+
+	pin_mut!(is_joined, is_world_readable);
+	if is_joined.or(is_world_readable).await {
+		return Ok(None);
+	}
+
+	if let Ok(left_count) = services
+		.state_cache
+		.get_left_count(room_id, user_id)
+		.await
+	{
+		return Ok(Some(PduCount::Normal(left_count)));
+	}
+
+	if services
+		.state_cache
+		.is_invited(user_id, room_id)
+		.await
+	{
+		return Ok(None);
+	}
+
+	Err!(Request(Forbidden("You don't have permission to view this room.")))
+}
+
 pub(crate) async fn lazy_loading_witness<'a, I>(
 	services: &Services,
 	lazy_loading_context: &lazy_loading::Context<'_>,
@@ -12,6 +12,7 @@
 pub(super) mod events;
 pub(super) mod filter;
 pub(super) mod keys;
+pub(super) mod livekit;
 pub(super) mod media;
 pub(super) mod media_legacy;
 pub(super) mod membership;
@@ -33,6 +34,7 @@
 pub(super) mod state;
 pub(super) mod sync;
 pub(super) mod tag;
+pub(super) mod terms;
 pub(super) mod thirdparty;
 pub(super) mod threads;
 pub(super) mod to_device;
@@ -59,6 +61,7 @@
 pub(super) use events::*;
 pub(super) use filter::*;
 pub(super) use keys::*;
+pub(super) use livekit::*;
 pub(super) use media::*;
 pub(super) use media_legacy::*;
 pub(super) use membership::*;
@@ -80,6 +83,7 @@
 pub(super) use state::*;
 pub(super) use sync::*;
 pub(super) use tag::*;
+pub(super) use terms::*;
 pub(super) use thirdparty::*;
 pub(super) use threads::*;
 pub(super) use to_device::*;
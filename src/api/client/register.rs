@@ -12,11 +12,12 @@
 		uiaa::{AuthFlow, AuthType, UiaaInfo},
 	},
 };
+use serde_json::value::to_raw_value;
 use tuwunel_core::{Err, Error, Result, debug_info, debug_warn, info, utils};
 use tuwunel_service::users::{Register, device::generate_refresh_token};
 
 use super::SESSION_ID_LENGTH;
-use crate::Ruma;
+use crate::{Ruma, client::utils::audit};
 
 const RANDOM_USER_ID_LENGTH: usize = 10;
 
@@ -89,7 +90,7 @@ pub(crate) async fn get_register_available_route(
 		};
 
 	// Check if username is creative enough
-	if services.users.exists(&user_id).await {
+	if services.users.exists(&user_id).await || services.server_notices.is_system_user(&user_id) {
 		return Err!(Request(UserInUse("User ID is not available.")));
 	}
 
@@ -223,7 +224,11 @@ pub(crate) async fn register_route(
 				},
 			};
 
-			if services.users.exists(&proposed_user_id).await {
+			if services.users.exists(&proposed_user_id).await
+				|| services
+					.server_notices
+					.is_system_user(&proposed_user_id)
+			{
 				return Err!(Request(UserInUse("User ID is not available.")));
 			}
 
@@ -289,6 +294,17 @@ pub(crate) async fn register_route(
 		body.appservice_info.is_some() || is_guest
 	};
 
+	// Terms of service must be accepted
+	if services.terms.is_enabled() {
+		for flow in &mut uiaainfo.flows {
+			flow.stages
+				.retain(|stage| *stage != AuthType::Dummy);
+			flow.stages.push(AuthType::Terms);
+		}
+
+		uiaainfo.params = to_raw_value(&services.terms.params()).ok();
+	}
+
 	if !skip_auth {
 		match &body.auth {
 			| Some(auth) => {
@@ -340,6 +356,10 @@ pub(crate) async fn register_route(
 		})
 		.await?;
 
+	if !skip_auth && services.terms.is_enabled() {
+		services.terms.accept(&user_id);
+	}
+
 	if (!is_guest && body.inhibit_login)
 		|| body
 			.appservice_info
@@ -395,6 +415,7 @@ pub(crate) async fn register_route(
 			debug_info!("{notice}");
 		}
 
+		audit(&services, Some(&*user_id), "register", user_id.as_str());
 		if services.server.config.admin_room_notices {
 			services.admin.notice(&notice).await;
 		}
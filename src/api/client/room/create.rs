@@ -38,7 +38,10 @@
 };
 use tuwunel_service::{Services, appservice::RegistrationInfo, rooms::state::RoomMutexGuard};
 
-use crate::{Ruma, client::utils::invite_check};
+use crate::{
+	Ruma,
+	client::utils::{audit, invite_check},
+};
 
 /// # `POST /_matrix/client/v3/createRoom`
 ///
@@ -430,6 +433,7 @@ pub(crate) async fn create_room_route(
 
 	if body.visibility == room::Visibility::Public {
 		services.directory.set_public(&room_id);
+		audit(&services, Some(sender_user), "directory publish", room_id.as_str());
 
 		if services.server.config.admin_room_notices {
 			services
@@ -7,11 +7,16 @@
 	utils::{BoolExt, FutureBoolExt, TryFutureExtExt, future::OptionFutureExt},
 };
 
-use crate::{Ruma, client::is_ignored_pdu};
+use crate::{
+	Ruma,
+	client::{is_ignored_pdu, utils::audit},
+};
 
 /// # `GET /_matrix/client/r0/rooms/{roomId}/event/{eventId}`
 ///
 /// Gets a single event.
+///
+/// - The event must be visible to the user according to `history_visibility`
 pub(crate) async fn get_room_event_route(
 	State(services): State<crate::State>,
 	body: Ruma<get_room_event::v3::Request>,
@@ -46,6 +51,7 @@ pub(crate) async fn get_room_event_route(
 			pin_mut!(is_admin, can_redact);
 
 			if is_admin.or(can_redact).await {
+				audit(&services, Some(sender_user), "event unredacted", event_id.as_str());
 				services
 					.retention
 					.get_original_pdu(event_id)
@@ -71,8 +77,10 @@ pub(crate) async fn get_room_event_route(
 
 	let mut event = event?;
 
+	// Events the user is not allowed to see are indistinguishable from events
+	// which do not exist.
 	if !visible || is_ignored_pdu(&services, &event, body.sender_user()).await {
-		return Err!(Request(Forbidden("You don't have permission to view this event.")));
+		return Err!(Request(NotFound("Event {} not found.", event_id)));
 	}
 
 	debug_assert!(
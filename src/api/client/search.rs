@@ -20,7 +20,7 @@
 };
 use tuwunel_service::{Services, rooms::search::RoomQuery};
 
-use crate::Ruma;
+use crate::{Ruma, client::history_horizon};
 
 type RoomStates = BTreeMap<OwnedRoomId, RoomState>;
 type RoomState = Vec<Raw<AnyStateEvent>>;
@@ -33,8 +33,8 @@
 ///
 /// Searches rooms for messages.
 ///
-/// - Only works if the user is currently joined to the room (TODO: Respect
-///   history visibility)
+/// - Searches the rooms the user is joined to, or the rooms given in the filter
+///   whose history the user can see; results respect `history_visibility`
 pub(crate) async fn search_events_route(
 	State(services): State<crate::State>,
 	uri: Uri,
@@ -227,11 +227,12 @@ async fn check_room_visible(
 	let check_visible = search.filter.rooms.is_some();
 	let check_state = check_visible && search.include_state.is_some_and(is_true!());
 
-	let is_joined = !check_visible
-		|| services
-			.state_cache
-			.is_joined(user_id, room_id)
-			.await;
+	// Rooms given explicitly may also be rooms the user left or can peek into;
+	// the events themselves are filtered by their visibility to the user.
+	let history_visible = !check_visible
+		|| history_horizon(services, room_id, user_id)
+			.await
+			.is_ok();
 
 	let state_visible = !check_state
 		|| services
@@ -239,7 +240,7 @@ async fn check_room_visible(
 			.user_can_see_state_events(user_id, room_id)
 			.await;
 
-	if !is_joined || !state_visible {
+	if !history_visible || !state_visible {
 		return Err!(Request(Forbidden("You don't have permission to view {room_id:?}")));
 	}
 
@@ -1,52 +1,112 @@
 use std::str::FromStr;
 
-use jwt::{Algorithm, DecodingKey, Validation, decode};
+use futures::{FutureExt, StreamExt};
+use jwt::{Algorithm, DecodingKey, Validation, decode, decode_header};
 use ruma::{
-	OwnedUserId, UserId,
+	OwnedRoomId, OwnedUserId, UserId,
 	api::client::session::login::v3::{Request, Token},
 };
-use serde::Deserialize;
-use tuwunel_core::{Err, Result, at, config::JwtConfig, debug, err, jwt, warn};
-use tuwunel_service::Services;
+use serde_json::{Map, Value as JsonValue};
+use tuwunel_core::{Err, Result, config::JwtConfig, debug, err, info, jwt, warn};
+use tuwunel_service::{Services, users::Register};
 
 use crate::Ruma;
 
-#[derive(Debug, Deserialize)]
-struct Claim {
-	/// Subject is the localpart of the User MXID
-	sub: String,
-	preferred_username: Option<String>,
-}
+type Claims = Map<String, JsonValue>;
 
 pub(super) async fn handle_login(
 	services: &Services,
 	_body: &Ruma<Request>,
 	info: &Token,
 ) -> Result<OwnedUserId> {
-	let user_id = validate_user(services, &info.token)?;
+	let config = &services.config.jwt;
+	let claims = validate(services, &info.token).await?;
+	let user_id = user_id(services, &claims)?;
+	let displayname = config
+		.displayname_claim
+		.as_deref()
+		.and_then(|name| claim(&claims, name))
+		.and_then(JsonValue::as_str)
+		.filter(|displayname| !displayname.is_empty());
+
 	if !services.users.exists(&user_id).await {
-		let config = &services.config.jwt;
 		if !config.register_user {
 			return Err!(Request(NotFound("User {user_id} is not registered on this server.")));
 		}
 
 		services
 			.users
-			.create(&user_id, Some("*"), Some("jwt"))
+			.full_register(Register {
+				user_id: Some(&user_id),
+				password: Some("*"),
+				origin: Some("jwt"),
+				displayname,
+				..Default::default()
+			})
+			.boxed()
 			.await?;
+	} else if let Some(displayname) = displayname
+		&& services
+			.users
+			.displayname(&user_id)
+			.await
+			.ok()
+			.as_deref() != Some(displayname)
+	{
+		let all_joined_rooms: Vec<OwnedRoomId> = services
+			.state_cache
+			.rooms_joined(&user_id)
+			.map(ToOwned::to_owned)
+			.collect()
+			.await;
+
+		services
+			.users
+			.update_displayname(&user_id, Some(displayname), &all_joined_rooms)
+			.await;
+	}
+
+	// only perform admin add/remove check if admin_claim is set
+	if let Some(admin_claim) = config.admin_claim.as_deref() {
+		let is_jwt_admin = claim(&claims, admin_claim)
+			.is_some_and(|value| is_admin(value, config.admin_claim_value.as_deref()));
+
+		let is_tuwunel_admin = services.admin.user_is_admin(&user_id).await;
+		if is_jwt_admin && !is_tuwunel_admin {
+			info!("Granting admin rights to {user_id} from JWT claim");
+			services
+				.admin
+				.make_user_admin(&user_id)
+				.boxed()
+				.await?;
+		} else if !is_jwt_admin && is_tuwunel_admin {
+			info!("Revoking admin rights of {user_id} from JWT claim");
+			services.admin.revoke_admin(&user_id).await?;
+		}
 	}
 
 	Ok(user_id)
 }
 
-pub(crate) fn validate_user(services: &Services, token: &str) -> Result<OwnedUserId> {
-	let config = &services.config.jwt;
-	if !config.enable {
-		return Err!(Request(Unauthorized("JWT login is not enabled.")));
-	}
+pub(crate) async fn validate_user(services: &Services, token: &str) -> Result<OwnedUserId> {
+	let claims = validate(services, token).await?;
+
+	user_id(services, &claims)
+}
+
+fn user_id(services: &Services, claims: &Claims) -> Result<OwnedUserId> {
+	let local = match services.config.jwt.localpart_claim.as_deref() {
+		| Some(name) => claim(claims, name),
+		| None => claims
+			.get("preferred_username")
+			.or_else(|| claims.get("sub")),
+	};
+
+	let local = local
+		.and_then(JsonValue::as_str)
+		.ok_or_else(|| err!(Request(Forbidden("JWT token does not claim a username."))))?
+		.to_lowercase();
 
-	let claim = validate(config, token)?;
-	let local = claim.preferred_username.unwrap_or(claim.sub).to_lowercase();
 	let server = &services.server.name;
 	let user_id = UserId::parse_with_server_name(local, server).map_err(|e| {
 		err!(Request(InvalidUsername("JWT subject is not a valid user MXID: {e}")))
@@ -55,14 +115,50 @@ pub(crate) fn validate_user(services: &Services, token: &str) -> Result<OwnedUse
 	Ok(user_id)
 }
 
-fn validate(config: &JwtConfig, token: &str) -> Result<Claim> {
-	let verifier = init_verifier(config)?;
-	let validator = init_validator(config)?;
-	decode::<Claim>(token, &verifier, &validator)
-		.map(|decoded| (decoded.header, decoded.claims))
-		.inspect(|(head, claim)| debug!(?head, ?claim, "JWT token decoded"))
+async fn validate(services: &Services, token: &str) -> Result<Claims> {
+	let config = &services.config.jwt;
+	if !config.enable {
+		return Err!(Request(Unauthorized("JWT login is not enabled.")));
+	}
+
+	let (verifier, alg) = if config.jwks_url.is_some() {
+		let header = decode_header(token)
+			.map_err(|e| err!(Request(Forbidden("Invalid JWT token: {e}"))))?;
+
+		services
+			.jwks
+			.get(header.kid.as_deref())
+			.await?
+	} else {
+		(init_verifier(config)?, None)
+	};
+
+	let validator = init_validator(config, alg)?;
+	decode::<Claims>(token, &verifier, &validator)
+		.inspect(|decoded| {
+			debug!(head = ?decoded.header, claims = ?decoded.claims, "JWT token decoded");
+		})
 		.map_err(|e| err!(Request(Forbidden("Invalid JWT token: {e}"))))
-		.map(at!(1))
+		.map(|decoded| decoded.claims)
+}
+
+/// The claim named by its dot-separated path.
+fn claim<'a>(claims: &'a Claims, path: &str) -> Option<&'a JsonValue> {
+	let mut parts = path.split('.');
+	let first = claims.get(parts.next()?)?;
+
+	parts.try_fold(first, |value, part| value.get(part))
+}
+
+fn is_admin(value: &JsonValue, admin_value: Option<&str>) -> bool {
+	match (value, admin_value) {
+		| (JsonValue::Bool(is_admin), None) => *is_admin,
+		| (JsonValue::String(value), Some(admin_value)) => value == admin_value,
+		| (JsonValue::Array(values), Some(admin_value)) => values
+			.iter()
+			.any(|value| value.as_str() == Some(admin_value)),
+		| _ => false,
+	}
 }
 
 fn init_verifier(config: &JwtConfig) -> Result<DecodingKey> {
@@ -87,14 +183,22 @@ fn init_verifier(config: &JwtConfig) -> Result<DecodingKey> {
 	})
 }
 
-fn init_validator(config: &JwtConfig) -> Result<Validation> {
-	let alg = config.algorithm.as_str();
-	let alg = Algorithm::from_str(alg).map_err(|e| {
-		err!(Config("jwt.algorithm", "JWT algorithm is not recognized or configured {e}"))
-	})?;
+/// The validator of tokens signed with `alg`, the configured algorithm by
+/// default.
+fn init_validator(config: &JwtConfig, alg: Option<Algorithm>) -> Result<Validation> {
+	let alg = match alg {
+		| Some(alg) => alg,
+		| None => Algorithm::from_str(config.algorithm.as_str()).map_err(|e| {
+			err!(Config("jwt.algorithm", "JWT algorithm is not recognized or configured {e}"))
+		})?,
+	};
 
 	let mut validator = Validation::new(alg);
-	let mut required_spec_claims: Vec<_> = ["sub"].into();
+	let mut required_spec_claims: Vec<_> = Vec::new();
+
+	if config.localpart_claim.is_none() {
+		required_spec_claims.push("sub");
+	}
 
 	validator.validate_exp = config.validate_exp;
 	if config.require_exp {
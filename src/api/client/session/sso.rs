@@ -39,7 +39,7 @@
 use url::Url;
 
 use super::TOKEN_LENGTH;
-use crate::Ruma;
+use crate::{Ruma, client::utils::audit};
 
 /// Grant phase query string.
 #[derive(Debug, Serialize)]
@@ -504,6 +504,7 @@ async fn register_user(
 		format!("New user \"{user_id}\" registered on this server via {idp_name} ({idp_id})",);
 
 	info!("{notice}");
+	audit(services, Some(user_id), "register sso", user_id.as_str());
 	if services.server.config.admin_room_notices {
 		services.admin.notice(&notice).await;
 	}
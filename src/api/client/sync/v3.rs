@@ -68,7 +68,10 @@
 };
 
 use super::{load_timeline, share_encrypted_room};
-use crate::{Ruma, client::ignored_filter};
+use crate::{
+	Ruma,
+	client::{ignored_filter, visibility_filter},
+};
 
 #[derive(Default)]
 struct StateChanges {
@@ -759,6 +762,7 @@ async fn load_left_room(
 		.into_iter()
 		.stream()
 		.wide_filter_map(|item| ignored_filter(services, item, sender_user))
+		.wide_filter_map(|item| visibility_filter(services, item, sender_user))
 		.map(at!(1))
 		.ready_filter(|pdu| filter.room.timeline.matches(pdu))
 		.take(timeline_limit)
@@ -0,0 +1,134 @@
+use axum::extract::State;
+use ruma::api::client::uiaa::{AuthFlow, AuthType, UiaaInfo};
+use serde_json::value::to_raw_value;
+use tuwunel_core::{Err, Error, Result, utils};
+
+use super::SESSION_ID_LENGTH;
+use crate::Ruma;
+
+/// # `GET /_matrix/client/unstable/org.tuwunel/terms`
+///
+/// Returns the policy documents and whether the user accepted their current
+/// version.
+pub(crate) async fn get_terms_route(
+	State(services): State<crate::State>,
+	body: Ruma<get_terms::Request>,
+) -> Result<get_terms::Response> {
+	let sender_user = body.sender_user();
+
+	let mut terms = services.terms.params();
+	terms["version"] = services.terms.version().into();
+	terms["accepted"] = services
+		.terms
+		.has_consented(sender_user)
+		.await
+		.into();
+
+	if let Ok(consent) = services.terms.consent(sender_user).await {
+		terms["accepted_version"] = consent.version.into();
+		terms["accepted_at"] = consent.accepted_at.into();
+	}
+
+	Ok(get_terms::Response { terms })
+}
+
+/// # `POST /_matrix/client/unstable/org.tuwunel/terms`
+///
+/// Accepts the current policy documents through the `m.login.terms`
+/// user-interactive authentication stage.
+pub(crate) async fn accept_terms_route(
+	State(services): State<crate::State>,
+	body: Ruma<accept_terms::Request>,
+) -> Result<accept_terms::Response> {
+	if !services.terms.is_enabled() {
+		return Err!(Request(NotFound("No terms of service are configured.")));
+	}
+
+	let sender_user = body.sender_user();
+	let sender_device = body.sender_device()?;
+
+	let mut uiaainfo = UiaaInfo {
+		flows: vec![AuthFlow::new(vec![AuthType::Terms])],
+		params: to_raw_value(&services.terms.params()).ok(),
+		..Default::default()
+	};
+
+	match &body.auth {
+		| Some(auth) => {
+			let (worked, uiaainfo) = services
+				.uiaa
+				.try_auth(sender_user, sender_device, auth, &uiaainfo)
+				.await?;
+
+			if !worked {
+				return Err(Error::Uiaa(uiaainfo));
+			}
+		},
+		| None => match &body.json_body {
+			| Some(json) => {
+				uiaainfo.session = Some(utils::random_string(SESSION_ID_LENGTH));
+				services
+					.uiaa
+					.create(sender_user, sender_device, &uiaainfo, json);
+
+				return Err(Error::Uiaa(uiaainfo));
+			},
+			| None => return Err!(Request(NotJson("JSON body is not valid"))),
+		},
+	}
+
+	services.terms.accept(sender_user);
+
+	Ok(accept_terms::Response {})
+}
+
+/// `GET /_matrix/client/unstable/org.tuwunel/terms`
+pub(crate) mod get_terms {
+	use ruma::api::{Metadata, metadata, request, response};
+	use serde_json::Value as JsonValue;
+
+	const METADATA: Metadata = metadata! {
+		method: GET,
+		rate_limited: false,
+		authentication: AccessToken,
+		history: {
+			unstable => "/_matrix/client/unstable/org.tuwunel/terms",
+		}
+	};
+
+	#[request]
+	pub(crate) struct Request {}
+
+	#[response]
+	pub(crate) struct Response {
+		/// The `m.login.terms` parameters, with the current version and the
+		/// acceptance of the user.
+		#[ruma_api(body)]
+		pub(crate) terms: JsonValue,
+	}
+}
+
+/// `POST /_matrix/client/unstable/org.tuwunel/terms`
+pub(crate) mod accept_terms {
+	use ruma::api::{Metadata, client::uiaa::AuthData, metadata, request, response};
+
+	const METADATA: Metadata = metadata! {
+		method: POST,
+		rate_limited: false,
+		authentication: AccessToken,
+		history: {
+			unstable => "/_matrix/client/unstable/org.tuwunel/terms",
+		}
+	};
+
+	#[request]
+	pub(crate) struct Request {
+		/// Additional authentication information for the user-interactive
+		/// authentication API.
+		#[serde(skip_serializing_if = "Option::is_none")]
+		pub(crate) auth: Option<AuthData>,
+	}
+
+	#[response]
+	pub(crate) struct Response {}
+}
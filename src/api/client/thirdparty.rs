@@ -1,16 +1,96 @@
-use std::collections::BTreeMap;
-
-use ruma::api::client::thirdparty::get_protocols;
+use axum::extract::State;
+use ruma::api::client::thirdparty::{
+	get_location_for_protocol, get_location_for_room_alias, get_protocol, get_protocols,
+	get_user_for_protocol, get_user_for_user_id,
+};
 use tuwunel_core::Result;
 
 use crate::Ruma;
 
-/// # `GET /_matrix/client/r0/thirdparty/protocols`
+/// # `GET /_matrix/client/v3/thirdparty/protocols`
 ///
-/// TODO: Fetches all metadata about protocols supported by the homeserver.
+/// Fetches all metadata about protocols supported by the homeserver, as
+/// provided by its appservices.
 pub(crate) async fn get_protocols_route(
+	State(services): State<crate::State>,
 	_body: Ruma<get_protocols::v3::Request>,
 ) -> Result<get_protocols::v3::Response> {
-	// TODO
-	Ok(get_protocols::v3::Response { protocols: BTreeMap::new() })
+	let protocols = services.appservice.get_protocols().await;
+
+	Ok(get_protocols::v3::Response { protocols })
+}
+
+/// # `GET /_matrix/client/v3/thirdparty/protocol/{protocol}`
+///
+/// Fetches the metadata about a protocol supported by the homeserver.
+pub(crate) async fn get_protocol_route(
+	State(services): State<crate::State>,
+	body: Ruma<get_protocol::v3::Request>,
+) -> Result<get_protocol::v3::Response> {
+	let protocol = services
+		.appservice
+		.get_protocol(&body.protocol)
+		.await?;
+
+	Ok(get_protocol::v3::Response { protocol })
+}
+
+/// # `GET /_matrix/client/v3/thirdparty/location/{protocol}`
+///
+/// Looks up the Matrix room aliases of third-party locations.
+pub(crate) async fn get_location_for_protocol_route(
+	State(services): State<crate::State>,
+	body: Ruma<get_location_for_protocol::v3::Request>,
+) -> Result<get_location_for_protocol::v3::Response> {
+	let locations = services
+		.appservice
+		.query_locations(&body.protocol, &body.fields)
+		.await?;
+
+	Ok(get_location_for_protocol::v3::Response { locations })
+}
+
+/// # `GET /_matrix/client/v3/thirdparty/location`
+///
+/// Looks up the third-party locations bridged to a Matrix room alias.
+pub(crate) async fn get_location_for_room_alias_route(
+	State(services): State<crate::State>,
+	body: Ruma<get_location_for_room_alias::v3::Request>,
+) -> Result<get_location_for_room_alias::v3::Response> {
+	let locations = services
+		.appservice
+		.query_locations_by_alias(&body.alias)
+		.await;
+
+	Ok(get_location_for_room_alias::v3::Response { locations })
+}
+
+/// # `GET /_matrix/client/v3/thirdparty/user/{protocol}`
+///
+/// Looks up the Matrix user IDs of third-party users.
+pub(crate) async fn get_user_for_protocol_route(
+	State(services): State<crate::State>,
+	body: Ruma<get_user_for_protocol::v3::Request>,
+) -> Result<get_user_for_protocol::v3::Response> {
+	let users = services
+		.appservice
+		.query_users(&body.protocol, &body.fields)
+		.await?;
+
+	Ok(get_user_for_protocol::v3::Response { users })
+}
+
+/// # `GET /_matrix/client/v3/thirdparty/user`
+///
+/// Looks up the third-party users bridged to a Matrix user ID.
+pub(crate) async fn get_user_for_user_id_route(
+	State(services): State<crate::State>,
+	body: Ruma<get_user_for_user_id::v3::Request>,
+) -> Result<get_user_for_user_id::v3::Response> {
+	let users = services
+		.appservice
+		.query_users_by_id(&body.userid)
+		.await;
+
+	Ok(get_user_for_user_id::v3::Response { users })
 }
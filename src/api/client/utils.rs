@@ -1,6 +1,17 @@
 use ruma::{RoomId, UserId};
 use tuwunel_core::{Err, Result, warn};
-use tuwunel_service::Services;
+use tuwunel_service::{Services, audit};
+
+/// Records an administratively relevant action of a client in the audit log.
+pub(crate) fn audit(services: &Services, actor: Option<&UserId>, action: &str, target: &str) {
+	services.audit.record(audit::Entry {
+		actor: actor.map(ToOwned::to_owned),
+		source: "client".to_owned(),
+		action: action.to_owned(),
+		target: Some(target.to_owned()),
+		..Default::default()
+	});
+}
 
 pub(crate) async fn invite_check(
 	services: &Services,
@@ -26,7 +26,7 @@ pub(crate) async fn well_known_client(
 	// Add RTC transport configuration if available (MSC4143 / Element Call)
 	// Element Call has evolved through several versions with different field
 	// expectations
-	let rtc_foci = services
+	let mut rtc_foci: Vec<_> = services
 		.server
 		.config
 		.well_known
@@ -51,6 +51,17 @@ pub(crate) async fn well_known_client(
 		})
 		.inspect_err(inspect_log)?;
 
+	// Advertise the built-in LiveKit service unless transports are configured
+	if rtc_foci.is_empty() && services.livekit.is_enabled() {
+		let service_url =
+			format!("{}/_tuwunel/livekit", homeserver.base_url.trim_end_matches('/'));
+		let transport = [("livekit_service_url".to_owned(), service_url.into())]
+			.into_iter()
+			.collect();
+
+		rtc_foci.push(RtcFocusInfo::new("livekit", transport)?);
+	}
+
 	Ok(discover_homeserver::Response {
 		rtc_foci,
 		..discover_homeserver::Response::new(homeserver)
@@ -4,6 +4,7 @@
 
 pub mod client;
 pub mod router;
+pub mod scim;
 pub mod server;
 
 use log as _;
@@ -19,7 +19,7 @@
 pub(super) use self::{
 	args::Args as Ruma, auth::auth_uiaa, response::RumaResponse, state::State,
 };
-use crate::{client, server};
+use crate::{client, scim, server};
 
 pub fn build(router: Router<State>, server: &Server) -> Router<State> {
 	let config = &server.config;
@@ -70,6 +70,8 @@ pub fn build(router: Router<State>, server: &Server) -> Router<State> {
 		.ruma_route(&client::set_room_account_data_route)
 		.ruma_route(&client::get_global_account_data_route)
 		.ruma_route(&client::get_room_account_data_route)
+		.ruma_route(&client::delete_global_account_data_route)
+		.ruma_route(&client::delete_room_account_data_route)
 		.ruma_route(&client::set_displayname_route)
 		.ruma_route(&client::get_displayname_route)
 		.ruma_route(&client::set_avatar_url_route)
@@ -129,6 +131,11 @@ pub fn build(router: Router<State>, server: &Server) -> Router<State> {
 		.ruma_route(&client::search_users_route)
 		.ruma_route(&client::get_member_events_route)
 		.ruma_route(&client::get_protocols_route)
+		.ruma_route(&client::get_protocol_route)
+		.ruma_route(&client::get_location_for_protocol_route)
+		.ruma_route(&client::get_location_for_room_alias_route)
+		.ruma_route(&client::get_user_for_protocol_route)
+		.ruma_route(&client::get_user_for_user_id_route)
 		.ruma_route(&client::send_message_event_route)
 		.ruma_route(&client::send_state_event_for_key_route)
 		.ruma_route(&client::get_state_events_route)
@@ -212,11 +219,40 @@ pub fn build(router: Router<State>, server: &Server) -> Router<State> {
 		.route(
 			"/_matrix/client/unstable/org.tuwunel/rooms/{room_id}/delete_events",
 			post(client::delete_events_route),
-		);
+		)
+		.ruma_route(&client::get_terms_route)
+		.ruma_route(&client::accept_terms_route);
 
 	// SS endpoint not related to federation
 	router = router.ruma_route(&server::get_openid_userinfo_route);
 
+	if config.scim.enable {
+		router = router
+			.route("/scim/v2/ServiceProviderConfig", get(scim::get_service_provider_config_route))
+			.route("/scim/v2/ResourceTypes", get(scim::get_resource_types_route))
+			.route("/scim/v2/Schemas", get(scim::get_schemas_route))
+			.route("/scim/v2/Users", get(scim::list_users_route).post(scim::create_user_route))
+			.route(
+				"/scim/v2/Users/{id}",
+				get(scim::get_user_route)
+					.put(scim::replace_user_route)
+					.patch(scim::patch_user_route)
+					.delete(scim::delete_user_route),
+			)
+			.route("/scim/v2/Groups", get(scim::list_groups_route).post(scim::create_group_route))
+			.route(
+				"/scim/v2/Groups/{id}",
+				get(scim::get_group_route)
+					.put(scim::replace_group_route)
+					.patch(scim::patch_group_route)
+					.delete(scim::delete_group_route),
+			);
+	}
+
+	if config.livekit.enable {
+		router = router.route("/_tuwunel/livekit/sfu/get", post(client::livekit_sfu_route));
+	}
+
 	if config.allow_federation {
 		router = router
 			.ruma_route(&server::get_server_version_route)
@@ -17,28 +17,40 @@
 	},
 	pin_mut,
 };
+use http::StatusCode;
 use ruma::{
 	CanonicalJsonValue, OwnedDeviceId, OwnedServerName, OwnedUserId,
 	api::{
 		AuthScheme, IncomingRequest, Metadata,
 		client::{
+			account::{deactivate, whoami},
+			config::get_global_account_data,
 			directory::get_public_rooms,
-			error::ErrorKind,
+			discovery::get_capabilities,
+			error::{Error as RumaError, ErrorBody, ErrorKind},
+			filter::{create_filter, get_filter},
+			keys::{claim_keys, get_keys, upload_keys},
 			profile::{
 				get_avatar_url, get_display_name, get_profile, get_profile_field,
 				get_timezone_key,
 			},
+			push::get_pushrules_all,
+			session::{logout, logout_all},
+			sync::sync_events,
+			to_device::send_event_to_device,
 			voip::get_turn_server_info,
 		},
 		federation::openid::get_openid_userinfo,
 	},
 };
+use serde_json::json;
 use tuwunel_core::{Err, Error, Result, is_less_than, utils::result::LogDebugErr};
 use tuwunel_service::{Services, appservice::RegistrationInfo};
 
 pub(crate) use self::uiaa::auth_uiaa;
 use self::{appservice::auth_appservice, server::auth_server};
 use super::request::Request;
+use crate::client::{accept_terms, get_terms};
 
 enum Token {
 	Appservice(Box<RegistrationInfo>),
@@ -97,6 +109,14 @@ pub(super) async fn auth(
 		check_auth_still_required(services, metadata, &token)?;
 	}
 
+	if let User((user_id, ..)) = &token
+		&& metadata.authentication == AccessToken
+		&& !is_consent_exempt(metadata)
+		&& services.terms.is_blocked(user_id).await
+	{
+		return Err(consent_not_given(services.terms.consent_uri()));
+	}
+
 	match (metadata.authentication, token) {
 		| (AuthScheme::None, Invalid)
 			if request.query.access_token.is_some()
@@ -203,6 +223,45 @@ fn check_auth_still_required(services: &Services, metadata: &Metadata, token: &T
 	}
 }
 
+/// Endpoints a client needs to keep running, and to let its user accept the
+/// terms of service, before the user accepted them.
+fn is_consent_exempt(metadata: &Metadata) -> bool {
+	matches!(
+		*metadata,
+		sync_events::v3::Request::METADATA
+			| sync_events::v5::Request::METADATA
+			| whoami::v3::Request::METADATA
+			| logout::v3::Request::METADATA
+			| logout_all::v3::Request::METADATA
+			| deactivate::v3::Request::METADATA
+			| get_capabilities::v3::Request::METADATA
+			| get_global_account_data::v3::Request::METADATA
+			| get_pushrules_all::v3::Request::METADATA
+			| create_filter::v3::Request::METADATA
+			| get_filter::v3::Request::METADATA
+			| upload_keys::v3::Request::METADATA
+			| get_keys::v3::Request::METADATA
+			| claim_keys::v3::Request::METADATA
+			| send_event_to_device::v3::Request::METADATA
+			| get_terms::Request::METADATA
+			| accept_terms::Request::METADATA
+	)
+}
+
+/// The `M_CONSENT_NOT_GIVEN` error pointing the user to the policies to accept.
+fn consent_not_given(consent_uri: Option<String>) -> Error {
+	let mut body = json!({
+		"errcode": "M_CONSENT_NOT_GIVEN",
+		"error": "You must accept the terms of service to continue using this server.",
+	});
+
+	if let Some(consent_uri) = consent_uri {
+		body["consent_uri"] = consent_uri.into();
+	}
+
+	Error::Ruma(RumaError::new(StatusCode::FORBIDDEN, ErrorBody::Json(body)))
+}
+
 async fn find_token(services: &Services, token: Option<&str>) -> Result<Token> {
 	let Some(token) = token else {
 		return Ok(Token::None);
@@ -226,3 +285,43 @@ async fn find_token(services: &Services, token: Option<&str>) -> Result<Token> {
 		| _ => Ok(Token::Invalid),
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use ruma::api::{
+		IncomingRequest, OutgoingResponse,
+		client::{message::send_message_event, sync::sync_events, uiaa::UiaaResponse},
+	};
+	use serde_json::{Value as JsonValue, json};
+
+	use super::{consent_not_given, is_consent_exempt};
+	use crate::client::accept_terms;
+
+	#[test]
+	fn consent_exempt_endpoints() {
+		assert!(is_consent_exempt(&sync_events::v3::Request::METADATA));
+		assert!(!is_consent_exempt(&send_message_event::v3::Request::METADATA));
+		assert!(is_consent_exempt(&accept_terms::Request::METADATA));
+	}
+
+	#[test]
+	fn consent_error_response() {
+		let uri = "https://example.org/terms.html".to_owned();
+		let response = UiaaResponse::from(consent_not_given(Some(uri)))
+			.try_into_http_response::<Vec<u8>>()
+			.expect("error response");
+
+		assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+
+		let body: JsonValue = serde_json::from_slice(response.body()).expect("json body");
+		assert_eq!(body["errcode"], json!("M_CONSENT_NOT_GIVEN"));
+		assert_eq!(body["consent_uri"], json!("https://example.org/terms.html"));
+
+		let response = UiaaResponse::from(consent_not_given(None))
+			.try_into_http_response::<Vec<u8>>()
+			.expect("error response");
+
+		let body: JsonValue = serde_json::from_slice(response.body()).expect("json body");
+		assert!(body.get("consent_uri").is_none());
+	}
+}
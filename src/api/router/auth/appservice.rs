@@ -1,4 +1,4 @@
-use ruma::{OwnedUserId, UserId};
+use ruma::{OwnedDeviceId, OwnedUserId, UserId};
 use tuwunel_core::{Err, Result};
 use tuwunel_service::{Services, appservice::RegistrationInfo};
 
@@ -29,8 +29,26 @@ pub(super) async fn auth_appservice(
 		return Err!(Request(Exclusive("User is not in namespace.")));
 	}
 
+	// MSC3202: the appservice may also masquerade as a device of the user.
+	let sender_device = match request.query.device_id.as_deref() {
+		| None => None,
+		| Some(device_id) => {
+			let device_id: OwnedDeviceId = device_id.into();
+			if !services
+				.users
+				.device_exists(&user_id, &device_id)
+				.await
+			{
+				return Err!(Request(Forbidden("Device does not exist.")));
+			}
+
+			Some(device_id)
+		},
+	};
+
 	Ok(Auth {
 		sender_user: Some(user_id),
+		sender_device,
 		appservice_info: Some(*info),
 		..Auth::default()
 	})
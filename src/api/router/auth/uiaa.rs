@@ -37,7 +37,7 @@ pub(crate) async fn auth_uiaa<T>(services: &Services, body: &Ruma<T>) -> Result<
 		.transpose()?
 	{
 		| Some(AuthData::Jwt(Jwt { ref token, .. })) => {
-			let sender_user = jwt::validate_user(services, token)?;
+			let sender_user = jwt::validate_user(services, token).await?;
 			if !services.users.exists(&sender_user).await {
 				return Err!(Request(NotFound("User {sender_user} is not registered.")));
 			}
@@ -12,9 +12,12 @@
 pub(super) struct QueryParams {
 	pub(super) access_token: Option<String>,
 	pub(super) user_id: Option<UserId>,
+	#[serde(alias = "org.matrix.msc3202.device_id")]
+	pub(super) device_id: Option<DeviceId>,
 }
 
 pub(super) type UserId = SmallString<[u8; 48]>;
+pub(super) type DeviceId = SmallString<[u8; 32]>;
 
 #[derive(Debug)]
 pub(super) struct Request {
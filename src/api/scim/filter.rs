@@ -0,0 +1,495 @@
+//! Filter expressions and attribute paths (RFC 7644 section 3.4.2.2 and 3.5.2).
+
+use std::{cmp::Ordering, iter::Peekable, vec::IntoIter};
+
+use serde_json::Value as JsonValue;
+use tuwunel_core::{Err, Result, err};
+
+/// A parsed filter expression.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Filter {
+	Present(AttrPath),
+	Compare(AttrPath, CompareOp, JsonValue),
+	ValuePath(AttrPath, Box<Self>),
+	Not(Box<Self>),
+	And(Box<Self>, Box<Self>),
+	Or(Box<Self>, Box<Self>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum CompareOp {
+	Eq,
+	Ne,
+	Co,
+	Sw,
+	Ew,
+	Gt,
+	Lt,
+	Ge,
+	Le,
+}
+
+/// An attribute with an optional sub-attribute. Names are matched without
+/// regard to case, and a leading schema URN is dropped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct AttrPath {
+	pub(super) attr: String,
+	pub(super) sub_attr: Option<String>,
+}
+
+/// The target of a PATCH operation: an attribute, optionally narrowed down to
+/// the elements of a multi-valued attribute matching a filter.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Path {
+	pub(super) attr: AttrPath,
+	pub(super) filter: Option<Filter>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+	Open,
+	Close,
+	OpenBracket,
+	CloseBracket,
+	Word(String),
+	Value(JsonValue),
+}
+
+type Tokens = Peekable<IntoIter<Token>>;
+
+impl Filter {
+	pub(super) fn parse(input: &str) -> Result<Self> {
+		let mut tokens = tokenize(input)?.into_iter().peekable();
+		let filter = parse_or(&mut tokens)?;
+		if let Some(token) = tokens.next() {
+			return Err!(Request(InvalidParam("Unexpected {token:?} in filter.")));
+		}
+
+		Ok(filter)
+	}
+
+	/// Whether the resource matches the filter.
+	pub(super) fn matches(&self, resource: &JsonValue) -> bool {
+		match self {
+			| Self::Present(path) => path
+				.values(resource)
+				.into_iter()
+				.any(|value| !value.is_null()),
+			| Self::Compare(path, CompareOp::Ne, value) => !path
+				.values(resource)
+				.into_iter()
+				.any(|lhs| CompareOp::Eq.compare(lhs, value)),
+			| Self::Compare(path, op, value) => path
+				.values(resource)
+				.into_iter()
+				.any(|lhs| op.compare(lhs, value)),
+			| Self::ValuePath(path, filter) => path
+				.elements(resource)
+				.into_iter()
+				.any(|element| filter.matches(element)),
+			| Self::Not(filter) => !filter.matches(resource),
+			| Self::And(lhs, rhs) => lhs.matches(resource) && rhs.matches(resource),
+			| Self::Or(lhs, rhs) => lhs.matches(resource) || rhs.matches(resource),
+		}
+	}
+
+	/// The value the filter requires for a single-valued attribute, when it is
+	/// nothing but an equality test on it.
+	pub(super) fn equality_on(&self, attr: &str) -> Option<&str> {
+		match self {
+			| Self::Compare(path, CompareOp::Eq, JsonValue::String(value))
+				if path.sub_attr.is_none() && path.attr.eq_ignore_ascii_case(attr) =>
+				Some(value),
+			| _ => None,
+		}
+	}
+}
+
+impl CompareOp {
+	fn parse(op: &str) -> Result<Self> {
+		Ok(match op.to_ascii_lowercase().as_str() {
+			| "eq" => Self::Eq,
+			| "ne" => Self::Ne,
+			| "co" => Self::Co,
+			| "sw" => Self::Sw,
+			| "ew" => Self::Ew,
+			| "gt" => Self::Gt,
+			| "lt" => Self::Lt,
+			| "ge" => Self::Ge,
+			| "le" => Self::Le,
+			| _ => return Err!(Request(InvalidParam("Unknown filter operator {op:?}."))),
+		})
+	}
+
+	/// Strings compare without regard to case, as none of the supported
+	/// attributes is case-exact.
+	fn compare(self, lhs: &JsonValue, rhs: &JsonValue) -> bool {
+		let ordering = match (lhs, rhs) {
+			| (JsonValue::String(lhs), JsonValue::String(rhs)) => {
+				let (lhs, rhs) = (lhs.to_lowercase(), rhs.to_lowercase());
+				match self {
+					| Self::Co => return lhs.contains(&rhs),
+					| Self::Sw => return lhs.starts_with(&rhs),
+					| Self::Ew => return lhs.ends_with(&rhs),
+					| _ => Some(lhs.cmp(&rhs)),
+				}
+			},
+			| (JsonValue::Number(lhs), JsonValue::Number(rhs)) => lhs
+				.as_f64()
+				.zip(rhs.as_f64())
+				.and_then(|(lhs, rhs)| lhs.partial_cmp(&rhs)),
+			| (lhs, rhs) => (lhs == rhs).then_some(Ordering::Equal),
+		};
+
+		match self {
+			| Self::Eq => ordering == Some(Ordering::Equal),
+			| Self::Ne => ordering != Some(Ordering::Equal),
+			| Self::Gt => ordering == Some(Ordering::Greater),
+			| Self::Lt => ordering == Some(Ordering::Less),
+			| Self::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+			| Self::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+			| Self::Co | Self::Sw | Self::Ew => false,
+		}
+	}
+}
+
+impl AttrPath {
+	fn parse(path: &str) -> Result<Self> {
+		let path = path
+			.rsplit_once(':')
+			.map_or(path, |(_urn, path)| path);
+
+		let (attr, sub_attr) = match path.split_once('.') {
+			| Some((attr, sub_attr)) => (attr, Some(sub_attr.to_owned())),
+			| None => (path, None),
+		};
+
+		if attr.is_empty() || sub_attr.as_ref().is_some_and(String::is_empty) {
+			return Err!(Request(InvalidParam("Invalid attribute path {path:?}.")));
+		}
+
+		Ok(Self { attr: attr.to_owned(), sub_attr })
+	}
+
+	/// The values of the attribute in the resource. Elements of multi-valued
+	/// attributes count individually, through their `value` sub-attribute when
+	/// they are complex.
+	pub(super) fn values<'a>(&self, resource: &'a JsonValue) -> Vec<&'a JsonValue> {
+		let Some(value) = get(resource, &self.attr) else {
+			return Vec::new();
+		};
+
+		let sub_attr = self.sub_attr.as_deref();
+		match value {
+			| JsonValue::Array(elements) => elements
+				.iter()
+				.filter_map(|element| match element {
+					| JsonValue::Object(_) => get(element, sub_attr.unwrap_or("value")),
+					| element => sub_attr.is_none().then_some(element),
+				})
+				.collect(),
+			| value => sub_attr
+				.map_or(Some(value), |sub_attr| get(value, sub_attr))
+				.into_iter()
+				.collect(),
+		}
+	}
+
+	/// The elements of a multi-valued attribute in the resource.
+	fn elements<'a>(&self, resource: &'a JsonValue) -> Vec<&'a JsonValue> {
+		match get(resource, &self.attr) {
+			| Some(JsonValue::Array(elements)) => elements.iter().collect(),
+			| Some(value) => vec![value],
+			| None => Vec::new(),
+		}
+	}
+
+	/// Whether this is the given attribute, without sub-attribute.
+	pub(super) fn is(&self, attr: &str) -> bool {
+		self.sub_attr.is_none() && self.attr.eq_ignore_ascii_case(attr)
+	}
+}
+
+impl Path {
+	pub(super) fn parse(input: &str) -> Result<Self> {
+		let mut tokens = tokenize(input)?.into_iter().peekable();
+		let mut attr = attr_path(&mut tokens)?;
+		let filter = if tokens.next_if_eq(&Token::OpenBracket).is_some() {
+			let filter = parse_or(&mut tokens)?;
+			expect(&mut tokens, &Token::CloseBracket)?;
+			Some(filter)
+		} else {
+			None
+		};
+
+		// A sub-attribute may follow the filter, e.g. `emails[type eq "work"].value`.
+		if filter.is_some()
+			&& attr.sub_attr.is_none()
+			&& let Some(Token::Word(word)) = tokens.peek()
+			&& let Some(sub_attr) = word.strip_prefix('.')
+		{
+			attr.sub_attr = Some(sub_attr.to_owned());
+			tokens.next();
+		}
+
+		if let Some(token) = tokens.next() {
+			return Err!(Request(InvalidParam("Unexpected {token:?} in path.")));
+		}
+
+		Ok(Self { attr, filter })
+	}
+}
+
+/// Looks up an attribute of a complex value without regard to case.
+pub(super) fn get<'a>(value: &'a JsonValue, attr: &str) -> Option<&'a JsonValue> {
+	value
+		.as_object()?
+		.iter()
+		.find(|(key, _)| key.eq_ignore_ascii_case(attr))
+		.map(|(_, value)| value)
+}
+
+fn parse_or(tokens: &mut Tokens) -> Result<Filter> {
+	let mut filter = parse_and(tokens)?;
+	while keyword(tokens, "or") {
+		filter = Filter::Or(Box::new(filter), Box::new(parse_and(tokens)?));
+	}
+
+	Ok(filter)
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<Filter> {
+	let mut filter = parse_not(tokens)?;
+	while keyword(tokens, "and") {
+		filter = Filter::And(Box::new(filter), Box::new(parse_not(tokens)?));
+	}
+
+	Ok(filter)
+}
+
+fn parse_not(tokens: &mut Tokens) -> Result<Filter> {
+	if !keyword(tokens, "not") {
+		return parse_primary(tokens);
+	}
+
+	expect(tokens, &Token::Open)?;
+	let filter = parse_or(tokens)?;
+	expect(tokens, &Token::Close)?;
+
+	Ok(Filter::Not(Box::new(filter)))
+}
+
+fn parse_primary(tokens: &mut Tokens) -> Result<Filter> {
+	if tokens.next_if_eq(&Token::Open).is_some() {
+		let filter = parse_or(tokens)?;
+		expect(tokens, &Token::Close)?;
+		return Ok(filter);
+	}
+
+	let attr = attr_path(tokens)?;
+	if tokens.next_if_eq(&Token::OpenBracket).is_some() {
+		let filter = parse_or(tokens)?;
+		expect(tokens, &Token::CloseBracket)?;
+		return Ok(Filter::ValuePath(attr, Box::new(filter)));
+	}
+
+	if keyword(tokens, "pr") {
+		return Ok(Filter::Present(attr));
+	}
+
+	let op = match tokens.next() {
+		| Some(Token::Word(op)) => CompareOp::parse(&op)?,
+		| token => return Err!(Request(InvalidParam("Expected an operator, found {token:?}."))),
+	};
+
+	let value = match tokens.next() {
+		| Some(Token::Value(value)) => value,
+		| Some(Token::Word(word)) => serde_json::from_str(&word)
+			.ok()
+			.filter(|value: &JsonValue| {
+				value.is_boolean() || value.is_null() || value.is_number()
+			})
+			.ok_or_else(|| err!(Request(InvalidParam("Invalid filter value {word:?}."))))?,
+		| token => return Err!(Request(InvalidParam("Expected a value, found {token:?}."))),
+	};
+
+	Ok(Filter::Compare(attr, op, value))
+}
+
+fn attr_path(tokens: &mut Tokens) -> Result<AttrPath> {
+	match tokens.next() {
+		| Some(Token::Word(path)) => AttrPath::parse(&path),
+		| token => Err!(Request(InvalidParam("Expected an attribute, found {token:?}."))),
+	}
+}
+
+fn keyword(tokens: &mut Tokens, keyword: &str) -> bool {
+	tokens
+		.next_if(|token| matches!(token, Token::Word(word) if word.eq_ignore_ascii_case(keyword)))
+		.is_some()
+}
+
+fn expect(tokens: &mut Tokens, expected: &Token) -> Result {
+	match tokens.next() {
+		| Some(ref token) if token == expected => Ok(()),
+		| token => Err!(Request(InvalidParam("Expected {expected:?}, found {token:?}."))),
+	}
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+	const DELIMITERS: &str = "()[]\"";
+
+	let mut tokens = Vec::new();
+	let mut chars = input.char_indices().peekable();
+	while let Some((start, c)) = chars.next() {
+		match c {
+			| c if c.is_whitespace() => {},
+			| '(' => tokens.push(Token::Open),
+			| ')' => tokens.push(Token::Close),
+			| '[' => tokens.push(Token::OpenBracket),
+			| ']' => tokens.push(Token::CloseBracket),
+			| '"' => {
+				let mut escaped = false;
+				let end = chars
+					.by_ref()
+					.find(|&(_, c)| match c {
+						| '\\' if !escaped => {
+							escaped = true;
+							false
+						},
+						| '"' if !escaped => true,
+						| _ => {
+							escaped = false;
+							false
+						},
+					})
+					.map(|(end, _)| end)
+					.ok_or_else(|| {
+						err!(Request(InvalidParam("Unterminated string in filter.")))
+					})?;
+
+				let value = input
+					.get(start..=end)
+					.and_then(|literal| serde_json::from_str(literal).ok())
+					.ok_or_else(|| err!(Request(InvalidParam("Invalid string in filter."))))?;
+
+				tokens.push(Token::Value(value));
+			},
+			| _ => {
+				let end = input
+					.get(start..)
+					.and_then(|rest| {
+						rest.find(|c: char| c.is_whitespace() || DELIMITERS.contains(c))
+					})
+					.map_or(input.len(), |len| start.saturating_add(len));
+
+				while chars.next_if(|&(i, _)| i < end).is_some() {}
+
+				let word = input.get(start..end).unwrap_or_default();
+				tokens.push(Token::Word(word.to_owned()));
+			},
+		}
+	}
+
+	Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::{AttrPath, CompareOp, Filter, Path};
+
+	fn user() -> serde_json::Value {
+		json!({
+			"id": "alice",
+			"userName": "alice",
+			"displayName": "Alice Liddell",
+			"active": true,
+			"name": { "formatted": "Alice Liddell" },
+			"emails": [
+				{ "value": "alice@example.org", "type": "work" },
+				{ "value": "alice@home.example", "type": "home" },
+			],
+		})
+	}
+
+	#[test]
+	fn parses_precedence() {
+		let filter = Filter::parse(r#"userName eq "a" or active eq true and not (title pr)"#)
+			.expect("valid filter");
+
+		let Filter::Or(lhs, rhs) = filter else {
+			panic!("or must bind loosest");
+		};
+
+		assert!(matches!(*lhs, Filter::Compare(_, CompareOp::Eq, _)));
+		assert!(matches!(*rhs, Filter::And(_, ref not) if matches!(**not, Filter::Not(_))));
+	}
+
+	#[test]
+	fn strips_schema_urn() {
+		let filter =
+			Filter::parse(r#"urn:ietf:params:scim:schemas:core:2.0:User:userName eq "x""#)
+				.expect("valid filter");
+
+		assert_eq!(filter.equality_on("username"), Some("x"));
+	}
+
+	#[test]
+	fn matches_attributes() {
+		let user = user();
+		let matches = |filter: &str| {
+			Filter::parse(filter)
+				.expect("valid filter")
+				.matches(&user)
+		};
+
+		assert!(matches(r#"userName eq "ALICE""#));
+		assert!(matches(r#"displayName co "liddell""#));
+		assert!(matches(r#"name.formatted sw "Alice""#));
+		assert!(matches("active eq true"));
+		assert!(!matches("active eq false"));
+		assert!(matches("title ne \"Queen\""));
+		assert!(!matches("title pr"));
+		assert!(matches(r#"emails ew "@home.example""#));
+		assert!(matches(r#"emails[type eq "work" and value co "example.org"]"#));
+		assert!(!matches(r#"emails[type eq "work" and value co "home"]"#));
+		assert!(matches(r#"not (userName eq "bob")"#));
+		assert!(matches(r#"userName gt "aaa" and userName lt "b""#));
+	}
+
+	#[test]
+	fn rejects_invalid_filters() {
+		for filter in [
+			"",
+			"userName",
+			"userName eq",
+			r#"userName xx "a""#,
+			r#"userName eq "a"#,
+			r#"(userName eq "a""#,
+			"userName eq alice",
+			r#"userName eq "a" extra"#,
+		] {
+			assert!(Filter::parse(filter).is_err(), "{filter:?} must be rejected");
+		}
+	}
+
+	#[test]
+	fn parses_paths() {
+		let path = Path::parse(r#"members[value eq "alice"]"#).expect("valid path");
+		assert!(path.attr.is("members"));
+		assert!(path.filter.is_some());
+
+		let path = Path::parse("name.givenName").expect("valid path");
+		assert_eq!(path.attr, AttrPath {
+			attr: "name".to_owned(),
+			sub_attr: Some("givenName".to_owned()),
+		});
+		assert!(path.filter.is_none());
+
+		let path = Path::parse(r#"emails[type eq "work"].value"#).expect("valid path");
+		assert_eq!(path.attr.sub_attr.as_deref(), Some("value"));
+		assert!(path.filter.is_some());
+	}
+}
@@ -0,0 +1,329 @@
+use std::collections::BTreeSet;
+
+use axum::{
+	body::Bytes,
+	extract::{Path, RawQuery, State},
+};
+use futures::{FutureExt, StreamExt};
+use http::HeaderMap;
+use ruma::{OwnedRoomId, OwnedRoomOrAliasId, OwnedUserId, RoomId};
+use serde_json::{Value as JsonValue, json};
+use tuwunel_core::utils::ReadyExt;
+use tuwunel_service::Services;
+
+use super::{
+	GROUP_SCHEMA, ListQuery, audit, authorize,
+	filter::get,
+	location, parse_body, patch,
+	response::{Result, Scim, ScimError},
+	users::find_user,
+};
+
+/// # `GET /scim/v2/Groups`
+///
+/// Lists the workspaces matching the filter. Rooms of workspaces are only
+/// reachable by their id.
+pub(crate) async fn list_groups_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	RawQuery(query): RawQuery,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let query = ListQuery::parse(query.as_deref())?;
+	let filter = query.filter()?;
+
+	let workspaces: Vec<(String, OwnedRoomId)> = services.workspace.workspaces().collect().await;
+
+	let mut resources = Vec::new();
+	for (workspace_id, space_room_id) in &workspaces {
+		let resource = group_resource(&services, workspace_id, space_room_id).await;
+		if filter
+			.as_ref()
+			.is_none_or(|filter| filter.matches(&resource))
+		{
+			resources.push(resource);
+		}
+	}
+
+	Ok(Scim::ok(query.respond(resources)))
+}
+
+/// # `GET /scim/v2/Groups/{id}`
+pub(crate) async fn get_group_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	Path(id): Path<String>,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let room_id = group_room(&services, &id)
+		.await
+		.ok_or_else(|| ScimError::not_found(format!("Group {id} not found.")))?;
+
+	Ok(Scim::ok(group_resource(&services, &id, &room_id).await))
+}
+
+/// # `POST /scim/v2/Groups`
+///
+/// Groups cannot be created through SCIM; the request binds the existing
+/// workspace, or room or room alias of a workspace, named by its externalId or
+/// displayName and joins the listed members to it.
+pub(crate) async fn create_group_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	body: Bytes,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let body = parse_body(&body)?;
+	let mut found = None;
+	for attr in ["externalId", "displayName"] {
+		if let Some(name) = get(&body, attr).and_then(JsonValue::as_str)
+			&& let Some(group) = find_group(&services, name).await
+		{
+			found = Some(group);
+			break;
+		}
+	}
+
+	let (id, room_id) = found.ok_or_else(|| {
+		ScimError::bad_request(
+			"invalidValue",
+			"Groups must name an existing workspace, or a room or room alias of one.",
+		)
+	})?;
+
+	let current = members_of(&services, &room_id).await;
+	let wanted = requested_members(&services, &body).await?;
+	let wanted = current.union(&wanted).cloned().collect();
+	update_members(&services, &room_id, &current, &wanted).await?;
+	audit(&services, "create group", room_id.as_str());
+
+	Ok(Scim::created(group_resource(&services, &id, &room_id).await))
+}
+
+/// # `PUT /scim/v2/Groups/{id}`
+///
+/// Replaces the members of the group; other attributes are read-only.
+pub(crate) async fn replace_group_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	Path(id): Path<String>,
+	body: Bytes,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let room_id = group_room(&services, &id)
+		.await
+		.ok_or_else(|| ScimError::not_found(format!("Group {id} not found.")))?;
+
+	let body = parse_body(&body)?;
+	let current = members_of(&services, &room_id).await;
+	let wanted = requested_members(&services, &body).await?;
+	update_members(&services, &room_id, &current, &wanted).await?;
+	audit(&services, "replace group", room_id.as_str());
+
+	Ok(Scim::ok(group_resource(&services, &id, &room_id).await))
+}
+
+/// # `PATCH /scim/v2/Groups/{id}`
+pub(crate) async fn patch_group_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	Path(id): Path<String>,
+	body: Bytes,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let room_id = group_room(&services, &id)
+		.await
+		.ok_or_else(|| ScimError::not_found(format!("Group {id} not found.")))?;
+
+	let body = parse_body(&body)?;
+	let mut resource = group_resource(&services, &id, &room_id).await;
+	patch::apply(&mut resource, &body)?;
+
+	let current = members_of(&services, &room_id).await;
+	let wanted = requested_members(&services, &resource).await?;
+	update_members(&services, &room_id, &current, &wanted).await?;
+	audit(&services, "patch group", room_id.as_str());
+
+	Ok(Scim::ok(group_resource(&services, &id, &room_id).await))
+}
+
+/// # `DELETE /scim/v2/Groups/{id}`
+///
+/// Workspaces and rooms outlive their provisioning; clients should remove the
+/// members instead.
+pub(crate) async fn delete_group_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	Path(id): Path<String>,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	if group_room(&services, &id).await.is_none() {
+		return Err(ScimError::not_found(format!("Group {id} not found.")));
+	}
+
+	Err(ScimError::bad_request("mutability", "Groups cannot be deleted."))
+}
+
+/// Joins the wanted members missing from the room and removes the others.
+async fn update_members(
+	services: &Services,
+	room_id: &RoomId,
+	current: &BTreeSet<OwnedUserId>,
+	wanted: &BTreeSet<OwnedUserId>,
+) -> Result<()> {
+	for user_id in wanted.difference(current) {
+		let state_lock = services.state.mutex.lock(room_id).await;
+		services
+			.membership
+			.join(user_id, room_id, None, None, &[], false, &state_lock)
+			.boxed()
+			.await?;
+	}
+
+	for user_id in current.difference(wanted) {
+		let state_lock = services.state.mutex.lock(room_id).await;
+		services
+			.membership
+			.leave(user_id, room_id, None, false, &state_lock)
+			.boxed()
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// The users listed in the members attribute.
+async fn requested_members(
+	services: &Services,
+	resource: &JsonValue,
+) -> Result<BTreeSet<OwnedUserId>> {
+	let mut members = BTreeSet::new();
+	for member in get(resource, "members")
+		.and_then(JsonValue::as_array)
+		.into_iter()
+		.flatten()
+	{
+		let value = get(member, "value")
+			.and_then(JsonValue::as_str)
+			.ok_or_else(|| ScimError::bad_request("invalidValue", "Member without value."))?;
+
+		let user_id = find_user(services, value).await.ok_or_else(|| {
+			ScimError::bad_request("invalidValue", format!("Unknown member {value}."))
+		})?;
+
+		members.insert(user_id);
+	}
+
+	Ok(members)
+}
+
+/// The local users joined to the room.
+async fn members_of(services: &Services, room_id: &RoomId) -> BTreeSet<OwnedUserId> {
+	services
+		.state_cache
+		.local_users_in_room(room_id)
+		.ready_filter(|user_id| *user_id != services.globals.server_user)
+		.map(ToOwned::to_owned)
+		.collect()
+		.await
+}
+
+/// The SCIM representation of a workspace or room.
+async fn group_resource(services: &Services, id: &str, room_id: &RoomId) -> JsonValue {
+	let mut members = Vec::new();
+	for user_id in members_of(services, room_id).await {
+		let localpart = user_id.localpart();
+		let display = services
+			.users
+			.displayname(&user_id)
+			.await
+			.unwrap_or_else(|_| localpart.to_owned());
+
+		members.push(json!({
+			"value": localpart,
+			"display": display,
+			"$ref": location(services, "Users", localpart),
+		}));
+	}
+
+	let display_name = services
+		.state_accessor
+		.get_name(room_id)
+		.await
+		.unwrap_or_else(|_| id.to_owned());
+
+	json!({
+		"schemas": [GROUP_SCHEMA],
+		"id": id,
+		"displayName": display_name,
+		"members": members,
+		"meta": {
+			"resourceType": "Group",
+			"location": location(services, "Groups", id),
+		},
+	})
+}
+
+/// Resolves a group id, a room ID or a workspace ID, to its room.
+async fn group_room(services: &Services, id: &str) -> Option<OwnedRoomId> {
+	if id.starts_with('!') {
+		let room_id = RoomId::parse(id).ok()?;
+		return is_group_room(services, &room_id)
+			.await
+			.then_some(room_id);
+	}
+
+	services
+		.workspace
+		.get_space_room_id(id)
+		.await
+		.ok()
+}
+
+/// Resolves a workspace ID, room ID or room alias to the group id and room.
+async fn find_group(services: &Services, name: &str) -> Option<(String, OwnedRoomId)> {
+	if let Some(room_id) = group_room(services, name).await {
+		return Some((name.to_owned(), room_id));
+	}
+
+	let alias = OwnedRoomOrAliasId::try_from(name)
+		.ok()
+		.filter(|alias| !alias.is_room_id())?;
+
+	let room_id = services.alias.maybe_resolve(&alias).await.ok()?;
+	if !is_group_room(services, &room_id).await {
+		return None;
+	}
+
+	Some((room_id.to_string(), room_id))
+}
+
+/// Whether the room may be provisioned as a group: the space room of a
+/// workspace or a room of one. Other rooms, the admin room above all, are
+/// out of reach of SCIM clients.
+async fn is_group_room(services: &Services, room_id: &RoomId) -> bool {
+	if services.admin.is_admin_room(room_id).await {
+		return false;
+	}
+
+	if services
+		.workspace
+		.get_workspace_id(room_id)
+		.await
+		.is_ok()
+	{
+		return true;
+	}
+
+	services
+		.workspace
+		.workspaces()
+		.ready_any(|(_, space_room_id)| *space_room_id == *room_id)
+		.await
+}
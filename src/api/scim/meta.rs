@@ -0,0 +1,126 @@
+//! Discovery endpoints (RFC 7644 section 4).
+
+use axum::extract::State;
+use http::HeaderMap;
+use serde_json::{Value as JsonValue, json};
+
+use super::{
+	GROUP_SCHEMA, MAX_RESULTS, USER_SCHEMA, authorize,
+	response::{Result, Scim},
+};
+
+/// # `GET /scim/v2/ServiceProviderConfig`
+pub(crate) async fn get_service_provider_config_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	Ok(Scim::ok(json!({
+		"schemas": ["urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig"],
+		"patch": { "supported": true },
+		"bulk": { "supported": false, "maxOperations": 0, "maxPayloadSize": 0 },
+		"filter": { "supported": true, "maxResults": MAX_RESULTS },
+		"changePassword": { "supported": true },
+		"sort": { "supported": false },
+		"etag": { "supported": false },
+		"authenticationSchemes": [{
+			"type": "oauthbearertoken",
+			"name": "OAuth Bearer Token",
+			"description": "Authentication with the configured SCIM bearer token.",
+			"primary": true,
+		}],
+		"meta": { "resourceType": "ServiceProviderConfig" },
+	})))
+}
+
+/// # `GET /scim/v2/ResourceTypes`
+pub(crate) async fn get_resource_types_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	Ok(Scim::ok(list(vec![
+		resource_type("User", "/Users", USER_SCHEMA),
+		resource_type("Group", "/Groups", GROUP_SCHEMA),
+	])))
+}
+
+/// # `GET /scim/v2/Schemas`
+pub(crate) async fn get_schemas_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let user = json!({
+		"id": USER_SCHEMA,
+		"name": "User",
+		"description": "Local Matrix account, identified by its localpart.",
+		"attributes": [
+			attribute("userName", "string", true, "immutable"),
+			attribute("displayName", "string", false, "readWrite"),
+			attribute("active", "boolean", false, "readWrite"),
+			json!({
+				"name": "password",
+				"type": "string",
+				"multiValued": false,
+				"required": false,
+				"mutability": "writeOnly",
+				"returned": "never",
+			}),
+		],
+		"meta": { "resourceType": "Schema" },
+	});
+
+	let group = json!({
+		"id": GROUP_SCHEMA,
+		"name": "Group",
+		"description": "Workspace or room; members are the local users joined to it.",
+		"attributes": [
+			attribute("displayName", "string", true, "readOnly"),
+			json!({
+				"name": "members",
+				"type": "complex",
+				"multiValued": true,
+				"required": false,
+				"mutability": "readWrite",
+				"returned": "default",
+				"subAttributes": [
+					attribute("value", "string", true, "immutable"),
+					attribute("display", "string", false, "readOnly"),
+					attribute("$ref", "reference", false, "immutable"),
+				],
+			}),
+		],
+		"meta": { "resourceType": "Schema" },
+	});
+
+	Ok(Scim::ok(list(vec![user, group])))
+}
+
+fn resource_type(name: &str, endpoint: &str, schema: &str) -> JsonValue {
+	json!({
+		"schemas": ["urn:ietf:params:scim:schemas:core:2.0:ResourceType"],
+		"id": name,
+		"name": name,
+		"endpoint": endpoint,
+		"schema": schema,
+		"meta": { "resourceType": "ResourceType" },
+	})
+}
+
+fn attribute(name: &str, kind: &str, required: bool, mutability: &str) -> JsonValue {
+	json!({
+		"name": name,
+		"type": kind,
+		"multiValued": false,
+		"required": required,
+		"caseExact": false,
+		"mutability": mutability,
+		"returned": "default",
+	})
+}
+
+fn list(resources: Vec<JsonValue>) -> JsonValue { super::ListQuery::default().respond(resources) }
@@ -0,0 +1,136 @@
+//! SCIM 2.0 provisioning (RFC 7643, RFC 7644).
+//!
+//! Users are local accounts identified by their localpart. Groups are
+//! workspaces, identified by their workspace ID, or rooms, identified by their
+//! room ID; their members are the local users joined to the (space) room.
+
+mod filter;
+mod groups;
+mod meta;
+mod patch;
+mod response;
+mod users;
+
+use axum::body::Bytes;
+use http::{HeaderMap, StatusCode, header::AUTHORIZATION};
+use serde::Deserialize;
+use serde_json::{Value as JsonValue, json};
+use tuwunel_core::utils::hash::secrets_equal;
+use tuwunel_service::{Services, audit};
+
+use self::{
+	filter::Filter,
+	response::{Result, ScimError},
+};
+pub(crate) use self::{groups::*, meta::*, users::*};
+
+const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+const LIST_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+
+/// Upper bound of resources returned by one query.
+const MAX_RESULTS: usize = 200;
+
+/// Query parameters of the list endpoints (RFC 7644 section 3.4.2).
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListQuery {
+	filter: Option<String>,
+	start_index: Option<usize>,
+	count: Option<usize>,
+}
+
+impl ListQuery {
+	fn parse(query: Option<&str>) -> Result<Self> {
+		serde_html_form::from_str(query.unwrap_or_default())
+			.map_err(|e| ScimError::bad_request("invalidValue", format!("Invalid query: {e}")))
+	}
+
+	fn filter(&self) -> Result<Option<Filter>> {
+		self.filter
+			.as_deref()
+			.map(Filter::parse)
+			.transpose()
+			.map_err(|e| ScimError::bad_request("invalidFilter", e.sanitized_message()))
+	}
+
+	/// Pages through the matching resources; `startIndex` is 1-based.
+	fn respond(&self, resources: Vec<JsonValue>) -> JsonValue {
+		let start_index = self.start_index.unwrap_or(1).max(1);
+		let count = self.count.unwrap_or(MAX_RESULTS).min(MAX_RESULTS);
+
+		let total_results = resources.len();
+		let resources: Vec<_> = resources
+			.into_iter()
+			.skip(start_index.saturating_sub(1))
+			.take(count)
+			.collect();
+
+		json!({
+			"schemas": [LIST_SCHEMA],
+			"totalResults": total_results,
+			"startIndex": start_index,
+			"itemsPerPage": resources.len(),
+			"Resources": resources,
+		})
+	}
+}
+
+/// Checks the bearer token of the provisioning client.
+async fn authorize(services: &Services, headers: &HeaderMap) -> Result<()> {
+	let config = &services.server.config.scim;
+	let expected = match &config.token_file {
+		| Some(token_file) => tokio::fs::read_to_string(token_file)
+			.await
+			.map(|token| token.trim().to_owned())
+			.ok(),
+		| None => config.token.clone(),
+	};
+
+	let token = headers
+		.get(AUTHORIZATION)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.strip_prefix("Bearer "));
+
+	match (token, expected) {
+		| (Some(token), Some(expected))
+			if !expected.is_empty() && secrets_equal(token.as_bytes(), expected.as_bytes()) =>
+			Ok(()),
+		| _ => Err(ScimError::new(StatusCode::UNAUTHORIZED, "Invalid SCIM bearer token.")),
+	}
+}
+
+/// Parses a request body into a JSON object.
+fn parse_body(body: &Bytes) -> Result<JsonValue> {
+	serde_json::from_slice(body)
+		.ok()
+		.filter(JsonValue::is_object)
+		.ok_or_else(|| {
+			ScimError::bad_request("invalidSyntax", "Request body must be a JSON object.")
+		})
+}
+
+/// Records a provisioning change in the audit log.
+fn audit(services: &Services, action: &str, target: &str) {
+	services.audit.record(audit::Entry {
+		source: "scim".to_owned(),
+		action: action.to_owned(),
+		target: Some(target.to_owned()),
+		..Default::default()
+	});
+}
+
+/// Location of a resource, absolute when the client base URL is known.
+fn location(services: &Services, endpoint: &str, id: &str) -> String {
+	let base = services
+		.server
+		.config
+		.well_known
+		.client
+		.as_ref()
+		.map(|url| url.as_str().trim_end_matches('/').to_owned())
+		.unwrap_or_default();
+
+	format!("{base}/scim/v2/{endpoint}/{id}")
+}
@@ -0,0 +1,320 @@
+//! PATCH operations (RFC 7644 section 3.5.2), applied to the JSON
+//! representation of a resource.
+
+use serde_json::{Map, Value as JsonValue};
+
+use super::{
+	filter::{Path, get},
+	response::{Result, ScimError},
+};
+
+/// Applies the operations of a PATCH request to the resource.
+pub(super) fn apply(resource: &mut JsonValue, request: &JsonValue) -> Result<()> {
+	let operations = get(request, "Operations")
+		.and_then(JsonValue::as_array)
+		.ok_or_else(|| ScimError::bad_request("invalidSyntax", "Missing PATCH Operations."))?;
+
+	for operation in operations {
+		let op = get(operation, "op")
+			.and_then(JsonValue::as_str)
+			.map(str::to_ascii_lowercase)
+			.unwrap_or_default();
+
+		let path = get(operation, "path")
+			.and_then(JsonValue::as_str)
+			.map(parse_path)
+			.transpose()?;
+
+		let value = get(operation, "value");
+		match (op.as_str(), path) {
+			| ("add" | "replace", None) => {
+				let attrs = value
+					.and_then(JsonValue::as_object)
+					.ok_or_else(|| {
+						ScimError::bad_request(
+							"invalidValue",
+							"Operation without path needs an object.",
+						)
+					})?;
+
+				// Some clients name sub-attributes by their path here.
+				for (attr, value) in attrs {
+					set(resource, &parse_path(attr)?, value.clone(), op == "add")?;
+				}
+			},
+			| ("add" | "replace", Some(path)) => {
+				let value = value.cloned().ok_or_else(|| {
+					ScimError::bad_request("invalidValue", "Operation without value.")
+				})?;
+
+				set(resource, &path, value, op == "add")?;
+			},
+			| ("remove", Some(path)) => remove(resource, &path, value),
+			| ("remove", None) =>
+				return Err(ScimError::bad_request("noTarget", "Remove operation without path.")),
+			| _ =>
+				return Err(ScimError::bad_request(
+					"invalidSyntax",
+					format!("Unsupported PATCH operation {op:?}."),
+				)),
+		}
+	}
+
+	Ok(())
+}
+
+fn parse_path(path: &str) -> Result<Path> {
+	Path::parse(path).map_err(|e| ScimError::bad_request("invalidPath", e.sanitized_message()))
+}
+
+fn set(resource: &mut JsonValue, path: &Path, value: JsonValue, add: bool) -> Result<()> {
+	let object = resource
+		.as_object_mut()
+		.ok_or_else(|| ScimError::bad_request("invalidPath", "Resource is not an object."))?;
+
+	let key = key_of(object, &path.attr.attr);
+	if let Some(filter) = &path.filter {
+		let mut matched = false;
+		for element in object
+			.get_mut(&key)
+			.and_then(JsonValue::as_array_mut)
+			.into_iter()
+			.flatten()
+			.filter(|element| filter.matches(element))
+		{
+			matched = true;
+			match (&path.attr.sub_attr, &value) {
+				| (Some(sub_attr), _) =>
+					if let Some(element) = element.as_object_mut() {
+						let sub_key = key_of(element, sub_attr);
+						element.insert(sub_key, value.clone());
+					},
+				| (None, JsonValue::Object(attrs)) if add && element.is_object() =>
+					if let Some(element) = element.as_object_mut() {
+						element.extend(attrs.clone());
+					},
+				| (None, _) => *element = value.clone(),
+			}
+		}
+
+		if !matched {
+			return Err(ScimError::bad_request("noTarget", "No value matches the path filter."));
+		}
+
+		return Ok(());
+	}
+
+	if let Some(sub_attr) = &path.attr.sub_attr {
+		let complex = object
+			.entry(key)
+			.or_insert_with(|| JsonValue::Object(Map::new()));
+
+		if !complex.is_object() {
+			*complex = JsonValue::Object(Map::new());
+		}
+
+		if let Some(complex) = complex.as_object_mut() {
+			let sub_key = key_of(complex, sub_attr);
+			complex.insert(sub_key, value);
+		}
+
+		return Ok(());
+	}
+
+	if add && let Some(JsonValue::Array(elements)) = object.get_mut(&key) {
+		let values = match value {
+			| JsonValue::Array(values) => values,
+			| value => vec![value],
+		};
+
+		for value in values {
+			if !elements.contains(&value) {
+				elements.push(value);
+			}
+		}
+
+		return Ok(());
+	}
+
+	object.insert(key, value);
+
+	Ok(())
+}
+
+fn remove(resource: &mut JsonValue, path: &Path, value: Option<&JsonValue>) {
+	let Some(object) = resource.as_object_mut() else {
+		return;
+	};
+
+	let key = key_of(object, &path.attr.attr);
+	let Some(target) = object.get_mut(&key) else {
+		return;
+	};
+
+	// Some clients name the elements to remove in the value instead of a filter.
+	let by_value = value.is_some() && target.is_array();
+	if path.filter.is_none() && path.attr.sub_attr.is_none() && !by_value {
+		object.remove(&key);
+		return;
+	}
+
+	match (&path.filter, &path.attr.sub_attr, target) {
+		| (Some(filter), None, JsonValue::Array(elements)) => {
+			elements.retain(|element| !filter.matches(element));
+		},
+		| (Some(filter), Some(sub_attr), JsonValue::Array(elements)) => {
+			for element in elements
+				.iter_mut()
+				.filter(|element| filter.matches(element))
+				.filter_map(JsonValue::as_object_mut)
+			{
+				let sub_key = key_of(element, sub_attr);
+				element.remove(&sub_key);
+			}
+		},
+		| (None, Some(sub_attr), JsonValue::Object(complex)) => {
+			let sub_key = key_of(complex, sub_attr);
+			complex.remove(&sub_key);
+		},
+		| (None, None, JsonValue::Array(elements)) => {
+			let removed: Vec<_> = match value {
+				| Some(JsonValue::Array(values)) => values.iter().collect(),
+				| value => value.into_iter().collect(),
+			};
+
+			elements.retain(|element| {
+				!removed.iter().any(|removed| {
+					*removed == element
+						|| get(removed, "value")
+							.is_some_and(|value| Some(value) == get(element, "value"))
+				})
+			});
+		},
+		| _ => {},
+	}
+}
+
+/// The key of an attribute of the object, matched without regard to case.
+fn key_of(object: &Map<String, JsonValue>, attr: &str) -> String {
+	object
+		.keys()
+		.find(|key| key.eq_ignore_ascii_case(attr))
+		.cloned()
+		.unwrap_or_else(|| attr.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::{Value as JsonValue, json};
+
+	use super::apply;
+
+	fn user() -> JsonValue {
+		json!({
+			"userName": "alice",
+			"active": true,
+			"name": { "formatted": "Alice Liddell" },
+			"emails": [
+				{ "value": "alice@example.org", "type": "work" },
+				{ "value": "alice@home.example", "type": "home" },
+			],
+		})
+	}
+
+	fn patch(resource: &mut JsonValue, operations: JsonValue) -> super::Result<()> {
+		apply(resource, &json!({ "Operations": operations }))
+	}
+
+	#[test]
+	fn replace_attributes() {
+		let mut user = user();
+		patch(
+			&mut user,
+			json!([
+				{ "op": "Replace", "path": "active", "value": false },
+				{ "op": "replace", "value": { "DisplayName": "Alice", "name.formatted": "A. L." } },
+			]),
+		)
+		.expect("valid patch");
+
+		assert_eq!(user["active"], json!(false));
+		assert_eq!(user["DisplayName"], json!("Alice"));
+		assert_eq!(user["name"]["formatted"], json!("A. L."));
+	}
+
+	#[test]
+	fn add_to_multi_valued() {
+		let mut user = user();
+		let other = json!({ "value": "alice@other.example", "type": "other" });
+		let existing = user["emails"][0].clone();
+		patch(
+			&mut user,
+			json!([
+				{ "op": "add", "path": "emails", "value": [other, existing] },
+			]),
+		)
+		.expect("valid patch");
+
+		let emails = user["emails"].as_array().expect("emails");
+		assert_eq!(emails.len(), 3);
+		assert_eq!(emails[2], other);
+	}
+
+	#[test]
+	fn replace_filtered() {
+		let mut user = user();
+		patch(
+			&mut user,
+			json!([
+				{ "op": "replace", "path": "emails[type eq \"work\"].value", "value": "a@example.org" },
+			]),
+		)
+		.expect("valid patch");
+
+		assert_eq!(user["emails"][0]["value"], json!("a@example.org"));
+		assert_eq!(user["emails"][1]["value"], json!("alice@home.example"));
+
+		let error = patch(
+			&mut user,
+			json!([
+				{ "op": "replace", "path": "emails[type eq \"none\"].value", "value": "x" },
+			]),
+		)
+		.expect_err("no matching email");
+
+		assert!(format!("{error:?}").contains("noTarget"));
+	}
+
+	#[test]
+	fn remove_values() {
+		let mut user = user();
+		patch(
+			&mut user,
+			json!([
+				{ "op": "remove", "path": "emails", "value": [{ "value": "alice@example.org" }] },
+				{ "op": "remove", "path": "name.formatted" },
+			]),
+		)
+		.expect("valid patch");
+
+		assert_eq!(user["emails"].as_array().map(Vec::len), Some(1));
+		assert_eq!(user["name"], json!({}));
+
+		patch(&mut user, json!([{ "op": "remove", "path": "emails[type eq \"home\"]" }]))
+			.expect("valid patch");
+
+		assert_eq!(user["emails"], json!([]));
+
+		patch(&mut user, json!([{ "op": "remove", "path": "active" }])).expect("valid patch");
+		assert!(user.get("active").is_none());
+	}
+
+	#[test]
+	fn invalid_operations() {
+		let mut user = user();
+		assert!(patch(&mut user, json!([{ "op": "remove" }])).is_err());
+		assert!(patch(&mut user, json!([{ "op": "move", "path": "active" }])).is_err());
+		assert!(patch(&mut user, json!([{ "op": "add", "path": "active" }])).is_err());
+		assert!(apply(&mut user, &json!({})).is_err());
+	}
+}
@@ -0,0 +1,90 @@
+use axum::{
+	Json,
+	response::{IntoResponse, Response},
+};
+use http::{StatusCode, header};
+use serde_json::{Value as JsonValue, json};
+
+use super::ERROR_SCHEMA;
+
+/// Media type of SCIM requests and responses (RFC 7644 section 8.1).
+const CONTENT_TYPE: &str = "application/scim+json";
+
+pub(crate) type Result<T = Scim> = std::result::Result<T, ScimError>;
+
+/// A SCIM resource or message with its status.
+pub(crate) struct Scim(pub(super) StatusCode, pub(super) JsonValue);
+
+/// A SCIM error (RFC 7644 section 3.12).
+#[derive(Debug)]
+pub(crate) struct ScimError {
+	status: StatusCode,
+	scim_type: Option<&'static str>,
+	detail: String,
+}
+
+impl Scim {
+	pub(super) fn ok(body: JsonValue) -> Self { Self(StatusCode::OK, body) }
+
+	pub(super) fn created(body: JsonValue) -> Self { Self(StatusCode::CREATED, body) }
+
+	pub(super) fn no_content() -> Self { Self(StatusCode::NO_CONTENT, JsonValue::Null) }
+}
+
+impl IntoResponse for Scim {
+	fn into_response(self) -> Response {
+		if self.1.is_null() {
+			return self.0.into_response();
+		}
+
+		(self.0, [(header::CONTENT_TYPE, CONTENT_TYPE)], Json(self.1)).into_response()
+	}
+}
+
+impl ScimError {
+	pub(super) fn new(status: StatusCode, detail: impl Into<String>) -> Self {
+		Self {
+			status,
+			scim_type: None,
+			detail: detail.into(),
+		}
+	}
+
+	pub(super) fn bad_request(scim_type: &'static str, detail: impl Into<String>) -> Self {
+		Self {
+			scim_type: Some(scim_type),
+			..Self::new(StatusCode::BAD_REQUEST, detail)
+		}
+	}
+
+	pub(super) fn not_found(detail: impl Into<String>) -> Self {
+		Self::new(StatusCode::NOT_FOUND, detail)
+	}
+
+	pub(super) fn uniqueness(detail: impl Into<String>) -> Self {
+		Self {
+			scim_type: Some("uniqueness"),
+			..Self::new(StatusCode::CONFLICT, detail)
+		}
+	}
+}
+
+impl From<tuwunel_core::Error> for ScimError {
+	fn from(e: tuwunel_core::Error) -> Self { Self::new(e.status_code(), e.sanitized_message()) }
+}
+
+impl IntoResponse for ScimError {
+	fn into_response(self) -> Response {
+		let mut body = json!({
+			"schemas": [ERROR_SCHEMA],
+			"status": self.status.as_u16().to_string(),
+			"detail": self.detail,
+		});
+
+		if let Some(scim_type) = self.scim_type {
+			body["scimType"] = scim_type.into();
+		}
+
+		Scim(self.status, body).into_response()
+	}
+}
@@ -0,0 +1,338 @@
+use axum::{
+	body::Bytes,
+	extract::{Path, RawQuery, State},
+};
+use futures::{FutureExt, StreamExt};
+use http::HeaderMap;
+use ruma::{OwnedRoomId, OwnedUserId, UserId};
+use serde_json::{Value as JsonValue, json};
+use tuwunel_core::utils;
+use tuwunel_service::{Services, users::Register};
+
+use super::{
+	ListQuery, USER_SCHEMA, audit, authorize,
+	filter::get,
+	location, parse_body, patch,
+	response::{Result, Scim, ScimError},
+};
+
+/// Length of the password given to a reactivated account without one.
+const RANDOM_PASSWORD_LENGTH: usize = 25;
+
+/// # `GET /scim/v2/Users`
+///
+/// Lists the local users matching the filter.
+pub(crate) async fn list_users_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	RawQuery(query): RawQuery,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let query = ListQuery::parse(query.as_deref())?;
+	let filter = query.filter()?;
+
+	// Provisioning clients look up a user by userName before creating it.
+	if let Some(user_name) = filter
+		.as_ref()
+		.and_then(|filter| filter.equality_on("userName"))
+	{
+		let resources = match find_user(&services, user_name).await {
+			| Some(user_id) => vec![user_resource(&services, &user_id).await],
+			| None => Vec::new(),
+		};
+
+		return Ok(Scim::ok(query.respond(resources)));
+	}
+
+	let user_ids: Vec<OwnedUserId> = services
+		.users
+		.stream()
+		.filter(|user_id| {
+			let local = services.globals.user_is_local(user_id)
+				&& *user_id != services.globals.server_user;
+
+			async move { local }
+		})
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	let mut resources = Vec::new();
+	for user_id in &user_ids {
+		let resource = user_resource(&services, user_id).await;
+		if filter
+			.as_ref()
+			.is_none_or(|filter| filter.matches(&resource))
+		{
+			resources.push(resource);
+		}
+	}
+
+	Ok(Scim::ok(query.respond(resources)))
+}
+
+/// # `GET /scim/v2/Users/{id}`
+pub(crate) async fn get_user_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	Path(id): Path<String>,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let user_id = local_user(&services, &id).await?;
+
+	Ok(Scim::ok(user_resource(&services, &user_id).await))
+}
+
+/// # `POST /scim/v2/Users`
+///
+/// Creates a local account. Users provisioned without a password can only
+/// log in through single sign-on.
+pub(crate) async fn create_user_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	body: Bytes,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let body = parse_body(&body)?;
+	let user_name = get(&body, "userName")
+		.and_then(JsonValue::as_str)
+		.ok_or_else(|| ScimError::bad_request("invalidValue", "userName is required."))?;
+
+	let user_id =
+		UserId::parse_with_server_name(user_name.to_lowercase(), services.globals.server_name())
+			.map_err(|e| {
+				ScimError::bad_request("invalidValue", format!("Invalid userName: {e}"))
+			})?;
+
+	if services.users.exists(&user_id).await {
+		return Err(ScimError::uniqueness(format!("User {user_name} already exists.")));
+	}
+
+	let password = get(&body, "password").and_then(JsonValue::as_str);
+	let displayname = displayname_of(&body);
+	services
+		.users
+		.full_register(Register {
+			user_id: Some(&user_id),
+			password: Some(password.unwrap_or("*")),
+			origin: Some(if password.is_some() { "password" } else { "scim" }),
+			displayname: displayname.as_deref(),
+			..Default::default()
+		})
+		.boxed()
+		.await?;
+
+	if active_of(&body) == Some(false) {
+		services
+			.deactivate
+			.full_deactivate(&user_id)
+			.boxed()
+			.await?;
+	}
+
+	audit(&services, "create user", user_id.as_str());
+
+	Ok(Scim::created(user_resource(&services, &user_id).await))
+}
+
+/// # `PUT /scim/v2/Users/{id}`
+///
+/// Attributes missing from the request are left unchanged.
+pub(crate) async fn replace_user_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	Path(id): Path<String>,
+	body: Bytes,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let user_id = local_user(&services, &id).await?;
+	let body = parse_body(&body)?;
+	let current = user_resource(&services, &user_id).await;
+	update_user(&services, &user_id, &current, &body).await?;
+	audit(&services, "replace user", user_id.as_str());
+
+	Ok(Scim::ok(user_resource(&services, &user_id).await))
+}
+
+/// # `PATCH /scim/v2/Users/{id}`
+pub(crate) async fn patch_user_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	Path(id): Path<String>,
+	body: Bytes,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let user_id = local_user(&services, &id).await?;
+	let body = parse_body(&body)?;
+	let current = user_resource(&services, &user_id).await;
+
+	let mut updated = current.clone();
+	patch::apply(&mut updated, &body)?;
+	update_user(&services, &user_id, &current, &updated).await?;
+	audit(&services, "patch user", user_id.as_str());
+
+	Ok(Scim::ok(user_resource(&services, &user_id).await))
+}
+
+/// # `DELETE /scim/v2/Users/{id}`
+///
+/// Deactivates the account; Matrix user IDs are never reused, so the user
+/// remains listed with `active` set to false.
+pub(crate) async fn delete_user_route(
+	State(services): State<crate::State>,
+	headers: HeaderMap,
+	Path(id): Path<String>,
+) -> Result {
+	authorize(&services, &headers).await?;
+
+	let user_id = local_user(&services, &id).await?;
+	if services.users.is_active(&user_id).await {
+		services
+			.deactivate
+			.full_deactivate(&user_id)
+			.boxed()
+			.await?;
+	}
+
+	audit(&services, "delete user", user_id.as_str());
+
+	Ok(Scim::no_content())
+}
+
+/// Applies the differences between the current and the requested
+/// representation of a user.
+async fn update_user(
+	services: &Services,
+	user_id: &UserId,
+	current: &JsonValue,
+	updated: &JsonValue,
+) -> Result<()> {
+	if let Some(user_name) = get(updated, "userName").and_then(JsonValue::as_str)
+		&& !user_name.eq_ignore_ascii_case(user_id.localpart())
+	{
+		return Err(ScimError::bad_request("mutability", "userName cannot be changed."));
+	}
+
+	let password = get(updated, "password").and_then(JsonValue::as_str);
+	let was_active = active_of(current).unwrap_or(false);
+	match active_of(updated) {
+		| Some(false) if was_active => {
+			services
+				.deactivate
+				.full_deactivate(user_id)
+				.boxed()
+				.await?;
+
+			return Ok(());
+		},
+		| Some(true) if !was_active => {
+			let placeholder = match services.users.origin(user_id).await.as_deref() {
+				| Ok("password") => utils::random_string(RANDOM_PASSWORD_LENGTH),
+				| _ => "*".to_owned(),
+			};
+
+			services
+				.users
+				.set_password(user_id, Some(password.unwrap_or(&placeholder)))
+				.await?;
+		},
+		| _ =>
+			if let Some(password) = password {
+				services
+					.users
+					.set_password(user_id, Some(password))
+					.await?;
+			},
+	}
+
+	let displayname = displayname_of(updated);
+	if displayname.is_some()
+		&& displayname.as_deref() != get(current, "displayName").and_then(JsonValue::as_str)
+	{
+		let all_joined_rooms: Vec<OwnedRoomId> = services
+			.state_cache
+			.rooms_joined(user_id)
+			.map(ToOwned::to_owned)
+			.collect()
+			.await;
+
+		services
+			.users
+			.update_displayname(user_id, displayname.as_deref(), &all_joined_rooms)
+			.await;
+	}
+
+	Ok(())
+}
+
+/// The SCIM representation of a local user.
+async fn user_resource(services: &Services, user_id: &UserId) -> JsonValue {
+	let id = user_id.localpart();
+	let mut resource = json!({
+		"schemas": [USER_SCHEMA],
+		"id": id,
+		"userName": id,
+		"active": services.users.is_active(user_id).await,
+		"meta": {
+			"resourceType": "User",
+			"location": location(services, "Users", id),
+		},
+	});
+
+	if let Ok(displayname) = services.users.displayname(user_id).await {
+		resource["displayName"] = displayname.into();
+	}
+
+	resource
+}
+
+async fn local_user(services: &Services, id: &str) -> Result<OwnedUserId> {
+	find_user(services, id)
+		.await
+		.ok_or_else(|| ScimError::not_found(format!("User {id} not found.")))
+}
+
+/// Resolves a userName or id to an existing local user.
+pub(super) async fn find_user(services: &Services, user_name: &str) -> Option<OwnedUserId> {
+	let user_id =
+		UserId::parse_with_server_name(user_name.to_lowercase(), services.globals.server_name())
+			.ok()?;
+
+	let exists = user_id != services.globals.server_user && services.users.exists(&user_id).await;
+
+	exists.then_some(user_id)
+}
+
+/// The displayName of a user, falling back to its name.
+fn displayname_of(resource: &JsonValue) -> Option<String> {
+	if let Some(displayname) = get(resource, "displayName").and_then(JsonValue::as_str) {
+		return Some(displayname.to_owned());
+	}
+
+	let name = get(resource, "name")?;
+	if let Some(formatted) = get(name, "formatted").and_then(JsonValue::as_str) {
+		return Some(formatted.to_owned());
+	}
+
+	let parts: Vec<_> = ["givenName", "familyName"]
+		.into_iter()
+		.filter_map(|part| get(name, part).and_then(JsonValue::as_str))
+		.filter(|part| !part.is_empty())
+		.collect();
+
+	(!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// The `active` attribute; some clients send booleans as strings.
+fn active_of(resource: &JsonValue) -> Option<bool> {
+	match get(resource, "active")? {
+		| JsonValue::Bool(active) => Some(*active),
+		| JsonValue::String(active) => active.to_ascii_lowercase().parse().ok(),
+		| _ => None,
+	}
+}
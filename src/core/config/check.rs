@@ -45,6 +45,41 @@ pub fn check(config: &Config) -> Result {
 		));
 	}
 
+	if config.scim.enable && config.scim.token.is_none() && config.scim.token_file.is_none() {
+		return Err!(Config(
+			"scim.token",
+			"SCIM provisioning cannot be enabled without a token or token_file set"
+		));
+	}
+
+	if config.terms.block_without_consent
+		&& !config.terms.policies.is_empty()
+		&& config.terms.consent_url.is_none()
+	{
+		return Err!(Config(
+			"terms.consent_url",
+			"Requests of users without consent cannot be blocked without a consent page where \
+			 they accept the policies"
+		));
+	}
+
+	if config.livekit.enable {
+		let livekit = &config.livekit;
+		if livekit.url.is_none() || livekit.key.is_none() {
+			return Err!(Config(
+				"livekit.url",
+				"The LiveKit service cannot be enabled without its url and key set"
+			));
+		}
+
+		if livekit.secret.is_none() && livekit.secret_file.is_none() {
+			return Err!(Config(
+				"livekit.secret",
+				"The LiveKit service cannot be enabled without a secret or secret_file set"
+			));
+		}
+	}
+
 	if cfg!(all(feature = "hardened_malloc", feature = "jemalloc", not(target_env = "msvc"))) {
 		debug_warn!(
 			"hardened_malloc and jemalloc compile-time features are both enabled, this causes \
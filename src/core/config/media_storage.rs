@@ -26,6 +26,10 @@ pub struct MediaStorageConfig {
 	/// Hybrid storage strategy configuration
 	#[serde(default)]
 	pub hybrid: HybridStrategyConfig,
+
+	/// Encryption at rest configuration; media is stored in plaintext
+	/// unless set
+	pub encryption: Option<MediaEncryptionConfig>,
 }
 
 impl Default for MediaStorageConfig {
@@ -35,6 +39,7 @@ fn default() -> Self {
 			filesystem: FilesystemStorageConfig::default(),
 			s3: None,
 			hybrid: HybridStrategyConfig::default(),
+			encryption: None,
 		}
 	}
 }
@@ -107,6 +112,66 @@ pub struct S3StorageConfig {
 	pub force_path_style: bool,
 }
 
+/// Media encryption at rest configuration
+///
+/// Each file is encrypted with AES-256-GCM under its own data key, which is
+/// stored with the file wrapped by the master key. Master keys are 32 bytes,
+/// base64 encoded.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MediaEncryptionConfig {
+	/// File containing the master key
+	pub key_file: Option<PathBuf>,
+
+	/// Environment variable containing the master key, used when no key
+	/// file is set
+	///
+	/// example: "TUWUNEL_MEDIA_KEY"
+	pub key_env: Option<String>,
+
+	/// Files containing master keys replaced by the current one
+	///
+	/// Files encrypted under these keys remain readable until the re-wrap
+	/// job has wrapped their data keys by the current master key; remove a
+	/// key once the job reported finishing.
+	///
+	/// default: []
+	#[serde(default)]
+	pub previous_key_files: Vec<PathBuf>,
+
+	/// Environment variables containing master keys replaced by the current
+	/// one, as `previous_key_files`
+	///
+	/// default: []
+	#[serde(default)]
+	pub previous_key_envs: Vec<String>,
+
+	/// Size in bytes of the independently encrypted chunks of a file, which
+	/// is the least the storage backend reads for a range of the file. Media
+	/// is still served to clients whole.
+	///
+	/// default: 65536
+	#[serde(default = "default_encryption_chunk_size")]
+	pub chunk_size: u32,
+
+	/// Re-wrap the data keys of files encrypted under previous master keys,
+	/// and encrypt files stored before encryption was enabled, in the
+	/// background on startup
+	///
+	/// default: true
+	#[serde(default = "default_true")]
+	pub rewrap_on_startup: bool,
+
+	/// Refuse to read files which are not encrypted, instead of reading
+	/// them as stored before encryption was enabled
+	///
+	/// Enable this once the re-wrap job reported finishing without failures;
+	/// any file still in plaintext becomes unreadable.
+	///
+	/// default: false
+	#[serde(default)]
+	pub refuse_plaintext: bool,
+}
+
 /// Hybrid storage strategy configuration
 #[derive(Clone, Debug, Deserialize)]
 pub struct HybridStrategyConfig {
@@ -201,6 +266,10 @@ const fn default_cleanup_interval() -> u64 {
 	3600 // 1 hour
 }
 
+const fn default_encryption_chunk_size() -> u32 {
+	65536 // 64 KiB
+}
+
 fn default_storage_strategy() -> StorageStrategy {
 	StorageStrategy::Filesystem
 }
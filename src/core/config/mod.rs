@@ -18,16 +18,17 @@
 use itertools::Itertools;
 use regex::RegexSet;
 use ruma::{
-	OwnedMxcUri, OwnedRoomOrAliasId, OwnedServerName, OwnedUserId, RoomVersionId,
+	OwnedMxcUri, OwnedRoomId, OwnedRoomOrAliasId, OwnedServerName, OwnedUserId, RoomVersionId,
 	api::client::discovery::discover_support::ContactRole,
 };
-use serde::{Deserialize, de::IgnoredAny};
+use serde::{Deserialize, Serialize, de::IgnoredAny};
 use tuwunel_macros::config_example_generator;
 use url::Url;
 
 use self::proxy::ProxyConfig;
 pub use self::media_storage::{
-	FilesystemStorageConfig, HybridStrategyConfig, MediaStorageConfig, S3StorageConfig, StorageStrategy,
+	FilesystemStorageConfig, HybridStrategyConfig, MediaEncryptionConfig, MediaStorageConfig,
+	S3StorageConfig, StorageStrategy,
 };
 pub use self::{check::check, manager::Manager};
 use crate::{
@@ -61,7 +62,9 @@
 ### https://tuwunel.chat/configuration.html
 "#,
 	ignore = "catchall well_known tls blurhashing allow_invalid_tls_certificates ldap jwt \
-	          appservice identity_provider"
+	          scim terms admin_roles server_notices media_scanning \
+	          media_sanitizing media_retention livekit appservice \
+	          identity_provider"
 )]
 pub struct Config {
 	/// The server_name is the pretty name of this server. It is used as a
@@ -1635,6 +1638,36 @@ pub struct Config {
 	#[serde(default)]
 	pub media_storage: MediaStorageConfig,
 
+	/// Path of the `pdftoppm` program (poppler-utils) rendering the first
+	/// page of PDF documents for their thumbnails. Requires the
+	/// `media_thumbnail_pdf` feature.
+	///
+	/// default: "pdftoppm"
+	#[serde(default = "default_thumbnail_pdftoppm_path")]
+	pub thumbnail_pdftoppm_path: String,
+
+	/// Path of the `ffmpeg` program extracting a poster frame of videos for
+	/// their thumbnails. Requires the `media_thumbnail_video` feature.
+	///
+	/// default: "ffmpeg"
+	#[serde(default = "default_thumbnail_ffmpeg_path")]
+	pub thumbnail_ffmpeg_path: String,
+
+	/// Time in seconds rendering a PDF document or extracting a video frame
+	/// for a thumbnail may take before it is abandoned.
+	///
+	/// default: 15
+	#[serde(default = "default_thumbnail_render_timeout")]
+	pub thumbnail_render_timeout: u64,
+
+	/// Interval in seconds between summaries of the media statistics posted
+	/// to the admin room: totals, uploads of the day, and the top uploaders,
+	/// content types and remote servers. 0 disables the summaries.
+	///
+	/// default: 0
+	#[serde(default)]
+	pub media_stats_summary_interval: u64,
+
 	/// Vector list of regex patterns of server names that tuwunel will refuse
 	/// to download remote media from.
 	///
@@ -1780,6 +1813,49 @@ pub struct Config {
 	#[serde(default)]
 	pub url_preview_check_root_domain: bool,
 
+	/// Time in seconds URL previews are cached for when the previewed page
+	/// does not set a `max-age` in its `Cache-Control` header. Previews are
+	/// cached for at least a minute, also of pages responding with `no-store`,
+	/// `no-cache` or `private`. Expired previews are fetched again on request;
+	/// the images of previews are deleted a day after they expired or were
+	/// replaced.
+	///
+	/// default: 86400
+	#[serde(default = "default_url_preview_cache_ttl")]
+	pub url_preview_cache_ttl: u64,
+
+	/// Maximum time in seconds URL previews are cached for, regardless of the
+	/// `Cache-Control` header of the previewed page.
+	///
+	/// default: 604800
+	#[serde(default = "default_url_preview_max_cache_ttl")]
+	pub url_preview_max_cache_ttl: u64,
+
+	/// Maximum number of requests per minute made to a single domain for URL
+	/// previews, including the requests for their images and oEmbed data.
+	/// Previews of URLs beyond the limit fail until the minute has passed.
+	/// 0 disables the limit.
+	///
+	/// default: 30
+	#[serde(default = "default_url_preview_domain_rate_limit")]
+	pub url_preview_domain_rate_limit: u32,
+
+	/// Use the oEmbed data which previewed pages advertise with a
+	/// `<link type="application/json+oembed">` element for their titles and
+	/// images.
+	#[serde(default = "true_fn")]
+	pub url_preview_oembed: bool,
+
+	/// oEmbed endpoints to request previews from instead of the previewed
+	/// pages, by URL scheme. `*` in a scheme matches any characters, e.g.:
+	///
+	/// url_preview_oembed_providers = {
+	/// "https://www.youtube.com/watch*" = "https://www.youtube.com/oembed" }
+	///
+	/// default: {}
+	#[serde(default)]
+	pub url_preview_oembed_providers: BTreeMap<String, Url>,
+
 	/// List of forbidden room aliases and room IDs as strings of regex
 	/// patterns.
 	///
@@ -2292,6 +2368,38 @@ pub struct Config {
 	#[serde(default)]
 	pub jwt: JwtConfig,
 
+	// external structure; separate section
+	#[serde(default)]
+	pub scim: ScimConfig,
+
+	// external structure; separate section
+	#[serde(default)]
+	pub terms: TermsConfig,
+
+	// external structure; separate section
+	#[serde(default)]
+	pub admin_roles: AdminRolesConfig,
+
+	// external structure; separate section
+	#[serde(default)]
+	pub server_notices: ServerNoticesConfig,
+
+	// external structure; separate section
+	#[serde(default)]
+	pub media_scanning: MediaScanningConfig,
+
+	// external structure; separate section
+	#[serde(default)]
+	pub media_sanitizing: MediaSanitizingConfig,
+
+	// external structure; separate section
+	#[serde(default)]
+	pub media_retention: MediaRetentionConfig,
+
+	// external structure; separate section
+	#[serde(default)]
+	pub livekit: LiveKitConfig,
+
 	// external structure; separate section
 	#[serde(default)]
 	pub appservice: BTreeMap<String, AppService>,
@@ -2520,6 +2628,81 @@ pub struct LdapConfig {
 	/// default:
 	#[serde(default)]
 	pub admin_filter: String,
+
+	/// Interval in seconds between synchronisations of the directory.
+	///
+	/// Each synchronisation joins and leaves LDAP users to the rooms and
+	/// workspaces mapped to their groups, updates their displayname from
+	/// `name_attribute`, grants or revokes admin status according to
+	/// `admin_filter` and deactivates users missing from the directory. A bind
+	/// DN without `{username}` is required to search the directory. Set to 0
+	/// to disable.
+	///
+	/// default: 0
+	#[serde(default)]
+	pub sync_interval: u64,
+
+	/// Root of the searches for groups.
+	///
+	/// Defaults to `base_dn` if empty.
+	///
+	/// example: "ou=groups,dc=example,dc=org"
+	///
+	/// default:
+	#[serde(default)]
+	pub group_base_dn: String,
+
+	/// Search filter to find the groups.
+	///
+	/// default: "(|(objectClass=groupOfNames)(objectClass=groupOfUniqueNames))"
+	#[serde(default = "default_ldap_group_filter")]
+	pub group_filter: String,
+
+	/// Attribute of a group containing the distinguished names of its members.
+	///
+	/// example: "uniqueMember"
+	///
+	/// default: "member"
+	#[serde(default = "default_ldap_group_member_attribute")]
+	pub group_member_attribute: String,
+
+	/// Rooms members of a group are joined to, keyed by the distinguished
+	/// name of the group. LDAP users are made to leave these rooms once they
+	/// are no longer members of any group mapped to them.
+	///
+	/// example: { "cn=engineering,ou=groups,dc=example,dc=org" =
+	/// ["#engineering:example.com"] }
+	///
+	/// default: {}
+	#[serde(default)]
+	pub group_rooms: BTreeMap<String, Vec<OwnedRoomOrAliasId>>,
+
+	/// Workspaces members of a group are joined to, keyed by the
+	/// distinguished name of the group. Membership of a workspace is
+	/// membership of its space room.
+	///
+	/// example: { "cn=engineering,ou=groups,dc=example,dc=org" = ["eng"] }
+	///
+	/// default: {}
+	#[serde(default)]
+	pub group_workspaces: BTreeMap<String, Vec<String>>,
+
+	/// Whether synchronisation deactivates LDAP users which are no longer
+	/// found in the directory. Nothing is deactivated when the search returns
+	/// no users at all.
+	///
+	/// default: true
+	#[serde(default = "true_fn")]
+	pub sync_deactivate: bool,
+
+	/// Whether synchronisation adopts existing users with the "password"
+	/// origin which are found in the directory, turning them into LDAP users.
+	/// Users who registered before LDAP was enabled, or who were created by an
+	/// admin, are otherwise never synchronised.
+	///
+	/// default: false
+	#[serde(default)]
+	pub sync_adopt_password_users: bool,
 }
 
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -2552,6 +2735,55 @@ pub struct JwtConfig {
 	#[serde(default = "default_jwt_format")]
 	pub format: String,
 
+	/// URL of a JSON Web Key Set published by the identity provider. When set,
+	/// tokens are verified with the key named by their 'kid' header instead of
+	/// 'key', so the provider can rotate its keys. The algorithm of a key
+	/// defaults to 'algorithm' when the set does not specify it.
+	///
+	/// example: "https://idp.example.com/.well-known/jwks.json"
+	pub jwks_url: Option<Url>,
+
+	/// Interval in seconds between refreshes of the key set. A token signed
+	/// with an unknown key also triggers a refresh, at most every
+	/// 'jwks_min_refresh_interval' seconds.
+	///
+	/// default: 3600
+	#[serde(default = "default_jwks_refresh_interval")]
+	pub jwks_refresh_interval: u64,
+
+	/// Minimum interval in seconds between refreshes of the key set caused by
+	/// tokens signed with an unknown key.
+	///
+	/// default: 30
+	#[serde(default = "default_jwks_min_refresh_interval")]
+	pub jwks_min_refresh_interval: u64,
+
+	/// Claim holding the localpart of the user. By default the
+	/// 'preferred_username' claim is used when present, otherwise 'sub'.
+	/// Nested claims are named by their dot-separated path.
+	///
+	/// example: "email"
+	pub localpart_claim: Option<String>,
+
+	/// Claim holding the displayname of the user, set on every login. Nested
+	/// claims are named by their dot-separated path.
+	///
+	/// example: "name"
+	pub displayname_claim: Option<String>,
+
+	/// Claim granting server admin rights. The user is an admin when the claim
+	/// is true, equals 'admin_claim_value', or is a list containing it; the
+	/// rights are granted or revoked on every login. Nested claims are named
+	/// by their dot-separated path.
+	///
+	/// example: "realm_access.roles"
+	pub admin_claim: Option<String>,
+
+	/// Value of 'admin_claim' granting server admin rights.
+	///
+	/// example: "matrix-admin"
+	pub admin_claim_value: Option<String>,
+
 	/// Automatically create new user from a valid claim, otherwise access is
 	/// denied for an unknown even with an authentic token.
 	///
@@ -2616,6 +2848,450 @@ pub struct JwtConfig {
 	pub validate_signature: bool,
 }
 
+#[derive(Clone, Debug, Default, Deserialize)]
+#[config_example_generator(filename = "tuwunel-example.toml", section = "global.scim")]
+pub struct ScimConfig {
+	/// Enable the SCIM 2.0 provisioning API under `/scim/v2`. Users are
+	/// provisioned as local accounts, and groups map onto workspaces or rooms.
+	///
+	/// default: false
+	#[serde(default)]
+	pub enable: bool,
+
+	/// Bearer token the provisioning client must authenticate with.
+	///
+	/// display: sensitive
+	/// default:
+	#[serde(default)]
+	pub token: Option<String>,
+
+	/// Path to a file on the system that contains the bearer token. This
+	/// takes precedence over `token`.
+	///
+	/// default:
+	#[serde(default)]
+	pub token_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[config_example_generator(filename = "tuwunel-example.toml", section = "global.terms")]
+pub struct TermsConfig {
+	/// Policy documents users must accept, offered through the
+	/// `m.login.terms` authentication stage at registration. Each policy is
+	/// a table with its `version` and one `{ name, url }` document per
+	/// language, e.g.:
+	///
+	/// [global.terms.policies.privacy_policy]
+	/// version = "1.0"
+	/// en = { name = "Privacy Policy", url = "https://example.org/privacy-1.0.html" }
+	///
+	/// Changing the version of any policy requires every user to accept the
+	/// policies again.
+	///
+	/// default: {}
+	#[serde(default)]
+	pub policies: BTreeMap<String, TermsPolicy>,
+
+	/// Refuse requests of users who have not accepted the current policies,
+	/// except those needed to keep a client running and to accept them.
+	/// Requires `consent_url` to be set: users who registered without the
+	/// `m.login.terms` stage, e.g. through SSO, JWT, LDAP or SCIM, can only
+	/// accept the policies on that page.
+	#[serde(default)]
+	pub block_without_consent: bool,
+
+	/// Page where users review and accept the policies, returned to clients
+	/// in `M_CONSENT_NOT_GIVEN` errors. It must record the acceptance through
+	/// `POST /_matrix/client/unstable/org.tuwunel/terms`. Defaults to the
+	/// English document of the first policy.
+	///
+	/// example: "https://example.org/consent.html"
+	pub consent_url: Option<Url>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TermsPolicy {
+	pub version: String,
+
+	/// Documents by language code.
+	#[serde(flatten)]
+	pub documents: BTreeMap<String, TermsDocument>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TermsDocument {
+	pub name: String,
+	pub url: Url,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[config_example_generator(filename = "tuwunel-example.toml", section = "global.admin_roles")]
+pub struct AdminRolesConfig {
+	/// Role of the admins who were not assigned one with
+	/// `!admin users set-admin-role`. The default keeps every admin able to
+	/// run every command. Users joined to the admin room by a force-join
+	/// command get the `support` role instead.
+	///
+	/// default: "superadmin"
+	#[serde(default = "default_admin_role")]
+	pub default_role: String,
+
+	/// Admin roles in addition to, or replacing, the built-in `superadmin`,
+	/// `operator`, `moderator` and `support` roles. Each role is a list of
+	/// the admin commands it may run: a command group such as "rooms", a
+	/// command such as "users list-users", or "*" for every command, e.g.:
+	///
+	/// [global.admin_roles.roles]
+	/// helpdesk = ["users list-users", "users reset-password", "token"]
+	///
+	/// Assigning admin roles, registering appservices and joining users to
+	/// the admin room are reserved to superadmins. Admins may reset the
+	/// passwords of or deactivate only the accounts of admins of a lower role.
+	/// Commands of the server console are never restricted.
+	///
+	/// default: {}
+	#[serde(default)]
+	pub roles: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for AdminRolesConfig {
+	fn default() -> Self {
+		Self {
+			default_role: default_admin_role(),
+			roles: BTreeMap::new(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[config_example_generator(filename = "tuwunel-example.toml", section = "global.server_notices")]
+pub struct ServerNoticesConfig {
+	/// Localpart of the system user sending server notices to users. Each
+	/// user receives the notices in a room of their own, tagged
+	/// `m.server_notice`. Server notices are disabled unless this is set.
+	/// The localpart is reserved against registration; the server refuses to
+	/// start when it names an existing account of a person.
+	///
+	/// example: "notices"
+	pub system_localpart: Option<String>,
+
+	/// Display name of the system user.
+	///
+	/// default: "Server Notices"
+	#[serde(default = "default_server_notices_name")]
+	pub system_display_name: String,
+
+	/// Avatar of the system user, as an `mxc://` URI.
+	pub system_avatar_url: Option<OwnedMxcUri>,
+
+	/// Name of the rooms in which users receive server notices.
+	///
+	/// default: "Server Notices"
+	#[serde(default = "default_server_notices_name")]
+	pub room_name: String,
+}
+
+impl Default for ServerNoticesConfig {
+	fn default() -> Self {
+		Self {
+			system_localpart: None,
+			system_display_name: default_server_notices_name(),
+			system_avatar_url: None,
+			room_name: default_server_notices_name(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[config_example_generator(filename = "tuwunel-example.toml", section = "global.media_scanning")]
+pub struct MediaScanningConfig {
+	/// Scanner checking media for malware before it is stored or served:
+	/// "clamd" for a ClamAV daemon or "http" for a generic HTTP scanner.
+	/// Media is not scanned unless this is set. Infected media is
+	/// quarantined and clients receive an error.
+	///
+	/// example: "clamd"
+	pub backend: Option<String>,
+
+	/// Address of the ClamAV daemon: "host:port" for TCP or the absolute
+	/// path of its unix socket.
+	///
+	/// default: "127.0.0.1:3310"
+	#[serde(default = "default_clamd_address")]
+	pub clamd_address: String,
+
+	/// URL of the HTTP scanner. The content is POSTed as the request body and
+	/// the scanner replies with JSON `{"clean": true}`, or
+	/// `{"clean": false, "threat": "<name>"}` for infected content.
+	///
+	/// example: "http://127.0.0.1:8080/scan"
+	pub http_url: Option<Url>,
+
+	/// Bearer token sent to the HTTP scanner.
+	///
+	/// display: sensitive
+	pub http_token: Option<String>,
+
+	/// Timeout of a single scan in seconds.
+	///
+	/// default: 60
+	#[serde(default = "default_media_scan_timeout")]
+	pub timeout: u64,
+
+	/// Serve and store media when the scanner can not be reached or fails.
+	/// By default such media is refused until it can be scanned: uploads are
+	/// rejected and not stored, while stored media is not served.
+	#[serde(default)]
+	pub fail_open: bool,
+}
+
+impl Default for MediaScanningConfig {
+	fn default() -> Self {
+		Self {
+			backend: None,
+			clamd_address: default_clamd_address(),
+			http_url: None,
+			http_token: None,
+			timeout: default_media_scan_timeout(),
+			fail_open: false,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[config_example_generator(
+	filename = "tuwunel-example.toml",
+	section = "global.media_sanitizing"
+)]
+pub struct MediaSanitizingConfig {
+	/// Content types of uploaded images whose metadata (EXIF, XMP, ICC
+	/// profiles) is stripped. The EXIF orientation is applied to the image
+	/// beforehand. Supported are JPEG, PNG and WebP; WebP images are
+	/// re-encoded losslessly. Images which can not be processed are stored
+	/// as uploaded.
+	///
+	/// example: ["image/jpeg", "image/png", "image/webp"]
+	///
+	/// default: []
+	#[serde(default)]
+	pub strip_metadata: Vec<String>,
+
+	/// Content types of uploaded images which are scaled down to
+	/// `max_dimension` when larger. Their metadata is stripped as well.
+	///
+	/// example: ["image/jpeg", "image/png"]
+	///
+	/// default: []
+	#[serde(default)]
+	pub downscale: Vec<String>,
+
+	/// Maximum width and height of downscaled images in pixels. The aspect
+	/// ratio is kept. 0 disables downscaling.
+	///
+	/// default: 4096
+	#[serde(default = "default_media_sanitizing_max_dimension")]
+	pub max_dimension: u32,
+
+	/// Quality of re-encoded JPEG images, from 1 to 100.
+	///
+	/// default: 90
+	#[serde(default = "default_media_sanitizing_jpeg_quality")]
+	pub jpeg_quality: u8,
+}
+
+impl MediaSanitizingConfig {
+	/// Whether uploads of the content type are processed.
+	#[must_use]
+	pub fn is_enabled_for(&self, content_type: &str) -> bool {
+		self.strip_metadata
+			.iter()
+			.chain(self.downscale.iter())
+			.any(|ct| ct.eq_ignore_ascii_case(content_type))
+	}
+}
+
+impl Default for MediaSanitizingConfig {
+	fn default() -> Self {
+		Self {
+			strip_metadata: Vec::new(),
+			downscale: Vec::new(),
+			max_dimension: default_media_sanitizing_max_dimension(),
+			jpeg_quality: default_media_sanitizing_jpeg_quality(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[config_example_generator(
+	filename = "tuwunel-example.toml",
+	section = "global.media_retention"
+)]
+pub struct MediaRetentionConfig {
+	/// Local uploads which are not referenced by any event, e.g. as an
+	/// attachment, an inline image of a formatted body or an image of an emote
+	/// pack, nor are the avatar or in the emote pack of a local user, are
+	/// deleted this many days after their upload. 0 keeps them forever.
+	///
+	/// References in encrypted events are not visible to the server, so
+	/// uploads are kept once their uploader sent an encrypted event after
+	/// the upload; uploads from before this was recorded are kept while
+	/// their uploader is joined to an encrypted room.
+	///
+	/// default: 0
+	#[serde(default)]
+	pub unreferenced_local_days: u64,
+
+	/// Maximum age in days of local media referenced in the rooms of a
+	/// workspace, by workspace ID, e.g.:
+	///
+	/// workspace_max_age_days = { acme = 365 }
+	///
+	/// Media referenced in several workspaces expires with the shortest
+	/// maximum age.
+	///
+	/// default: {}
+	#[serde(default)]
+	pub workspace_max_age_days: BTreeMap<String, u64>,
+
+	/// Remote media which was not downloaded from this server for this many
+	/// days is evicted from the cache; it is fetched again when requested.
+	/// 0 keeps it forever.
+	///
+	/// default: 0
+	#[serde(default)]
+	pub cold_remote_days: u64,
+
+	/// Interval between runs of the cleanup in seconds.
+	///
+	/// default: 86400
+	#[serde(default = "default_media_retention_interval")]
+	pub interval: u64,
+}
+
+impl MediaRetentionConfig {
+	/// Whether any retention policy is configured.
+	#[must_use]
+	pub fn is_enabled(&self) -> bool {
+		self.unreferenced_local_days > 0
+			|| self.cold_remote_days > 0
+			|| !self.workspace_max_age_days.is_empty()
+	}
+}
+
+impl Default for MediaRetentionConfig {
+	fn default() -> Self {
+		Self {
+			unreferenced_local_days: 0,
+			workspace_max_age_days: BTreeMap::new(),
+			cold_remote_days: 0,
+			interval: default_media_retention_interval(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[config_example_generator(filename = "tuwunel-example.toml", section = "global.livekit")]
+pub struct LiveKitConfig {
+	/// Enable the built-in MatrixRTC authorization service under
+	/// `/_tuwunel/livekit`, replacing a separate lk-jwt-service. Clients
+	/// exchange an OpenID token for a LiveKit access token to join the call
+	/// of a room they are joined to. When no `rtc_transports` are configured
+	/// in `[global.well_known]` the service is advertised there.
+	///
+	/// default: false
+	#[serde(default)]
+	pub enable: bool,
+
+	/// URL of the LiveKit SFU clients connect to.
+	///
+	/// example: "wss://livekit.example.com"
+	pub url: Option<Url>,
+
+	/// LiveKit API key the access tokens are issued with.
+	///
+	/// example: "devkey"
+	pub key: Option<String>,
+
+	/// LiveKit API secret the access tokens are signed with.
+	///
+	/// display: sensitive
+	pub secret: Option<String>,
+
+	/// Path to a file on the system that contains the LiveKit API secret.
+	/// This takes precedence over `secret`.
+	///
+	/// default:
+	pub secret_file: Option<PathBuf>,
+
+	/// Lifetime of issued access tokens in seconds.
+	///
+	/// default: 3600
+	#[serde(default = "default_livekit_token_ttl")]
+	pub token_ttl: u64,
+
+	/// Issue access tokens to users of other servers. Their OpenID tokens
+	/// are verified with their server over federation.
+	#[serde(default)]
+	pub federated_users: bool,
+
+	/// Only issue access tokens for the rooms configured in `rooms`.
+	#[serde(default)]
+	pub restrict_rooms: bool,
+
+	/// Allow participants to publish audio and video by default.
+	///
+	/// default: true
+	#[serde(default = "true_fn")]
+	pub can_publish: bool,
+
+	/// Allow participants to subscribe to the tracks of others by default.
+	///
+	/// default: true
+	#[serde(default = "true_fn")]
+	pub can_subscribe: bool,
+
+	/// Allow participants to send data messages by default.
+	///
+	/// default: true
+	#[serde(default = "true_fn")]
+	pub can_publish_data: bool,
+
+	/// Grants of specific rooms overriding the defaults above, e.g.:
+	///
+	/// [global.livekit.rooms."!town-hall:example.com"]
+	/// can_publish = false
+	///
+	/// default: {}
+	#[serde(default)]
+	pub rooms: BTreeMap<OwnedRoomId, LiveKitGrant>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct LiveKitGrant {
+	pub can_publish: Option<bool>,
+	pub can_subscribe: Option<bool>,
+	pub can_publish_data: Option<bool>,
+}
+
+impl Default for LiveKitConfig {
+	fn default() -> Self {
+		Self {
+			enable: false,
+			url: None,
+			key: None,
+			secret: None,
+			secret_file: None,
+			token_ttl: default_livekit_token_ttl(),
+			federated_users: false,
+			restrict_rooms: false,
+			can_publish: true,
+			can_subscribe: true,
+			can_publish_data: true,
+			rooms: BTreeMap::new(),
+		}
+	}
+}
+
 #[derive(Clone, Debug, Deserialize)]
 #[config_example_generator(
 	filename = "tuwunel-example.toml",
@@ -2916,6 +3592,14 @@ pub struct AppService {
 	/// default: false
 	#[serde(default)]
 	pub device_management: bool,
+
+	/// Whether the application service wants to receive the device list
+	/// changes and one-time key counts of its users, as part of MSC3202.
+	/// To-device messages for its users are sent along with ephemeral data.
+	///
+	/// default: false
+	#[serde(default)]
+	pub receive_device_lists: bool,
 }
 
 impl From<AppService> for ruma::api::appservice::Registration {
@@ -3208,6 +3892,28 @@ fn default_jaeger_filter() -> String {
 		.to_owned()
 }
 
+fn default_admin_role() -> String { "superadmin".to_owned() }
+
+fn default_server_notices_name() -> String { "Server Notices".to_owned() }
+
+fn default_clamd_address() -> String { "127.0.0.1:3310".to_owned() }
+
+fn default_media_scan_timeout() -> u64 { 60 }
+
+fn default_media_sanitizing_max_dimension() -> u32 { 4096 }
+
+fn default_media_sanitizing_jpeg_quality() -> u8 { 90 }
+
+fn default_thumbnail_pdftoppm_path() -> String { "pdftoppm".to_owned() }
+
+fn default_thumbnail_ffmpeg_path() -> String { "ffmpeg".to_owned() }
+
+fn default_thumbnail_render_timeout() -> u64 { 15 }
+
+fn default_media_retention_interval() -> u64 { 86400 }
+
+fn default_livekit_token_ttl() -> u64 { 3600 }
+
 fn default_tracing_flame_output_path() -> String { "./tracing.folded".to_owned() }
 
 fn default_trusted_servers() -> Vec<OwnedServerName> {
@@ -3313,6 +4019,12 @@ fn default_url_preview_max_spider_size() -> usize {
 	256_000 // 256KB
 }
 
+fn default_url_preview_cache_ttl() -> u64 { 60 * 60 * 24 }
+
+fn default_url_preview_max_cache_ttl() -> u64 { 60 * 60 * 24 * 7 }
+
+fn default_url_preview_domain_rate_limit() -> u32 { 30 }
+
 // fn default_new_user_displayname_suffix() -> String { "💕".to_owned() }
 fn default_new_user_displayname_suffix() -> String { String::new() }
 
@@ -3399,10 +4111,20 @@ fn default_ldap_mail_attribute() -> String { String::from("mail") }
 
 fn default_ldap_name_attribute() -> String { String::from("givenName") }
 
+fn default_ldap_group_filter() -> String {
+	"(|(objectClass=groupOfNames)(objectClass=groupOfUniqueNames))".to_owned()
+}
+
+fn default_ldap_group_member_attribute() -> String { String::from("member") }
+
 fn default_jwt_algorithm() -> String { "HS256".to_owned() }
 
 fn default_jwt_format() -> String { "HMAC".to_owned() }
 
+fn default_jwks_refresh_interval() -> u64 { 3600 }
+
+fn default_jwks_min_refresh_interval() -> u64 { 30 }
+
 fn default_client_sync_timeout_min() -> u64 { 5000 }
 
 fn default_client_sync_timeout_default() -> u64 { 30000 }
@@ -4,7 +4,7 @@
 
 use ruma::{RoomVersionId, api::client::discovery::get_capabilities::v3::RoomVersionStability};
 
-use crate::{at, is_equal_to};
+use crate::{at, is_equal_to, matrix::room_version::OWNED_STATE_ROOM_VERSIONS};
 
 /// Partially supported non-compliant room versions
 pub const UNSTABLE_ROOM_VERSIONS: &[RoomVersionId] =
@@ -59,9 +59,14 @@ fn supported_stability(&self, stability: &RoomVersionStability) -> bool {
 }
 
 pub fn available_room_versions() -> impl Iterator<Item = RoomVersion> {
+	let owned_state_room_versions = OWNED_STATE_ROOM_VERSIONS
+		.iter()
+		.filter_map(|(id, _)| RoomVersionId::try_from(*id).ok());
+
 	let unstable_room_versions = UNSTABLE_ROOM_VERSIONS
 		.iter()
 		.cloned()
+		.chain(owned_state_room_versions)
 		.zip(once(RoomVersionStability::Unstable).cycle());
 
 	STABLE_ROOM_VERSIONS
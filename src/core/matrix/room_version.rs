@@ -3,12 +3,42 @@
 
 use crate::{Result, err, matrix::Event};
 
+/// Unstable room versions implementing owned state events (MSC3757), paired
+/// with the stable room version whose rules they otherwise follow.
+pub const OWNED_STATE_ROOM_VERSIONS: &[(&str, RoomVersionId)] = &[
+	("org.matrix.msc3757.10", RoomVersionId::V10),
+	("org.matrix.msc3757.11", RoomVersionId::V11),
+];
+
 pub fn rules(room_version_id: &RoomVersionId) -> Result<RoomVersionRules> {
-	room_version_id.rules().ok_or_else(|| {
-		err!(Request(UnsupportedRoomVersion(
-			"Unknown or unsupported room version {room_version_id:?}.",
-		)))
-	})
+	room_version_id
+		.rules()
+		.or_else(|| base_version(room_version_id).and_then(RoomVersionId::rules))
+		.ok_or_else(|| {
+			err!(Request(UnsupportedRoomVersion(
+				"Unknown or unsupported room version {room_version_id:?}.",
+			)))
+		})
+}
+
+/// The stable room version an unstable room version derives its rules from,
+/// or None for room versions known to ruma.
+#[must_use]
+pub fn base_version(room_version_id: &RoomVersionId) -> Option<&'static RoomVersionId> {
+	OWNED_STATE_ROOM_VERSIONS
+		.iter()
+		.find(|(id, _)| *id == room_version_id.as_str())
+		.map(|(_, base)| base)
+}
+
+/// Whether state keys prefixed by the sender's user ID are owned by the sender
+/// (MSC3757) in this room version.
+#[inline]
+#[must_use]
+pub fn owned_state(room_version_id: &RoomVersionId) -> bool {
+	OWNED_STATE_ROOM_VERSIONS
+		.iter()
+		.any(|(id, _)| *id == room_version_id.as_str())
 }
 
 pub fn from_create_event<Pdu: Event>(create_event: &Pdu) -> Result<RoomVersionId> {
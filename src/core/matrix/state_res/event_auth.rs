@@ -8,7 +8,7 @@
 	future::{join3, try_join},
 };
 use ruma::{
-	EventId, Int, OwnedEventId, OwnedUserId,
+	EventId, Int, OwnedEventId, OwnedUserId, UserId,
 	api::client::error::ErrorKind::InvalidParam,
 	events::{
 		StateEventType, TimelineEventType,
@@ -30,7 +30,7 @@
 };
 use crate::{
 	Err, Error, Result, err,
-	matrix::{Event, StateKey},
+	matrix::{Event, StateKey, room_version},
 	trace,
 	utils::stream::{IterStream, TryReadyExt},
 };
@@ -359,12 +359,26 @@ pub(super) async fn check_state_dependent_auth_rules<Fetch, Fut, Pdu>(
 
 	// Since v1, if the event has a state_key that starts with an @ and does not
 	// match the sender, reject.
-	if incoming_event
-		.state_key()
-		.is_some_and(|k| k.starts_with('@'))
-		&& incoming_event.state_key() != Some(incoming_event.sender().as_str())
+	//
+	// With owned state (MSC3757), the state_key may also start with the sender's
+	// user ID followed by an underscore, or belong to a user with less power than
+	// the sender.
+	if let Some(state_key) = incoming_event.state_key()
+		&& state_key.starts_with('@')
+		&& state_key != sender.as_str()
 	{
-		return Err!("sender cannot send event with `state_key` matching another user's ID");
+		if !room_version::owned_state(&room_create_event.room_version()?) {
+			return Err!("sender cannot send event with `state_key` matching another user's ID");
+		}
+
+		check_owned_state_key(
+			state_key,
+			sender,
+			current_room_power_levels_event.as_ref(),
+			&rules.authorization,
+			sender_power_level,
+			creators.clone(),
+		)?;
 	}
 
 	// If type is m.room.power_levels
@@ -668,6 +682,59 @@ fn check_power_level_maps<'a, K>(
 	Ok(())
 }
 
+/// Check whether the sender of a state event may use a `state_key` starting
+/// with a user ID other than its own, according to owned state (MSC3757).
+///
+/// The `state_key` must either be a valid user ID, or a valid user ID followed
+/// by an underscore and an arbitrary suffix. The sender is allowed if it owns
+/// the `state_key`, or if its power level is greater than the owner's.
+#[tracing::instrument(level = "trace", skip_all)]
+fn check_owned_state_key<Creators, Pdu>(
+	state_key: &str,
+	sender: &UserId,
+	current_room_power_levels_event: Option<&RoomPowerLevelsEvent<Pdu>>,
+	rules: &AuthorizationRules,
+	sender_power_level: UserPowerLevel,
+	creators: Creators,
+) -> Result
+where
+	Creators: Iterator<Item = OwnedUserId> + Clone,
+	Pdu: Event,
+{
+	let owner = state_key
+		.split_once(':')
+		.map(|(localpart, server_name)| {
+			let server_name = server_name
+				.split_once('_')
+				.map_or(server_name, |(server_name, _suffix)| server_name);
+
+			format!("{localpart}:{server_name}")
+		})
+		.and_then(|owner| OwnedUserId::parse(owner).ok())
+		.ok_or_else(|| {
+			err!(
+				"`state_key` neither equals a valid user ID, nor starts with one plus an \
+				 underscore"
+			)
+		})?;
+
+	if *owner == *sender {
+		trace!("allowing event with `state_key` owned by the sender");
+		return Ok(());
+	}
+
+	let owner_power_level = current_room_power_levels_event
+		.cloned()
+		.user_power_level(&owner, creators, rules)?;
+
+	if sender_power_level > owner_power_level {
+		trace!("allowing event with `state_key` owned by a user with less power");
+		return Ok(());
+	}
+
+	Err!("sender cannot send event with `state_key` owned by another user")
+}
+
 /// Check whether the given event passes the `m.room.redaction` authorization
 /// rules.
 fn check_room_redaction<Pdu>(
@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+
 use ruma::{
+	EventId, OwnedEventId, UserId,
 	events::{
 		TimelineEventType,
 		room::{
@@ -643,6 +646,149 @@ async fn user_id_state_key_is_sender() {
 		.unwrap();
 }
 
+/// Initial events of a room with owned state (MSC3757) where anyone may send
+/// call member events.
+fn owned_state_events(room_version: &str) -> HashMap<OwnedEventId, PduEvent> {
+	let mut init_events = INITIAL_EVENTS();
+	*init_events.get_mut(&event_id("CREATE")).unwrap() = to_pdu_event::<&EventId>(
+		"CREATE",
+		alice(),
+		TimelineEventType::RoomCreate,
+		Some(""),
+		to_raw_json_value(&json!({
+			"creator": alice(),
+			"room_version": room_version,
+		}))
+		.unwrap(),
+		&[],
+		&[],
+	);
+	*init_events.get_mut(&event_id("IPOWER")).unwrap() = to_pdu_event(
+		"IPOWER",
+		alice(),
+		TimelineEventType::RoomPowerLevels,
+		Some(""),
+		to_raw_json_value(&json!({
+			"users": { alice(): 100 },
+			"events": { "org.matrix.msc3401.call.member": 0 },
+		}))
+		.unwrap(),
+		&["CREATE", "IMA"],
+		&["IMA"],
+	);
+
+	init_events
+}
+
+fn call_member_event(sender: &UserId, state_key: &str) -> PduEvent {
+	to_pdu_event(
+		"HELLO",
+		sender,
+		"org.matrix.msc3401.call.member".into(),
+		Some(state_key),
+		to_raw_json_value(&json!({})).unwrap(),
+		&["IMC", "IPOWER", "CREATE"],
+		&["IPOWER"],
+	)
+}
+
+#[tokio::test]
+async fn owned_state_key_suffix_is_sender() {
+	let _guard = init_subscriber();
+
+	let incoming_event = call_member_event(charlie(), "@charlie:foo_DEVICE");
+
+	let init_events = owned_state_events("org.matrix.msc3757.10");
+	let auth_events = TestStateMap::new(&init_events);
+	let fetch_state = auth_events.fetch_state_fn();
+
+	// Can send state event with a state key made of the sender's user ID and a
+	// suffix.
+	check_state_dependent_auth_rules(&RoomVersionRules::V10, &incoming_event, &fetch_state)
+		.await
+		.unwrap();
+}
+
+#[tokio::test]
+async fn owned_state_key_suffix_without_owned_state() {
+	let _guard = init_subscriber();
+
+	let incoming_event = call_member_event(charlie(), "@charlie:foo_DEVICE");
+
+	let init_events = owned_state_events("10");
+	let auth_events = TestStateMap::new(&init_events);
+	let fetch_state = auth_events.fetch_state_fn();
+
+	// Room versions without owned state only allow the exact user ID.
+	check_state_dependent_auth_rules(&RoomVersionRules::V10, &incoming_event, &fetch_state)
+		.await
+		.unwrap_err();
+}
+
+#[tokio::test]
+async fn owned_state_key_other_user_less_power() {
+	let _guard = init_subscriber();
+
+	let incoming_event = call_member_event(alice(), "@charlie:foo_DEVICE");
+
+	let init_events = owned_state_events("org.matrix.msc3757.10");
+	let auth_events = TestStateMap::new(&init_events);
+	let fetch_state = auth_events.fetch_state_fn();
+
+	// Can send state event owned by a user with less power than the sender.
+	check_state_dependent_auth_rules(&RoomVersionRules::V10, &incoming_event, &fetch_state)
+		.await
+		.unwrap();
+
+	let incoming_event = call_member_event(alice(), charlie().as_str());
+
+	// Including a state key which is exactly the other user's ID.
+	check_state_dependent_auth_rules(&RoomVersionRules::V10, &incoming_event, &fetch_state)
+		.await
+		.unwrap();
+}
+
+#[tokio::test]
+async fn owned_state_key_other_user_same_power() {
+	let _guard = init_subscriber();
+
+	let incoming_event = call_member_event(charlie(), "@bob:foo_DEVICE");
+
+	let init_events = owned_state_events("org.matrix.msc3757.11");
+	let auth_events = TestStateMap::new(&init_events);
+	let fetch_state = auth_events.fetch_state_fn();
+
+	// Cannot send state event owned by a user with as much power as the sender.
+	check_state_dependent_auth_rules(&RoomVersionRules::V11, &incoming_event, &fetch_state)
+		.await
+		.unwrap_err();
+
+	let incoming_event = call_member_event(charlie(), "@alice:foo_DEVICE");
+
+	// Nor one owned by a user with more power.
+	check_state_dependent_auth_rules(&RoomVersionRules::V11, &incoming_event, &fetch_state)
+		.await
+		.unwrap_err();
+}
+
+#[tokio::test]
+async fn owned_state_key_invalid_user_id() {
+	let _guard = init_subscriber();
+
+	let init_events = owned_state_events("org.matrix.msc3757.11");
+	let auth_events = TestStateMap::new(&init_events);
+	let fetch_state = auth_events.fetch_state_fn();
+
+	// Cannot send state event with a state key which does not start with a valid
+	// user ID, even with enough power.
+	for state_key in ["@charlie", "@charlie_DEVICE"] {
+		let incoming_event = call_member_event(alice(), state_key);
+		check_state_dependent_auth_rules(&RoomVersionRules::V11, &incoming_event, &fetch_state)
+			.await
+			.unwrap_err();
+	}
+}
+
 #[tokio::test]
 async fn auth_event_in_different_room() {
 	let _guard = init_subscriber();
@@ -8,3 +8,15 @@ pub fn verify_password(password: &str, password_hash: &str) -> Result {
 }
 
 pub fn password(password: &str) -> Result<String> { argon::password(password) }
+
+/// Compares two secrets in time independent of their contents. Both are hashed
+/// first so that neither their length nor a common prefix is revealed.
+#[must_use]
+pub fn secrets_equal(a: &[u8], b: &[u8]) -> bool {
+	let (a, b) = (sha256::hash(a), sha256::hash(b));
+
+	a.iter()
+		.zip(b.iter())
+		.fold(0_u8, |diff, (x, y)| diff | (x ^ y))
+		== 0
+}
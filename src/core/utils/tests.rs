@@ -317,3 +317,13 @@ async fn set_difference_sorted_stream2() {
 	println!("{r:?}");
 	assert!(r.eq(&["aaa", "eee", "hhh"]));
 }
+
+#[test]
+fn secrets_equal() {
+	use utils::hash::secrets_equal;
+
+	assert!(secrets_equal(b"token", b"token"));
+	assert!(!secrets_equal(b"token", b"tokem"));
+	assert!(!secrets_equal(b"token", b"token2"));
+	assert!(!secrets_equal(b"", b"token"));
+}
@@ -34,6 +34,10 @@ pub(super) fn open_list(engine: &Arc<Engine>, maps: &[Descriptor]) -> Result<Map
 		name: "aliasid_alias",
 		..descriptor::RANDOM_SMALL
 	},
+	Descriptor {
+		name: "auditid_entry",
+		..descriptor::SEQUENTIAL
+	},
 	Descriptor {
 		name: "authchainkey_authchain",
 		cache_disp: CacheDisp::SharedWith("shorteventid_authchain"),
@@ -122,14 +126,41 @@ pub(super) fn open_list(engine: &Arc<Engine>, maps: &[Descriptor]) -> Result<Map
 		name: "logintoken_expiresatuserid",
 		..descriptor::RANDOM_SMALL
 	},
+	Descriptor {
+		name: "mediaid_accessed",
+		val_size_hint: Some(8),
+		..descriptor::RANDOM_SMALL
+	},
+	Descriptor {
+		name: "mediaid_created",
+		val_size_hint: Some(8),
+		..descriptor::RANDOM_SMALL
+	},
 	Descriptor {
 		name: "mediaid_file",
 		..descriptor::RANDOM_SMALL
 	},
+	Descriptor {
+		name: "mediaid_info",
+		..descriptor::RANDOM_SMALL
+	},
+	Descriptor {
+		name: "mediaid_quarantine",
+		..descriptor::RANDOM_SMALL
+	},
 	Descriptor {
 		name: "mediaid_user",
 		..descriptor::RANDOM_SMALL
 	},
+	Descriptor {
+		name: "media_stats",
+		..descriptor::RANDOM_SMALL
+	},
+	Descriptor {
+		name: "media_sha256_blocked",
+		key_size_hint: Some(32),
+		..descriptor::RANDOM_SMALL
+	},
 	Descriptor {
 		// Maps SHA-256 content hash (32 bytes) → u32 reference count
 		// Used for content-based media deduplication
@@ -138,6 +169,11 @@ pub(super) fn open_list(engine: &Arc<Engine>, maps: &[Descriptor]) -> Result<Map
 		val_size_hint: Some(4),
 		..descriptor::RANDOM_SMALL
 	},
+	Descriptor {
+		name: "media_sha256_scan",
+		key_size_hint: Some(32),
+		..descriptor::RANDOM_SMALL
+	},
 	Descriptor {
 		// Maps media DB key → SHA-256 content hash (32 bytes)
 		// Allows delete() to find which hash to decrement when removing an MXC
@@ -406,6 +442,10 @@ pub(super) fn open_list(engine: &Arc<Engine>, maps: &[Descriptor]) -> Result<Map
 		name: "workspaceid_roomids",
 		..descriptor::RANDOM_SMALL
 	},
+	Descriptor {
+		name: "url_preview_replaced",
+		..descriptor::RANDOM_SMALL
+	},
 	Descriptor {
 		name: "url_previews",
 		..descriptor::RANDOM
@@ -439,6 +479,10 @@ pub(super) fn open_list(engine: &Arc<Engine>, maps: &[Descriptor]) -> Result<Map
 		name: "userfilterid_filter",
 		..descriptor::RANDOM_SMALL
 	},
+	Descriptor {
+		name: "userid_adminrole",
+		..descriptor::RANDOM_SMALL
+	},
 	Descriptor {
 		name: "userid_avatarurl",
 		..descriptor::RANDOM_SMALL
@@ -447,6 +491,10 @@ pub(super) fn open_list(engine: &Arc<Engine>, maps: &[Descriptor]) -> Result<Map
 		name: "userid_blurhash",
 		..descriptor::RANDOM_SMALL
 	},
+	Descriptor {
+		name: "userid_consent",
+		..descriptor::RANDOM_SMALL
+	},
 	Descriptor {
 		name: "userid_dehydrateddevice",
 		..descriptor::RANDOM_SMALL
@@ -459,6 +507,11 @@ pub(super) fn open_list(engine: &Arc<Engine>, maps: &[Descriptor]) -> Result<Map
 		name: "userid_displayname",
 		..descriptor::RANDOM_SMALL
 	},
+	Descriptor {
+		name: "userid_encryptedsent",
+		val_size_hint: Some(8),
+		..descriptor::RANDOM_SMALL
+	},
 	Descriptor {
 		name: "userid_lastonetimekeyupdate",
 		..descriptor::RANDOM_SMALL
@@ -487,6 +540,10 @@ pub(super) fn open_list(engine: &Arc<Engine>, maps: &[Descriptor]) -> Result<Map
 		name: "userid_selfsigningkeyid",
 		..descriptor::RANDOM_SMALL
 	},
+	Descriptor {
+		name: "userid_servernoticeroomid",
+		..descriptor::RANDOM_SMALL
+	},
 	Descriptor {
 		name: "userid_usersigningkeyid",
 		..descriptor::RANDOM_SMALL
@@ -1,5 +1,6 @@
 mod direct;
 mod room_tags;
+mod upgrade;
 
 use std::sync::Arc;
 
@@ -13,6 +14,7 @@
 	serde::Raw,
 };
 use serde::Deserialize;
+use serde_json::{json, value::RawValue as RawJsonValue};
 use tuwunel_core::{
 	Err, Result, at, err, implement,
 	utils::{ReadyExt, result::LogErr, stream::TryIgnore},
@@ -82,6 +84,33 @@ pub async fn update(
 	Ok(())
 }
 
+/// Removes an event from the account data of the user (MSC3391). The entry is
+/// replaced by a tombstone with empty content, which informs other devices of
+/// the removal through sync. Removing absent account data does nothing.
+#[implement(Service)]
+pub async fn delete(
+	&self,
+	room_id: Option<&RoomId>,
+	user_id: &UserId,
+	event_type: RoomAccountDataEventType,
+) -> Result {
+	if self
+		.get_raw(room_id, user_id, &event_type.to_string())
+		.await
+		.is_err()
+	{
+		return Ok(());
+	}
+
+	let tombstone = json!({
+		"type": event_type.to_string(),
+		"content": {},
+	});
+
+	self.update(room_id, user_id, event_type, &tombstone)
+		.await
+}
+
 /// Searches the room account data for a specific kind.
 #[implement(Service)]
 pub async fn get_global<T>(&self, user_id: &UserId, kind: GlobalAccountDataEventType) -> Result<T>
@@ -126,9 +155,18 @@ pub async fn get_raw(
 				.get(&roomuserdataid)
 		})
 		.await
+		.and_then(|handle| {
+			if is_tombstone(&handle) {
+				return Err!(Request(NotFound("Account data {kind:?} was deleted.")));
+			}
+
+			Ok(handle)
+		})
 }
 
 /// Returns all changes to the account data that happened after `since`.
+/// Deletions are included as events with empty content, except when `since` is
+/// zero, because there is nothing to remove for an initial sync.
 #[implement(Service)]
 pub fn changes_since<'a>(
 	&'a self,
@@ -149,6 +187,7 @@ pub fn changes_since<'a>(
 		.ready_take_while(move |((room_id_, user_id_, count, _), _): &(Key<'_>, _)| {
 			room_id == *room_id_ && user_id == *user_id_ && to.is_none_or(|to| *count <= to)
 		})
+		.ready_filter(move |(_, v)| since > 0 || !is_tombstone(v))
 		.map(move |(_, v)| {
 			match room_id {
 				| Some(_) => serde_json::from_slice::<Raw<AnyRoomAccountDataEvent>>(v)
@@ -189,3 +228,15 @@ pub async fn last_count<'a>(
 		.await
 		.ok_or_else(|| err!(Request(NotFound("No account data found."))))
 }
+
+/// Whether the serialized account data event has empty content, which marks
+/// deleted account data (MSC3391).
+fn is_tombstone(event: &[u8]) -> bool {
+	#[derive(Deserialize)]
+	struct Content<'a> {
+		#[serde(borrow)]
+		content: &'a RawJsonValue,
+	}
+
+	serde_json::from_slice::<Content<'_>>(event).is_ok_and(|event| event.content.get() == "{}")
+}
@@ -0,0 +1,189 @@
+use futures::StreamExt;
+use ruma::{
+	RoomId, UserId,
+	events::{
+		AnyRawAccountDataEvent, GlobalAccountDataEventType, RoomAccountDataEventType,
+		direct::DirectEvent,
+	},
+};
+use serde_json::Value as JsonValue;
+use tuwunel_core::{Result, implement, is_equal_to, utils::ReadyExt};
+use tuwunel_database::Deserialized;
+
+/// Carries the data a user attached to an upgraded room over to its
+/// replacement: room account data (including tags), `m.direct` entries and
+/// room-specific push rules. Data which already exists for the new room is
+/// left alone, so this can safely be repeated.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn copy_room_data(&self, user_id: &UserId, from: &RoomId, to: &RoomId) -> Result {
+	self.copy_room_account_data(user_id, from, to)
+		.await?;
+
+	self.copy_direct(user_id, from, to).await?;
+
+	self.copy_push_rules(user_id, from, to).await
+}
+
+#[implement(super::Service)]
+async fn copy_room_account_data(&self, user_id: &UserId, from: &RoomId, to: &RoomId) -> Result {
+	let events: Vec<JsonValue> = self
+		.changes_since(Some(from), user_id, 0, None)
+		.ready_filter_map(|event| match event {
+			| AnyRawAccountDataEvent::Room(event) =>
+				serde_json::from_str(event.json().get()).ok(),
+			| AnyRawAccountDataEvent::Global(_) => None,
+		})
+		.collect()
+		.await;
+
+	for event in &events {
+		let Some(kind) = event.get("type").and_then(JsonValue::as_str) else {
+			continue;
+		};
+
+		// The read marker points at an event of the old room.
+		if kind == RoomAccountDataEventType::FullyRead.to_cow_str() {
+			continue;
+		}
+
+		if self
+			.get_raw(Some(to), user_id, kind)
+			.await
+			.is_ok()
+		{
+			continue;
+		}
+
+		self.update(Some(to), user_id, kind.into(), event)
+			.await?;
+	}
+
+	Ok(())
+}
+
+#[implement(super::Service)]
+async fn copy_direct(&self, user_id: &UserId, from: &RoomId, to: &RoomId) -> Result {
+	let Ok(mut direct_event) = self
+		.get_global::<DirectEvent>(user_id, GlobalAccountDataEventType::Direct)
+		.await
+	else {
+		return Ok(());
+	};
+
+	let mut room_ids_updated = false;
+	for room_ids in direct_event.content.0.values_mut() {
+		if room_ids.iter().any(is_equal_to!(from)) && !room_ids.iter().any(is_equal_to!(to)) {
+			room_ids.push(to.to_owned());
+			room_ids_updated = true;
+		}
+	}
+
+	if !room_ids_updated {
+		return Ok(());
+	}
+
+	self.update(
+		None,
+		user_id,
+		GlobalAccountDataEventType::Direct
+			.to_string()
+			.into(),
+		&serde_json::to_value(&direct_event)?,
+	)
+	.await
+}
+
+#[implement(super::Service)]
+async fn copy_push_rules(&self, user_id: &UserId, from: &RoomId, to: &RoomId) -> Result {
+	let kind = GlobalAccountDataEventType::PushRules.to_string();
+	let Ok(mut event) = self
+		.get_raw(None, user_id, &kind)
+		.await
+		.deserialized::<JsonValue>()
+	else {
+		return Ok(());
+	};
+
+	let Some(ruleset) = event
+		.pointer_mut("/content/global")
+		.and_then(JsonValue::as_object_mut)
+	else {
+		return Ok(());
+	};
+
+	let mut rules_updated = false;
+	for (rule_kind, rules) in ruleset.iter_mut() {
+		let Some(rules) = rules.as_array_mut() else {
+			continue;
+		};
+
+		let mut copies = Vec::new();
+		for (i, rule) in rules.iter().enumerate() {
+			if let Some(copy) = copy_push_rule(rule_kind, rule, from, to)
+				&& !rules
+					.iter()
+					.any(|rule| rule.get("rule_id") == copy.get("rule_id"))
+			{
+				copies.push((i, copy));
+			}
+		}
+
+		// Each copy directly follows its original to keep the priority order.
+		rules_updated |= !copies.is_empty();
+		for (i, copy) in copies.into_iter().rev() {
+			rules.insert(i.saturating_add(1), copy);
+		}
+	}
+
+	if !rules_updated {
+		return Ok(());
+	}
+
+	self.update(None, user_id, kind.into(), &event)
+		.await
+}
+
+/// Returns the rule rewritten for the new room when it only applies to the
+/// old room: room rules named after it, and other user-defined rules matching
+/// on its room ID.
+fn copy_push_rule(kind: &str, rule: &JsonValue, from: &RoomId, to: &RoomId) -> Option<JsonValue> {
+	let rule_id = rule.get("rule_id")?.as_str()?;
+
+	// Server-default rules apply everywhere already.
+	if rule_id.starts_with('.') {
+		return None;
+	}
+
+	let mut copy = rule.clone();
+	if kind == "room" {
+		if rule_id != from.as_str() {
+			return None;
+		}
+	} else {
+		let mut matched = false;
+		let conditions = copy.get_mut("conditions")?.as_array_mut()?;
+		for condition in conditions.iter_mut().filter(|condition| {
+			condition.get("key").and_then(JsonValue::as_str) == Some("room_id")
+				&& condition
+					.get("pattern")
+					.and_then(JsonValue::as_str)
+					== Some(from.as_str())
+		}) {
+			condition
+				.as_object_mut()?
+				.insert("pattern".into(), to.as_str().into());
+
+			matched = true;
+		}
+
+		if !matched {
+			return None;
+		}
+	}
+
+	copy.as_object_mut()?
+		.insert("rule_id".into(), rule_id.replace(from.as_str(), to.as_str()).into());
+
+	Some(copy)
+}
@@ -213,6 +213,9 @@ pub async fn revoke_admin(&self, user_id: &UserId) -> Result {
 			&state_lock,
 		)
 		.boxed()
-		.await
-		.map(|_| ())
+		.await?;
+
+	self.unset_admin_role(user_id);
+
+	Ok(())
 }
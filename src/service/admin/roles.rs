@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+
+use futures::Stream;
+use ruma::{RoomId, UserId};
+use tuwunel_core::{
+	Err, Result, implement,
+	utils::stream::{ReadyExt, TryIgnore},
+};
+use tuwunel_database::Deserialized;
+
+/// Role of the server user and, by default, of every admin.
+const SUPERADMIN: &str = "superadmin";
+
+/// Role of the users joined to the admin room by a command, rather than the
+/// default role.
+const JOINED_ROLE: &str = "support";
+
+/// Built-in roles and the commands they may run; configured roles of the same
+/// name replace them.
+const BUILTIN_ROLES: &[(&str, &[&str])] = &[
+	(SUPERADMIN, &["*"]),
+	("operator", &[
+		"appservices",
+		"audit",
+		"federation",
+		"media",
+		"notices",
+		"rooms",
+		"server",
+		"token",
+		"users",
+	]),
+	("moderator", &[
+		"federation disable-room",
+		"federation enable-room",
+		"federation remote-user-in-rooms",
+		"media delete",
+		"media delete-all-from-user",
+		"media delete-list",
+		"media get-file-info",
+		"rooms",
+		"users deactivate",
+		"users force-leave-room",
+		"users last-active",
+		"users list-joined-rooms",
+		"users list-users",
+		"users redact-event",
+	]),
+	("support", &[
+		"rooms exists",
+		"rooms info",
+		"rooms list-rooms",
+		"token",
+		"users consent-status",
+		"users delete-device",
+		"users last-active",
+		"users list-joined-rooms",
+		"users list-users",
+		"users reset-password",
+	]),
+];
+
+/// Commands only superadmins may run, whatever the grants of other roles:
+/// they could otherwise make any account a superadmin or act as any user.
+const SUPERADMIN_COMMANDS: &[&str] = &[
+	"appservices register",
+	"users make-user-admin",
+	"users set-admin-role",
+	"users unset-admin-role",
+];
+
+/// Checks whether the admin may run the command, given as the path of its
+/// (sub)command names, e.g. `["users", "list-users"]`.
+#[implement(super::Service)]
+pub async fn check_admin_permission(&self, user_id: &UserId, command: &[&str]) -> Result {
+	let role = self.admin_role(user_id).await;
+	let Some(grants) = self.admin_role_grants(&role) else {
+		return Err!(
+			"Permission denied: the admin role {role:?} of {user_id} is not configured."
+		);
+	};
+
+	if !role_permits(&role, &grants, command) {
+		return Err!(
+			"Permission denied: the admin role {role:?} of {user_id} does not permit `{}`.",
+			command.join(" ")
+		);
+	}
+
+	Ok(())
+}
+
+/// Checks whether the admin may act on the account of the target, e.g. reset
+/// its password or deactivate it: admins may only act on the accounts of
+/// admins of a lower role.
+#[implement(super::Service)]
+pub async fn check_admin_target(&self, user_id: &UserId, target: &UserId) -> Result {
+	if target != self.services.globals.server_user && !self.user_is_admin(target).await {
+		return Ok(());
+	}
+
+	let role = self.admin_role(user_id).await;
+	let target_role = self.admin_role(target).await;
+	if !outranks(&role, &target_role) {
+		return Err!(
+			"Permission denied: the admin role {role:?} of {user_id} does not outrank the admin \
+			 role {target_role:?} of {target}."
+		);
+	}
+
+	Ok(())
+}
+
+/// Checks whether the admin may join users to the room by a command: joining
+/// the admin room makes them admins, which only superadmins may do.
+#[implement(super::Service)]
+pub async fn check_admin_room_join(&self, user_id: &UserId, room_id: &RoomId) -> Result {
+	if !self.is_admin_room(room_id).await {
+		return Ok(());
+	}
+
+	let role = self.admin_role(user_id).await;
+	if role != SUPERADMIN {
+		return Err!(
+			"Permission denied: the admin role {role:?} of {user_id} does not permit joining \
+			 users to the admin room."
+		);
+	}
+
+	Ok(())
+}
+
+/// Assigns the lowest built-in role to the user joined to the admin room by a
+/// command, unless they were assigned one already.
+#[implement(super::Service)]
+pub async fn set_joined_admin_role(&self, user_id: &UserId) {
+	if self
+		.db
+		.userid_adminrole
+		.get(user_id)
+		.await
+		.is_err()
+	{
+		self.db
+			.userid_adminrole
+			.insert(user_id, JOINED_ROLE);
+	}
+}
+
+/// The role of the admin; the configured default when none was assigned.
+#[implement(super::Service)]
+pub async fn admin_role(&self, user_id: &UserId) -> String {
+	if user_id == self.services.globals.server_user {
+		return SUPERADMIN.to_owned();
+	}
+
+	self.db
+		.userid_adminrole
+		.get(user_id)
+		.await
+		.deserialized()
+		.unwrap_or_else(|_| {
+			self.services
+				.server
+				.config
+				.admin_roles
+				.default_role
+				.clone()
+		})
+}
+
+/// Assigns a role to the admin.
+#[implement(super::Service)]
+pub fn set_admin_role(&self, user_id: &UserId, role: &str) -> Result {
+	if self.admin_role_grants(role).is_none() {
+		return Err!("Admin role {role:?} does not exist.");
+	}
+
+	self.db.userid_adminrole.insert(user_id, role);
+
+	Ok(())
+}
+
+/// Removes the role assigned to the admin, who gets the default role back.
+#[implement(super::Service)]
+pub fn unset_admin_role(&self, user_id: &UserId) { self.db.userid_adminrole.remove(user_id); }
+
+/// Users with an assigned role.
+#[implement(super::Service)]
+pub fn admin_role_assignments(&self) -> impl Stream<Item = (&UserId, &str)> + Send + '_ {
+	self.db
+		.userid_adminrole
+		.stream()
+		.ignore_err()
+		.ready_filter(|(user_id, _): &(&UserId, &str)| {
+			*user_id != self.services.globals.server_user
+		})
+}
+
+/// Every role with the commands it may run.
+#[implement(super::Service)]
+pub fn admin_roles(&self) -> BTreeMap<String, Vec<String>> {
+	let builtin = BUILTIN_ROLES.iter().map(|(role, grants)| {
+		let grants = grants
+			.iter()
+			.copied()
+			.map(ToOwned::to_owned)
+			.collect();
+
+		((*role).to_owned(), grants)
+	});
+
+	let configured = self
+		.services
+		.server
+		.config
+		.admin_roles
+		.roles
+		.clone();
+
+	builtin.chain(configured).collect()
+}
+
+/// The commands the role may run; none when the role does not exist.
+#[implement(super::Service)]
+pub fn admin_role_grants(&self, role: &str) -> Option<Vec<String>> {
+	self.admin_roles().remove(role)
+}
+
+/// Whether the role with the grants may run the command.
+fn role_permits(role: &str, grants: &[String], command: &[&str]) -> bool {
+	if role != SUPERADMIN
+		&& SUPERADMIN_COMMANDS
+			.iter()
+			.any(|only| permits(only, command))
+	{
+		return false;
+	}
+
+	grants.iter().any(|grant| permits(grant, command))
+}
+
+/// Whether admins of the role may act on the accounts of admins of the
+/// target role. Superadmins outrank everyone; built-in roles outrank the
+/// built-in roles after them. Configured roles outrank none, and are
+/// outranked by superadmins only.
+fn outranks(role: &str, target: &str) -> bool {
+	let rank = |role: &str| {
+		BUILTIN_ROLES
+			.iter()
+			.position(|(builtin, _)| *builtin == role)
+	};
+
+	match (rank(role), rank(target)) {
+		| (Some(0), _) => true,
+		| (Some(role), Some(target)) => role < target,
+		| _ => false,
+	}
+}
+
+/// Whether the grant covers the command: "*", a command group, or a
+/// command and its subcommands.
+fn permits(grant: &str, command: &[&str]) -> bool {
+	let grant: Vec<_> = grant.split_whitespace().collect();
+
+	grant == ["*"] || (!grant.is_empty() && command.starts_with(&grant))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{BUILTIN_ROLES, SUPERADMIN, outranks, role_permits};
+
+	fn permitted(role: &str, command: &str) -> bool {
+		let (_, grants) = BUILTIN_ROLES
+			.iter()
+			.find(|(builtin, _)| *builtin == role)
+			.expect("built-in role");
+
+		let grants: Vec<_> = grants.iter().map(ToString::to_string).collect();
+		let command: Vec<_> = command.split_whitespace().collect();
+
+		role_permits(role, &grants, &command)
+	}
+
+	#[test]
+	fn superadmin_permits_everything() {
+		assert!(permitted(SUPERADMIN, "users set-admin-role"), "role assignment");
+		assert!(permitted(SUPERADMIN, "appservices register"), "appservice registration");
+		assert!(permitted(SUPERADMIN, "server restart"), "server commands");
+	}
+
+	#[test]
+	fn no_role_assigns_roles() {
+		for (role, _) in BUILTIN_ROLES
+			.iter()
+			.filter(|(role, _)| *role != SUPERADMIN)
+		{
+			for command in [
+				"users make-user-admin",
+				"users set-admin-role",
+				"users unset-admin-role",
+				"appservices register",
+			] {
+				assert!(!permitted(role, command), "{role} may run {command}");
+			}
+		}
+	}
+
+	#[test]
+	fn configured_wildcard_is_not_superadmin() {
+		let grants = vec!["*".to_owned()];
+
+		assert!(role_permits("custom", &grants, &["users", "list-users"]), "wildcard");
+		assert!(
+			!role_permits("custom", &grants, &["users", "set-admin-role"]),
+			"role assignment by a configured role"
+		);
+	}
+
+	#[test]
+	fn builtin_grants() {
+		assert!(permitted("operator", "users reset-password"), "operator resets passwords");
+		assert!(permitted("operator", "appservices list-registered"), "operator lists");
+		assert!(permitted("moderator", "users deactivate"), "moderator deactivates");
+		assert!(!permitted("moderator", "users reset-password"), "moderator resets passwords");
+		assert!(permitted("support", "users reset-password"), "support resets passwords");
+		assert!(!permitted("support", "users deactivate"), "support deactivates");
+	}
+
+	#[test]
+	fn targets_of_lower_roles_only() {
+		assert!(outranks(SUPERADMIN, SUPERADMIN), "superadmin on superadmin");
+		assert!(outranks(SUPERADMIN, "custom"), "superadmin on configured role");
+		assert!(outranks("operator", "moderator"), "operator on moderator");
+		assert!(outranks("moderator", "support"), "moderator on support");
+
+		assert!(!outranks("operator", SUPERADMIN), "operator on superadmin");
+		assert!(!outranks("moderator", SUPERADMIN), "moderator on superadmin");
+		assert!(!outranks("support", SUPERADMIN), "support on superadmin");
+		assert!(!outranks("moderator", "moderator"), "moderator on moderator");
+		assert!(!outranks("support", "operator"), "support on operator");
+		assert!(!outranks("operator", "custom"), "operator on configured role");
+		assert!(!outranks("custom", "support"), "configured role on support");
+	}
+}
@@ -0,0 +1,203 @@
+use std::fmt;
+
+use regex::Regex;
+use ruma::ServerName;
+
+use super::{NamespaceRegex, RegistrationInfo};
+
+/// Overlap between the exclusive namespaces of two appservices.
+#[derive(Clone, Debug)]
+pub struct Collision {
+	pub kind: &'static str,
+	pub first: String,
+	pub second: String,
+	pub sample: String,
+}
+
+impl fmt::Display for Collision {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"exclusive {} namespaces of {:?} and {:?} overlap, e.g. on {:?}",
+			self.kind, self.first, self.second, self.sample
+		)
+	}
+}
+
+/// Finds the overlaps between the exclusive namespaces of all pairs of the
+/// given registrations.
+pub fn find_collisions(infos: &[&RegistrationInfo], server_name: &ServerName) -> Vec<Collision> {
+	infos
+		.iter()
+		.enumerate()
+		.flat_map(|(i, a)| {
+			infos
+				.iter()
+				.skip(i.saturating_add(1))
+				.flat_map(move |b| collisions(a, b, server_name))
+		})
+		.collect()
+}
+
+/// Finds the overlaps between the exclusive namespaces of two registrations.
+/// The sender of each appservice is part of its exclusive user namespace.
+pub fn collisions(
+	a: &RegistrationInfo,
+	b: &RegistrationInfo,
+	server_name: &ServerName,
+) -> Vec<Collision> {
+	let collision = |kind, sample: String| Collision {
+		kind,
+		first: a.registration.id.clone(),
+		second: b.registration.id.clone(),
+		sample,
+	};
+
+	let sender = |info: &RegistrationInfo| {
+		format!("@{}:{server_name}", info.registration.sender_localpart)
+	};
+
+	let mut collisions = Vec::new();
+	if a.registration.sender_localpart == b.registration.sender_localpart {
+		collisions.push(collision("user", sender(a)));
+	} else {
+		for (x, y) in [(a, b), (b, a)] {
+			if y.users.is_exclusive_match(&sender(x)) {
+				collisions.push(collision("user", sender(x)));
+			}
+		}
+	}
+
+	for (kind, x, y) in [
+		("user", &a.users, &b.users),
+		("alias", &a.aliases, &b.aliases),
+		("room", &a.rooms, &b.rooms),
+	] {
+		if let Some(sample) = overlap(x, y) {
+			collisions.push(collision(kind, sample));
+		}
+	}
+
+	collisions
+}
+
+/// Returns an identifier both exclusive namespaces match. The regular
+/// expressions are compared by matching a representative sample of each
+/// against the other; a result is therefore always a true overlap, while some
+/// intricate overlaps may go unnoticed.
+fn overlap(a: &NamespaceRegex, b: &NamespaceRegex) -> Option<String> {
+	let (Some(a), Some(b)) = (&a.exclusive, &b.exclusive) else {
+		return None;
+	};
+
+	let matches = |pattern: &str, sample: &str| {
+		Regex::new(pattern).is_ok_and(|regex| regex.is_match(sample))
+	};
+
+	a.patterns().iter().find_map(|x| {
+		b.patterns().iter().find_map(|y| {
+			if x == y {
+				return Some(sample(x).unwrap_or_else(|| x.clone()));
+			}
+
+			sample(y)
+				.filter(|s| matches(x, s))
+				.or_else(|| sample(x).filter(|s| matches(y, s)))
+		})
+	})
+}
+
+/// Builds a string matched by the regular expression: anchors are dropped,
+/// wildcards and classes are replaced by one of their characters and only
+/// the first alternative of each group is followed. The result is verified
+/// against the expression.
+fn sample(pattern: &str) -> Option<String> {
+	let mut out = String::new();
+	let mut chars = pattern.chars().peekable();
+	let mut skip_depth: Option<usize> = None;
+	let mut depth = 0_usize;
+
+	while let Some(c) = chars.next() {
+		if let Some(skip) = skip_depth {
+			match c {
+				| '\\' => _ = chars.next(),
+				| '(' => depth = depth.saturating_add(1),
+				| ')' => {
+					if depth == skip {
+						skip_depth = None;
+					}
+					depth = depth.saturating_sub(1);
+				},
+				| _ => {},
+			}
+			continue;
+		}
+
+		match c {
+			| '^' | '$' | '+' | '?' | ')' =>
+				if c == ')' {
+					depth = depth.saturating_sub(1);
+				},
+			| '(' => {
+				depth = depth.saturating_add(1);
+				if chars.peek() == Some(&'?') {
+					// non-capturing or flag group prefix
+					chars
+						.by_ref()
+						.take_while(|&c| c != ':')
+						.for_each(drop);
+				}
+			},
+			| '|' if depth == 0 => break,
+			| '|' => skip_depth = Some(depth),
+			| '*' => {
+				out.pop();
+			},
+			| '{' => {
+				chars
+					.by_ref()
+					.take_while(|&c| c != '}')
+					.for_each(drop);
+			},
+			| '.' => out.push('a'),
+			| '[' => {
+				let class: String = chars.by_ref().take_while(|&c| c != ']').collect();
+				let first = class.chars().next()?;
+				if first == '^' {
+					return None;
+				}
+
+				out.push(if first == '\\' { class.chars().nth(1)? } else { first });
+			},
+			| '\\' => match chars.next()? {
+				| 'd' => out.push('0'),
+				| 'w' => out.push('a'),
+				| 's' => out.push(' '),
+				| c if c.is_ascii_alphanumeric() => return None,
+				| c => out.push(c),
+			},
+			| c => out.push(c),
+		}
+	}
+
+	Regex::new(pattern)
+		.ok()
+		.filter(|regex| regex.is_match(&out))
+		.map(|_| out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::sample;
+
+	#[test]
+	fn sample_matches_pattern() {
+		assert_eq!(
+			sample("@_discord_.*:example\\.org").as_deref(),
+			Some("@_discord_:example.org")
+		);
+		assert_eq!(sample("^@(telegram|tg)_[0-9]+$").as_deref(), Some("@telegram_0"));
+		assert_eq!(sample("#irc_.+").as_deref(), Some("#irc_a"));
+		assert_eq!(sample("@[^a]"), None);
+	}
+}
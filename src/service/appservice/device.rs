@@ -0,0 +1,304 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use futures::StreamExt;
+use ruma::{
+	DeviceId, OwnedDeviceId, OwnedUserId, RoomId, UserId,
+	api::federation::transactions::edu::{DirectDeviceContent, Edu},
+	serde::Raw,
+	to_device::DeviceIdOrAllDevices,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::value::to_raw_value;
+use tuwunel_core::{Result, implement, utils::ReadyExt};
+
+use super::RegistrationInfo;
+use crate::sending::EduBuf;
+
+/// Device list change queued for an appservice receiving device lists
+/// (MSC3202). Tagged so that it cannot be mistaken for a federation EDU.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "org.tuwunel.device_list", rename_all = "snake_case")]
+pub(crate) enum DeviceListUpdate {
+	/// The devices or keys of the user changed.
+	Changed {
+		user_id: OwnedUserId,
+	},
+
+	/// The user no longer shares a room with the appservice.
+	Left {
+		user_id: OwnedUserId,
+	},
+
+	/// One-time keys of the device were claimed.
+	KeysClaimed {
+		user_id: OwnedUserId,
+		device_id: OwnedDeviceId,
+	},
+}
+
+/// The device list changes of a transaction to an appservice.
+#[derive(Debug, Default)]
+pub(crate) struct DeviceListChanges {
+	pub(crate) changed: BTreeSet<OwnedUserId>,
+	pub(crate) left: BTreeSet<OwnedUserId>,
+
+	/// Devices of users of the appservice whose one-time key counts are sent.
+	pub(crate) key_counts: BTreeSet<(OwnedUserId, OwnedDeviceId)>,
+}
+
+impl DeviceListChanges {
+	/// Adds a queued update; the latest of a change and a leave of a user wins.
+	/// Users of the appservice only get their one-time key counts.
+	pub(crate) fn add<F>(&mut self, update: DeviceListUpdate, is_user_match: F)
+	where
+		F: Fn(&UserId) -> bool,
+	{
+		match update {
+			| DeviceListUpdate::Changed { user_id } if !is_user_match(&user_id) => {
+				self.left.remove(&user_id);
+				self.changed.insert(user_id);
+			},
+			| DeviceListUpdate::Left { user_id } if !is_user_match(&user_id) => {
+				self.changed.remove(&user_id);
+				self.left.insert(user_id);
+			},
+			| DeviceListUpdate::KeysClaimed { user_id, device_id } if is_user_match(&user_id) => {
+				self.key_counts.insert((user_id, device_id));
+			},
+			| _ => {},
+		}
+	}
+}
+
+/// Called by users::add_to_device_event() after storing a message for a
+/// local device. The message is queued for the appservices receiving the
+/// ephemeral data of the user (MSC2409, MSC4203).
+#[implement(super::Service)]
+#[tracing::instrument(name = "to_device", level = "debug", skip_all)]
+pub(crate) async fn append_to_device(
+	&self,
+	sender: &UserId,
+	target_user_id: &UserId,
+	target_device_id: &DeviceId,
+	event_type: &str,
+	content: &serde_json::Value,
+) -> Result {
+	let appservices: Vec<_> = self
+		.read()
+		.await
+		.values()
+		.filter(|appservice| appservice.registration.receive_ephemeral)
+		.filter(|appservice| appservice.is_user_match(target_user_id))
+		.map(|appservice| appservice.registration.id.clone())
+		.collect();
+
+	if appservices.is_empty() {
+		return Ok(());
+	}
+
+	let target_device_id = DeviceIdOrAllDevices::DeviceId(target_device_id.to_owned());
+	let content = Raw::from_json(to_raw_value(content)?);
+	let edu = Edu::DirectToDevice(DirectDeviceContent {
+		sender: sender.to_owned(),
+		ev_type: event_type.into(),
+		message_id: self
+			.services
+			.globals
+			.next_count()
+			.to_string()
+			.into(),
+		messages: BTreeMap::from([(
+			target_user_id.to_owned(),
+			BTreeMap::from([(target_device_id, content)]),
+		)]),
+	});
+
+	let mut buf = EduBuf::new();
+	serde_json::to_writer(&mut buf, &edu)?;
+
+	for id in appservices {
+		self.services
+			.sending
+			.send_edu_appservice(id, buf.clone())?;
+	}
+
+	Ok(())
+}
+
+/// Called when the keys of a user change, or with the device when one-time
+/// keys of the device were claimed. The change is queued for the appservices
+/// receiving device lists (MSC3202) which manage the user or share a room
+/// with them.
+#[implement(super::Service)]
+#[tracing::instrument(name = "device_update", level = "debug", skip_all)]
+pub(crate) async fn append_device_update(
+	&self,
+	user_id: &UserId,
+	device_id: Option<&DeviceId>,
+) -> Result {
+	let appservices: Vec<_> = self
+		.read()
+		.await
+		.values()
+		.filter(|appservice| appservice.receive_device_lists)
+		.cloned()
+		.collect();
+
+	if appservices.is_empty() {
+		return Ok(());
+	}
+
+	let update = match device_id {
+		| Some(device_id) => DeviceListUpdate::KeysClaimed {
+			user_id: user_id.to_owned(),
+			device_id: device_id.to_owned(),
+		},
+		| None => DeviceListUpdate::Changed { user_id: user_id.to_owned() },
+	};
+
+	let mut buf = EduBuf::new();
+	serde_json::to_writer(&mut buf, &update)?;
+
+	for appservice in appservices {
+		if self
+			.is_device_list_interested(&appservice, user_id)
+			.await
+		{
+			self.services
+				.sending
+				.send_edu_appservice(appservice.registration.id, buf.clone())?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Called after the user left or was banned from the room. The appservices
+/// receiving device lists which no longer share a room with the user, or with
+/// the members of the room when a user of the appservice left, are told the
+/// device lists of those users are no longer tracked.
+#[implement(super::Service)]
+#[tracing::instrument(name = "device_left", level = "debug", skip_all)]
+pub(crate) async fn append_device_left(&self, user_id: &UserId, room_id: &RoomId) -> Result {
+	let appservices: Vec<_> = self
+		.read()
+		.await
+		.values()
+		.filter(|appservice| appservice.receive_device_lists)
+		.cloned()
+		.collect();
+
+	let state_cache = &self.services.state_cache;
+	for appservice in appservices {
+		let in_room = state_cache
+			.appservice_in_room(room_id, &appservice)
+			.await;
+
+		let users: Vec<OwnedUserId> = match (appservice.is_user_match(user_id), in_room) {
+			| (false, true) => vec![user_id.to_owned()],
+			| (true, false) =>
+				state_cache
+					.room_members(room_id)
+					.ready_filter(|member| !appservice.is_user_match(member))
+					.map(ToOwned::to_owned)
+					.collect()
+					.await,
+			| _ => continue,
+		};
+
+		for user_id in users {
+			if self
+				.is_device_list_interested(&appservice, &user_id)
+				.await
+			{
+				continue;
+			}
+
+			let mut buf = EduBuf::new();
+			serde_json::to_writer(&mut buf, &DeviceListUpdate::Left { user_id })?;
+			self.services
+				.sending
+				.send_edu_appservice(appservice.registration.id.clone(), buf)?;
+		}
+	}
+
+	Ok(())
+}
+
+#[implement(super::Service)]
+async fn is_device_list_interested(
+	&self,
+	appservice: &RegistrationInfo,
+	user_id: &UserId,
+) -> bool {
+	if appservice.is_user_match(user_id) {
+		return true;
+	}
+
+	self.services
+		.state_cache
+		.rooms_joined(user_id)
+		.any(|room_id| {
+			self.services
+				.state_cache
+				.appservice_in_room(room_id, appservice)
+		})
+		.await
+}
+
+#[cfg(test)]
+mod tests {
+	use ruma::{OwnedUserId, UserId, owned_device_id, owned_user_id};
+
+	use super::{DeviceListChanges, DeviceListUpdate};
+
+	fn is_bridged(user_id: &UserId) -> bool { user_id.localpart().starts_with("bridge_") }
+
+	#[test]
+	fn update_is_not_a_federation_edu() {
+		let update = DeviceListUpdate::Left {
+			user_id: owned_user_id!("@alice:example.org"),
+		};
+		let json = serde_json::to_string(&update).expect("serialized");
+
+		assert_eq!(serde_json::from_str::<DeviceListUpdate>(&json).ok(), Some(update));
+		assert!(
+			serde_json::from_str::<DeviceListUpdate>(
+				r#"{"edu_type":"m.device_list_update","content":{}}"#
+			)
+			.is_err()
+		);
+	}
+
+	#[test]
+	fn changes_of_a_transaction() {
+		let alice: OwnedUserId = owned_user_id!("@alice:example.org");
+		let bob: OwnedUserId = owned_user_id!("@bob:example.org");
+		let bridged: OwnedUserId = owned_user_id!("@bridge_carol:example.org");
+
+		let mut changes = DeviceListChanges::default();
+		for update in [
+			DeviceListUpdate::Changed { user_id: alice.clone() },
+			DeviceListUpdate::Changed { user_id: bob.clone() },
+			DeviceListUpdate::Left { user_id: alice.clone() },
+			DeviceListUpdate::Changed { user_id: bridged.clone() },
+			DeviceListUpdate::KeysClaimed {
+				user_id: bridged.clone(),
+				device_id: owned_device_id!("BRIDGE"),
+			},
+			DeviceListUpdate::KeysClaimed {
+				user_id: bob.clone(),
+				device_id: owned_device_id!("BOB"),
+			},
+		] {
+			changes.add(update, is_bridged);
+		}
+
+		assert_eq!(changes.changed.into_iter().collect::<Vec<_>>(), [bob]);
+		assert_eq!(changes.left.into_iter().collect::<Vec<_>>(), [alice]);
+		assert_eq!(changes.key_counts.into_iter().collect::<Vec<_>>(), [(
+			bridged,
+			owned_device_id!("BRIDGE")
+		)]);
+	}
+}
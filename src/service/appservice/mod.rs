@@ -1,7 +1,10 @@
 mod append;
+mod collision;
+mod device;
 mod namespace_regex;
 mod registration_info;
 pub(crate) mod request;
+mod thirdparty;
 
 use std::{
 	collections::{BTreeMap, HashSet},
@@ -11,15 +14,20 @@
 
 use async_trait::async_trait;
 use futures::{Future, FutureExt, Stream, StreamExt, TryStreamExt};
-use ruma::{RoomAliasId, RoomId, UserId, api::appservice::Registration};
+use ruma::{RoomAliasId, RoomId, ServerName, UserId, api::appservice::Registration};
 use tokio::sync::{RwLock, RwLockReadGuard};
-use tuwunel_core::{Err, Result, debug, err, utils::stream::IterStream};
+use tuwunel_core::{Err, Result, debug, err, utils::stream::IterStream, warn};
 use tuwunel_database::Map;
 
-pub use self::{namespace_regex::NamespaceRegex, registration_info::RegistrationInfo};
+pub(crate) use self::device::{DeviceListChanges, DeviceListUpdate};
+use self::thirdparty::ProtocolCache;
+pub use self::{
+	collision::Collision, namespace_regex::NamespaceRegex, registration_info::RegistrationInfo,
+};
 
 pub struct Service {
 	registration_info: RwLock<Registrations>,
+	protocols: RwLock<ProtocolCache>,
 	services: Arc<crate::services::OnceServices>,
 	db: Data,
 }
@@ -35,6 +43,7 @@ impl crate::Service for Service {
 	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
 		Ok(Arc::new(Self {
 			registration_info: RwLock::new(BTreeMap::new()),
+			protocols: RwLock::new(BTreeMap::new()),
 			services: args.services.clone(),
 			db: Data {
 				id_appserviceregistrations: args.db["id_appserviceregistrations"].clone(),
@@ -57,30 +66,27 @@ impl Service {
 	async fn init_registrations(&self) -> Result {
 		// Registrations from configuration file
 		let confs = self
-			.services
-			.server
-			.config
-			.appservice
-			.clone()
+			.config_registrations()?
 			.into_iter()
 			.stream()
-			.map(|(id, mut reg)| {
-				reg.id.clone_from(&id);
-				reg.sender_localpart
-					.get_or_insert_with(|| id.clone());
+			.map(Ok);
 
-				Ok((id, reg))
+		// Registrations from database
+		let dbs = self
+			.db
+			.id_appserviceregistrations
+			.keys()
+			.and_then(async |id: &str| {
+				Ok((id.to_owned(), self.get_db_registration_info(id).await?))
 			});
 
-		// Registrations from database
-		self.iter_db_ids()
-			.chain(confs.map_ok(|(id, reg)| (id, reg.into())))
-			.try_for_each(async |(id, reg): (_, Registration)| {
-				debug!(?id, ?reg, "appservice registration");
+		dbs.chain(confs)
+			.try_for_each(async |(id, info): (_, RegistrationInfo)| {
+				debug!(?id, reg = ?info.registration, "appservice registration");
 				self.registration_info
 					.write()
 					.await
-					.insert(id.clone(), reg.try_into()?)
+					.insert(id.clone(), info)
 					.map_or(Ok(()), |_| Err!("Conflicting Appservice ID: {id:?}"))
 			})
 			.await
@@ -90,21 +96,92 @@ async fn init_registrations(&self) -> Result {
 	async fn check_registrations(&self) -> Result {
 		let regs = self.registration_info.read().await;
 
-		let num_as_tokens = regs
-			.values()
-			.map(|info| &info.registration.as_token)
-			.collect::<HashSet<_>>()
-			.len();
-
-		if num_as_tokens < regs.len() {
-			return Err!(
-				"Conflicting Appservice registrations: each must have a unique as_token."
-			);
+		check_registrations(&regs, self.services.globals.server_name())
+	}
+
+	/// Registrations defined in the configuration file.
+	fn config_registrations(&self) -> Result<Vec<(String, RegistrationInfo)>> {
+		self.services
+			.server
+			.config
+			.appservice
+			.clone()
+			.into_iter()
+			.map(|(id, mut reg)| -> Result<_> {
+				reg.id.clone_from(&id);
+				reg.sender_localpart
+					.get_or_insert_with(|| id.clone());
+
+				let receive_device_lists = reg.receive_device_lists;
+				let info = RegistrationInfo {
+					receive_device_lists,
+					..Registration::from(reg).try_into()?
+				};
+
+				Ok((id, info))
+			})
+			.collect()
+	}
+
+	/// Replaces the registrations from the configuration file with those of
+	/// the current configuration, e.g. after it was reloaded. Registrations
+	/// made through the admin room are kept. Nothing changes when the new set
+	/// of registrations has conflicting IDs or tokens.
+	#[tracing::instrument(name = "reload", skip(self))]
+	pub async fn reload_config_registrations(&self) -> Result {
+		let confs = self.config_registrations()?;
+		let mut regs = self.registration_info.write().await;
+
+		let mut reloaded = Registrations::new();
+		for (id, info) in regs.iter() {
+			if self
+				.db
+				.id_appserviceregistrations
+				.contains(id)
+				.await
+			{
+				reloaded.insert(id.clone(), info.clone());
+			}
+		}
+
+		for (id, info) in confs {
+			if reloaded.insert(id.clone(), info).is_some() {
+				return Err!("Conflicting Appservice ID: {id:?}");
+			}
+		}
+
+		check_registrations(&reloaded, self.services.globals.server_name())?;
+
+		let removed: Vec<_> = regs
+			.keys()
+			.filter(|id| !reloaded.contains_key(*id))
+			.cloned()
+			.collect();
+
+		*regs = reloaded;
+		drop(regs);
+		self.clear_protocol_cache().await;
+
+		for id in removed {
+			debug!(?id, "appservice removed from configuration");
+			self.services
+				.sending
+				.cleanup_events(Some(&id), None, None)
+				.await?;
 		}
 
 		Ok(())
 	}
 
+	/// Lists the overlaps between the exclusive namespaces of the registered
+	/// appservices.
+	pub async fn collisions(&self) -> Vec<Collision> {
+		let regs = self.read().await;
+		let infos: Vec<_> = regs.values().collect();
+
+		collision::find_collisions(&infos, self.services.globals.server_name())
+	}
+
 	/// Registers an appservice and returns the ID to the caller
 	pub async fn register_appservice(
 		&self,
@@ -123,11 +200,29 @@ pub async fn register_appservice(
 				.await?;
 		}
 
-		//TODO: Check for collisions between exclusive appservice namespaces
-		self.registration_info
-			.write()
-			.await
-			.insert(registration.id.clone(), registration.clone().try_into()?);
+		let info = RegistrationInfo::try_from(registration.clone())?
+			.with_unstable(appservice_config_body.as_bytes());
+
+		let mut regs = self.registration_info.write().await;
+		let server_name = self.services.globals.server_name();
+		let collisions: Vec<_> = regs
+			.values()
+			.filter(|other| other.registration.id != registration.id)
+			.flat_map(|other| collision::collisions(&info, other, server_name))
+			.collect();
+
+		if !collisions.is_empty() {
+			let collisions: Vec<_> = collisions
+				.iter()
+				.map(ToString::to_string)
+				.collect();
+			let collisions = collisions.join("; ");
+			return Err!(Request(Exclusive("Appservice namespaces collide: {collisions}")));
+		}
+
+		regs.insert(registration.id.clone(), info);
+		drop(regs);
+		self.clear_protocol_cache().await;
 
 		self.db
 			.id_appserviceregistrations
@@ -149,6 +244,8 @@ pub async fn unregister_appservice(&self, appservice_id: &str) -> Result {
 			.remove(appservice_id)
 			.ok_or_else(|| err!("Appservice not found"))?;
 
+		self.clear_protocol_cache().await;
+
 		// remove the appservice from the database
 		self.db
 			.id_appserviceregistrations
@@ -171,6 +268,14 @@ pub async fn get_registration(&self, id: &str) -> Option<Registration> {
 			.map(|info| info.registration)
 	}
 
+	pub async fn get_registration_info(&self, id: &str) -> Option<RegistrationInfo> {
+		self.registration_info
+			.read()
+			.await
+			.get(id)
+			.cloned()
+	}
+
 	pub async fn find_from_access_token(&self, token: &str) -> Result<RegistrationInfo> {
 		self.read()
 			.await
@@ -223,6 +328,15 @@ pub fn iter_db_ids(&self) -> impl Stream<Item = Result<(String, Registration)>>
 			})
 	}
 
+	async fn get_db_registration_info(&self, id: &str) -> Result<RegistrationInfo> {
+		let bytes = self.db.id_appserviceregistrations.get(id).await?;
+
+		let registration: Registration = serde_yaml::from_slice(&bytes)
+			.map_err(|e| err!(Database("Invalid appservice {id:?} registration: {e:?}")))?;
+
+		Ok(RegistrationInfo::try_from(registration)?.with_unstable(&bytes))
+	}
+
 	pub async fn get_db_registration(&self, id: &str) -> Result<Registration> {
 		self.db
 			.id_appserviceregistrations
@@ -236,3 +350,25 @@ pub fn read(&self) -> impl Future<Output = RwLockReadGuard<'_, Registrations>> +
 		self.registration_info.read()
 	}
 }
+
+/// Checks a set of registrations for conflicting tokens. Overlapping exclusive
+/// namespaces are only warned about: existing deployments may have them, and
+/// registrations made through the admin room are refused on overlap instead.
+fn check_registrations(regs: &Registrations, server_name: &ServerName) -> Result {
+	let num_as_tokens = regs
+		.values()
+		.map(|info| &info.registration.as_token)
+		.collect::<HashSet<_>>()
+		.len();
+
+	if num_as_tokens < regs.len() {
+		return Err!("Conflicting Appservice registrations: each must have a unique as_token.");
+	}
+
+	let infos: Vec<_> = regs.values().collect();
+	for collision in collision::find_collisions(&infos, server_name) {
+		warn!("Conflicting Appservice registrations: {collision}");
+	}
+
+	Ok(())
+}
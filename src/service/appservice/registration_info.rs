@@ -1,4 +1,5 @@
 use ruma::{UserId, api::appservice::Registration};
+use serde::Deserialize;
 use tuwunel_core::Result;
 
 use super::NamespaceRegex;
@@ -10,6 +11,22 @@ pub struct RegistrationInfo {
 	pub users: NamespaceRegex,
 	pub aliases: NamespaceRegex,
 	pub rooms: NamespaceRegex,
+
+	/// Whether device list changes and one-time key counts of its users are
+	/// sent to the appservice (MSC3202).
+	pub receive_device_lists: bool,
+}
+
+/// Registration keys of unstable features which [`Registration`] does not
+/// model.
+#[derive(Debug, Default, Deserialize)]
+struct Unstable {
+	#[serde(
+		default,
+		rename = "org.matrix.msc3202",
+		alias = "receive_device_lists"
+	)]
+	msc3202: bool,
 }
 
 impl RegistrationInfo {
@@ -25,6 +42,14 @@ pub fn is_exclusive_user_match(&self, user_id: &UserId) -> bool {
 		self.users.is_exclusive_match(user_id.as_str())
 			|| self.registration.sender_localpart == user_id.localpart()
 	}
+
+	/// Applies the keys of unstable features found in the YAML registration.
+	#[must_use]
+	pub fn with_unstable(mut self, yaml: &[u8]) -> Self {
+		let unstable: Unstable = serde_yaml::from_slice(yaml).unwrap_or_default();
+		self.receive_device_lists = unstable.msc3202;
+		self
+	}
 }
 
 impl TryFrom<Registration> for RegistrationInfo {
@@ -35,6 +60,7 @@ fn try_from(value: Registration) -> Result<Self, regex::Error> {
 			users: value.namespaces.users.clone().try_into()?,
 			aliases: value.namespaces.aliases.clone().try_into()?,
 			rooms: value.namespaces.rooms.clone().try_into()?,
+			receive_device_lists: false,
 			registration: value,
 		})
 	}
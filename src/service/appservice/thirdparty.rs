@@ -0,0 +1,263 @@
+use std::{
+	collections::BTreeMap,
+	time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use ruma::{
+	RoomAliasId, UserId,
+	api::appservice::thirdparty::{
+		get_location_for_protocol, get_location_for_room_alias, get_protocol,
+		get_user_for_protocol, get_user_for_user_id,
+	},
+	thirdparty::{Location, Protocol, ProtocolInstance, User},
+};
+use tuwunel_core::{
+	Err, Result, implement,
+	result::LogErr,
+	utils::stream::{BroadbandExt, IterStream},
+};
+
+use super::RegistrationInfo;
+
+/// How long the protocol metadata of the appservices is cached.
+const PROTOCOL_CACHE_TTL: Duration = Duration::from_secs(300);
+
+pub(super) type ProtocolCache = BTreeMap<String, (Instant, Protocol)>;
+
+/// Metadata of all third-party protocols declared by the registered
+/// appservices. Protocols no appservice could describe are left out.
+#[implement(super::Service)]
+pub async fn get_protocols(&self) -> BTreeMap<String, Protocol> {
+	let mut names: Vec<String> = self
+		.read()
+		.await
+		.values()
+		.filter_map(|info| info.registration.protocols.clone())
+		.flatten()
+		.collect();
+
+	names.sort_unstable();
+	names.dedup();
+
+	names
+		.into_iter()
+		.stream()
+		.broad_filter_map(async |name| {
+			let protocol = self.get_protocol(&name).await.ok()?;
+
+			Some((name, protocol))
+		})
+		.collect()
+		.await
+}
+
+/// Metadata of a third-party protocol merged from the appservices declaring
+/// it. The metadata is cached for a while; the instances of each appservice
+/// are identified by the appservice and their network.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn get_protocol(&self, name: &str) -> Result<Protocol> {
+	if let Some((fetched, protocol)) = self.protocols.read().await.get(name)
+		&& fetched.elapsed() < PROTOCOL_CACHE_TTL
+	{
+		return Ok(protocol.clone());
+	}
+
+	let appservices = self.protocol_appservices(name).await;
+	if appservices.is_empty() {
+		return Err!(Request(NotFound("No appservice provides the protocol {name:?}.")));
+	}
+
+	let mut responses = appservices
+		.into_iter()
+		.stream()
+		.broad_filter_map(async |info| {
+			let id = info.registration.id.clone();
+			let request = get_protocol::v1::Request::new(name.to_owned());
+			let response = self
+				.send_request(info.registration, request)
+				.await
+				.log_err()
+				.ok()
+				.flatten()?;
+
+			Some((id, response.protocol))
+		})
+		.collect::<Vec<_>>()
+		.await;
+
+	// Deterministic choice of the metadata when several appservices differ.
+	responses.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+	let mut merged: Option<Protocol> = None;
+	for (id, protocol) in responses {
+		let mut protocol: Protocol = protocol.into();
+		for instance in &mut protocol.instances {
+			instance.instance_id = Some(instance_id(&id, instance));
+		}
+
+		match &mut merged {
+			| Some(merged) => merged.instances.append(&mut protocol.instances),
+			| None => merged = Some(protocol),
+		}
+	}
+
+	let Some(protocol) = merged else {
+		return Err!(Request(Unknown("No appservice responded for the protocol {name:?}.")));
+	};
+
+	self.protocols
+		.write()
+		.await
+		.insert(name.to_owned(), (Instant::now(), protocol.clone()));
+
+	Ok(protocol)
+}
+
+/// Looks up third-party locations of a protocol matching the fields.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn query_locations(
+	&self,
+	protocol: &str,
+	fields: &BTreeMap<String, String>,
+) -> Result<Vec<Location>> {
+	let appservices = self.protocol_appservices(protocol).await;
+	if appservices.is_empty() {
+		return Err!(Request(NotFound("No appservice provides the protocol {protocol:?}.")));
+	}
+
+	Ok(appservices
+		.into_iter()
+		.stream()
+		.broad_filter_map(async |info| {
+			let mut request = get_location_for_protocol::v1::Request::new(protocol.to_owned());
+			request.fields.clone_from(fields);
+
+			self.send_request(info.registration, request)
+				.await
+				.log_err()
+				.ok()
+				.flatten()
+		})
+		.flat_map(|response| response.locations.into_iter().stream())
+		.collect()
+		.await)
+}
+
+/// Looks up the third-party locations bridged to a room alias, asking the
+/// appservices whose namespace includes the alias.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn query_locations_by_alias(&self, alias: &RoomAliasId) -> Vec<Location> {
+	let appservices: Vec<_> = self
+		.read()
+		.await
+		.values()
+		.filter(|info| info.registration.protocols.is_some())
+		.filter(|info| info.aliases.is_match(alias.as_str()))
+		.cloned()
+		.collect();
+
+	appservices
+		.into_iter()
+		.stream()
+		.broad_filter_map(async |info| {
+			let request = get_location_for_room_alias::v1::Request::new(alias.to_owned());
+
+			self.send_request(info.registration, request)
+				.await
+				.log_err()
+				.ok()
+				.flatten()
+		})
+		.flat_map(|response| response.locations.into_iter().stream())
+		.collect()
+		.await
+}
+
+/// Looks up third-party users of a protocol matching the fields.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn query_users(
+	&self,
+	protocol: &str,
+	fields: &BTreeMap<String, String>,
+) -> Result<Vec<User>> {
+	let appservices = self.protocol_appservices(protocol).await;
+	if appservices.is_empty() {
+		return Err!(Request(NotFound("No appservice provides the protocol {protocol:?}.")));
+	}
+
+	Ok(appservices
+		.into_iter()
+		.stream()
+		.broad_filter_map(async |info| {
+			let mut request = get_user_for_protocol::v1::Request::new(protocol.to_owned());
+			request.fields.clone_from(fields);
+
+			self.send_request(info.registration, request)
+				.await
+				.log_err()
+				.ok()
+				.flatten()
+		})
+		.flat_map(|response| response.users.into_iter().stream())
+		.collect()
+		.await)
+}
+
+/// Looks up the third-party users bridged to a Matrix user, asking the
+/// appservices whose namespace includes the user.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn query_users_by_id(&self, user_id: &UserId) -> Vec<User> {
+	let appservices: Vec<_> = self
+		.read()
+		.await
+		.values()
+		.filter(|info| info.registration.protocols.is_some())
+		.filter(|info| info.is_user_match(user_id))
+		.cloned()
+		.collect();
+
+	appservices
+		.into_iter()
+		.stream()
+		.broad_filter_map(async |info| {
+			let request = get_user_for_user_id::v1::Request::new(user_id.to_owned());
+
+			self.send_request(info.registration, request)
+				.await
+				.log_err()
+				.ok()
+				.flatten()
+		})
+		.flat_map(|response| response.users.into_iter().stream())
+		.collect()
+		.await
+}
+
+/// Drops the cached protocol metadata, e.g. after the registrations changed.
+#[implement(super::Service)]
+pub(super) async fn clear_protocol_cache(&self) { self.protocols.write().await.clear(); }
+
+#[implement(super::Service)]
+async fn protocol_appservices(&self, protocol: &str) -> Vec<RegistrationInfo> {
+	self.read()
+		.await
+		.values()
+		.filter(|info| {
+			info.registration
+				.protocols
+				.as_ref()
+				.is_some_and(|protocols| protocols.iter().any(|p| p == protocol))
+		})
+		.cloned()
+		.collect()
+}
+
+fn instance_id(appservice_id: &str, instance: &ProtocolInstance) -> String {
+	format!("{appservice_id}|{}", instance.network_id)
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+use ruma::OwnedUserId;
+use serde::{Deserialize, Serialize};
+use tuwunel_core::{
+	Result, implement,
+	utils::{self, stream::TryIgnore},
+};
+use tuwunel_database::{Json, Map};
+
+/// Append-only record of administrative actions.
+pub struct Service {
+	db: Data,
+	services: Arc<crate::services::OnceServices>,
+}
+
+struct Data {
+	auditid_entry: Arc<Map>,
+}
+
+/// One administrative action.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Entry {
+	/// Position in the log; assigned when recorded.
+	#[serde(default)]
+	pub id: u64,
+
+	/// Time of the action in milliseconds since the unix epoch; assigned when
+	/// recorded.
+	#[serde(default)]
+	pub ts: u64,
+
+	/// User performing the action; none for the server console and startup
+	/// commands.
+	pub actor: Option<OwnedUserId>,
+
+	/// Where the action came from, e.g. `admin_room`, `console` or `client`.
+	pub source: String,
+
+	/// The command line or endpoint.
+	pub action: String,
+
+	/// User, room, event or media the action applied to.
+	pub target: Option<String>,
+
+	/// Error of a failed action.
+	pub error: Option<String>,
+}
+
+impl crate::Service for Service {
+	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
+		Ok(Arc::new(Self {
+			db: Data {
+				auditid_entry: args.db["auditid_entry"].clone(),
+			},
+			services: args.services.clone(),
+		}))
+	}
+
+	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
+}
+
+/// Appends an entry to the log.
+#[implement(Service)]
+pub fn record(&self, mut entry: Entry) {
+	let count = self.services.globals.next_count();
+
+	entry.id = *count;
+	entry.ts = utils::millis_since_unix_epoch();
+
+	self.db
+		.auditid_entry
+		.raw_put(entry.id.to_be_bytes(), Json(entry));
+}
+
+/// Entries from the newest to the oldest.
+#[implement(Service)]
+pub fn entries(&self) -> impl Stream<Item = Entry> + Send + '_ {
+	self.db
+		.auditid_entry
+		.rev_raw_stream()
+		.ignore_err()
+		.filter_map(async |(_, val)| serde_json::from_slice(val).ok())
+}
@@ -1,11 +1,17 @@
 use std::{
+	io,
+	net::{IpAddr, SocketAddr},
 	sync::{Arc, LazyLock},
 	time::Duration,
 };
 
 use ipaddress::IPAddress;
-use reqwest::{dns::Resolve, redirect};
+use reqwest::{
+	dns::{Addrs, Name, Resolve, Resolving},
+	redirect,
+};
 use tuwunel_core::{Config, Result, either::Either, err, implement, trace};
+use url::Host;
 
 use crate::{service, services::OnceServices};
 
@@ -55,13 +61,22 @@ fn make($services: Arc<OnceServices>) -> Result<reqwest::Client> {
 					.clone()
 					.and_then(Either::right);
 
+				let denylist: Arc<[IPAddress]> = services
+					.client
+					.cidr_range_denylist
+					.clone()
+					.into();
+
 				base(config)
 				.and_then(|builder| {
 					builder_interface(builder, url_preview_bind_iface.as_deref())
 				})?
 				.local_address(url_preview_bind_addr)
-				.dns_resolver2(Arc::clone(&services.resolver.resolver))
-				.redirect(redirect::Policy::limited(3))
+				.dns_resolver2(Arc::new(DenylistResolver {
+					inner: services.resolver.resolver.clone(),
+					denylist: denylist.clone(),
+				}))
+				.redirect(denylist_redirect(denylist, 3))
 			}),
 
 			extern_media: create_client!(config, services; base(config)?
@@ -233,6 +248,67 @@ fn appservice_resolver(services: &Arc<OnceServices>) -> Arc<dyn Resolve> {
 	}
 }
 
+/// Resolves names with the inner resolver, refusing those with an address in
+/// `ip_range_denylist`. The addresses are checked as they are connected to,
+/// so a name can not be rebound to a denied address after a prior check.
+pub(crate) struct DenylistResolver {
+	pub(crate) inner: Arc<dyn Resolve>,
+	pub(crate) denylist: Arc<[IPAddress]>,
+}
+
+impl Resolve for DenylistResolver {
+	fn resolve(&self, name: Name) -> Resolving {
+		let host = name.as_str().to_owned();
+		let resolving = self.inner.resolve(name);
+		let denylist = self.denylist.clone();
+
+		Box::pin(async move {
+			let addrs: Vec<SocketAddr> = resolving.await?.collect();
+			if let Some(addr) = addrs
+				.iter()
+				.find(|addr| !allowed_ip(&denylist, addr.ip()))
+			{
+				let error = format!("{host} resolves to the denied address {}", addr.ip());
+				return Err(io::Error::new(io::ErrorKind::PermissionDenied, error).into());
+			}
+
+			Ok(Box::new(addrs.into_iter()) as Addrs)
+		})
+	}
+}
+
+/// Follows at most `max` redirects to HTTP(S) URLs, refusing those to hosts
+/// given as an address in `ip_range_denylist`; the addresses of named hosts
+/// are checked by the [`DenylistResolver`].
+pub(crate) fn denylist_redirect(denylist: Arc<[IPAddress]>, max: usize) -> redirect::Policy {
+	redirect::Policy::custom(move |attempt| {
+		let url = attempt.url();
+		let allowed = matches!(url.scheme(), "http" | "https")
+			&& match url.host() {
+				| Some(Host::Ipv4(ip)) => allowed_ip(&denylist, ip.into()),
+				| Some(Host::Ipv6(ip)) => allowed_ip(&denylist, ip.into()),
+				| Some(Host::Domain(_)) => true,
+				| None => false,
+			};
+
+		if attempt.previous().len() >= max {
+			attempt.error("too many redirects")
+		} else if !allowed {
+			let error = format!("redirect to {url} is forbidden");
+			attempt.error(error)
+		} else {
+			attempt.follow()
+		}
+	})
+}
+
+/// Whether the address is in none of the ranges of the denylist.
+#[must_use]
+pub(crate) fn allowed_ip(denylist: &[IPAddress], ip: IpAddr) -> bool {
+	IPAddress::parse(ip.to_canonical().to_string())
+		.is_ok_and(|ip| denylist.iter().all(|cidr| !cidr.includes(&ip)))
+}
+
 #[inline]
 #[must_use]
 #[implement(Service)]
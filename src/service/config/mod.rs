@@ -9,6 +9,7 @@
 
 pub struct Service {
 	server: Arc<Server>,
+	services: Arc<crate::services::OnceServices>,
 }
 
 const SIGNAL: &str = "SIGUSR1";
@@ -16,7 +17,10 @@ pub struct Service {
 #[async_trait]
 impl crate::Service for Service {
 	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
-		Ok(Arc::new(Self { server: args.server.clone() }))
+		Ok(Arc::new(Self {
+			server: args.server.clone(),
+			services: args.services.clone(),
+		}))
 	}
 
 	async fn worker(self: Arc<Self>) -> Result {
@@ -27,7 +31,7 @@ async fn worker(self: Arc<Self>) -> Result {
 				signal = signaled.recv() => if signal !=  Ok(SIGNAL) { continue; },
 			}
 
-			if let Err(e) = self.handle_reload() {
+			if let Err(e) = self.handle_reload().await {
 				error!("Failed to reload config: {e}");
 			}
 		}
@@ -46,20 +50,32 @@ fn deref(&self) -> &Self::Target { &self.server.config }
 }
 
 #[implement(Service)]
-fn handle_reload(&self) -> Result {
-	if self.server.config.config_reload_signal {
-		#[cfg(all(feature = "systemd", target_os = "linux"))]
-		sd_notify::notify(true, &[sd_notify::NotifyState::Reloading])
-			.expect("failed to notify systemd of reloading state");
-
-		self.reload(iter::empty())?;
+async fn handle_reload(&self) -> Result {
+	if !self.server.config.config_reload_signal {
+		return Ok(());
+	}
 
-		#[cfg(all(feature = "systemd", target_os = "linux"))]
-		sd_notify::notify(true, &[sd_notify::NotifyState::Ready])
-			.expect("failed to notify systemd of ready state");
+	#[cfg(all(feature = "systemd", target_os = "linux"))]
+	sd_notify::notify(true, &[sd_notify::NotifyState::Reloading])
+		.expect("failed to notify systemd of reloading state");
+
+	let result = self.reload(iter::empty()).map(|_| ());
+	if result.is_ok()
+		&& let Err(e) = self
+			.services
+			.appservice
+			.reload_config_registrations()
+			.await
+	{
+		error!("Failed to reload appservice registrations, keeping the previous ones: {e}");
 	}
 
-	Ok(())
+	// systemd must leave the reloading state whether or not the reload worked.
+	#[cfg(all(feature = "systemd", target_os = "linux"))]
+	sd_notify::notify(true, &[sd_notify::NotifyState::Ready])
+		.expect("failed to notify systemd of ready state");
+
+	result
 }
 
 #[implement(Service)]
@@ -0,0 +1,154 @@
+#[cfg(test)]
+mod tests;
+
+use std::{
+	str::FromStr,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use http::header::ACCEPT;
+use tokio::sync::RwLock;
+use tuwunel_core::{
+	Err, Result, debug, debug_info, err,
+	jwt::{
+		Algorithm, DecodingKey,
+		jwk::{Jwk, JwkSet},
+	},
+	result::LogErr,
+};
+use url::Url;
+
+/// Caches the JSON Web Key Set verifying JWT logins.
+pub struct Service {
+	services: Arc<crate::services::OnceServices>,
+	keys: RwLock<Keys>,
+}
+
+#[derive(Default)]
+struct Keys {
+	set: Option<JwkSet>,
+	fetched: Option<Instant>,
+}
+
+#[async_trait]
+impl crate::Service for Service {
+	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
+		Ok(Arc::new(Self {
+			services: args.services.clone(),
+			keys: RwLock::default(),
+		}))
+	}
+
+	async fn worker(self: Arc<Self>) -> Result {
+		let config = &self.services.server.config.jwt;
+		if !config.enable || config.jwks_url.is_none() || config.jwks_refresh_interval == 0 {
+			return Ok(());
+		}
+
+		let interval = Duration::from_secs(config.jwks_refresh_interval);
+		loop {
+			self.refresh().await.log_err().ok();
+
+			tokio::select! {
+				() = tokio::time::sleep(interval) => {},
+				() = self.services.server.until_shutdown() => return Ok(())
+			};
+		}
+	}
+
+	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
+}
+
+impl Service {
+	/// Key named by the `kid` header of a token, with its algorithm when the
+	/// key set specifies one. A token without `kid` is only accepted when the
+	/// set holds a single key. Unknown keys trigger a refresh of the set,
+	/// rate-limited by `jwks_min_refresh_interval`.
+	pub async fn get(&self, kid: Option<&str>) -> Result<(DecodingKey, Option<Algorithm>)> {
+		if let Some(key) = self.find(kid).await {
+			return key;
+		}
+
+		self.refresh().await?;
+
+		self.find(kid)
+			.await
+			.unwrap_or_else(|| Err!(Request(Forbidden("JWT signing key {kid:?} is not known."))))
+	}
+
+	/// Fetches the key set again.
+	pub async fn refresh(&self) -> Result {
+		let Some(url) = &self.services.server.config.jwt.jwks_url else {
+			return Err!(Config("jwt.jwks_url", "No JWKS URL is configured."));
+		};
+
+		// Only one refresh at a time; others wait and reuse its result.
+		let mut keys = self.keys.write().await;
+		let min_interval = Duration::from_secs(
+			self.services
+				.server
+				.config
+				.jwt
+				.jwks_min_refresh_interval,
+		);
+
+		if keys
+			.fetched
+			.is_some_and(|fetched| fetched.elapsed() < min_interval)
+		{
+			return Ok(());
+		}
+
+		keys.fetched = Some(Instant::now());
+		let set = fetch(&self.services.client.oauth, url).await?;
+		debug_info!(keys = set.keys.len(), "Fetched JWKS from {url}");
+		keys.set = Some(set);
+
+		Ok(())
+	}
+
+	async fn find(&self, kid: Option<&str>) -> Option<Result<(DecodingKey, Option<Algorithm>)>> {
+		let keys = self.keys.read().await;
+		let set = keys.set.as_ref()?;
+
+		select(set, kid).map(decoding_key)
+	}
+}
+
+/// Downloads and parses a key set.
+pub(crate) async fn fetch(client: &reqwest::Client, url: &Url) -> Result<JwkSet> {
+	client
+		.get(url.clone())
+		.header(ACCEPT, "application/json")
+		.send()
+		.await?
+		.error_for_status()?
+		.json()
+		.await
+		.map_err(|e| err!(Request(Unknown("Invalid JWKS from {url}: {e}"))))
+}
+
+/// The key of the set named by `kid`, or its only key when `kid` is absent.
+fn select<'a>(set: &'a JwkSet, kid: Option<&str>) -> Option<&'a Jwk> {
+	match kid {
+		| Some(kid) => set.find(kid),
+		| None if set.keys.len() == 1 => set.keys.first(),
+		| None => None,
+	}
+}
+
+fn decoding_key(jwk: &Jwk) -> Result<(DecodingKey, Option<Algorithm>)> {
+	let key = DecodingKey::from_jwk(jwk)
+		.map_err(|e| err!(Request(Forbidden("JWT signing key is not usable: {e}"))))?;
+
+	let alg = jwk
+		.common
+		.key_algorithm
+		.and_then(|alg| Algorithm::from_str(&alg.to_string()).ok());
+
+	debug!(kid = ?jwk.common.key_id, ?alg, "Selected JWT signing key");
+
+	Ok((key, alg))
+}
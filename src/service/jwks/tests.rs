@@ -0,0 +1,105 @@
+use serde_json::json;
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::TcpListener,
+};
+use tuwunel_core::jwt::{Algorithm, EncodingKey, Header, Validation, decode, encode};
+use url::Url;
+
+use super::{decoding_key, fetch, select};
+
+/// Serves one response with the body to each connection.
+async fn serve(body: String) -> Url {
+	let listener = TcpListener::bind("127.0.0.1:0")
+		.await
+		.expect("bound listener");
+
+	let addr = listener.local_addr().expect("local address");
+	tokio::spawn(async move {
+		while let Ok((mut stream, _)) = listener.accept().await {
+			let mut request = [0_u8; 1024];
+			_ = stream.read(&mut request).await;
+
+			let response = format!(
+				"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: \
+				 {}\r\nconnection: close\r\n\r\n{body}",
+				body.len()
+			);
+
+			_ = stream.write_all(response.as_bytes()).await;
+		}
+	});
+
+	format!("http://{addr}/jwks.json")
+		.parse()
+		.expect("valid URL")
+}
+
+fn key_set() -> String {
+	json!({
+		"keys": [
+			// "secret" and "rotated", base64url-encoded.
+			{ "kty": "oct", "kid": "old", "alg": "HS256", "k": "c2VjcmV0" },
+			{ "kty": "oct", "kid": "new", "k": "cm90YXRlZA" },
+		]
+	})
+	.to_string()
+}
+
+#[tokio::test]
+async fn fetch_and_select_by_kid() {
+	let url = serve(key_set()).await;
+	let set = fetch(&reqwest::Client::new(), &url)
+		.await
+		.expect("fetched key set");
+
+	assert_eq!(set.keys.len(), 2);
+	assert!(select(&set, Some("old")).is_some());
+	assert!(select(&set, Some("new")).is_some());
+	assert!(select(&set, Some("gone")).is_none());
+	assert!(select(&set, None).is_none(), "kid is required with several keys");
+
+	let (_, alg) = decoding_key(select(&set, Some("old")).unwrap()).unwrap();
+	assert_eq!(alg, Some(Algorithm::HS256));
+
+	let (_, alg) = decoding_key(select(&set, Some("new")).unwrap()).unwrap();
+	assert_eq!(alg, None);
+}
+
+#[tokio::test]
+async fn verify_with_rotated_key() {
+	let url = serve(key_set()).await;
+	let set = fetch(&reqwest::Client::new(), &url)
+		.await
+		.expect("fetched key set");
+
+	let header = Header {
+		kid: Some("new".to_owned()),
+		..Header::new(Algorithm::HS256)
+	};
+
+	let claims = json!({ "sub": "alice" });
+	let token = encode(&header, &claims, &EncodingKey::from_secret(b"rotated")).unwrap();
+
+	let mut validation = Validation::new(Algorithm::HS256);
+	validation.validate_exp = false;
+	validation.set_required_spec_claims(&["sub"]);
+
+	let (new, _) = decoding_key(select(&set, Some("new")).unwrap()).unwrap();
+	let decoded = decode::<serde_json::Value>(&token, &new, &validation).expect("valid token");
+	assert_eq!(decoded.claims["sub"], "alice");
+
+	let (old, _) = decoding_key(select(&set, Some("old")).unwrap()).unwrap();
+	assert!(decode::<serde_json::Value>(&token, &old, &validation).is_err());
+}
+
+#[tokio::test]
+async fn fetch_rejects_invalid_set() {
+	let url = serve("{\"keys\": 1}".to_owned()).await;
+
+	assert!(
+		fetch(&reqwest::Client::new(), &url)
+			.await
+			.is_err()
+	);
+}
@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ldap3::{LdapConnAsync, Scope, SearchEntry};
+use ruma::OwnedRoomOrAliasId;
+use tuwunel_core::{
+	Err, Result, config::LdapConfig, debug, err, error, implement, result::LogErr, trace,
+};
+
+/// Users and groups of the directory, as far as the synchronisation is
+/// concerned. Users are identified by their lowercased `uid_attribute`, and
+/// groups by their lowercased distinguished name.
+#[derive(Debug, Default)]
+pub(super) struct Directory {
+	/// Displayname of each user, from `name_attribute`.
+	pub(super) users: BTreeMap<String, Option<String>>,
+
+	/// Users matching `admin_filter`; none when it isn't configured.
+	pub(super) admins: Option<BTreeSet<String>>,
+
+	/// Users belonging to each group.
+	pub(super) groups: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Directory {
+	/// Builds the directory from the entries returned by the user, admin and
+	/// group searches.
+	pub(super) fn new(
+		config: &LdapConfig,
+		users: Vec<SearchEntry>,
+		admins: Option<Vec<SearchEntry>>,
+		groups: Vec<SearchEntry>,
+	) -> Self {
+		let uids: BTreeMap<String, String> = users
+			.iter()
+			.filter_map(|entry| Some((entry.dn.to_lowercase(), uid(config, entry)?)))
+			.collect();
+
+		let groups = groups
+			.into_iter()
+			.map(|entry| {
+				let members = entry
+					.attrs
+					.get(&config.group_member_attribute)
+					.into_iter()
+					.flatten()
+					.filter_map(|member| uids.get(&member.to_lowercase()))
+					.cloned()
+					.collect();
+
+				(entry.dn.to_lowercase(), members)
+			})
+			.collect();
+
+		let admins = admins.map(|admins| {
+			admins
+				.iter()
+				.filter_map(|entry| uid(config, entry))
+				.collect()
+		});
+
+		let users = users
+			.into_iter()
+			.filter_map(|entry| {
+				let displayname = entry
+					.attrs
+					.get(&config.name_attribute)
+					.and_then(|names| names.first())
+					.cloned();
+
+				Some((uid(config, &entry)?, displayname))
+			})
+			.collect();
+
+		Self { users, admins, groups }
+	}
+
+	/// Whether the user is a member of the group.
+	pub(super) fn is_member(&self, group: &str, uid: &str) -> bool {
+		self.groups
+			.get(&group.to_lowercase())
+			.is_some_and(|members| members.contains(uid))
+	}
+
+	/// Rooms mapped to the groups of the user.
+	pub(super) fn rooms_of<'a>(
+		&self,
+		config: &'a LdapConfig,
+		uid: &str,
+	) -> BTreeSet<&'a OwnedRoomOrAliasId> {
+		config
+			.group_rooms
+			.iter()
+			.filter(|(group, _)| self.is_member(group, uid))
+			.flat_map(|(_, rooms)| rooms)
+			.collect()
+	}
+
+	/// Workspaces mapped to the groups of the user.
+	pub(super) fn workspaces_of<'a>(
+		&self,
+		config: &'a LdapConfig,
+		uid: &str,
+	) -> BTreeSet<&'a str> {
+		config
+			.group_workspaces
+			.iter()
+			.filter(|(group, _)| self.is_member(group, uid))
+			.flat_map(|(_, workspaces)| workspaces)
+			.map(String::as_str)
+			.collect()
+	}
+}
+
+fn uid(config: &LdapConfig, entry: &SearchEntry) -> Option<String> {
+	entry
+		.attrs
+		.get(&config.uid_attribute)?
+		.first()
+		.map(|uid| uid.to_lowercase())
+}
+
+/// Searches the directory for all users, admins and groups.
+#[implement(super::Service)]
+pub(super) async fn fetch_directory(&self) -> Result<Directory> {
+	let config = &self.services.server.config.ldap;
+	let uri = config
+		.uri
+		.as_ref()
+		.ok_or_else(|| err!(Ldap(error!("LDAP URI is not configured."))))?;
+
+	if uri.scheme().starts_with("ldaps") {
+		self.services.globals.init_rustls_provider()?;
+	}
+
+	debug!(?uri, "LDAP creating connection...");
+	let (conn, mut ldap) = LdapConnAsync::new(uri.as_str())
+		.await
+		.map_err(|e| err!(Ldap(error!("LDAP connection setup error: {e}"))))?;
+
+	let driver = self.services.server.runtime().spawn(async move {
+		match conn.drive().await {
+			| Err(e) => error!("LDAP connection error: {e}"),
+			| Ok(()) => debug!("LDAP connection completed."),
+		}
+	});
+
+	match (&config.bind_dn, &config.bind_password_file) {
+		| (Some(bind_dn), _) if bind_dn.contains("{username}") => {
+			return Err!(Ldap("LDAP synchronisation requires a bind DN without {{username}}."));
+		},
+		| (Some(bind_dn), Some(bind_password_file)) => {
+			let bind_pw = String::from_utf8(std::fs::read(bind_password_file)?)?;
+			ldap.simple_bind(bind_dn, bind_pw.trim())
+				.await
+				.and_then(ldap3::LdapResult::success)
+				.map_err(|e| err!(Ldap(error!("LDAP bind error: {e}"))))?;
+		},
+		| (..) => {},
+	}
+
+	let mut search = async |base: &str, filter: &str, attrs: &[&String]| {
+		ldap.search(base, Scope::Subtree, filter, attrs)
+			.await
+			.and_then(ldap3::SearchResult::success)
+			.inspect(|(entries, result)| trace!(?entries, ?result, "LDAP Search"))
+			.map(|(entries, _result)| {
+				entries
+					.into_iter()
+					.map(SearchEntry::construct)
+					.collect::<Vec<_>>()
+			})
+			.map_err(|e| err!(Ldap(error!(?attrs, ?filter, "LDAP search error: {e}"))))
+	};
+
+	let user_attrs = [&config.uid_attribute, &config.name_attribute];
+	let user_filter = config.filter.replace("{username}", "*");
+	let users = search(&config.base_dn, &user_filter, &user_attrs).await?;
+
+	let admins = if config.admin_filter.is_empty() {
+		None
+	} else {
+		let admin_base_dn = if config.admin_base_dn.is_empty() {
+			&config.base_dn
+		} else {
+			&config.admin_base_dn
+		};
+
+		let admin_filter = config.admin_filter.replace("{username}", "*");
+		Some(search(admin_base_dn, &admin_filter, &user_attrs).await?)
+	};
+
+	let groups = if config.group_rooms.is_empty() && config.group_workspaces.is_empty() {
+		Vec::new()
+	} else {
+		let group_base_dn = if config.group_base_dn.is_empty() {
+			&config.base_dn
+		} else {
+			&config.group_base_dn
+		};
+
+		let group_attrs = [&config.group_member_attribute];
+		search(group_base_dn, &config.group_filter, &group_attrs).await?
+	};
+
+	ldap.unbind()
+		.await
+		.map_err(|e| err!(Ldap(error!("LDAP unbind error: {e}"))))?;
+
+	driver.await.log_err().ok();
+
+	Ok(Directory::new(config, users, admins, groups))
+}
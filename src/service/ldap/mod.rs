@@ -0,0 +1,49 @@
+#[cfg(feature = "ldap")]
+mod directory;
+#[cfg(feature = "ldap")]
+mod sync;
+#[cfg(all(test, feature = "ldap"))]
+mod tests;
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tuwunel_core::{Result, debug_info, result::LogErr};
+
+/// Periodically synchronises LDAP users with the directory.
+pub struct Service {
+	services: Arc<crate::services::OnceServices>,
+}
+
+#[async_trait]
+impl crate::Service for Service {
+	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
+		Ok(Arc::new(Self { services: args.services.clone() }))
+	}
+
+	async fn worker(self: Arc<Self>) -> Result {
+		let config = &self.services.server.config.ldap;
+		if !config.enable || config.sync_interval == 0 {
+			return Ok(());
+		}
+
+		let interval = Duration::from_secs(config.sync_interval);
+		loop {
+			debug_info!("Synchronising LDAP users");
+			self.sync().await.log_err().ok();
+
+			tokio::select! {
+				() = tokio::time::sleep(interval) => {},
+				() = self.services.server.until_shutdown() => return Ok(())
+			};
+		}
+	}
+
+	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
+}
+
+#[cfg(not(feature = "ldap"))]
+impl Service {
+	#[expect(clippy::unused_async)]
+	pub async fn sync(&self) -> Result { tuwunel_core::Err!(FeatureDisabled("ldap")) }
+}
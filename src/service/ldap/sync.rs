@@ -0,0 +1,277 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use futures::{FutureExt, StreamExt};
+use ruma::{OwnedRoomId, OwnedRoomOrAliasId, OwnedUserId, UserId};
+use tuwunel_core::{Result, config::LdapConfig, implement, info, result::LogErr, warn};
+
+use super::directory::Directory;
+
+/// Rooms joined and left by the synchronisation, keyed by the configured room
+/// or workspace they were resolved from.
+pub(super) struct Mapped<'a> {
+	pub(super) rooms: BTreeMap<&'a OwnedRoomOrAliasId, OwnedRoomId>,
+	pub(super) workspaces: BTreeMap<&'a str, OwnedRoomId>,
+}
+
+/// What the synchronisation knows of a local user before acting on it.
+#[derive(Debug, Default)]
+pub(super) struct Current {
+	pub(super) displayname: Option<String>,
+	pub(super) is_admin: bool,
+
+	/// Managed rooms the user is joined to.
+	pub(super) joined: BTreeSet<OwnedRoomId>,
+}
+
+/// Change bringing a local user in line with the directory.
+#[derive(Debug, Eq, PartialEq)]
+pub(super) enum Action {
+	Deactivate,
+	SetDisplayname(String),
+	MakeAdmin,
+	RevokeAdmin,
+	Join(OwnedRoomId),
+	Leave(OwnedRoomId),
+}
+
+/// Synchronises every local LDAP user with the directory.
+#[implement(super::Service)]
+#[tracing::instrument(level = "debug", skip(self))]
+pub async fn sync(&self) -> Result {
+	let config = &self.services.server.config.ldap;
+	let directory = self.fetch_directory().await?;
+
+	let mut mapped = Mapped {
+		rooms: BTreeMap::new(),
+		workspaces: BTreeMap::new(),
+	};
+
+	for room in config.group_rooms.values().flatten() {
+		match self.services.alias.maybe_resolve(room).await {
+			| Ok(room_id) => {
+				mapped.rooms.insert(room, room_id);
+			},
+			| Err(e) => warn!("Failed to resolve LDAP group room {room}: {e}"),
+		}
+	}
+
+	for workspace_id in config.group_workspaces.values().flatten() {
+		match self
+			.services
+			.workspace
+			.get_space_room_id(workspace_id)
+			.await
+		{
+			| Ok(room_id) => {
+				mapped
+					.workspaces
+					.insert(workspace_id.as_str(), room_id);
+			},
+			| Err(e) =>
+				warn!("Failed to find the space of LDAP group workspace {workspace_id}: {e}"),
+		}
+	}
+
+	let user_ids: Vec<OwnedUserId> = self
+		.services
+		.users
+		.list_local_users()
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	for user_id in &user_ids {
+		let origin = self.services.users.origin(user_id).await;
+		let origin = origin.as_deref().unwrap_or("password");
+
+		let is_ldap = match origin {
+			| "ldap" => true,
+			| "password" if config.sync_adopt_password_users => {
+				let uid = user_id.localpart().to_lowercase();
+				let adopt = directory.users.contains_key(&uid)
+					&& *user_id != self.services.globals.server_user;
+
+				if adopt {
+					info!("Adopting {user_id} found in the LDAP directory");
+					self.services.users.set_origin(user_id, "ldap");
+				}
+
+				adopt
+			},
+			| _ => false,
+		};
+
+		if is_ldap {
+			self.sync_user(&directory, &mapped, user_id)
+				.await
+				.log_err()
+				.ok();
+		}
+	}
+
+	Ok(())
+}
+
+#[implement(super::Service)]
+async fn sync_user(
+	&self,
+	directory: &Directory,
+	mapped: &Mapped<'_>,
+	user_id: &UserId,
+) -> Result {
+	let config = &self.services.server.config.ldap;
+	let uid = user_id.localpart().to_lowercase();
+
+	let mut current = Current {
+		displayname: self
+			.services
+			.users
+			.displayname(user_id)
+			.await
+			.ok(),
+		is_admin: self.services.admin.user_is_admin(user_id).await,
+		joined: BTreeSet::new(),
+	};
+
+	for room_id in mapped
+		.rooms
+		.values()
+		.chain(mapped.workspaces.values())
+	{
+		if self
+			.services
+			.state_cache
+			.is_joined(user_id, room_id)
+			.await
+		{
+			current.joined.insert(room_id.clone());
+		}
+	}
+
+	for action in plan(config, directory, mapped, &uid, &current) {
+		match action {
+			| Action::Deactivate => {
+				info!("Deactivating {user_id} which is no longer in the LDAP directory");
+				self.services
+					.deactivate
+					.full_deactivate(user_id)
+					.boxed()
+					.await?;
+			},
+			| Action::SetDisplayname(displayname) => {
+				let all_joined_rooms: Vec<OwnedRoomId> = self
+					.services
+					.state_cache
+					.rooms_joined(user_id)
+					.map(Into::into)
+					.collect()
+					.await;
+
+				self.services
+					.users
+					.update_displayname(user_id, Some(displayname.as_str()), &all_joined_rooms)
+					.await;
+			},
+			| Action::MakeAdmin => {
+				self.services
+					.admin
+					.make_user_admin(user_id)
+					.boxed()
+					.await?;
+			},
+			| Action::RevokeAdmin => {
+				self.services.admin.revoke_admin(user_id).await?;
+			},
+			| Action::Join(room_id) => {
+				let state_lock = self.services.state.mutex.lock(&room_id).await;
+				match self
+					.services
+					.membership
+					.join(user_id, &room_id, None, None, &[], false, &state_lock)
+					.boxed()
+					.await
+				{
+					| Ok(()) => info!("LDAP made {user_id} join {room_id}"),
+					| Err(e) => warn!("LDAP failed to make {user_id} join {room_id}: {e}"),
+				}
+			},
+			| Action::Leave(room_id) => {
+				let state_lock = self.services.state.mutex.lock(&room_id).await;
+				match self
+					.services
+					.membership
+					.leave(user_id, &room_id, None, false, &state_lock)
+					.boxed()
+					.await
+				{
+					| Ok(()) => info!("LDAP made {user_id} leave {room_id}"),
+					| Err(e) => warn!("LDAP failed to make {user_id} leave {room_id}: {e}"),
+				}
+			},
+		}
+	}
+
+	Ok(())
+}
+
+/// Decides what to change of a local user with the given `uid` so it matches
+/// the directory.
+pub(super) fn plan(
+	config: &LdapConfig,
+	directory: &Directory,
+	mapped: &Mapped<'_>,
+	uid: &str,
+	current: &Current,
+) -> Vec<Action> {
+	let Some(displayname) = directory.users.get(uid) else {
+		// An empty result more likely means a misconfigured search than an
+		// empty directory.
+		return (config.sync_deactivate && !directory.users.is_empty())
+			.then_some(Action::Deactivate)
+			.into_iter()
+			.collect();
+	};
+
+	let mut actions = Vec::new();
+	if let Some(displayname) = displayname
+		&& current.displayname.as_ref() != Some(displayname)
+	{
+		actions.push(Action::SetDisplayname(displayname.clone()));
+	}
+
+	if let Some(admins) = &directory.admins {
+		match (admins.contains(uid), current.is_admin) {
+			| (true, false) => actions.push(Action::MakeAdmin),
+			| (false, true) => actions.push(Action::RevokeAdmin),
+			| _ => {},
+		}
+	}
+
+	let wanted: BTreeSet<&OwnedRoomId> = directory
+		.rooms_of(config, uid)
+		.into_iter()
+		.filter_map(|room| mapped.rooms.get(room))
+		.chain(
+			directory
+				.workspaces_of(config, uid)
+				.into_iter()
+				.filter_map(|workspace_id| mapped.workspaces.get(workspace_id)),
+		)
+		.collect();
+
+	let managed: BTreeSet<&OwnedRoomId> = mapped
+		.rooms
+		.values()
+		.chain(mapped.workspaces.values())
+		.collect();
+
+	for room_id in managed {
+		match (wanted.contains(room_id), current.joined.contains(room_id)) {
+			| (true, false) => actions.push(Action::Join(room_id.clone())),
+			| (false, true) => actions.push(Action::Leave(room_id.clone())),
+			| _ => {},
+		}
+	}
+
+	actions
+}
@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use ldap3::SearchEntry;
+use ruma::{OwnedRoomId, OwnedRoomOrAliasId, owned_room_alias_id, owned_room_id};
+use tuwunel_core::config::LdapConfig;
+
+use super::{
+	directory::Directory,
+	sync::{Action, Current, Mapped, plan},
+};
+
+const ENGINEERING: &str = "cn=engineering,ou=groups,dc=example,dc=org";
+const SALES: &str = "cn=sales,ou=groups,dc=example,dc=org";
+
+fn config() -> LdapConfig {
+	LdapConfig {
+		uid_attribute: "uid".to_owned(),
+		name_attribute: "cn".to_owned(),
+		group_member_attribute: "member".to_owned(),
+		group_rooms: BTreeMap::from([
+			(ENGINEERING.to_owned(), vec![
+				OwnedRoomOrAliasId::from(owned_room_alias_id!("#engineering:example.org")),
+				OwnedRoomOrAliasId::from(owned_room_id!("!general:example.org")),
+			]),
+			(SALES.to_owned(), vec![OwnedRoomOrAliasId::from(owned_room_id!(
+				"!general:example.org"
+			))]),
+		]),
+		group_workspaces: BTreeMap::from([(ENGINEERING.to_owned(), vec!["eng".to_owned()])]),
+		..Default::default()
+	}
+}
+
+fn entry(dn: &str, attrs: &[(&str, &[&str])]) -> SearchEntry {
+	SearchEntry {
+		dn: dn.to_owned(),
+		attrs: attrs
+			.iter()
+			.map(|(attr, values)| {
+				let values = values
+					.iter()
+					.copied()
+					.map(str::to_owned)
+					.collect();
+				((*attr).to_owned(), values)
+			})
+			.collect(),
+		bin_attrs: HashMap::new(),
+	}
+}
+
+fn users() -> Vec<SearchEntry> {
+	vec![
+		entry("uid=alice,ou=users,dc=example,dc=org", &[
+			("uid", &["Alice"]),
+			("cn", &["Alice A."]),
+		]),
+		entry("uid=bob,ou=users,dc=example,dc=org", &[("uid", &["bob"])]),
+		entry("cn=printer,ou=users,dc=example,dc=org", &[("cn", &["Printer"])]),
+	]
+}
+
+fn groups() -> Vec<SearchEntry> {
+	vec![
+		entry(ENGINEERING, &[("member", &[
+			"UID=alice,OU=users,DC=example,DC=org",
+			"uid=carol,ou=users,dc=example,dc=org",
+		])]),
+		entry(SALES, &[("member", &["uid=bob,ou=users,dc=example,dc=org"])]),
+	]
+}
+
+#[test]
+fn users_by_lowercased_uid() {
+	let directory = Directory::new(&config(), users(), None, Vec::new());
+
+	assert_eq!(directory.users.len(), 2);
+	assert_eq!(directory.users["alice"].as_deref(), Some("Alice A."));
+	assert_eq!(directory.users["bob"], None);
+}
+
+#[test]
+fn group_members_by_dn() {
+	let directory = Directory::new(&config(), users(), None, groups());
+
+	assert!(directory.is_member(ENGINEERING, "alice"));
+	assert!(directory.is_member(&ENGINEERING.to_uppercase(), "alice"));
+	assert!(!directory.is_member(ENGINEERING, "bob"));
+	assert!(!directory.is_member(ENGINEERING, "carol"));
+	assert!(directory.is_member(SALES, "bob"));
+}
+
+#[test]
+fn rooms_and_workspaces_of_groups() {
+	let config = config();
+	let directory = Directory::new(&config, users(), None, groups());
+
+	let alice_rooms = directory.rooms_of(&config, "alice");
+	assert_eq!(alice_rooms.len(), 2);
+	assert_eq!(
+		directory
+			.workspaces_of(&config, "alice")
+			.into_iter()
+			.collect::<Vec<_>>(),
+		["eng"]
+	);
+
+	let bob_rooms = directory.rooms_of(&config, "bob");
+	assert_eq!(bob_rooms.len(), 1);
+	assert!(
+		bob_rooms
+			.iter()
+			.all(|room| alice_rooms.contains(room))
+	);
+	assert!(directory.workspaces_of(&config, "bob").is_empty());
+
+	assert!(directory.rooms_of(&config, "carol").is_empty());
+}
+
+#[test]
+fn admins_only_with_filter() {
+	let admins = vec![entry("uid=alice,ou=users,dc=example,dc=org", &[("uid", &["alice"])])];
+
+	let directory = Directory::new(&config(), users(), None, Vec::new());
+	assert!(directory.admins.is_none());
+
+	let directory = Directory::new(&config(), users(), Some(admins), Vec::new());
+	let admins = directory.admins.expect("admin filter configured");
+	assert!(admins.contains("alice"));
+	assert!(!admins.contains("bob"));
+}
+
+fn mapped_rooms(config: &LdapConfig) -> Mapped<'_> {
+	let rooms = config
+		.group_rooms
+		.values()
+		.flatten()
+		.map(|room| {
+			let room_id = match room.as_str() {
+				| "#engineering:example.org" => owned_room_id!("!engineering:example.org"),
+				| _ => owned_room_id!("!general:example.org"),
+			};
+
+			(room, room_id)
+		})
+		.collect();
+
+	let workspaces = BTreeMap::from([("eng", owned_room_id!("!eng:example.org"))]);
+
+	Mapped { rooms, workspaces }
+}
+
+fn joined(rooms: &[&str]) -> BTreeSet<OwnedRoomId> {
+	rooms
+		.iter()
+		.map(|room_id| OwnedRoomId::try_from(*room_id).expect("valid room id"))
+		.collect()
+}
+
+#[test]
+fn plan_deactivates_missing_users() {
+	let config = LdapConfig { sync_deactivate: true, ..config() };
+	let mapped = mapped_rooms(&config);
+	let current = Current::default();
+
+	let directory = Directory::new(&config, users(), None, groups());
+	assert_eq!(plan(&config, &directory, &mapped, "carol", &current), [Action::Deactivate]);
+
+	let directory = Directory::new(&config, Vec::new(), None, Vec::new());
+	assert!(plan(&config, &directory, &mapped, "carol", &current).is_empty());
+
+	let config = LdapConfig { sync_deactivate: false, ..config() };
+	let mapped = mapped_rooms(&config);
+	let directory = Directory::new(&config, users(), None, groups());
+	assert!(plan(&config, &directory, &mapped, "carol", &current).is_empty());
+}
+
+#[test]
+fn plan_joins_and_leaves_managed_rooms() {
+	let config = config();
+	let mapped = mapped_rooms(&config);
+	let directory = Directory::new(&config, users(), None, groups());
+
+	let current = Current {
+		displayname: Some("Alice A.".to_owned()),
+		joined: joined(&["!general:example.org"]),
+		..Default::default()
+	};
+
+	assert_eq!(plan(&config, &directory, &mapped, "alice", &current), [
+		Action::Join(owned_room_id!("!eng:example.org")),
+		Action::Join(owned_room_id!("!engineering:example.org")),
+	]);
+
+	let current = Current {
+		joined: joined(&["!eng:example.org", "!engineering:example.org", "!general:example.org"]),
+		..Default::default()
+	};
+
+	assert_eq!(plan(&config, &directory, &mapped, "bob", &current), [
+		Action::Leave(owned_room_id!("!eng:example.org")),
+		Action::Leave(owned_room_id!("!engineering:example.org")),
+	]);
+}
+
+#[test]
+fn plan_updates_displayname_and_admin() {
+	let config = config();
+	let mapped = mapped_rooms(&config);
+	let admins = vec![entry("uid=alice,ou=users,dc=example,dc=org", &[("uid", &["alice"])])];
+	let directory = Directory::new(&config, users(), Some(admins), Vec::new());
+
+	let current = Current {
+		displayname: Some("alice".to_owned()),
+		..Default::default()
+	};
+
+	assert_eq!(plan(&config, &directory, &mapped, "alice", &current), [
+		Action::SetDisplayname("Alice A.".to_owned()),
+		Action::MakeAdmin,
+	]);
+
+	let current = Current { is_admin: true, ..Default::default() };
+	assert_eq!(plan(&config, &directory, &mapped, "bob", &current), [Action::RevokeAdmin]);
+
+	let directory = Directory::new(&config, users(), None, Vec::new());
+	assert!(plan(&config, &directory, &mapped, "bob", &current).is_empty());
+}
@@ -0,0 +1,226 @@
+#[cfg(test)]
+mod tests;
+
+use std::sync::Arc;
+
+use ruma::{OwnedUserId, RoomId, ServerName, api::federation::openid::get_openid_userinfo};
+use serde::Serialize;
+use tuwunel_core::{
+	Err, Result, err, implement,
+	jwt::{Algorithm, EncodingKey, Header, encode},
+	utils,
+};
+use url::Url;
+
+/// Issues LiveKit access tokens to the participants of MatrixRTC calls as
+/// configured in `[global.livekit]`.
+pub struct Service {
+	services: Arc<crate::services::OnceServices>,
+}
+
+/// What a participant may do in the call of a room.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Grant {
+	pub can_publish: bool,
+	pub can_subscribe: bool,
+	pub can_publish_data: bool,
+}
+
+/// An access token for the LiveKit SFU at `url`.
+#[derive(Debug)]
+pub struct Access {
+	pub url: Url,
+	pub jwt: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Claims<'a> {
+	iss: &'a str,
+	sub: String,
+	name: String,
+	nbf: u64,
+	exp: u64,
+	video: VideoGrant<'a>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VideoGrant<'a> {
+	room: &'a str,
+	room_join: bool,
+	can_publish: bool,
+	can_subscribe: bool,
+	can_publish_data: bool,
+}
+
+impl crate::Service for Service {
+	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
+		Ok(Arc::new(Self { services: args.services.clone() }))
+	}
+
+	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
+}
+
+/// Whether the service is enabled.
+#[implement(Service)]
+#[inline]
+pub fn is_enabled(&self) -> bool { self.services.server.config.livekit.enable }
+
+/// Exchanges the OpenID token of a Matrix user for an access token to the
+/// call of a room they are joined to.
+#[implement(Service)]
+#[tracing::instrument(level = "debug", skip(self, openid_token))]
+pub async fn authorize(
+	&self,
+	room_id: &RoomId,
+	openid_token: &str,
+	matrix_server_name: &ServerName,
+	device_id: &str,
+) -> Result<Access> {
+	let config = &self.services.server.config.livekit;
+	let (Some(url), Some(key)) = (&config.url, &config.key) else {
+		return Err!(Config("livekit.url", "The LiveKit service is not configured."));
+	};
+
+	let Some(grant) = self.grant(room_id) else {
+		return Err!(Request(Forbidden("Calls are not available in this room.")));
+	};
+
+	let user_id = self
+		.verify_openid_token(openid_token, matrix_server_name)
+		.await?;
+
+	if !self
+		.services
+		.state_cache
+		.is_joined(&user_id, room_id)
+		.await
+	{
+		return Err!(Request(Forbidden("You are not joined to this room.")));
+	}
+
+	let name = self
+		.services
+		.users
+		.displayname(&user_id)
+		.await
+		.unwrap_or_else(|_| user_id.to_string());
+
+	let nbf = utils::time::now_secs();
+	let claims = Claims {
+		iss: key,
+		sub: format!("{user_id}:{device_id}"),
+		name,
+		nbf,
+		exp: nbf.saturating_add(config.token_ttl),
+		video: VideoGrant {
+			room: room_id.as_str(),
+			room_join: true,
+			can_publish: grant.can_publish,
+			can_subscribe: grant.can_subscribe,
+			can_publish_data: grant.can_publish_data,
+		},
+	};
+
+	Ok(Access {
+		url: url.clone(),
+		jwt: sign(&claims, &self.secret().await?)?,
+	})
+}
+
+/// The grant of the room; None when calls are not allowed in it.
+#[implement(Service)]
+pub fn grant(&self, room_id: &RoomId) -> Option<Grant> {
+	let config = &self.services.server.config.livekit;
+	let room = config.rooms.get(room_id);
+	if room.is_none() && config.restrict_rooms {
+		return None;
+	}
+
+	Some(Grant {
+		can_publish: room
+			.and_then(|room| room.can_publish)
+			.unwrap_or(config.can_publish),
+		can_subscribe: room
+			.and_then(|room| room.can_subscribe)
+			.unwrap_or(config.can_subscribe),
+		can_publish_data: room
+			.and_then(|room| room.can_publish_data)
+			.unwrap_or(config.can_publish_data),
+	})
+}
+
+/// Finds the user an OpenID token was issued to; tokens of other servers are
+/// verified with them over federation when `federated_users` is enabled.
+#[implement(Service)]
+async fn verify_openid_token(
+	&self,
+	openid_token: &str,
+	matrix_server_name: &ServerName,
+) -> Result<OwnedUserId> {
+	if self
+		.services
+		.globals
+		.server_is_ours(matrix_server_name)
+	{
+		return self
+			.services
+			.users
+			.find_from_openid_token(openid_token)
+			.await;
+	}
+
+	if !self
+		.services
+		.server
+		.config
+		.livekit
+		.federated_users
+	{
+		return Err!(Request(Forbidden("Calls are only available to local users.")));
+	}
+
+	let request = get_openid_userinfo::v1::Request::new(openid_token.to_owned());
+	let user_id = self
+		.services
+		.federation
+		.execute(matrix_server_name, request)
+		.await
+		.map_err(|e| err!(Request(Unauthorized("OpenID token could not be verified: {e}"))))?
+		.sub;
+
+	if user_id.server_name() != matrix_server_name {
+		return Err!(Request(Unauthorized(
+			"OpenID token of {user_id} was verified by {matrix_server_name}."
+		)));
+	}
+
+	Ok(user_id)
+}
+
+#[implement(Service)]
+async fn secret(&self) -> Result<String> {
+	let config = &self.services.server.config.livekit;
+	let secret = match &config.secret_file {
+		| Some(secret_file) => tokio::fs::read_to_string(secret_file)
+			.await?
+			.trim()
+			.to_owned(),
+		| None => config.secret.clone().unwrap_or_default(),
+	};
+
+	if secret.is_empty() {
+		return Err!(Config("livekit.secret", "The LiveKit secret is empty."));
+	}
+
+	Ok(secret)
+}
+
+fn sign(claims: &Claims<'_>, secret: &str) -> Result<String> {
+	encode(
+		&Header::new(Algorithm::HS256),
+		claims,
+		&EncodingKey::from_secret(secret.as_bytes()),
+	)
+	.map_err(|e| err!("Failed to sign LiveKit access token: {e}"))
+}
@@ -0,0 +1,46 @@
+use serde_json::Value as JsonValue;
+use tuwunel_core::jwt::{Algorithm, DecodingKey, Validation, decode};
+
+use super::{Claims, VideoGrant, sign};
+
+fn claims() -> Claims<'static> {
+	Claims {
+		iss: "devkey",
+		sub: "@alice:example.com:DEVICE".to_owned(),
+		name: "Alice".to_owned(),
+		nbf: 1_700_000_000,
+		exp: u64::MAX >> 12,
+		video: VideoGrant {
+			room: "!call:example.com",
+			room_join: true,
+			can_publish: false,
+			can_subscribe: true,
+			can_publish_data: true,
+		},
+	}
+}
+
+#[test]
+fn signed_for_livekit() {
+	let jwt = sign(&claims(), "secret").expect("signed token");
+
+	let mut validation = Validation::new(Algorithm::HS256);
+	validation.set_issuer(&["devkey"]);
+	let token = decode::<JsonValue>(&jwt, &DecodingKey::from_secret(b"secret"), &validation)
+		.expect("valid token");
+
+	let claims = token.claims;
+	assert_eq!(claims["sub"], "@alice:example.com:DEVICE");
+	assert_eq!(claims["video"]["room"], "!call:example.com");
+	assert_eq!(claims["video"]["roomJoin"], true);
+	assert_eq!(claims["video"]["canPublish"], false);
+	assert_eq!(claims["video"]["canPublishData"], true);
+}
+
+#[test]
+fn rejected_with_other_secret() {
+	let jwt = sign(&claims(), "secret").expect("signed token");
+	let validation = Validation::new(Algorithm::HS256);
+
+	assert!(decode::<JsonValue>(&jwt, &DecodingKey::from_secret(b"other"), &validation).is_err());
+}
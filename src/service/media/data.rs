@@ -1,24 +1,53 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use futures::{StreamExt, pin_mut};
-use ruma::{Mxc, OwnedMxcUri, UserId, http_headers::ContentDisposition};
+use ruma::{Mxc, OwnedMxcUri, OwnedUserId, UserId, http_headers::ContentDisposition};
+use serde::{Deserialize, Serialize};
 use tuwunel_core::{
 	Err, Result, debug, debug_info, err,
 	utils::{ReadyExt, str_from_bytes, stream::TryIgnore, string_from_bytes},
 };
-use tuwunel_database::{Database, Interfix, Map, serialize_key};
+use tuwunel_database::{Database, Interfix, Json, Map, serialize_key};
 
 use super::{preview::UrlPreviewData, thumbnail::Dim};
 
 pub(crate) struct Data {
 	mediaid_file: Arc<Map>,
 	mediaid_user: Arc<Map>,
+	/// Maps MXC → quarantine record (JSON)
+	pub(super) mediaid_quarantine: Arc<Map>,
+	/// Maps SHA-256 content hash (32 bytes) → blocklist entry (JSON)
+	pub(super) media_sha256_blocked: Arc<Map>,
 	url_previews: Arc<Map>,
+	/// Maps MXC of the image of a replaced URL preview → time of replacement
+	/// (u64 secs)
+	url_preview_replaced: Arc<Map>,
 	/// Maps SHA-256 content hash (32 bytes) → reference count (u32, big-endian)
 	media_sha256_refs: Arc<Map>,
+	/// Maps SHA-256 content hash (32 bytes) → malware scan result (JSON)
+	pub(super) media_sha256_scan: Arc<Map>,
 	/// Maps media DB key → SHA-256 content hash (32 bytes)
 	/// Needed so that delete() can look up a key's hash without re-reading the file
 	mediaid_sha256: Arc<Map>,
+	/// Maps MXC → time of upload or first fetch (u64 millis)
+	pub(super) mediaid_created: Arc<Map>,
+	/// Maps MXC → time of the last download (u64 millis)
+	pub(super) mediaid_accessed: Arc<Map>,
+	/// Maps MXC → size and origin of the media (JSON)
+	pub(super) mediaid_info: Arc<Map>,
+	/// Maps (group, name) → count and total size of the media (JSON)
+	pub(super) media_stats: Arc<Map>,
+	/// Maps local user → time of the last encrypted event they sent (u64
+	/// millis)
+	pub(super) userid_encryptedsent: Arc<Map>,
+	pub(super) global: Arc<Map>,
+}
+
+/// A URL preview with its time of expiry in seconds since the unix epoch.
+#[derive(Deserialize, Serialize)]
+struct CachedUrlPreview {
+	expires: u64,
+	preview: UrlPreviewData,
 }
 
 #[derive(Debug)]
@@ -33,9 +62,19 @@ pub(super) fn new(db: &Arc<Database>) -> Self {
 		Self {
 			mediaid_file: db["mediaid_file"].clone(),
 			mediaid_user: db["mediaid_user"].clone(),
+			mediaid_quarantine: db["mediaid_quarantine"].clone(),
+			media_sha256_blocked: db["media_sha256_blocked"].clone(),
 			url_previews: db["url_previews"].clone(),
+			url_preview_replaced: db["url_preview_replaced"].clone(),
 			media_sha256_refs: db["media_sha256_refs"].clone(),
+			media_sha256_scan: db["media_sha256_scan"].clone(),
 			mediaid_sha256: db["mediaid_sha256"].clone(),
+			mediaid_created: db["mediaid_created"].clone(),
+			mediaid_accessed: db["mediaid_accessed"].clone(),
+			mediaid_info: db["mediaid_info"].clone(),
+			media_stats: db["media_stats"].clone(),
+			userid_encryptedsent: db["userid_encryptedsent"].clone(),
+			global: db["global"].clone(),
 		}
 	}
 
@@ -47,7 +86,7 @@ pub(super) fn create_file_metadata(
 		content_disposition: Option<&ContentDisposition>,
 		content_type: Option<&str>,
 	) -> Result<Vec<u8>> {
-		let dim: &[u32] = &[dim.width, dim.height];
+		let dim: &[u32] = &dim.key();
 		let key = (mxc, dim, content_disposition, content_type);
 		let key = serialize_key(key)?;
 		self.mediaid_file.insert(&key, []);
@@ -84,6 +123,10 @@ pub(super) async fn delete_file_mxc(&self, mxc: &Mxc<'_>) {
 				self.mediaid_user.remove(key);
 			})
 			.await;
+
+		let mxc = mxc.to_string();
+		self.mediaid_created.remove(&mxc);
+		self.mediaid_accessed.remove(&mxc);
 	}
 
 	/// Searches for all files with the given MXC
@@ -113,7 +156,7 @@ pub(super) async fn search_file_metadata(
 		mxc: &Mxc<'_>,
 		dim: &Dim,
 	) -> Result<Metadata> {
-		let dim: &[u32] = &[dim.width, dim.height];
+		let dim: &[u32] = &dim.key();
 		let prefix = (mxc, dim, Interfix);
 
 		let keys = self
@@ -151,6 +194,17 @@ pub(super) async fn search_file_metadata(
 		Ok(Metadata { content_disposition, content_type, key })
 	}
 
+	/// The user of ours who uploaded the media.
+	pub(super) async fn get_uploader(&self, mxc: &Mxc<'_>) -> Option<OwnedUserId> {
+		let prefix = (mxc, Interfix);
+		self.mediaid_user
+			.stream_prefix_raw(&prefix)
+			.ignore_err()
+			.ready_filter_map(|(_, user)| str_from_bytes(user).ok()?.try_into().ok())
+			.next()
+			.await
+	}
+
 	/// Gets all the MXCs associated with a user
 	pub(super) async fn get_all_user_mxcs(&self, user_id: &UserId) -> Vec<OwnedMxcUri> {
 		self.mediaid_user
@@ -235,6 +289,35 @@ pub(super) fn remove_media_hash(&self, key: &[u8]) {
 		self.mediaid_sha256.remove(key);
 	}
 
+	/// Gets the content hashes of all the media DB keys.
+	pub(super) async fn get_all_media_hashes(&self) -> Vec<(Vec<u8>, [u8; 32])> {
+		self.mediaid_sha256
+			.raw_stream()
+			.ignore_err()
+			.ready_filter_map(|(key, hash)| Some((key.to_vec(), hash.try_into().ok()?)))
+			.collect()
+			.await
+	}
+
+	/// Gets all the content hashes with a reference count.
+	pub(super) async fn get_all_sha256_refs(&self) -> Vec<[u8; 32]> {
+		self.media_sha256_refs
+			.raw_keys()
+			.ignore_err()
+			.ready_filter_map(|hash| hash.try_into().ok())
+			.collect()
+			.await
+	}
+
+	/// Replaces the reference counts of all the content hashes.
+	pub(super) async fn set_sha256_refs(&self, refs: &HashMap<[u8; 32], u32>) {
+		self.media_sha256_refs.clear().await;
+		for (hash, count) in refs {
+			self.media_sha256_refs
+				.insert(hash.as_slice(), &count.to_be_bytes());
+		}
+	}
+
 
 	#[inline]
 	pub(super) fn remove_url_preview(&self, url: &str) -> Result {
@@ -246,105 +329,108 @@ pub(super) fn set_url_preview(
 		&self,
 		url: &str,
 		data: &UrlPreviewData,
-		timestamp: Duration,
+		expires: Duration,
 	) -> Result {
-		let mut value = Vec::<u8>::new();
-		value.extend_from_slice(&timestamp.as_secs().to_be_bytes());
-		value.push(0xFF);
-		value.extend_from_slice(
-			data.title
-				.as_ref()
-				.map(String::as_bytes)
-				.unwrap_or_default(),
-		);
-		value.push(0xFF);
-		value.extend_from_slice(
-			data.description
-				.as_ref()
-				.map(String::as_bytes)
-				.unwrap_or_default(),
-		);
-		value.push(0xFF);
-		value.extend_from_slice(
-			data.image
-				.as_ref()
-				.map(String::as_bytes)
-				.unwrap_or_default(),
-		);
-		value.push(0xFF);
-		value.extend_from_slice(&data.image_size.unwrap_or(0).to_be_bytes());
-		value.push(0xFF);
-		value.extend_from_slice(&data.image_width.unwrap_or(0).to_be_bytes());
-		value.push(0xFF);
-		value.extend_from_slice(&data.image_height.unwrap_or(0).to_be_bytes());
-
-		self.url_previews.insert(url.as_bytes(), &value);
+		let cached = CachedUrlPreview {
+			expires: expires.as_secs(),
+			preview: data.clone(),
+		};
+
+		self.url_previews.raw_put(url, Json(cached));
 
 		Ok(())
 	}
 
-	pub(super) async fn get_url_preview(&self, url: &str) -> Result<UrlPreviewData> {
-		let values = self.url_previews.get(url).await?;
+	/// Gets the URL preview along with its time of expiry since the unix epoch.
+	pub(super) async fn get_url_preview(&self, url: &str) -> Result<(Duration, UrlPreviewData)> {
+		let value = self.url_previews.get(url).await?;
 
-		let mut values = values.split(|&b| b == 0xFF);
+		// Previews were stored as fields separated by 0xFF, led by their time of
+		// creation as big-endian seconds; those expire right away.
+		if value.first() != Some(&b'{') {
+			return Ok(legacy_url_preview(&value));
+		}
 
-		let _ts = values.next();
-		/* if we ever decide to use timestamp, this is here.
-		match values.next().map(|b| u64::from_be_bytes(b.try_into().expect("valid BE array"))) {
-			Some(0) => None,
-			x => x,
-		};*/
+		let cached: CachedUrlPreview = serde_json::from_slice(&value)?;
 
-		let title = match values
-			.next()
-			.and_then(|b| String::from_utf8(b.to_vec()).ok())
-		{
-			| Some(s) if s.is_empty() => None,
-			| x => x,
-		};
-		let description = match values
-			.next()
-			.and_then(|b| String::from_utf8(b.to_vec()).ok())
-		{
-			| Some(s) if s.is_empty() => None,
-			| x => x,
-		};
-		let image = match values
+		Ok((Duration::from_secs(cached.expires), cached.preview))
+	}
+
+	/// Keeps the image of a replaced preview, replaced at the time since the
+	/// unix epoch, until it is pruned.
+	pub(super) fn set_url_preview_replaced(&self, mxc: &str, replaced: Duration) {
+		self.url_preview_replaced
+			.raw_put(mxc, replaced.as_secs());
+	}
+
+	#[inline]
+	pub(super) fn remove_url_preview_replaced(&self, mxc: &str) {
+		self.url_preview_replaced.remove(mxc.as_bytes());
+	}
+
+	/// Gets the images of replaced previews along with their time of
+	/// replacement since the unix epoch.
+	pub(super) async fn get_url_preview_replaced(&self) -> Vec<(String, Duration)> {
+		self.url_preview_replaced
+			.stream()
+			.ignore_err()
+			.map(|(mxc, replaced): (&str, u64)| (mxc.to_owned(), Duration::from_secs(replaced)))
+			.collect()
+			.await
+	}
+
+	/// Gets the URLs of all the stored previews.
+	pub(super) async fn get_url_preview_urls(&self) -> Vec<String> {
+		self.url_previews
+			.raw_keys()
+			.ignore_err()
+			.ready_filter_map(|url| str_from_bytes(url).ok().map(ToOwned::to_owned))
+			.collect()
+			.await
+	}
+}
+
+fn legacy_url_preview(value: &[u8]) -> (Duration, UrlPreviewData) {
+	let mut values = value.split(|&b| b == 0xFF);
+
+	let created = values
+		.next()
+		.and_then(|b| b.try_into().ok())
+		.map(u64::from_be_bytes)
+		.map(Duration::from_secs)
+		.unwrap_or_default();
+
+	let mut string = || {
+		values
 			.next()
 			.and_then(|b| String::from_utf8(b.to_vec()).ok())
-		{
-			| Some(s) if s.is_empty() => None,
-			| x => x,
-		};
-		let image_size = match values
-			.next()
-			.map(|b| usize::from_be_bytes(b.try_into().unwrap_or_default()))
-		{
-			| Some(0) => None,
-			| x => x,
-		};
-		let image_width = match values
-			.next()
-			.map(|b| u32::from_be_bytes(b.try_into().unwrap_or_default()))
-		{
-			| Some(0) => None,
-			| x => x,
-		};
-		let image_height = match values
+			.filter(|s| !s.is_empty())
+	};
+
+	let title = string();
+	let description = string();
+	let image = string();
+	let image_size = values
+		.next()
+		.map(|b| usize::from_be_bytes(b.try_into().unwrap_or_default()))
+		.filter(|&size| size != 0);
+
+	let mut dimension = || {
+		values
 			.next()
 			.map(|b| u32::from_be_bytes(b.try_into().unwrap_or_default()))
-		{
-			| Some(0) => None,
-			| x => x,
-		};
-
-		Ok(UrlPreviewData {
-			title,
-			description,
-			image,
-			image_size,
-			image_width,
-			image_height,
-		})
-	}
+			.filter(|&dim| dim != 0)
+	};
+
+	let image_width = dimension();
+	let image_height = dimension();
+
+	(created, UrlPreviewData {
+		title,
+		description,
+		image,
+		image_size,
+		image_width,
+		image_height,
+	})
 }
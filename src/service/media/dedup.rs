@@ -0,0 +1,558 @@
+use std::{
+	collections::{HashMap, HashSet},
+	fmt,
+	io::ErrorKind::NotFound,
+};
+
+use sha2::Digest;
+use tokio::fs;
+use tuwunel_core::{
+	Result, debug, debug_warn, implement,
+	utils::{self, MutexMap},
+};
+
+use super::{Service, encode_key, sha256_hex, storage::MediaStorage};
+
+/// Outcome of folding the media stored by metadata key into content-addressed
+/// blobs.
+#[derive(Debug, Default)]
+pub struct DedupReport {
+	/// Files moved to the blob of their content hash.
+	pub files: usize,
+
+	/// Files of which a blob with identical content was already stored.
+	pub duplicates: usize,
+
+	/// Files of the database which were found in no storage.
+	pub missing: usize,
+
+	/// Blobs which were no longer referenced by any media.
+	pub orphans: usize,
+
+	/// Bytes freed by removing duplicates and orphans.
+	pub reclaimed: u64,
+}
+
+/// Where a file stored before deduplication was found.
+enum Legacy {
+	/// In the storage backend, by the metadata key.
+	Storage,
+
+	/// In the media directory, by the sha256 of the metadata key.
+	File,
+}
+
+impl fmt::Display for DedupReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let reclaimed = usize::try_from(self.reclaimed).unwrap_or(usize::MAX);
+
+		write!(
+			f,
+			"{} files deduplicated ({} duplicates, {} missing), {} orphaned blobs removed, {} \
+			 reclaimed",
+			self.files,
+			self.duplicates,
+			self.missing,
+			self.orphans,
+			utils::bytes::pretty(reclaimed),
+		)
+	}
+}
+
+/// Records of the content-addressed storage kept outside the storage backend:
+/// the content hash of each media and the references to each content in the
+/// database, and the files of the media directory stored before
+/// deduplication.
+pub(super) trait Records: Sync {
+	fn media_hash(&self, key: &[u8]) -> Option<[u8; 32]>;
+
+	fn set_media_hash(&self, key: &[u8], hash: &[u8; 32]);
+
+	fn remove_media_hash(&self, key: &[u8]);
+
+	/// Returns the new reference count.
+	fn increment_ref(&self, hash: &[u8; 32]) -> Result<u32>;
+
+	/// Returns the remaining reference count.
+	fn decrement_ref(&self, hash: &[u8; 32]) -> Result<u32>;
+
+	async fn media_keys(&self) -> Vec<Vec<u8>>;
+
+	async fn media_hashes(&self) -> Vec<(Vec<u8>, [u8; 32])>;
+
+	async fn referenced_hashes(&self) -> Vec<[u8; 32]>;
+
+	async fn set_refs(&self, refs: &HashMap<[u8; 32], u32>);
+
+	async fn read_legacy_file(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+	async fn remove_legacy_file(&self, key: &[u8]) -> Result;
+}
+
+/// Content stored once by its hash in the storage backend and counted for
+/// each media referring to it.
+pub(super) struct Blobs<'a, R> {
+	pub(super) records: &'a R,
+	pub(super) storage: &'a dyn MediaStorage,
+	pub(super) mutex: &'a MutexMap<[u8; 32], ()>,
+}
+
+/// Stores the content of the media under the metadata key by the hash of the
+/// content; identical content is stored once and counted for each key. The
+/// content previously stored under the key is released.
+#[implement(Service)]
+pub(super) async fn store_content(&self, key: &[u8], hash: &[u8; 32], file: &[u8]) -> Result {
+	self.blobs().store(key, hash, file).await
+}
+
+/// Releases the content of the media under the metadata key; the content is
+/// deleted from the storage backend with its last reference.
+#[implement(Service)]
+pub(super) async fn release_content(&self, key: &[u8]) -> Result {
+	self.blobs().release(key).await
+}
+
+/// Folds the media stored before deduplication, by metadata key, into blobs
+/// by the hash of their content, then recounts the references of all blobs
+/// and removes those no media refers to.
+#[implement(Service)]
+pub(super) async fn dedup_media(&self) -> Result<DedupReport> { self.blobs().dedup().await }
+
+#[implement(Service)]
+fn blobs(&self) -> Blobs<'_, Self> {
+	Blobs {
+		records: self,
+		storage: self.get_storage().as_ref(),
+		mutex: &self.content_mutex,
+	}
+}
+
+impl<R: Records> Blobs<'_, R> {
+	pub(super) async fn store(&self, key: &[u8], hash: &[u8; 32], file: &[u8]) -> Result {
+		let current = self.records.media_hash(key);
+		let replaced = current.filter(|current| current != hash);
+
+		let lock = self.mutex.lock(hash).await;
+		if current.as_ref() != Some(hash) {
+			self.records.set_media_hash(key, hash);
+			self.records.increment_ref(hash)?;
+		}
+
+		if self.storage.exists(hash).await.unwrap_or(false) {
+			debug!(hash = %sha256_hex(hash), "Content already stored");
+		} else {
+			debug!(hash = %sha256_hex(hash), "Storing new content");
+			self.storage.create(hash, file).await?;
+		}
+
+		drop(lock);
+		if let Some(replaced) = replaced {
+			self.release_hash(&replaced).await?;
+		}
+
+		Ok(())
+	}
+
+	pub(super) async fn release(&self, key: &[u8]) -> Result {
+		let Some(hash) = self.records.media_hash(key) else {
+			// Stored before deduplication, by the metadata key.
+			return self.storage.delete(key).await;
+		};
+
+		self.records.remove_media_hash(key);
+		self.release_hash(&hash).await
+	}
+
+	async fn release_hash(&self, hash: &[u8; 32]) -> Result {
+		let _lock = self.mutex.lock(hash).await;
+		let remaining = self.records.decrement_ref(hash)?;
+		if remaining > 0 {
+			debug!(hash = %sha256_hex(hash), ?remaining, "Content still referenced");
+			return Ok(());
+		}
+
+		debug!(hash = %sha256_hex(hash), "Deleting unreferenced content");
+		self.storage.delete(hash).await
+	}
+
+	pub(super) async fn dedup(&self) -> Result<DedupReport> {
+		let mut report = DedupReport::default();
+		let keys = self.records.media_keys().await;
+
+		for key in &keys {
+			if self.records.media_hash(key).is_some() {
+				continue;
+			}
+
+			let Some((file, legacy)) = self.read_legacy_content(key).await? else {
+				debug_warn!(key = %encode_key(key), "Media file not found in any storage");
+				report.missing = report.missing.saturating_add(1);
+				continue;
+			};
+
+			let hash: [u8; 32] = sha2::Sha256::digest(&file).into();
+			if self.storage.exists(&hash).await? {
+				report.duplicates = report.duplicates.saturating_add(1);
+				report.reclaimed = report
+					.reclaimed
+					.saturating_add(u64::try_from(file.len())?);
+			}
+
+			self.store(key, &hash, &file).await?;
+			match legacy {
+				| Legacy::Storage => self.storage.delete(key).await?,
+				| Legacy::File => self.records.remove_legacy_file(key).await?,
+			}
+
+			report.files = report.files.saturating_add(1);
+		}
+
+		let keys: HashSet<&[u8]> = keys.iter().map(Vec::as_slice).collect();
+		let mut refs: HashMap<[u8; 32], u32> = HashMap::new();
+		for (key, hash) in self.records.media_hashes().await {
+			if keys.contains(key.as_slice()) {
+				let count = refs.entry(hash).or_default();
+				*count = count.saturating_add(1);
+			} else {
+				self.records.remove_media_hash(&key);
+			}
+		}
+
+		for hash in self.records.referenced_hashes().await {
+			if refs.contains_key(&hash) {
+				continue;
+			}
+
+			if let Ok(Some(stored)) = self.storage.metadata(&hash).await {
+				report.reclaimed = report.reclaimed.saturating_add(stored.size);
+			}
+
+			self.storage.delete(&hash).await?;
+			report.orphans = report.orphans.saturating_add(1);
+		}
+
+		self.records.set_refs(&refs).await;
+
+		Ok(report)
+	}
+
+	async fn read_legacy_content(&self, key: &[u8]) -> Result<Option<(Vec<u8>, Legacy)>> {
+		// Keys too long for a file name fail to read from the filesystem backend.
+		if let Ok(Some(file)) = self.storage.read(key).await {
+			return Ok(Some((file.to_vec(), Legacy::Storage)));
+		}
+
+		Ok(self
+			.records
+			.read_legacy_file(key)
+			.await?
+			.map(|file| (file, Legacy::File)))
+	}
+}
+
+impl Records for Service {
+	fn media_hash(&self, key: &[u8]) -> Option<[u8; 32]> { self.db.get_media_hash(key) }
+
+	fn set_media_hash(&self, key: &[u8], hash: &[u8; 32]) { self.db.set_media_hash(key, hash); }
+
+	fn remove_media_hash(&self, key: &[u8]) { self.db.remove_media_hash(key); }
+
+	fn increment_ref(&self, hash: &[u8; 32]) -> Result<u32> { self.db.increment_sha256_ref(hash) }
+
+	fn decrement_ref(&self, hash: &[u8; 32]) -> Result<u32> { self.db.decrement_sha256_ref(hash) }
+
+	async fn media_keys(&self) -> Vec<Vec<u8>> { self.db.get_all_media_keys().await }
+
+	async fn media_hashes(&self) -> Vec<(Vec<u8>, [u8; 32])> {
+		self.db.get_all_media_hashes().await
+	}
+
+	async fn referenced_hashes(&self) -> Vec<[u8; 32]> { self.db.get_all_sha256_refs().await }
+
+	async fn set_refs(&self, refs: &HashMap<[u8; 32], u32>) {
+		self.db.set_sha256_refs(refs).await;
+	}
+
+	async fn read_legacy_file(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+		match fs::read(self.get_media_file(key)).await {
+			| Ok(file) => Ok(Some(file)),
+			| Err(e) if e.kind() == NotFound => Ok(None),
+			| Err(e) => Err(e.into()),
+		}
+	}
+
+	async fn remove_legacy_file(&self, key: &[u8]) -> Result { self.remove_media_file(key).await }
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		collections::{BTreeMap, HashMap},
+		io::ErrorKind::NotFound,
+		path::PathBuf,
+		sync::Mutex,
+	};
+
+	use sha2::Digest;
+	use tempfile::TempDir;
+	use tokio::fs;
+	use tuwunel_core::{Result, utils::MutexMap};
+
+	use super::{Blobs, Records};
+	use crate::media::{
+		encode_key,
+		storage::{MediaStorage, filesystem::FilesystemStorage},
+	};
+
+	/// Records kept in memory, with the legacy files in a directory.
+	struct Memory {
+		keys: Vec<Vec<u8>>,
+		hashes: Mutex<BTreeMap<Vec<u8>, [u8; 32]>>,
+		refs: Mutex<BTreeMap<[u8; 32], u32>>,
+		media_dir: PathBuf,
+	}
+
+	impl Memory {
+		fn refs(&self, hash: &[u8; 32]) -> u32 {
+			self.refs
+				.lock()
+				.unwrap()
+				.get(hash)
+				.copied()
+				.unwrap_or(0)
+		}
+
+		fn legacy_file(&self, key: &[u8]) -> PathBuf { self.media_dir.join(encode_key(key)) }
+	}
+
+	impl Records for Memory {
+		fn media_hash(&self, key: &[u8]) -> Option<[u8; 32]> {
+			self.hashes.lock().unwrap().get(key).copied()
+		}
+
+		fn set_media_hash(&self, key: &[u8], hash: &[u8; 32]) {
+			self.hashes
+				.lock()
+				.unwrap()
+				.insert(key.to_vec(), *hash);
+		}
+
+		fn remove_media_hash(&self, key: &[u8]) { self.hashes.lock().unwrap().remove(key); }
+
+		fn increment_ref(&self, hash: &[u8; 32]) -> Result<u32> {
+			let mut refs = self.refs.lock().unwrap();
+			let count = refs.entry(*hash).or_default();
+			*count = count.saturating_add(1);
+			Ok(*count)
+		}
+
+		fn decrement_ref(&self, hash: &[u8; 32]) -> Result<u32> {
+			let mut refs = self.refs.lock().unwrap();
+			let count = refs
+				.get(hash)
+				.copied()
+				.unwrap_or(0)
+				.saturating_sub(1);
+			if count == 0 {
+				refs.remove(hash);
+			} else {
+				refs.insert(*hash, count);
+			}
+
+			Ok(count)
+		}
+
+		async fn media_keys(&self) -> Vec<Vec<u8>> { self.keys.clone() }
+
+		async fn media_hashes(&self) -> Vec<(Vec<u8>, [u8; 32])> {
+			self.hashes
+				.lock()
+				.unwrap()
+				.iter()
+				.map(|(key, hash)| (key.clone(), *hash))
+				.collect()
+		}
+
+		async fn referenced_hashes(&self) -> Vec<[u8; 32]> {
+			self.refs
+				.lock()
+				.unwrap()
+				.keys()
+				.copied()
+				.collect()
+		}
+
+		async fn set_refs(&self, refs: &HashMap<[u8; 32], u32>) {
+			*self.refs.lock().unwrap() = refs
+				.iter()
+				.map(|(hash, count)| (*hash, *count))
+				.collect();
+		}
+
+		async fn read_legacy_file(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+			match fs::read(self.legacy_file(key)).await {
+				| Ok(file) => Ok(Some(file)),
+				| Err(e) if e.kind() == NotFound => Ok(None),
+				| Err(e) => Err(e.into()),
+			}
+		}
+
+		async fn remove_legacy_file(&self, key: &[u8]) -> Result {
+			Ok(fs::remove_file(self.legacy_file(key)).await?)
+		}
+	}
+
+	fn fixture(keys: &[&[u8]]) -> (TempDir, FilesystemStorage, Memory) {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let storage = FilesystemStorage::new(temp_dir.path().join("blobs")).unwrap();
+		let media_dir = temp_dir.path().join("media");
+		std::fs::create_dir(&media_dir).unwrap();
+
+		let records = Memory {
+			keys: keys.iter().map(|key| key.to_vec()).collect(),
+			hashes: Mutex::default(),
+			refs: Mutex::default(),
+			media_dir,
+		};
+
+		(temp_dir, storage, records)
+	}
+
+	fn hash(file: &[u8]) -> [u8; 32] { sha2::Sha256::digest(file).into() }
+
+	#[tokio::test]
+	async fn shared_content_outlives_delete() {
+		let (_temp_dir, storage, records) = fixture(&[]);
+		let mutex = MutexMap::new();
+		let blobs = Blobs {
+			records: &records,
+			storage: &storage,
+			mutex: &mutex,
+		};
+
+		let shared = hash(b"shared");
+		blobs
+			.store(b"a", &shared, b"shared")
+			.await
+			.unwrap();
+		blobs
+			.store(b"b", &shared, b"shared")
+			.await
+			.unwrap();
+		assert_eq!(records.refs(&shared), 2);
+
+		blobs.release(b"a").await.unwrap();
+		assert_eq!(records.refs(&shared), 1);
+		assert_eq!(
+			storage
+				.read(&shared)
+				.await
+				.unwrap()
+				.unwrap()
+				.as_ref(),
+			b"shared"
+		);
+
+		blobs.release(b"b").await.unwrap();
+		assert_eq!(records.refs(&shared), 0);
+		assert!(!storage.exists(&shared).await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn replace_in_place_releases_previous() {
+		let (_temp_dir, storage, records) = fixture(&[]);
+		let mutex = MutexMap::new();
+		let blobs = Blobs {
+			records: &records,
+			storage: &storage,
+			mutex: &mutex,
+		};
+
+		let (old, new) = (hash(b"old"), hash(b"new"));
+		blobs.store(b"a", &old, b"old").await.unwrap();
+		blobs.store(b"b", &old, b"old").await.unwrap();
+
+		// Storing the same content again counts it once.
+		blobs.store(b"a", &old, b"old").await.unwrap();
+		assert_eq!(records.refs(&old), 2);
+
+		blobs.store(b"a", &new, b"new").await.unwrap();
+		assert_eq!(records.media_hash(b"a"), Some(new));
+		assert_eq!((records.refs(&old), records.refs(&new)), (1, 1));
+		assert!(storage.exists(&old).await.unwrap());
+
+		blobs.store(b"b", &new, b"new").await.unwrap();
+		assert_eq!((records.refs(&old), records.refs(&new)), (0, 2));
+		assert!(!storage.exists(&old).await.unwrap());
+		assert_eq!(
+			storage
+				.read(&new)
+				.await
+				.unwrap()
+				.unwrap()
+				.as_ref(),
+			b"new"
+		);
+	}
+
+	#[tokio::test]
+	async fn dedup_reclaims_duplicates_and_orphans() {
+		let keys: [&[u8]; 4] = [b"in-storage", b"in-media-dir", b"missing", b"current"];
+		let (_temp_dir, storage, records) = fixture(&keys);
+		let mutex = MutexMap::new();
+		let blobs = Blobs {
+			records: &records,
+			storage: &storage,
+			mutex: &mutex,
+		};
+
+		// Stored before deduplication, by metadata key in either place
+		storage
+			.create(b"in-storage", b"dup")
+			.await
+			.unwrap();
+		fs::write(records.legacy_file(b"in-media-dir"), b"dup")
+			.await
+			.unwrap();
+
+		// Already deduplicated, with a miscounted reference and one of a
+		// media deleted since
+		let current = hash(b"current");
+		storage
+			.create(&current, b"current")
+			.await
+			.unwrap();
+		records.set_media_hash(b"current", &current);
+		records.set_media_hash(b"deleted", &current);
+		records.refs.lock().unwrap().insert(current, 5);
+
+		// Referenced by no media
+		let orphan = hash(b"orphan!");
+		storage.create(&orphan, b"orphan!").await.unwrap();
+		records.refs.lock().unwrap().insert(orphan, 1);
+
+		let report = blobs.dedup().await.unwrap();
+		assert_eq!(report.files, 2);
+		assert_eq!(report.duplicates, 1);
+		assert_eq!(report.missing, 1);
+		assert_eq!(report.orphans, 1);
+		assert_eq!(report.reclaimed, 10);
+
+		let dup = hash(b"dup");
+		assert_eq!(records.refs(&dup), 2);
+		assert_eq!(records.refs(&current), 1);
+		assert_eq!(records.refs(&orphan), 0);
+		assert_eq!(records.media_hash(b"deleted"), None);
+
+		assert!(storage.exists(&dup).await.unwrap());
+		assert!(!storage.exists(&orphan).await.unwrap());
+		assert!(!storage.exists(b"in-storage").await.unwrap());
+		assert!(
+			records
+				.read_legacy_file(b"in-media-dir")
+				.await
+				.unwrap()
+				.is_none()
+		);
+	}
+}
@@ -55,6 +55,25 @@ pub(crate) async fn migrate_sha256_media(services: &Services) -> Result {
 	Ok(())
 }
 
+/// Folds media stored by metadata key, from before content-addressed
+/// deduplication, into blobs shared by all media of identical content, and
+/// reports the space reclaimed. Upon success the database is keyed to not
+/// perform this again.
+pub(crate) async fn dedup_media(services: &Services) -> Result {
+	let db = &services.db;
+	let media = &services.media;
+
+	warn!("Deduplicating media files by their content");
+	media.init_storage().await?;
+
+	let timer = Instant::now();
+	let report = media.dedup_media().await?;
+
+	db["global"].insert(b"feat_media_dedup", []);
+	info!(elapsed = ?timer.elapsed(), "Finished deduplicating media: {report}");
+	Ok(())
+}
+
 /// Check is run on startup for prior-migrated media directories. This handles:
 /// - Going back and forth to non-sha256 legacy binaries (e.g. upstream).
 /// - Deletion of artifacts in the media directory which will then fall out of
@@ -1,14 +1,22 @@
 pub mod blurhash;
 mod data;
+mod dedup;
 pub(super) mod migrations;
 mod preview;
+mod quarantine;
 mod remote;
+mod retention;
+mod rewrap;
+mod sanitize;
+pub mod scan;
+mod stats;
 pub mod storage;
 mod tests;
 mod thumbnail;
 use std::{path::PathBuf, sync::Arc, time::SystemTime};
 
 use async_trait::async_trait;
+use futures::future::join4;
 use base64::{Engine as _, engine::general_purpose};
 use ruma::{Mxc, OwnedMxcUri, UserId, http_headers::ContentDisposition};
 use sha2::Digest;
@@ -21,7 +29,13 @@
 };
 
 use self::data::{Data, Metadata};
-pub use self::thumbnail::Dim;
+pub use self::{
+	dedup::DedupReport,
+	quarantine::{BlockedHash, Quarantine, parse_sha256, sha256_hex},
+	retention::{Expired, Expiry},
+	stats::{MediaInfo, StatsGroup, Totals},
+	thumbnail::Dim,
+};
 
 #[derive(Debug)]
 pub struct FileMeta {
@@ -32,8 +46,12 @@ pub struct FileMeta {
 
 pub struct Service {
 	url_preview_mutex: MutexMap<String, ()>,
+	url_preview_rates: preview::DomainRates,
+	content_mutex: MutexMap<[u8; 32], ()>,
+	stats_mutex: MutexMap<(StatsGroup, String), ()>,
 	pub(super) db: Data,
 	storage: Arc<OnceCell<Arc<dyn storage::MediaStorage>>>,
+	scanner: Option<Arc<dyn scan::Scanner>>,
 	services: Arc<crate::services::OnceServices>,
 }
 
@@ -51,22 +69,30 @@ impl crate::Service for Service {
 	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
 		Ok(Arc::new(Self {
 			url_preview_mutex: MutexMap::new(),
+			url_preview_rates: preview::DomainRates::default(),
+			content_mutex: MutexMap::new(),
+			stats_mutex: MutexMap::new(),
 			db: Data::new(args.db),
 			storage: Arc::new(OnceCell::new()),
+			scanner: scan::build(&args.server.config.media_scanning)?,
 			services: args.services.clone(),
 		}))
 	}
 
 	async fn worker(self: Arc<Self>) -> Result {
-		// Initialize storage (lazy init)
-		let storage = Self::build_storage(&self.services.server.config).await?;
-		self.storage
-			.set(storage)
-			.map_err(|_| err!(Config("media_storage", "Storage already initialized")))?;
-
+		self.init_storage().await?;
 		self.create_media_dir().await?;
+		self.init_encrypted_sent().await;
 
-		Ok(())
+		let (retention, stats, previews, rewrap) = join4(
+			self.retention_worker(),
+			self.stats_worker(),
+			self.url_preview_worker(),
+			self.rewrap_worker(),
+		)
+		.await;
+
+		retention.and(stats).and(previews).and(rewrap)
 	}
 
 	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
@@ -75,6 +101,19 @@ fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
 impl Service {
 	/// Build storage backend based on configuration
 	async fn build_storage(config: &tuwunel_core::config::Config) -> Result<Arc<dyn storage::MediaStorage>> {
+		let storage = Self::build_backend(config).await?;
+		let Some(encryption) = config.media_storage.encryption.as_ref() else {
+			return Ok(storage);
+		};
+
+		debug!("Initializing encryption of media at rest");
+		Ok(Arc::new(storage::encrypted::EncryptedStorage::new(storage, encryption)?))
+	}
+
+	/// Build the storage backend of the configured strategy
+	async fn build_backend(
+		config: &tuwunel_core::config::Config,
+	) -> Result<Arc<dyn storage::MediaStorage>> {
 		use tuwunel_core::config::StorageStrategy;
 
 		let media_path = config.database_path.join("media");
@@ -125,6 +164,13 @@ async fn build_storage(config: &tuwunel_core::config::Config) -> Result<Arc<dyn
 		}
 	}
 
+	/// Initializes the storage backend, unless the migrations already did.
+	pub(super) async fn init_storage(&self) -> Result<&Arc<dyn storage::MediaStorage>> {
+		self.storage
+			.get_or_try_init(|| Self::build_storage(&self.services.server.config))
+			.await
+	}
+
 	/// Get storage backend (panics if not initialized)
 	#[inline]
 	fn get_storage(&self) -> &Arc<dyn storage::MediaStorage> {
@@ -135,12 +181,8 @@ fn get_storage(&self) -> &Arc<dyn storage::MediaStorage> {
 
 	/// Uploads a file, with content-based deduplication.
 	///
-	/// Files with identical content are stored only once in the storage backend.
-	/// Deduplication is based on SHA-256 content hash. The storage backend is
-	/// checked with `exists()` before uploading — this avoids a race condition
-	/// where concurrent uploads of the same file would all see refcount=0.
-	/// If two concurrent uploads both pass the exists() check, the S3 PUT is
-	/// idempotent (same key, same bytes → same object), so no corruption occurs.
+	/// Files with identical content are stored only once in the storage backend,
+	/// by their SHA-256 content hash; each MXC counts as a reference to it.
 	pub async fn create(
 		&self,
 		mxc: &Mxc<'_>,
@@ -149,13 +191,11 @@ pub async fn create(
 		content_type: Option<&str>,
 		file: &[u8],
 	) -> Result {
-		// 1. Compute SHA-256 hash of the file content (= storage key)
 		let content_hash: [u8; 32] = sha2::Sha256::digest(file).into();
-		let hash_hex: String = content_hash.iter().map(|b| format!("{b:02x}")).collect();
+		trace!(?mxc, "Dedup: file size={} hash={}", file.len(), sha256_hex(&content_hash));
 
-		trace!(?mxc, "Dedup: file size={} hash={}", file.len(), &hash_hex[..16]);
+		self.check_blocked(&content_hash).await?;
 
-		// 2. Register MXC metadata in the database (unchanged behaviour)
 		let key = self.db.create_file_metadata(
 			mxc,
 			user,
@@ -164,37 +204,27 @@ pub async fn create(
 			content_type,
 		)?;
 
-		// 3. Store the hash → db-key mapping so delete() can find the hash
-		self.db.set_media_hash(&key, &content_hash);
-
-		// 4. Increment the reference count for this content hash
-		self.db.increment_sha256_ref(&content_hash)?;
-
-		// 5. Check storage backend to decide whether to upload.
-		//    Using exists() avoids a race condition: refcount is not atomic
-		//    across concurrent async tasks. If the file already exists in
-		//    storage, skip the upload (dedup hit). If two concurrent uploads
-		//    both see exists()==false, the PUT is idempotent (same key, same
-		//    bytes), so at worst we upload twice but end up with one object.
-		let already_stored = self
-			.get_storage()
-			.exists(&content_hash)
-			.await
-			.unwrap_or(false);
+		self.set_created(mxc);
+		self.store_content(&key, &content_hash, file).await?;
 
-		trace!(?mxc, "Dedup: exists={} hash={}", already_stored, &hash_hex[..16]);
+		let size = u64::try_from(file.len())?;
+		self.add_stats(mxc, user, content_type, size)
+			.await;
 
-		if already_stored {
-			debug!(?mxc, "Dedup: SKIP upload — file already in storage (hash={})", &hash_hex[..16]);
-		} else {
-			debug!(?mxc, "Dedup: UPLOAD — new unique file (hash={})", &hash_hex[..16]);
-			self.get_storage().create(&content_hash, file).await?;
+		// Scan the stored content. Infected media is kept in quarantine for review,
+		// while an upload which could not be scanned is removed again, so that a
+		// refused upload never leaves an unscanned MXC behind.
+		if let Err(e) = self.check_scan(mxc, &content_hash, file).await {
+			if !self.is_quarantined(mxc).await {
+				self.delete(mxc).await?;
+			}
+
+			return Err(e);
 		}
 
 		Ok(())
 	}
 
-
 	/// Deletes a file in the database and from the storage backend via an MXC.
 	///
 	/// Due to content-based deduplication, the physical file is only removed
@@ -205,31 +235,13 @@ pub async fn delete(&self, mxc: &Mxc<'_>) -> Result {
 			| Ok(keys) => {
 				for key in &keys {
 					trace!(?mxc, "MXC Key: {key:?}");
-
-					// Look up the content hash stored at upload time
-					if let Some(hash) = self.db.get_media_hash(key) {
-						let remaining = self.db.decrement_sha256_ref(&hash)?;
-						if remaining == 0 {
-							// Last reference gone — physically delete from storage
-							debug_info!(?mxc, "Last reference removed — deleting from storage");
-							if let Err(e) = self.get_storage().delete(&hash).await {
-								debug_error!(?mxc, "Failed to delete from storage: {e}");
-							}
-						} else {
-							debug_info!(?mxc, "Still {remaining} reference(s) remaining — keeping file in storage");
-						}
-						self.db.remove_media_hash(key);
-					} else {
-						// No hash found — legacy file (uploaded before dedup was added).
-						// Fall back to deleting by the DB key directly.
-						debug_info!(?mxc, "No content hash found for key — using legacy delete path");
-						if let Err(e) = self.get_storage().delete(key).await {
-							debug_error!(?mxc, "Failed to delete from storage (legacy): {e}");
-						}
+					if let Err(e) = self.release_content(key).await {
+						debug_error!(?mxc, "Failed to delete from storage: {e}");
 					}
 				}
 
 				debug_info!(?mxc, "Deleting from database");
+				self.remove_stats(mxc).await;
 				self.db.delete_file_mxc(mxc).await;
 
 				Ok(())
@@ -272,6 +284,10 @@ pub async fn delete_from_user(&self, user: &UserId) -> Result<usize> {
 
 	/// Downloads a file.
 	pub async fn get(&self, mxc: &Mxc<'_>) -> Result<Option<FileMeta>> {
+		if self.is_quarantined(mxc).await {
+			return Ok(None);
+		}
+
 		match self
 			.db
 			.search_file_metadata(mxc, &Dim::default())
@@ -279,19 +295,28 @@ pub async fn get(&self, mxc: &Mxc<'_>) -> Result<Option<FileMeta>> {
 		{
 			| Ok(Metadata { content_disposition, content_type, key }) => {
 				// Get the content hash for this file, or fallback to the legacy db key
-				let storage_key = self
-					.db
-					.get_media_hash(&key)
+				let hash = self.db.get_media_hash(&key);
+				let storage_key = hash
 					.map(|hash| hash.to_vec())
 					.unwrap_or(key);
 
 				// Use storage trait to read file
 				match self.get_storage().read(&storage_key).await? {
-					Some(bytes) => Ok(Some(FileMeta {
-						content: Some(bytes.to_vec()),
-						content_type,
-						content_disposition,
-					})),
+					Some(bytes) => {
+						if self.is_scanning() {
+							let hash =
+								hash.unwrap_or_else(|| sha2::Sha256::digest(&bytes).into());
+							self.check_scan(mxc, &hash, &bytes).await?;
+						}
+
+						self.set_accessed(mxc).await;
+
+						Ok(Some(FileMeta {
+							content: Some(bytes.to_vec()),
+							content_type,
+							content_disposition,
+						}))
+					},
 					None => Ok(None),
 				}
 			},
@@ -497,6 +522,10 @@ async fn create_media_file(&self, key: &[u8]) -> Result<fs::File> {
 
 	#[inline]
 	pub async fn get_metadata(&self, mxc: &Mxc<'_>) -> Option<FileMeta> {
+		if self.is_quarantined(mxc).await {
+			return None;
+		}
+
 		self.db
 			.search_file_metadata(mxc, &Dim::default())
 			.await
@@ -0,0 +1,659 @@
+//! URL Previews
+//!
+//! This functionality is gated by 'url_preview', but not at the unit level for
+//! historical and simplicity reasons. Instead the feature gates the inclusion
+//! of dependencies and nulls out results through the existing interface when
+//! not featured.
+
+mod oembed;
+#[cfg(test)]
+mod tests;
+
+use std::{
+	collections::HashMap,
+	sync::{Mutex, PoisonError},
+	time::{Duration, Instant},
+};
+
+use reqwest::{
+	Response,
+	header::{CACHE_CONTROL, CONTENT_TYPE, HeaderMap},
+};
+use ruma::Mxc;
+use serde::{Deserialize, Serialize};
+use tuwunel_core::{Err, Result, debug, debug_warn, err, implement, utils::time};
+use url::{Host, Url};
+
+use self::oembed::OEmbed;
+use super::Service;
+use crate::client::allowed_ip;
+
+/// Expired previews are removed along with their images after this long, as
+/// are the images of replaced previews, so clients still get to load the
+/// images of previews served before expiry.
+const PRUNE_AFTER: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// Previews are cached for at least this long, even of pages which may not be
+/// cached, so every request does not fetch the page and its image again.
+const MIN_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Window of `url_preview_domain_rate_limit`.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct UrlPreviewData {
+	#[serde(skip_serializing_if = "Option::is_none", rename = "og:title")]
+	pub title: Option<String>,
+	#[serde(
+		skip_serializing_if = "Option::is_none",
+		rename = "og:description"
+	)]
+	pub description: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none", rename = "og:image")]
+	pub image: Option<String>,
+	#[serde(
+		skip_serializing_if = "Option::is_none",
+		rename = "matrix:image:size"
+	)]
+	pub image_size: Option<usize>,
+	#[serde(
+		skip_serializing_if = "Option::is_none",
+		rename = "og:image:width"
+	)]
+	pub image_width: Option<u32>,
+	#[serde(
+		skip_serializing_if = "Option::is_none",
+		rename = "og:image:height"
+	)]
+	pub image_height: Option<u32>,
+}
+
+/// Requests to each domain in the current window of the rate limit.
+#[derive(Default)]
+pub(super) struct DomainRates(Mutex<HashMap<String, (Instant, u32)>>);
+
+impl DomainRates {
+	/// Counts a request to the domain; false when the domain is over the limit.
+	fn check(&self, domain: &str, limit: u32) -> bool {
+		if limit == 0 {
+			return true;
+		}
+
+		let now = Instant::now();
+		let mut domains = self
+			.0
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+
+		domains.retain(|_, (start, _)| now.duration_since(*start) < RATE_WINDOW);
+
+		let (_, count) = domains
+			.entry(domain.to_owned())
+			.or_insert((now, 0));
+
+		if *count >= limit {
+			return false;
+		}
+
+		*count = count.saturating_add(1);
+		true
+	}
+}
+
+#[implement(Service)]
+pub async fn remove_url_preview(&self, url: &str) -> Result {
+	if let Ok((_, preview)) = self.db.get_url_preview(url).await {
+		self.remove_url_preview_image(preview.image.as_deref())
+			.await;
+	}
+
+	self.db.remove_url_preview(url)
+}
+
+/// Stores the preview until it expires after `ttl`. The image of the preview
+/// it replaces is kept for `url_preview_worker` to delete after
+/// `PRUNE_AFTER`.
+#[implement(Service)]
+pub async fn set_url_preview(&self, url: &str, data: &UrlPreviewData, ttl: Duration) -> Result {
+	let replaced = self.db.get_url_preview(url).await;
+	self.db
+		.set_url_preview(url, data, time::now().saturating_add(ttl))?;
+
+	if let Ok((_, replaced)) = replaced
+		&& replaced.image != data.image
+		&& let Some(image) = replaced.image.as_deref()
+	{
+		self.db
+			.set_url_preview_replaced(image, time::now());
+	}
+
+	Ok(())
+}
+
+#[implement(Service)]
+pub async fn get_url_preview(&self, url: &Url) -> Result<UrlPreviewData> {
+	if let Some(preview) = self.cached_url_preview(url).await {
+		return Ok(preview);
+	}
+
+	// ensure that only one request is made per URL
+	let _request_lock = self.url_preview_mutex.lock(url.as_str()).await;
+
+	if let Some(preview) = self.cached_url_preview(url).await {
+		return Ok(preview);
+	}
+
+	match self.request_url_preview(url).await {
+		| Ok(preview) => Ok(preview),
+		| Err(e) => match self.db.get_url_preview(url.as_str()).await {
+			| Ok((_, expired)) => {
+				debug_warn!(?url, "Serving expired URL preview which failed to refresh: {e}");
+				Ok(expired)
+			},
+			| Err(_) => Err(e),
+		},
+	}
+}
+
+/// Periodically removes the expired URL previews along with their images.
+#[implement(Service)]
+pub(super) async fn url_preview_worker(&self) -> Result {
+	loop {
+		tokio::select! {
+			() = tokio::time::sleep(PRUNE_AFTER) => {},
+			() = self.services.server.until_shutdown() => return Ok(())
+		};
+
+		match self.prune_url_previews().await {
+			| Ok(count) => debug!(?count, "Removed expired URL previews"),
+			| Err(e) => debug_warn!("Failed to remove expired URL previews: {e}"),
+		}
+	}
+}
+
+/// Removes the previews which expired a while ago along with their images,
+/// and the images of the previews replaced a while ago. Returns the number of
+/// removed previews.
+#[implement(Service)]
+pub async fn prune_url_previews(&self) -> Result<usize> {
+	let before = time::now().saturating_sub(PRUNE_AFTER);
+
+	let mut count: usize = 0;
+	for url in self.db.get_url_preview_urls().await {
+		let _request_lock = self.url_preview_mutex.lock(url.as_str()).await;
+		if let Ok((expires, _)) = self.db.get_url_preview(&url).await
+			&& expires < before
+		{
+			self.remove_url_preview(&url).await?;
+			count = count.saturating_add(1);
+		}
+	}
+
+	for (image, replaced) in self.db.get_url_preview_replaced().await {
+		if replaced < before {
+			self.remove_url_preview_image(Some(&image)).await;
+			self.db.remove_url_preview_replaced(&image);
+		}
+	}
+
+	Ok(count)
+}
+
+#[implement(Service)]
+async fn cached_url_preview(&self, url: &Url) -> Option<UrlPreviewData> {
+	self.db
+		.get_url_preview(url.as_str())
+		.await
+		.ok()
+		.filter(|(expires, _)| *expires > time::now())
+		.map(|(_, preview)| preview)
+}
+
+#[implement(Service)]
+async fn remove_url_preview_image(&self, image: Option<&str>) {
+	let Some(Ok(mxc)) = image.map(Mxc::try_from) else {
+		return;
+	};
+
+	if let Err(e) = self.delete(&mxc).await {
+		debug_warn!(%mxc, "Failed to delete URL preview image: {e}");
+	}
+}
+
+#[implement(Service)]
+async fn request_url_preview(&self, url: &Url) -> Result<UrlPreviewData> {
+	let providers = &self.services.config.url_preview_oembed_providers;
+	let (data, ttl) = match oembed::provider(providers, url) {
+		| Some(endpoint) => self.request_oembed_preview(endpoint, url).await?,
+		| None => self.request_page_preview(url).await?,
+	};
+
+	self.set_url_preview(url.as_str(), &data, ttl)
+		.await?;
+
+	Ok(data)
+}
+
+#[implement(Service)]
+async fn request_page_preview(&self, url: &Url) -> Result<(UrlPreviewData, Duration)> {
+	let response = self.fetch_url_preview(url).await?;
+	let ttl = self.url_preview_ttl(response.headers());
+
+	let Some(content_type) = response.headers().get(CONTENT_TYPE) else {
+		return Err!(Request(Unknown("Unknown or invalid Content-Type header")));
+	};
+
+	let content_type = content_type
+		.to_str()
+		.map_err(|e| err!(Request(Unknown("Unknown or invalid Content-Type header: {e}"))))?
+		.to_owned();
+
+	let data = match content_type.as_str() {
+		| html if html.starts_with("text/html") => self.preview_html(url, response).await?,
+		| img if img.starts_with("image/") => self.preview_image(response).await?,
+		| _ => return Err!(Request(Unknown("Unsupported Content-Type"))),
+	};
+
+	Ok((data, ttl))
+}
+
+/// Previews the URL with the oEmbed data of a configured provider.
+#[implement(Service)]
+async fn request_oembed_preview(
+	&self,
+	endpoint: &Url,
+	url: &Url,
+) -> Result<(UrlPreviewData, Duration)> {
+	let (oembed, ttl) = self
+		.request_oembed(&oembed::request_url(endpoint, url)?)
+		.await?;
+
+	let mut data = match oembed.image() {
+		| Some(image) => self.download_image(image).await?,
+		| None => UrlPreviewData::default(),
+	};
+
+	data.title = oembed.title;
+	data.description = oembed.author_name;
+
+	Ok((data, ttl))
+}
+
+#[implement(Service)]
+async fn request_oembed(&self, url: &Url) -> Result<(OEmbed, Duration)> {
+	let response = self.fetch_url_preview(url).await?;
+	let ttl = self.url_preview_ttl(response.headers());
+
+	let limit = self.services.config.url_preview_max_spider_size;
+	let (body, truncated) = read_limited(response, limit).await?;
+	if truncated {
+		return Err!(Request(TooLarge("oEmbed data exceeds url_preview_max_spider_size")));
+	}
+
+	let oembed = oembed::parse(&body)?;
+	let max = Duration::from_secs(self.services.config.url_preview_max_cache_ttl);
+	let ttl = oembed
+		.cache_age()
+		.map_or(ttl, |cache_age| cache_age.min(max));
+
+	Ok((oembed, ttl))
+}
+
+/// Requests the URL for a preview, within the rate limit of its domain. The
+/// client checks the addresses of every hop against `ip_range_denylist`.
+#[implement(Service)]
+async fn fetch_url_preview(&self, url: &Url) -> Result<Response> {
+	let Some(host) = url.host() else {
+		return Err!(Request(InvalidParam("URL to preview has no host")));
+	};
+
+	let denied = match host {
+		| Host::Ipv4(ip) => !allowed_ip(&self.services.client.cidr_range_denylist, ip.into()),
+		| Host::Ipv6(ip) => !allowed_ip(&self.services.client.cidr_range_denylist, ip.into()),
+		| Host::Domain(_) => false,
+	};
+
+	if denied {
+		return Err!(Request(Forbidden("Requesting from this address is forbidden")));
+	}
+
+	let limit = self.services.config.url_preview_domain_rate_limit;
+	let domain = host.to_string();
+	if !self.url_preview_rates.check(&domain, limit) {
+		return Err!(Request(Unknown("Too many URL preview requests to {domain}")));
+	}
+
+	let response = self
+		.services
+		.client
+		.url_preview
+		.get(url.as_str())
+		.send()
+		.await?
+		.error_for_status()?;
+
+	debug!(?url, "URL preview response headers: {:?}", response.headers());
+
+	if let Some(remote_addr) = response.remote_addr() {
+		debug!(?url, "URL preview response remote address: {:?}", remote_addr);
+
+		if !allowed_ip(&self.services.client.cidr_range_denylist, remote_addr.ip()) {
+			return Err!(Request(Forbidden("Requesting from this address is forbidden")));
+		}
+	}
+
+	Ok(response)
+}
+
+#[implement(Service)]
+fn url_preview_ttl(&self, headers: &HeaderMap) -> Duration {
+	let config = &self.services.config;
+
+	cache_ttl(
+		headers,
+		Duration::from_secs(config.url_preview_cache_ttl),
+		Duration::from_secs(config.url_preview_max_cache_ttl),
+	)
+}
+
+/// The time to cache a preview for under the `Cache-Control` of the response:
+/// its `s-maxage` or `max-age`, otherwise `default`, up to `max`. Responses
+/// which shared caches may not store, and every other, are cached for at
+/// least `MIN_CACHE_TTL`.
+fn cache_ttl(headers: &HeaderMap, default: Duration, max: Duration) -> Duration {
+	let directives = headers
+		.get_all(CACHE_CONTROL)
+		.iter()
+		.filter_map(|value| value.to_str().ok())
+		.flat_map(|value| value.split(','));
+
+	let (mut max_age, mut shared_max_age) = (None, None);
+	for directive in directives {
+		let (name, value) = directive
+			.split_once('=')
+			.map_or((directive, None), |(name, value)| (name, Some(value)));
+
+		let seconds = value
+			.and_then(|value| value.trim().trim_matches('"').parse().ok())
+			.map(Duration::from_secs);
+
+		match (name.trim().to_ascii_lowercase().as_str(), value) {
+			| ("no-store", _) | ("no-cache" | "private", None) => return MIN_CACHE_TTL,
+			| ("max-age", _) => max_age = seconds,
+			| ("s-maxage", _) => shared_max_age = seconds,
+			| _ => {},
+		}
+	}
+
+	shared_max_age
+		.or(max_age)
+		.unwrap_or(default)
+		.min(max)
+		.max(MIN_CACHE_TTL)
+}
+
+/// Reads the body of the response up to `limit` bytes; true when the body was
+/// longer.
+async fn read_limited(mut response: Response, limit: usize) -> Result<(Vec<u8>, bool)> {
+	let mut bytes: Vec<u8> = Vec::new();
+	while let Some(chunk) = response.chunk().await? {
+		bytes.extend_from_slice(&chunk);
+		if bytes.len() > limit {
+			bytes.truncate(limit);
+			return Ok((bytes, true));
+		}
+	}
+
+	Ok((bytes, false))
+}
+
+#[cfg(feature = "url_preview")]
+#[implement(Service)]
+pub async fn download_image(&self, url: &str) -> Result<UrlPreviewData> {
+	let url = Url::parse(url).map_err(|e| err!(Request(Unknown("Invalid image URL: {e}"))))?;
+	let response = self.fetch_url_preview(&url).await?;
+
+	self.preview_image(response).await
+}
+
+#[cfg(not(feature = "url_preview"))]
+#[implement(Service)]
+#[expect(clippy::unused_async)]
+pub async fn download_image(&self, _url: &str) -> Result<UrlPreviewData> {
+	Err!(FeatureDisabled("url_preview"))
+}
+
+#[cfg(feature = "url_preview")]
+#[implement(Service)]
+async fn preview_image(&self, response: Response) -> Result<UrlPreviewData> {
+	use image::ImageReader;
+	use tuwunel_core::utils::random_string;
+
+	let (image, truncated) =
+		read_limited(response, self.services.config.max_request_size).await?;
+	if truncated {
+		return Err!(Request(TooLarge("URL preview image exceeds max_request_size")));
+	}
+
+	let mxc = Mxc {
+		server_name: self.services.globals.server_name(),
+		media_id: &random_string(super::MXC_LENGTH),
+	};
+
+	self.create(&mxc, None, None, None, &image)
+		.await?;
+
+	let cursor = std::io::Cursor::new(&image);
+	let (width, height) = match ImageReader::new(cursor).with_guessed_format() {
+		| Err(_) => (None, None),
+		| Ok(reader) => match reader.into_dimensions() {
+			| Err(_) => (None, None),
+			| Ok((width, height)) => (Some(width), Some(height)),
+		},
+	};
+
+	Ok(UrlPreviewData {
+		image: Some(mxc.to_string()),
+		image_size: Some(image.len()),
+		image_width: width,
+		image_height: height,
+		..Default::default()
+	})
+}
+
+#[cfg(not(feature = "url_preview"))]
+#[implement(Service)]
+#[expect(clippy::unused_async)]
+async fn preview_image(&self, _response: Response) -> Result<UrlPreviewData> {
+	Err!(FeatureDisabled("url_preview"))
+}
+
+#[cfg(feature = "url_preview")]
+#[implement(Service)]
+async fn preview_html(&self, url: &Url, response: Response) -> Result<UrlPreviewData> {
+	use webpage::HTML;
+
+	let limit = self.services.config.url_preview_max_spider_size;
+	let (bytes, truncated) = read_limited(response, limit).await?;
+	if truncated {
+		debug!(
+			"Response body from URL {} exceeds url_preview_max_spider_size ({}), not processing \
+			 the rest of the response body and assuming our necessary data is in this range.",
+			url, limit
+		);
+	}
+
+	let body = String::from_utf8_lossy(&bytes);
+	let oembed = match oembed::discover(&body, url) {
+		| Some(oembed_url) if self.services.config.url_preview_oembed => self
+			.request_oembed(&oembed_url)
+			.await
+			.inspect_err(|e| debug_warn!(?url, "Failed to request oEmbed data: {e}"))
+			.map(|(oembed, _)| oembed)
+			.unwrap_or_default(),
+		| _ => OEmbed::default(),
+	};
+
+	let Ok(html) = HTML::from_string(body.to_string(), Some(url.to_string())) else {
+		return Err!(Request(Unknown("Failed to parse HTML")));
+	};
+
+	let image = oembed
+		.image()
+		.map(ToOwned::to_owned)
+		.or_else(|| {
+			html.opengraph
+				.images
+				.first()
+				.map(|obj| obj.url.clone())
+		})
+		.and_then(|image| url.join(&image).ok());
+
+	let mut data = match image {
+		| None => UrlPreviewData::default(),
+		| Some(image) => self.download_image(image.as_str()).await?,
+	};
+
+	let props = html.opengraph.properties;
+
+	/* prefer oEmbed and OpenGraph titles, but fall back to HTML if not available */
+	data.title = oembed
+		.title
+		.or_else(|| props.get("title").cloned())
+		.or(html.title);
+	data.description = props
+		.get("description")
+		.cloned()
+		.or(html.description);
+
+	Ok(data)
+}
+
+#[cfg(not(feature = "url_preview"))]
+#[implement(Service)]
+#[expect(clippy::unused_async)]
+async fn preview_html(&self, _url: &Url, _response: Response) -> Result<UrlPreviewData> {
+	Err!(FeatureDisabled("url_preview"))
+}
+
+#[implement(Service)]
+pub fn url_preview_allowed(&self, url: &Url) -> bool {
+	if ["http", "https"]
+		.iter()
+		.all(|&scheme| scheme != url.scheme().to_lowercase())
+	{
+		debug!("Ignoring non-HTTP/HTTPS URL to preview: {}", url);
+		return false;
+	}
+
+	let host = match url.host_str() {
+		| None => {
+			debug!("Ignoring URL preview for a URL that does not have a host (?): {}", url);
+			return false;
+		},
+		| Some(h) => h.to_owned(),
+	};
+
+	let allowlist_domain_contains = &self
+		.services
+		.config
+		.url_preview_domain_contains_allowlist;
+	let allowlist_domain_explicit = &self
+		.services
+		.config
+		.url_preview_domain_explicit_allowlist;
+	let denylist_domain_explicit = &self
+		.services
+		.config
+		.url_preview_domain_explicit_denylist;
+	let allowlist_url_contains = &self
+		.services
+		.config
+		.url_preview_url_contains_allowlist;
+
+	if allowlist_domain_contains.contains(&"*".to_owned())
+		|| allowlist_domain_explicit.contains(&"*".to_owned())
+		|| allowlist_url_contains.contains(&"*".to_owned())
+	{
+		debug!("Config key contains * which is allowing all URL previews. Allowing URL {}", url);
+		return true;
+	}
+
+	if !host.is_empty() {
+		if denylist_domain_explicit.contains(&host) {
+			debug!(
+				"Host {} is not allowed by url_preview_domain_explicit_denylist (check 1/4)",
+				&host
+			);
+			return false;
+		}
+
+		if allowlist_domain_explicit.contains(&host) {
+			debug!(
+				"Host {} is allowed by url_preview_domain_explicit_allowlist (check 2/4)",
+				&host
+			);
+			return true;
+		}
+
+		if allowlist_domain_contains
+			.iter()
+			.any(|domain_s| domain_s.contains(&host.clone()))
+		{
+			debug!(
+				"Host {} is allowed by url_preview_domain_contains_allowlist (check 3/4)",
+				&host
+			);
+			return true;
+		}
+
+		if allowlist_url_contains
+			.iter()
+			.any(|url_s| url.to_string().contains(url_s))
+		{
+			debug!("URL {} is allowed by url_preview_url_contains_allowlist (check 4/4)", &host);
+			return true;
+		}
+
+		// check root domain if available and if user has root domain checks
+		if self.services.config.url_preview_check_root_domain {
+			debug!("Checking root domain");
+			match host.split_once('.') {
+				| None => return false,
+				| Some((_, root_domain)) => {
+					if denylist_domain_explicit.contains(&root_domain.to_owned()) {
+						debug!(
+							"Root domain {} is not allowed by \
+							 url_preview_domain_explicit_denylist (check 1/3)",
+							&root_domain
+						);
+						return true;
+					}
+
+					if allowlist_domain_explicit.contains(&root_domain.to_owned()) {
+						debug!(
+							"Root domain {} is allowed by url_preview_domain_explicit_allowlist \
+							 (check 2/3)",
+							&root_domain
+						);
+						return true;
+					}
+
+					if allowlist_domain_contains
+						.iter()
+						.any(|domain_s| domain_s.contains(&root_domain.to_owned()))
+					{
+						debug!(
+							"Root domain {} is allowed by url_preview_domain_contains_allowlist \
+							 (check 3/3)",
+							&root_domain
+						);
+						return true;
+					}
+				},
+			}
+		}
+	}
+
+	false
+}
@@ -0,0 +1,164 @@
+//! oEmbed data of previewed URLs, see <https://oembed.com>
+
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use tuwunel_core::{Result, err};
+use url::Url;
+
+/// The fields of an oEmbed response used for previews.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct OEmbed {
+	#[serde(rename = "type")]
+	pub(super) kind: Option<String>,
+	pub(super) title: Option<String>,
+	pub(super) author_name: Option<String>,
+
+	/// The image of `photo` responses.
+	pub(super) url: Option<String>,
+	pub(super) thumbnail_url: Option<String>,
+
+	/// Suggested time in seconds to cache the response for; providers send it
+	/// both as a number and as a string.
+	cache_age: Option<JsonValue>,
+}
+
+impl OEmbed {
+	/// The image to preview the URL with: the photo itself for `photo`
+	/// responses, the thumbnail otherwise.
+	pub(super) fn image(&self) -> Option<&str> {
+		match self.kind.as_deref() {
+			| Some("photo") => self
+				.url
+				.as_deref()
+				.or(self.thumbnail_url.as_deref()),
+			| _ => self.thumbnail_url.as_deref(),
+		}
+	}
+
+	pub(super) fn cache_age(&self) -> Option<Duration> {
+		match self.cache_age.as_ref()? {
+			| JsonValue::Number(age) => age.as_u64(),
+			| JsonValue::String(age) => age.trim().parse().ok(),
+			| _ => None,
+		}
+		.map(Duration::from_secs)
+	}
+}
+
+pub(super) fn parse(body: &[u8]) -> Result<OEmbed> {
+	serde_json::from_slice(body).map_err(|e| err!(Request(Unknown("Invalid oEmbed data: {e}"))))
+}
+
+/// The endpoint of the first configured provider with a scheme matching the
+/// URL.
+pub(super) fn provider<'a>(providers: &'a BTreeMap<String, Url>, url: &Url) -> Option<&'a Url> {
+	providers
+		.iter()
+		.find(|(scheme, _)| matches_scheme(scheme, url.as_str()))
+		.map(|(_, endpoint)| endpoint)
+}
+
+/// The URL requesting the JSON oEmbed data of the URL from the endpoint of a
+/// provider; a `{format}` placeholder in the endpoint is filled in as well.
+pub(super) fn request_url(endpoint: &Url, url: &Url) -> Result<Url> {
+	let mut request: Url = endpoint
+		.as_str()
+		.replace("%7Bformat%7D", "json")
+		.replace("{format}", "json")
+		.parse()
+		.map_err(|e| err!(Config("url_preview_oembed_providers", "Invalid endpoint: {e}")))?;
+
+	request
+		.query_pairs_mut()
+		.append_pair("url", url.as_str())
+		.append_pair("format", "json");
+
+	Ok(request)
+}
+
+/// The URL of the JSON oEmbed data a page advertises with a
+/// `<link type="application/json+oembed">` element.
+pub(super) fn discover(html: &str, base: &Url) -> Option<Url> {
+	html.split('<')
+		.filter_map(|tag| {
+			let (name, tag) = tag.split_at_checked(4)?;
+			(name.eq_ignore_ascii_case("link")
+				&& tag.starts_with(|c: char| c.is_ascii_whitespace()))
+			.then_some(tag)
+		})
+		.filter_map(|tag| tag.split('>').next())
+		.find(|tag| {
+			attributes(tag).any(|(name, value)| {
+				name.eq_ignore_ascii_case("type")
+					&& value.eq_ignore_ascii_case("application/json+oembed")
+			})
+		})
+		.and_then(|tag| {
+			attributes(tag)
+				.find_map(|(name, value)| name.eq_ignore_ascii_case("href").then_some(value))
+		})
+		.and_then(|href| base.join(&href.replace("&amp;", "&")).ok())
+}
+
+/// Whether the URL matches the scheme, in which `*` matches any characters.
+pub(super) fn matches_scheme(scheme: &str, url: &str) -> bool {
+	let mut parts = scheme.split('*');
+	let Some(mut rest) = parts
+		.next()
+		.and_then(|first| url.strip_prefix(first))
+	else {
+		return false;
+	};
+
+	let parts: Vec<_> = parts.collect();
+	let Some((last, middle)) = parts.split_last() else {
+		return rest.is_empty();
+	};
+
+	for part in middle {
+		match rest.split_once(part) {
+			| Some((_, after)) => rest = after,
+			| None => return false,
+		}
+	}
+
+	rest.ends_with(last)
+}
+
+/// The attributes of an element's start tag, without the element's name.
+fn attributes(tag: &str) -> impl Iterator<Item = (&str, &str)> {
+	let mut rest = tag;
+	std::iter::from_fn(move || {
+		rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+		if rest.is_empty() {
+			return None;
+		}
+
+		let (name, after) = rest.split_at(
+			rest.find(|c: char| c == '=' || c.is_ascii_whitespace())
+				.unwrap_or(rest.len()),
+		);
+
+		let after = after.trim_start();
+		let Some(value) = after.strip_prefix('=') else {
+			rest = after;
+			return Some((name, ""));
+		};
+
+		let value = value.trim_start();
+		let (value, after) = match value.chars().next() {
+			| Some(quote @ ('"' | '\'')) => value
+				.strip_prefix(quote)
+				.and_then(|value| value.split_once(quote))
+				.unwrap_or((value, "")),
+			| _ => value
+				.split_once(|c: char| c.is_ascii_whitespace())
+				.unwrap_or((value, "")),
+		};
+
+		rest = after;
+		Some((name, value))
+	})
+}
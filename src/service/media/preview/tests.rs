@@ -0,0 +1,294 @@
+use std::{
+	iter,
+	net::SocketAddr,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use ipaddress::IPAddress;
+use reqwest::{
+	dns::{Addrs, Name, Resolve, Resolving},
+	header::{CACHE_CONTROL, HeaderMap, HeaderValue},
+};
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::TcpListener,
+};
+use url::Url;
+
+use super::{DomainRates, MIN_CACHE_TTL, RATE_WINDOW, cache_ttl, oembed};
+use crate::client::{DenylistResolver, denylist_redirect};
+
+/// Serves the response of `respond` to the path of each request, given the
+/// address of the fixture.
+async fn serve<F>(respond: F) -> SocketAddr
+where
+	F: Fn(SocketAddr, &str) -> String + Send + 'static,
+{
+	let listener = TcpListener::bind("127.0.0.1:0")
+		.await
+		.expect("bound listener");
+
+	let addr = listener.local_addr().expect("local address");
+	tokio::spawn(async move {
+		while let Ok((mut stream, _)) = listener.accept().await {
+			let mut request = [0_u8; 1024];
+			let len = stream.read(&mut request).await.unwrap_or(0);
+			let request = String::from_utf8_lossy(request.get(..len).unwrap_or_default());
+			let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+			_ = stream
+				.write_all(respond(addr, path).as_bytes())
+				.await;
+		}
+	});
+
+	addr
+}
+
+fn ok(content_type: &str, body: &str) -> String {
+	format!(
+		"HTTP/1.1 200 OK\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: \
+		 close\r\n\r\n{body}",
+		body.len()
+	)
+}
+
+fn redirect(location: &str) -> String {
+	format!(
+		"HTTP/1.1 302 Found\r\nlocation: {location}\r\ncontent-length: 0\r\nconnection: \
+		 close\r\n\r\n"
+	)
+}
+
+/// Resolves every name to the loopback address.
+struct Loopback;
+
+impl Resolve for Loopback {
+	fn resolve(&self, _name: Name) -> Resolving {
+		let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+		Box::pin(async move { Ok(Box::new(iter::once(addr)) as Addrs) })
+	}
+}
+
+/// A client for URL previews as configured with the denylist.
+fn client(denylist: &[&str]) -> reqwest::Client {
+	let denylist: Arc<[IPAddress]> = denylist
+		.iter()
+		.map(|cidr| IPAddress::parse(*cidr).expect("valid range"))
+		.collect();
+
+	reqwest::Client::builder()
+		.no_proxy()
+		.dns_resolver2(Arc::new(DenylistResolver {
+			inner: Arc::new(Loopback),
+			denylist: denylist.clone(),
+		}))
+		.redirect(denylist_redirect(denylist, 3))
+		.build()
+		.expect("built client")
+}
+
+async fn fixture() -> SocketAddr {
+	serve(|addr, path| match path {
+		| "/page" => ok("text/html", "<html><head><title>Page</title></head></html>"),
+		| "/to-name" => redirect(&format!("http://fixture.test:{}/page", addr.port())),
+		| "/to-address" => redirect(&format!("http://127.0.0.2:{}/page", addr.port())),
+		| "/to-file" => redirect("file:///etc/passwd"),
+		| _ => redirect("/loop"),
+	})
+	.await
+}
+
+#[tokio::test]
+async fn redirect_to_allowed_name() {
+	let addr = fixture().await;
+	let response = client(&["10.0.0.0/8"])
+		.get(format!("http://fixture.test:{}/to-name", addr.port()))
+		.send()
+		.await
+		.expect("followed redirect");
+
+	assert_eq!(response.url().path(), "/page");
+	assert_eq!(response.remote_addr().map(|addr| addr.ip()), Some(addr.ip()));
+}
+
+#[tokio::test]
+async fn name_resolving_to_denied_address() {
+	let addr = fixture().await;
+	let result = client(&["127.0.0.0/8"])
+		.get(format!("http://fixture.test:{}/page", addr.port()))
+		.send()
+		.await;
+
+	assert!(result.is_err(), "connected to a denied address");
+}
+
+#[tokio::test]
+async fn redirect_to_denied_address() {
+	let addr = fixture().await;
+	let result = client(&["127.0.0.2/32"])
+		.get(format!("http://{addr}/to-address"))
+		.send()
+		.await;
+
+	assert!(result.is_err(), "followed redirect to a denied address");
+}
+
+#[tokio::test]
+async fn redirect_to_other_scheme() {
+	let addr = fixture().await;
+	let result = client(&[])
+		.get(format!("http://{addr}/to-file"))
+		.send()
+		.await;
+
+	assert!(result.is_err(), "followed redirect to a file");
+}
+
+#[tokio::test]
+async fn redirect_loop() {
+	let addr = fixture().await;
+	let result = client(&[])
+		.get(format!("http://{addr}/loop"))
+		.send()
+		.await;
+
+	assert!(result.is_err(), "followed redirects endlessly");
+}
+
+#[tokio::test]
+async fn discover_and_parse_oembed() {
+	let addr = serve(|_, path| match path {
+		| "/watch" => ok(
+			"text/html",
+			r#"<html><head>
+				<link rel="alternate" type="application/json+oembed"
+					href="/oembed?url=%2Fwatch&amp;format=json" title="Video">
+				</head></html>"#,
+		),
+		| _ => ok(
+			"application/json",
+			r#"{"type": "video", "title": "A video", "author_name": "Someone",
+				"thumbnail_url": "https://example.com/thumb.jpg", "cache_age": "3600"}"#,
+		),
+	})
+	.await;
+
+	let page: Url = format!("http://{addr}/watch")
+		.parse()
+		.expect("valid URL");
+
+	let html = reqwest::get(page.as_str())
+		.await
+		.and_then(reqwest::Response::error_for_status)
+		.expect("fetched page")
+		.text()
+		.await
+		.expect("page body");
+
+	let oembed_url = oembed::discover(&html, &page).expect("discovered oEmbed link");
+	assert_eq!(oembed_url.path(), "/oembed");
+	assert_eq!(oembed_url.query(), Some("url=%2Fwatch&format=json"));
+
+	let body = reqwest::get(oembed_url)
+		.await
+		.expect("fetched oEmbed data")
+		.bytes()
+		.await
+		.expect("oEmbed body");
+
+	let oembed = oembed::parse(&body).expect("valid oEmbed data");
+	assert_eq!(oembed.title.as_deref(), Some("A video"));
+	assert_eq!(oembed.image(), Some("https://example.com/thumb.jpg"));
+	assert_eq!(oembed.cache_age(), Some(Duration::from_secs(3600)));
+}
+
+#[test]
+fn oembed_photo_image() {
+	let oembed = oembed::parse(
+		br#"{"type": "photo", "url": "https://example.com/a.png",
+			"thumbnail_url": "https://example.com/t.png", "cache_age": 60}"#,
+	)
+	.expect("valid oEmbed data");
+
+	assert_eq!(oembed.image(), Some("https://example.com/a.png"));
+	assert_eq!(oembed.cache_age(), Some(Duration::from_secs(60)));
+}
+
+#[test]
+fn oembed_provider_schemes() {
+	assert!(oembed::matches_scheme(
+		"https://www.youtube.com/watch*",
+		"https://www.youtube.com/watch?v=abc"
+	));
+	assert!(oembed::matches_scheme(
+		"https://*.example.com/*/photo/*",
+		"https://a.example.com/u/photo/1"
+	));
+	assert!(oembed::matches_scheme("https://example.com/", "https://example.com/"));
+	assert!(!oembed::matches_scheme("https://example.com/", "https://example.com/x"));
+	assert!(!oembed::matches_scheme("https://*.example.com/*", "https://example.org/x"));
+
+	let endpoint: Url = "https://example.com/oembed.{format}"
+		.parse()
+		.expect("valid URL");
+	let url: Url = "https://example.com/photo/1"
+		.parse()
+		.expect("valid URL");
+	let request = oembed::request_url(&endpoint, &url).expect("request URL");
+
+	assert_eq!(
+		request.as_str(),
+		"https://example.com/oembed.json?url=https%3A%2F%2Fexample.com%2Fphoto%2F1&format=json"
+	);
+}
+
+#[test]
+fn cache_control() {
+	const DEFAULT: Duration = Duration::from_secs(86400);
+	const MAX: Duration = Duration::from_secs(604_800);
+
+	let ttl = |value: &str| {
+		let mut headers = HeaderMap::new();
+		headers.insert(CACHE_CONTROL, HeaderValue::from_str(value).expect("valid header"));
+		cache_ttl(&headers, DEFAULT, MAX)
+	};
+
+	assert_eq!(cache_ttl(&HeaderMap::new(), DEFAULT, MAX), DEFAULT);
+	assert_eq!(ttl("public, max-age=600"), Duration::from_secs(600));
+	assert_eq!(ttl("max-age=600, s-maxage=60"), Duration::from_secs(60));
+	assert_eq!(ttl("Max-Age=\"120\""), Duration::from_secs(120));
+	assert_eq!(ttl("max-age=31536000, immutable"), MAX);
+	assert_eq!(ttl("no-store"), MIN_CACHE_TTL);
+	assert_eq!(ttl("no-cache"), MIN_CACHE_TTL);
+	assert_eq!(ttl("private, max-age=600"), MIN_CACHE_TTL);
+	assert_eq!(ttl("max-age=0"), MIN_CACHE_TTL);
+	assert_eq!(ttl("private=\"set-cookie\", max-age=600"), Duration::from_secs(600));
+	assert_eq!(ttl("must-revalidate"), DEFAULT);
+}
+
+#[test]
+fn domain_rate_limit() {
+	let rates = DomainRates::default();
+
+	assert!(rates.check("example.com", 2));
+	assert!(rates.check("example.com", 2));
+	assert!(!rates.check("example.com", 2));
+	assert!(rates.check("example.org", 2));
+	assert!((0..10).all(|_| rates.check("example.net", 0)));
+
+	// an expired window starts over
+	rates
+		.0
+		.lock()
+		.expect("unpoisoned")
+		.entry("example.com".to_owned())
+		.and_modify(|(start, _)| {
+			*start = Instant::now()
+				.checked_sub(RATE_WINDOW)
+				.expect("monotonic clock past the window");
+		});
+
+	assert!(rates.check("example.com", 2));
+}
@@ -0,0 +1,382 @@
+use std::fmt::Write;
+
+use futures::{Stream, StreamExt};
+use ruma::{Mxc, OwnedMxcUri, OwnedUserId, RoomId, UserId};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sha2::Digest;
+use tuwunel_core::{
+	Err, Event, Result, debug_warn, err, implement,
+	utils::{self, ReadyExt, stream::TryIgnore},
+};
+use tuwunel_database::{Deserialized, Json};
+
+use super::Dim;
+
+/// Quarantined media is hidden from clients and federation but kept for
+/// review until it is released or deleted.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Quarantine {
+	/// Time of the quarantine in milliseconds since the unix epoch.
+	pub quarantined_at: u64,
+	pub quarantined_by: Option<OwnedUserId>,
+	pub reason: Option<String>,
+
+	/// Content hash put on the blocklist along with the quarantine; it is
+	/// removed again on release.
+	pub blocked_sha256: Option<String>,
+}
+
+/// Content whose SHA-256 hash is on the blocklist is refused on upload and
+/// when fetched from remote servers.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BlockedHash {
+	/// Time of blocking in milliseconds since the unix epoch.
+	pub blocked_at: u64,
+	pub reason: Option<String>,
+}
+
+/// Quarantines the media, optionally adding its content hash to the
+/// blocklist.
+#[implement(super::Service)]
+pub async fn quarantine(
+	&self,
+	mxc: &Mxc<'_>,
+	by: Option<&UserId>,
+	reason: Option<&str>,
+	block: bool,
+) -> Result {
+	let blocked_sha256 = if block {
+		let Some(hash) = self.content_sha256(mxc).await else {
+			return Err!(Request(NotFound("No content of {mxc} to block.")));
+		};
+
+		self.block_sha256(&hash, reason);
+		Some(sha256_hex(&hash))
+	} else {
+		None
+	};
+
+	let quarantine = Quarantine {
+		quarantined_at: utils::millis_since_unix_epoch(),
+		quarantined_by: by.map(ToOwned::to_owned),
+		reason: reason.map(ToOwned::to_owned),
+		blocked_sha256,
+	};
+
+	self.db
+		.mediaid_quarantine
+		.raw_put(mxc.to_string(), Json(quarantine));
+
+	Ok(())
+}
+
+/// Releases quarantined media. The content hash blocked along with the
+/// quarantine is removed from the blocklist unless other quarantined media
+/// blocked it too.
+#[implement(super::Service)]
+pub async fn release(&self, mxc: &Mxc<'_>) -> Result {
+	let mxc = mxc.to_string();
+	let Ok(quarantine) = self.get_quarantine_str(&mxc).await else {
+		return Err!(Request(NotFound("{mxc} is not quarantined.")));
+	};
+
+	self.db.mediaid_quarantine.remove(&mxc);
+
+	if let Some(hash) = quarantine.blocked_sha256 {
+		let still_blocked = self
+			.quarantined()
+			.any(async |(_, other)| other.blocked_sha256.as_ref() == Some(&hash))
+			.await;
+
+		if !still_blocked {
+			self.unblock_sha256(&parse_sha256(&hash)?);
+		}
+	}
+
+	Ok(())
+}
+
+/// Quarantines all media uploaded by the user. Returns the number of
+/// quarantined files.
+#[implement(super::Service)]
+pub async fn quarantine_from_user(
+	&self,
+	user_id: &UserId,
+	by: Option<&UserId>,
+	reason: Option<&str>,
+	block: bool,
+) -> Result<usize> {
+	let mxcs = self.db.get_all_user_mxcs(user_id).await;
+
+	Ok(self
+		.quarantine_list(&mxcs, by, reason, block)
+		.await)
+}
+
+/// Quarantines all media referenced by the events of the room. Returns the
+/// number of quarantined files.
+#[implement(super::Service)]
+pub async fn quarantine_from_room(
+	&self,
+	room_id: &RoomId,
+	by: Option<&UserId>,
+	reason: Option<&str>,
+	block: bool,
+) -> Result<usize> {
+	let mxcs = self.room_mxcs(room_id).await;
+
+	Ok(self
+		.quarantine_list(&mxcs, by, reason, block)
+		.await)
+}
+
+#[implement(super::Service)]
+async fn quarantine_list(
+	&self,
+	mxcs: &[OwnedMxcUri],
+	by: Option<&UserId>,
+	reason: Option<&str>,
+	block: bool,
+) -> usize {
+	let mut count: usize = 0;
+	for mxc in mxcs {
+		let Ok(mxc) = mxc.as_str().try_into() else {
+			debug_warn!(?mxc, "Invalid MXC, skipping");
+			continue;
+		};
+
+		match self.quarantine(&mxc, by, reason, block).await {
+			| Ok(()) => count = count.saturating_add(1),
+			| Err(e) => debug_warn!(%mxc, "Failed to quarantine: {e}"),
+		}
+	}
+
+	count
+}
+
+/// Whether the media is quarantined.
+#[implement(super::Service)]
+pub async fn is_quarantined(&self, mxc: &Mxc<'_>) -> bool {
+	self.db
+		.mediaid_quarantine
+		.exists(&mxc.to_string())
+		.await
+		.is_ok()
+}
+
+/// The quarantine record of the media.
+#[implement(super::Service)]
+pub async fn get_quarantine(&self, mxc: &Mxc<'_>) -> Result<Quarantine> {
+	self.get_quarantine_str(&mxc.to_string()).await
+}
+
+#[implement(super::Service)]
+async fn get_quarantine_str(&self, mxc: &str) -> Result<Quarantine> {
+	self.db
+		.mediaid_quarantine
+		.get(mxc)
+		.await
+		.deserialized()
+}
+
+/// All quarantined media.
+#[implement(super::Service)]
+pub fn quarantined(&self) -> impl Stream<Item = (OwnedMxcUri, Quarantine)> + Send + '_ {
+	self.db
+		.mediaid_quarantine
+		.stream()
+		.ignore_err()
+		.map(|(mxc, quarantine): (&str, Quarantine)| (mxc.into(), quarantine))
+}
+
+/// Adds a content hash to the blocklist.
+#[implement(super::Service)]
+pub fn block_sha256(&self, hash: &[u8; 32], reason: Option<&str>) {
+	let blocked = BlockedHash {
+		blocked_at: utils::millis_since_unix_epoch(),
+		reason: reason.map(ToOwned::to_owned),
+	};
+
+	self.db
+		.media_sha256_blocked
+		.raw_put(hash.as_slice(), Json(blocked));
+}
+
+/// Removes a content hash from the blocklist.
+#[implement(super::Service)]
+pub fn unblock_sha256(&self, hash: &[u8; 32]) {
+	self.db
+		.media_sha256_blocked
+		.remove(hash.as_slice());
+}
+
+/// Whether the content hash is on the blocklist.
+#[implement(super::Service)]
+pub async fn is_blocked_sha256(&self, hash: &[u8; 32]) -> bool {
+	self.db
+		.media_sha256_blocked
+		.exists(hash.as_slice())
+		.await
+		.is_ok()
+}
+
+/// All content hashes on the blocklist, hex-encoded.
+#[implement(super::Service)]
+pub fn blocked_sha256s(&self) -> impl Stream<Item = (String, BlockedHash)> + Send + '_ {
+	self.db
+		.media_sha256_blocked
+		.raw_stream()
+		.ignore_err()
+		.ready_filter_map(|(hash, blocked)| {
+			let hash: &[u8; 32] = hash.try_into().ok()?;
+			let blocked = serde_json::from_slice(blocked).ok()?;
+
+			Some((sha256_hex(hash), blocked))
+		})
+}
+
+/// Refuses content on the blocklist.
+#[implement(super::Service)]
+pub(super) async fn check_blocked(&self, hash: &[u8; 32]) -> Result {
+	if self.is_blocked_sha256(hash).await {
+		return Err!(Request(Forbidden(debug_warn!(
+			sha256 = %sha256_hex(hash),
+			"Refusing media content on the blocklist"
+		))));
+	}
+
+	Ok(())
+}
+
+/// Hides quarantined media as if it did not exist.
+#[implement(super::Service)]
+pub(super) async fn check_quarantine(&self, mxc: &Mxc<'_>) -> Result {
+	if self.is_quarantined(mxc).await {
+		return Err!(Request(NotFound("Media not found.")));
+	}
+
+	Ok(())
+}
+
+/// The SHA-256 hash of the original content of the media.
+#[implement(super::Service)]
+async fn content_sha256(&self, mxc: &Mxc<'_>) -> Option<[u8; 32]> {
+	let metadata = self
+		.db
+		.search_file_metadata(mxc, &Dim::default())
+		.await
+		.ok()?;
+
+	if let Some(hash) = self.db.get_media_hash(&metadata.key) {
+		return Some(hash);
+	}
+
+	// Legacy media stored under its database key
+	let content = self
+		.get_storage()
+		.read(&metadata.key)
+		.await
+		.ok()
+		.flatten()?;
+
+	Some(sha2::Sha256::digest(&content).into())
+}
+
+/// MXC URIs referenced by the events of the room.
+#[implement(super::Service)]
+pub(super) async fn room_mxcs(&self, room_id: &RoomId) -> Vec<OwnedMxcUri> {
+	let server_user = &self.services.globals.server_user;
+	let mut mxcs: Vec<OwnedMxcUri> = self
+		.services
+		.timeline
+		.all_pdus(server_user, room_id)
+		.map(|(_, pdu)| event_mxcs(&pdu.get_content_as_value()))
+		.collect::<Vec<_>>()
+		.await
+		.into_iter()
+		.flatten()
+		.collect();
+
+	mxcs.sort_unstable();
+	mxcs.dedup();
+	mxcs
+}
+
+/// MXC URIs referenced by the content of an event: attachments, their
+/// thumbnails (also encrypted ones), avatars, inline images of the formatted
+/// body and the images of emote and sticker packs.
+pub(super) fn event_mxcs(content: &JsonValue) -> Vec<OwnedMxcUri> {
+	const POINTERS: &[&str] = &[
+		"/url",
+		"/avatar_url",
+		"/file/url",
+		"/info/thumbnail_url",
+		"/info/thumbnail_file/url",
+		"/pack/avatar_url",
+	];
+
+	const BODIES: &[&str] = &["/formatted_body", "/m.new_content/formatted_body"];
+
+	let urls = POINTERS
+		.iter()
+		.filter_map(|pointer| content.pointer(pointer)?.as_str());
+
+	let inline = BODIES
+		.iter()
+		.filter_map(|pointer| content.pointer(pointer)?.as_str())
+		.flat_map(inline_mxcs);
+
+	let pack = content
+		.get("images")
+		.and_then(JsonValue::as_object)
+		.into_iter()
+		.flat_map(|images| images.values())
+		.filter_map(|image| image.get("url")?.as_str());
+
+	urls.chain(inline)
+		.chain(pack)
+		.filter(|url| url.starts_with("mxc://"))
+		.map(OwnedMxcUri::from)
+		.filter(|mxc| mxc.is_valid())
+		.collect()
+}
+
+/// The MXC URIs in the HTML, e.g. of `<img src="mxc://…">`.
+fn inline_mxcs(html: &str) -> impl Iterator<Item = &str> {
+	html.match_indices("mxc://")
+		.filter_map(|(start, _)| html.get(start..))
+		.filter_map(|url| {
+			url.split(|c: char| matches!(c, '"' | '\'' | '<' | '>') || c.is_whitespace())
+				.next()
+		})
+}
+
+#[must_use]
+pub fn sha256_hex(hash: &[u8; 32]) -> String {
+	hash.iter()
+		.fold(String::with_capacity(64), |mut out, byte| {
+			write!(out, "{byte:02x}").expect("writing to a String cannot fail");
+			out
+		})
+}
+
+/// Parses a hex-encoded SHA-256 hash.
+pub fn parse_sha256(hex: &str) -> Result<[u8; 32]> {
+	let hex = hex.trim();
+	if hex.len() != 64 || !hex.is_ascii() {
+		return Err!(Request(InvalidParam("Expected a SHA-256 hash of 64 hex digits.")));
+	}
+
+	let mut hash = [0_u8; 32];
+	for (byte, pair) in hash
+		.iter_mut()
+		.zip(hex.as_bytes().chunks_exact(2))
+	{
+		let pair = std::str::from_utf8(pair).map_err(|e| err!(Request(InvalidParam("{e}"))))?;
+		*byte = u8::from_str_radix(pair, 16)
+			.map_err(|e| err!(Request(InvalidParam("Invalid SHA-256 hash: {e}"))))?;
+	}
+
+	Ok(hash)
+}
@@ -13,6 +13,7 @@
 		federation::authenticated_media::{Content, FileOrLocation},
 	},
 };
+use sha2::Digest;
 use tuwunel_core::{
 	Err, Error, Result, debug_warn, err, implement,
 	utils::content_disposition::make_content_disposition,
@@ -30,6 +31,7 @@ pub async fn fetch_remote_thumbnail(
 	dim: &Dim,
 ) -> Result<FileMeta> {
 	self.check_fetch_authorized(mxc)?;
+	self.check_quarantine(mxc).await?;
 
 	let result = self
 		.fetch_thumbnail_authenticated(mxc, user, server, timeout_ms, dim)
@@ -55,6 +57,7 @@ pub async fn fetch_remote_content(
 	timeout_ms: Duration,
 ) -> Result<FileMeta> {
 	self.check_fetch_authorized(mxc)?;
+	self.check_quarantine(mxc).await?;
 
 	let result = self
 		.fetch_content_authenticated(mxc, user, server, timeout_ms)
@@ -87,7 +90,7 @@ async fn fetch_thumbnail_authenticated(
 		method: dim.method.clone().into(),
 		width: dim.width.into(),
 		height: dim.height.into(),
-		animated: true.into(),
+		animated: dim.animated.into(),
 		timeout_ms,
 	};
 
@@ -143,7 +146,7 @@ async fn fetch_thumbnail_unauthenticated(
 	let request = Request {
 		allow_remote: true,
 		allow_redirect: true,
-		animated: true.into(),
+		animated: dim.animated.into(),
 		method: dim.method.clone().into(),
 		width: dim.width.into(),
 		height: dim.height.into(),
@@ -259,13 +262,22 @@ async fn handle_location(
 	user: Option<&UserId>,
 	location: &str,
 ) -> Result<FileMeta> {
-	self.location_request(location)
+	let file = self
+		.location_request(location)
 		.await
 		.map_err(|error| {
 			err!(Request(NotFound(
 				debug_warn!(%mxc, ?user, ?location, ?error, "Fetching media from location failed")
 			)))
-		})
+		})?;
+
+	if let Some(content) = &file.content {
+		let hash = sha2::Sha256::digest(content).into();
+		self.check_blocked(&hash).await?;
+		self.check_scan(mxc, &hash, content).await?;
+	}
+
+	Ok(file)
 }
 
 #[implement(super::Service)]
@@ -372,6 +384,7 @@ pub async fn fetch_remote_thumbnail_legacy(
 
 	self.check_legacy_freeze()?;
 	self.check_fetch_authorized(&mxc)?;
+	self.check_quarantine(&mxc).await?;
 	let response = self
 		.services
 		.federation
@@ -388,7 +401,8 @@ pub async fn fetch_remote_thumbnail_legacy(
 		})
 		.await?;
 
-	let dim = Dim::from_ruma(body.width, body.height, body.method.clone())?;
+	let dim = Dim::from_ruma(body.width, body.height, body.method.clone())?
+		.with_animated(body.animated);
 	self.upload_thumbnail(
 		&mxc,
 		None,
@@ -412,6 +426,7 @@ pub async fn fetch_remote_content_legacy(
 ) -> Result<media::get_content::v3::Response, Error> {
 	self.check_legacy_freeze()?;
 	self.check_fetch_authorized(mxc)?;
+	self.check_quarantine(mxc).await?;
 	let response = self
 		.services
 		.federation
@@ -0,0 +1,427 @@
+use std::{
+	collections::{HashMap, HashSet},
+	fmt,
+	time::Duration,
+};
+
+use futures::StreamExt;
+use ruma::{Mxc, OwnedMxcUri, OwnedRoomId, RoomId, UserId};
+use serde_json::Value as JsonValue;
+use tuwunel_core::{
+	Result, debug_info, debug_warn, implement,
+	utils::{self, time::pretty},
+	warn,
+};
+use tuwunel_database::Deserialized;
+
+use super::quarantine::event_mxcs;
+
+/// Milliseconds of a day.
+const DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// Downloads update the time of the last access at most this often.
+const ACCESS_RESOLUTION: u64 = 60 * 60 * 1000;
+
+/// Key of the time since when encrypted events sent by local users are
+/// recorded.
+const ENCRYPTED_SENT_SINCE: &[u8] = b"media_encrypted_sent_since";
+
+/// Type of the account data holding the emote pack of a user.
+const USER_EMOTES: &str = "im.ponies.user_emotes";
+
+/// Media due for deletion under the policies of `[global.media_retention]`.
+#[derive(Debug)]
+pub struct Expired {
+	pub mxc: OwnedMxcUri,
+	pub expiry: Expiry,
+}
+
+#[derive(Debug)]
+pub enum Expiry {
+	/// Local upload which no event references.
+	Unreferenced {
+		age: Duration,
+	},
+
+	/// Local media older than the maximum age of a workspace it is
+	/// referenced in.
+	WorkspaceMaxAge {
+		workspace_id: String,
+		age: Duration,
+	},
+
+	/// Remote media which was not downloaded recently.
+	Cold {
+		idle: Duration,
+	},
+}
+
+impl fmt::Display for Expiry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			| Self::Unreferenced { age } =>
+				write!(f, "unreferenced, uploaded {} ago", pretty(*age)),
+			| Self::WorkspaceMaxAge { workspace_id, age } =>
+				write!(f, "in workspace {workspace_id}, uploaded {} ago", pretty(*age)),
+			| Self::Cold { idle } => write!(f, "remote, not downloaded for {}", pretty(*idle)),
+		}
+	}
+}
+
+/// Periodically deletes the media expired under the retention policies.
+#[implement(super::Service)]
+pub(super) async fn retention_worker(&self) -> Result {
+	let config = &self.services.server.config.media_retention;
+	if !config.is_enabled() || config.interval == 0 {
+		return Ok(());
+	}
+
+	let interval = Duration::from_secs(config.interval);
+	loop {
+		tokio::select! {
+			() = tokio::time::sleep(interval) => {},
+			() = self.services.server.until_shutdown() => return Ok(())
+		};
+
+		debug_info!("Applying media retention policies");
+		match self.apply_retention().await {
+			| Ok(count) => debug_info!(?count, "Deleted expired media"),
+			| Err(e) => warn!("Failed to apply media retention policies: {e}"),
+		}
+	}
+}
+
+/// Deletes the expired media. Returns the number of deleted files.
+#[implement(super::Service)]
+pub async fn apply_retention(&self) -> Result<usize> {
+	let mut count: usize = 0;
+	for Expired { mxc, expiry } in self.find_expired(true).await? {
+		let Ok(mxc) = mxc.as_str().try_into() else {
+			continue;
+		};
+
+		debug_info!(%mxc, %expiry, "Deleting expired media");
+		match self.delete(&mxc).await {
+			| Ok(()) => count = count.saturating_add(1),
+			| Err(e) => debug_warn!(%mxc, "Failed to delete expired media: {e}"),
+		}
+	}
+
+	Ok(count)
+}
+
+/// The media expired under the retention policies; quarantined media is
+/// kept for review. Only reads the database, e.g. for reports.
+#[implement(super::Service)]
+pub async fn expired_media(&self) -> Result<Vec<Expired>> { self.find_expired(false).await }
+
+/// The media expired under the retention policies. Media of which no time of
+/// upload was recorded yet, like media from before retention was configured,
+/// is not expired; with `record_unknown` it is recorded as uploaded now.
+///
+/// Local uploads only expire as unreferenced when their uploader cannot have
+/// referenced them in an encrypted event, whose content the server cannot
+/// see.
+#[implement(super::Service)]
+async fn find_expired(&self, record_unknown: bool) -> Result<Vec<Expired>> {
+	let config = &self.services.server.config.media_retention;
+	let now = utils::millis_since_unix_epoch();
+
+	let referenced = if config.unreferenced_local_days > 0 {
+		Some(self.referenced_local_mxcs().await)
+	} else {
+		None
+	};
+
+	let workspaces = self.workspace_max_ages().await;
+
+	let mut mxcs = self.get_all_mxcs().await?;
+	mxcs.sort_unstable();
+	mxcs.dedup();
+
+	let mut expired = Vec::new();
+	for mxc in mxcs {
+		let Ok(parsed) = mxc.as_str().try_into() else {
+			continue;
+		};
+
+		if self.is_quarantined(&parsed).await {
+			continue;
+		}
+
+		let created_at = match self.created_at(&parsed).await {
+			| Some(created_at) => created_at,
+			| None if record_unknown => self.record_created_at(&parsed).await,
+			| None => now,
+		};
+
+		let age = Duration::from_millis(now.saturating_sub(created_at));
+		let local = mxc
+			.server_name()
+			.is_ok_and(|server_name| self.services.globals.server_is_ours(server_name));
+
+		let expiry = if local {
+			if let Some((workspace_id, max_age_days)) = workspaces.get(&mxc)
+				&& age.as_millis() > u128::from(max_age_days.saturating_mul(DAY))
+			{
+				Some(Expiry::WorkspaceMaxAge { workspace_id: workspace_id.clone(), age })
+			} else if let Some(referenced) = &referenced
+				&& age.as_millis()
+					> u128::from(config.unreferenced_local_days.saturating_mul(DAY))
+				&& !referenced.contains(&mxc)
+				&& let Some(uploader) = self.db.get_uploader(&parsed).await
+				&& !self
+					.may_reference_encrypted(&uploader, created_at)
+					.await
+			{
+				Some(Expiry::Unreferenced { age })
+			} else {
+				None
+			}
+		} else if config.cold_remote_days > 0 {
+			let accessed_at = self
+				.accessed_at(&parsed)
+				.await
+				.unwrap_or(created_at);
+
+			let idle = Duration::from_millis(now.saturating_sub(accessed_at));
+			(idle.as_millis() > u128::from(config.cold_remote_days.saturating_mul(DAY)))
+				.then_some(Expiry::Cold { idle })
+		} else {
+			None
+		};
+
+		if let Some(expiry) = expiry {
+			expired.push(Expired { mxc, expiry });
+		}
+	}
+
+	Ok(expired)
+}
+
+/// Records the upload or first fetch of the media.
+#[implement(super::Service)]
+pub(super) fn set_created(&self, mxc: &Mxc<'_>) {
+	self.db
+		.mediaid_created
+		.raw_put(mxc.to_string(), utils::millis_since_unix_epoch());
+}
+
+/// Records a download of the media.
+#[implement(super::Service)]
+pub(super) async fn set_accessed(&self, mxc: &Mxc<'_>) {
+	let now = utils::millis_since_unix_epoch();
+	let mxc = mxc.to_string();
+	let accessed_at: Option<u64> = self
+		.db
+		.mediaid_accessed
+		.get(&mxc)
+		.await
+		.deserialized()
+		.ok();
+
+	if accessed_at.is_none_or(|accessed_at| now.saturating_sub(accessed_at) >= ACCESS_RESOLUTION)
+	{
+		self.db.mediaid_accessed.raw_put(mxc, now);
+	}
+}
+
+/// The time of the upload or first fetch of the media, if recorded.
+#[implement(super::Service)]
+pub(super) async fn created_at(&self, mxc: &Mxc<'_>) -> Option<u64> {
+	self.db
+		.mediaid_created
+		.get(&mxc.to_string())
+		.await
+		.deserialized()
+		.ok()
+}
+
+/// The time of the upload or first fetch of the media; recorded as now when
+/// it is not known.
+#[implement(super::Service)]
+pub(super) async fn record_created_at(&self, mxc: &Mxc<'_>) -> u64 {
+	if let Some(created_at) = self.created_at(mxc).await {
+		return created_at;
+	}
+
+	self.set_created(mxc);
+	utils::millis_since_unix_epoch()
+}
+
+/// Records an encrypted event sent by the local user, who may have referenced
+/// any of their uploads in it.
+#[implement(super::Service)]
+pub fn set_encrypted_sent(&self, user_id: &UserId) {
+	self.db
+		.userid_encryptedsent
+		.raw_put(user_id, utils::millis_since_unix_epoch());
+}
+
+/// Starts recording the encrypted events sent by local users, unless already
+/// recording.
+#[implement(super::Service)]
+pub(super) async fn init_encrypted_sent(&self) {
+	if self
+		.db
+		.global
+		.get(ENCRYPTED_SENT_SINCE)
+		.await
+		.is_err()
+	{
+		self.db
+			.global
+			.raw_put(ENCRYPTED_SENT_SINCE, utils::millis_since_unix_epoch());
+	}
+}
+
+/// Whether the uploader may have referenced media uploaded at the time in an
+/// encrypted event: they sent one after the upload, or the upload predates
+/// the recording of encrypted events and they are joined to an encrypted
+/// room.
+#[implement(super::Service)]
+async fn may_reference_encrypted(&self, uploader: &UserId, created_at: u64) -> bool {
+	let sent_at: Option<u64> = self
+		.db
+		.userid_encryptedsent
+		.get(uploader)
+		.await
+		.deserialized()
+		.ok();
+
+	if sent_at.is_some_and(|sent_at| sent_at >= created_at) {
+		return true;
+	}
+
+	let recorded_since: Option<u64> = self
+		.db
+		.global
+		.get(ENCRYPTED_SENT_SINCE)
+		.await
+		.deserialized()
+		.ok();
+
+	if recorded_since.is_some_and(|since| since <= created_at) {
+		return false;
+	}
+
+	self.services
+		.state_cache
+		.rooms_joined(uploader)
+		.any(|room_id| {
+			self.services
+				.state_accessor
+				.is_encrypted_room(room_id)
+		})
+		.await
+}
+
+#[implement(super::Service)]
+async fn accessed_at(&self, mxc: &Mxc<'_>) -> Option<u64> {
+	self.db
+		.mediaid_accessed
+		.get(&mxc.to_string())
+		.await
+		.deserialized()
+		.ok()
+}
+
+/// Local media referenced by the events of any room, as the avatar of a
+/// local user or in their emote pack.
+#[implement(super::Service)]
+async fn referenced_local_mxcs(&self) -> HashSet<OwnedMxcUri> {
+	let mut mxcs = HashSet::new();
+	let room_ids: Vec<OwnedRoomId> = self
+		.services
+		.metadata
+		.iter_ids()
+		.map(ToOwned::to_owned)
+		.collect()
+		.await;
+
+	for room_id in &room_ids {
+		mxcs.extend(self.local_mxcs_in(room_id).await);
+	}
+
+	let avatars: Vec<OwnedMxcUri> = self
+		.services
+		.users
+		.list_local_users()
+		.filter_map(async |user_id| self.services.users.avatar_url(user_id).await.ok())
+		.collect()
+		.await;
+
+	let emotes: Vec<OwnedMxcUri> = self
+		.services
+		.users
+		.list_local_users()
+		.filter_map(async |user_id| {
+			self.services
+				.account_data
+				.get_global::<JsonValue>(user_id, USER_EMOTES.into())
+				.await
+				.ok()
+		})
+		.filter_map(async |event| event.get("content").map(event_mxcs))
+		.collect::<Vec<_>>()
+		.await
+		.into_iter()
+		.flatten()
+		.collect();
+
+	mxcs.extend(avatars);
+	mxcs.extend(emotes);
+	mxcs
+}
+
+/// The local media referenced in the workspaces with a maximum age, along
+/// with the workspace of the shortest maximum age.
+#[implement(super::Service)]
+async fn workspace_max_ages(&self) -> HashMap<OwnedMxcUri, (String, u64)> {
+	let config = &self.services.server.config.media_retention;
+	let mut mxcs: HashMap<OwnedMxcUri, (String, u64)> = HashMap::new();
+
+	for (workspace_id, &max_age_days) in &config.workspace_max_age_days {
+		let mut room_ids: Vec<OwnedRoomId> = self
+			.services
+			.workspace
+			.rooms_by_workspace(workspace_id)
+			.collect()
+			.await;
+
+		if let Ok(space_room_id) = self
+			.services
+			.workspace
+			.get_space_room_id(workspace_id)
+			.await
+		{
+			room_ids.push(space_room_id);
+		}
+
+		for room_id in &room_ids {
+			for mxc in self.local_mxcs_in(room_id).await {
+				mxcs.entry(mxc)
+					.and_modify(|entry| {
+						if max_age_days < entry.1 {
+							*entry = (workspace_id.clone(), max_age_days);
+						}
+					})
+					.or_insert_with(|| (workspace_id.clone(), max_age_days));
+			}
+		}
+	}
+
+	mxcs
+}
+
+#[implement(super::Service)]
+async fn local_mxcs_in(&self, room_id: &RoomId) -> Vec<OwnedMxcUri> {
+	self.room_mxcs(room_id)
+		.await
+		.into_iter()
+		.filter(|mxc| {
+			mxc.server_name()
+				.is_ok_and(|server_name| self.services.globals.server_is_ours(server_name))
+		})
+		.collect()
+}
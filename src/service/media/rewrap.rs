@@ -0,0 +1,54 @@
+use tuwunel_core::{Result, debug_warn, implement, info};
+
+use super::sha256_hex;
+
+/// Re-wraps the content stored under previous master keys, and encrypts the
+/// content stored before encryption was enabled, once on startup.
+#[implement(super::Service)]
+pub(super) async fn rewrap_worker(&self) -> Result {
+	let config = &self
+		.services
+		.server
+		.config
+		.media_storage
+		.encryption;
+	if !config
+		.as_ref()
+		.is_some_and(|config| config.rewrap_on_startup)
+	{
+		return Ok(());
+	}
+
+	let (rewrapped, failed) = self.rewrap_media().await;
+	if rewrapped > 0 || failed > 0 {
+		info!(?rewrapped, ?failed, "Finished re-wrapping media by the current master key");
+	}
+
+	Ok(())
+}
+
+/// Re-wraps all content by the current master key of the storage backend.
+/// Returns the number of rewritten files and of files which failed.
+#[implement(super::Service)]
+pub async fn rewrap_media(&self) -> (usize, usize) {
+	let mut rewrapped: usize = 0;
+	let mut failed: usize = 0;
+	for hash in self.db.get_all_sha256_refs().await {
+		if !self.services.server.running() {
+			break;
+		}
+
+		// Rewriting must not resurrect content deleted meanwhile.
+		let _lock = self.content_mutex.lock(&hash).await;
+		match self.get_storage().rewrap(&hash).await {
+			| Ok(true) => rewrapped = rewrapped.saturating_add(1),
+			| Ok(false) => {},
+			| Err(e) => {
+				debug_warn!(hash = %sha256_hex(&hash), "Failed to re-wrap media: {e}");
+				failed = failed.saturating_add(1);
+			},
+		}
+	}
+
+	(rewrapped, failed)
+}
@@ -0,0 +1,336 @@
+use std::borrow::Cow;
+
+use tuwunel_core::implement;
+
+use super::Service;
+
+/// Processes an uploaded image as configured in `[global.media_sanitizing]`:
+/// metadata (EXIF, XMP, ICC) is stripped, the EXIF orientation applied and
+/// oversized images are scaled down. The original is kept when the content
+/// type is not configured, the image is animated or lossy WebP, or processing
+/// fails.
+#[implement(Service)]
+#[cfg(not(feature = "media_sanitize"))]
+pub fn sanitize<'a>(&self, file: &'a [u8], content_type: Option<&str>) -> Cow<'a, [u8]> {
+	let config = &self.services.server.config.media_sanitizing;
+	if content_type.is_some_and(|content_type| config.is_enabled_for(content_type)) {
+		tuwunel_core::debug_warn!("media sanitizing on upload support was not compiled");
+	}
+
+	Cow::Borrowed(file)
+}
+
+#[implement(Service)]
+#[cfg(feature = "media_sanitize")]
+#[tracing::instrument(
+	name = "sanitize",
+	level = "debug",
+	skip(self, file),
+	fields(bytes = file.len()),
+)]
+pub fn sanitize<'a>(&self, file: &'a [u8], content_type: Option<&str>) -> Cow<'a, [u8]> {
+	let config = &self.services.server.config.media_sanitizing;
+	let Some(content_type) = content_type.filter(|ct| config.is_enabled_for(ct)) else {
+		return Cow::Borrowed(file);
+	};
+
+	if let Some(reason) = skip_reason(file, content_type) {
+		tuwunel_core::debug!("Keeping original {reason} image");
+		return Cow::Borrowed(file);
+	}
+
+	let max_dimension = config
+		.downscale
+		.iter()
+		.any(|ct| ct.eq_ignore_ascii_case(content_type))
+		.then_some(config.max_dimension)
+		.filter(|&max| max > 0);
+
+	match process(file, content_type, max_dimension, config.jpeg_quality) {
+		| Ok(processed) => {
+			tuwunel_core::debug!(from = file.len(), to = processed.len(), "Sanitized image");
+			Cow::Owned(processed)
+		},
+		| Err(e) => {
+			tuwunel_core::debug_warn!("Keeping original image which could not be sanitized: {e}");
+			Cow::Borrowed(file)
+		},
+	}
+}
+
+/// Why an image of a configured type is kept as uploaded: encoding it again
+/// would drop all but the first frame of an animation, or turn a lossy WebP
+/// into a much larger lossless one.
+#[cfg(feature = "media_sanitize")]
+fn skip_reason(file: &[u8], content_type: &str) -> Option<&'static str> {
+	use image::ImageFormat;
+
+	match ImageFormat::from_mime_type(content_type)? {
+		| ImageFormat::Png => png_chunks(file)
+			.take_while(|kind| kind != b"IDAT")
+			.any(|kind| &kind == b"acTL")
+			.then_some("animated PNG"),
+
+		| ImageFormat::WebP => webp_chunks(file).find_map(|(fourcc, data)| match &fourcc {
+			| b"VP8X" => data
+				.first()
+				.is_some_and(|flags| flags & 0x02 != 0)
+				.then_some("animated WebP"),
+			| b"ANIM" | b"ANMF" => Some("animated WebP"),
+			| b"VP8 " => Some("lossy WebP"),
+			| _ => None,
+		}),
+
+		| _ => None,
+	}
+}
+
+/// Types of the chunks of a PNG file, in order.
+#[cfg(feature = "media_sanitize")]
+fn png_chunks(file: &[u8]) -> impl Iterator<Item = [u8; 4]> {
+	let mut rest = file.get(8..).unwrap_or_default();
+	std::iter::from_fn(move || {
+		let len = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?);
+		let kind: [u8; 4] = rest.get(4..8)?.try_into().ok()?;
+
+		// Length, type and CRC surround the data
+		let next = usize::try_from(len).ok()?.checked_add(12)?;
+		rest = rest.get(next..).unwrap_or_default();
+
+		Some(kind)
+	})
+}
+
+/// Four character codes and data of the chunks of a WebP file, in order.
+#[cfg(feature = "media_sanitize")]
+fn webp_chunks(file: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+	let mut rest = file.get(12..).unwrap_or_default();
+	std::iter::from_fn(move || {
+		let fourcc: [u8; 4] = rest.get(..4)?.try_into().ok()?;
+		let len = u32::from_le_bytes(rest.get(4..8)?.try_into().ok()?);
+		let end = usize::try_from(len).ok()?.checked_add(8)?;
+		let data = rest.get(8..end)?;
+
+		// Chunks are padded to an even size
+		rest = rest
+			.get(end.checked_add(end & 1)?..)
+			.unwrap_or_default();
+
+		Some((fourcc, data))
+	})
+}
+
+/// Decodes the image, applies its orientation and optionally scales it down,
+/// then encodes it again in its format without any of its metadata.
+#[cfg(feature = "media_sanitize")]
+fn process(
+	file: &[u8],
+	content_type: &str,
+	max_dimension: Option<u32>,
+	jpeg_quality: u8,
+) -> image::ImageResult<Vec<u8>> {
+	use std::io::Cursor;
+
+	use image::{
+		DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader,
+		codecs::{jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+		error::{ImageFormatHint, UnsupportedError, UnsupportedErrorKind},
+		imageops::FilterType,
+	};
+
+	let unsupported = |format: ImageFormatHint| {
+		ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+			format.clone(),
+			UnsupportedErrorKind::Format(format),
+		))
+	};
+
+	let format = ImageFormat::from_mime_type(content_type)
+		.ok_or_else(|| unsupported(ImageFormatHint::Name(content_type.to_owned())))?;
+
+	let mut decoder = ImageReader::with_format(Cursor::new(file), format).into_decoder()?;
+	let orientation = decoder.orientation()?;
+	let mut image = DynamicImage::from_decoder(decoder)?;
+	image.apply_orientation(orientation);
+
+	if let Some(max) = max_dimension
+		&& (image.width() > max || image.height() > max)
+	{
+		image = image.resize(max, max, FilterType::Lanczos3);
+	}
+
+	let mut out = Vec::with_capacity(file.len());
+	match format {
+		| ImageFormat::Jpeg => {
+			// JPEG has no alpha channel
+			let image = DynamicImage::ImageRgb8(image.into_rgb8());
+			image.write_with_encoder(JpegEncoder::new_with_quality(&mut out, jpeg_quality))?;
+		},
+		| ImageFormat::Png => image.write_with_encoder(PngEncoder::new(&mut out))?,
+		| ImageFormat::WebP => image.write_with_encoder(WebPEncoder::new_lossless(&mut out))?,
+		| format => return Err(unsupported(format.into())),
+	}
+
+	Ok(out)
+}
+
+#[cfg(all(test, feature = "media_sanitize"))]
+mod tests {
+	use std::io::Cursor;
+
+	use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Rgb, RgbImage};
+
+	use super::{process, skip_reason};
+
+	fn png(width: u32, height: u32) -> Vec<u8> {
+		let mut out = Vec::new();
+		DynamicImage::ImageRgb8(RgbImage::new(width, height))
+			.write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+			.unwrap();
+
+		out
+	}
+
+	#[test]
+	fn downscales_oversized() {
+		let processed = process(&png(400, 100), "image/png", Some(200), 90).unwrap();
+		let image = image::load_from_memory(&processed).unwrap();
+
+		assert_eq!((image.width(), image.height()), (200, 50));
+	}
+
+	#[test]
+	fn keeps_dimensions() {
+		let processed = process(&png(40, 10), "image/png", Some(200), 90).unwrap();
+		let image = image::load_from_memory(&processed).unwrap();
+
+		assert_eq!((image.width(), image.height()), (40, 10));
+	}
+
+	/// A JPEG whose left half is red and right half blue, with EXIF rotating
+	/// it clockwise and locating it in the northern hemisphere.
+	fn jpeg_with_exif() -> Vec<u8> {
+		let image =
+			RgbImage::from_fn(
+				40,
+				10,
+				|x, _| {
+					if x < 20 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 255]) }
+				},
+			);
+
+		let mut jpeg = Vec::new();
+		DynamicImage::ImageRgb8(image)
+			.write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+			.unwrap();
+
+		#[rustfmt::skip]
+		let tiff: &[u8] = &[
+			// Little endian header, IFD0 at 8
+			b'I', b'I', 42, 0, 8, 0, 0, 0,
+			// IFD0: Orientation 6 and GPSInfo at 38
+			2, 0,
+			0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0,
+			0x25, 0x88, 4, 0, 1, 0, 0, 0, 38, 0, 0, 0,
+			0, 0, 0, 0,
+			// GPS IFD: GPSLatitudeRef "N"
+			1, 0,
+			0x01, 0x00, 2, 0, 2, 0, 0, 0, b'N', 0, 0, 0,
+			0, 0, 0, 0,
+		];
+
+		let len = u16::try_from(tiff.len().saturating_add(8)).unwrap();
+		let mut app1 = vec![0xFF, 0xE1];
+		app1.extend_from_slice(&len.to_be_bytes());
+		app1.extend_from_slice(b"Exif\0\0");
+		app1.extend_from_slice(tiff);
+
+		// Right after the start of image marker
+		jpeg.splice(2..2, app1);
+		jpeg
+	}
+
+	#[test]
+	fn strips_exif_and_applies_orientation() {
+		let file = jpeg_with_exif();
+		let mut decoder = ImageReader::new(Cursor::new(&file))
+			.with_guessed_format()
+			.unwrap()
+			.into_decoder()
+			.unwrap();
+		assert!(decoder.exif_metadata().unwrap().is_some());
+
+		let processed = process(&file, "image/jpeg", None, 90).unwrap();
+		let mut decoder = ImageReader::new(Cursor::new(&processed))
+			.with_guessed_format()
+			.unwrap()
+			.into_decoder()
+			.unwrap();
+		assert!(decoder.exif_metadata().unwrap().is_none());
+		assert!(
+			!processed
+				.windows(4)
+				.any(|window| window == b"Exif")
+		);
+
+		let image = DynamicImage::from_decoder(decoder)
+			.unwrap()
+			.into_rgb8();
+		assert_eq!(image.dimensions(), (10, 40));
+
+		// The left half ends up on top
+		let [red, _, blue] = image.get_pixel(5, 5).0;
+		assert!(red > 200 && blue < 50);
+		let [red, _, blue] = image.get_pixel(5, 35).0;
+		assert!(red < 50 && blue > 200);
+	}
+
+	fn riff(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+		let mut body = b"WEBP".to_vec();
+		for (fourcc, data) in chunks {
+			body.extend_from_slice(*fourcc);
+			body.extend_from_slice(&u32::try_from(data.len()).unwrap().to_le_bytes());
+			body.extend_from_slice(data);
+			if !data.len().is_multiple_of(2) {
+				body.push(0);
+			}
+		}
+
+		let mut file = b"RIFF".to_vec();
+		file.extend_from_slice(&u32::try_from(body.len()).unwrap().to_le_bytes());
+		file.extend(body);
+		file
+	}
+
+	#[test]
+	fn skips_animated_and_lossy() {
+		let animated_webp =
+			riff(&[(b"VP8X", &[0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0]), (b"ANIM", &[0; 6])]);
+		assert_eq!(skip_reason(&animated_webp, "image/webp"), Some("animated WebP"));
+
+		let lossy_webp = riff(&[(b"VP8 ", &[0; 3])]);
+		assert_eq!(skip_reason(&lossy_webp, "image/webp"), Some("lossy WebP"));
+
+		let lossless_webp = riff(&[(b"VP8L", &[0; 5])]);
+		assert_eq!(skip_reason(&lossless_webp, "image/webp"), None);
+
+		let still = png(4, 4);
+		assert_eq!(skip_reason(&still, "image/png"), None);
+
+		// An animation control chunk right after the header
+		let mut apng = still[..33].to_vec();
+		apng.extend_from_slice(&[0, 0, 0, 8]);
+		apng.extend_from_slice(b"acTL");
+		apng.extend_from_slice(&[0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0]);
+		apng.extend_from_slice(&still[33..]);
+		assert_eq!(skip_reason(&apng, "image/png"), Some("animated PNG"));
+
+		assert_eq!(skip_reason(&jpeg_with_exif(), "image/jpeg"), None);
+	}
+
+	#[test]
+	fn rejects_garbage() {
+		assert!(process(b"not an image", "image/jpeg", None, 90).is_err());
+		assert!(process(&png(4, 4), "application/pdf", None, 90).is_err());
+	}
+}
@@ -0,0 +1,173 @@
+use std::{path::Path, time::Duration};
+
+use async_trait::async_trait;
+use tokio::{
+	io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+	net::{TcpStream, UnixStream},
+};
+use tuwunel_core::{Err, Result, err};
+
+use super::{Scanner, Verdict};
+
+/// Size of the chunks the content is streamed to clamd in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Scans content with a ClamAV daemon using the `INSTREAM` command.
+pub struct ClamdScanner {
+	address: String,
+	timeout: Duration,
+}
+
+impl ClamdScanner {
+	/// The address is either "host:port" or the absolute path of a unix
+	/// socket.
+	#[must_use]
+	pub fn new(address: &str, timeout: Duration) -> Self {
+		Self { address: address.to_owned(), timeout }
+	}
+
+	async fn instream(&self, content: &[u8]) -> Result<Verdict> {
+		if Path::new(&self.address).is_absolute() {
+			instream(UnixStream::connect(&self.address).await?, content).await
+		} else {
+			instream(TcpStream::connect(&self.address).await?, content).await
+		}
+	}
+}
+
+#[async_trait]
+impl Scanner for ClamdScanner {
+	async fn scan(&self, content: &[u8]) -> Result<Verdict> {
+		tokio::time::timeout(self.timeout, self.instream(content))
+			.await
+			.map_err(|_| err!("clamd at {} timed out", self.address))?
+	}
+}
+
+async fn instream<S>(mut stream: S, content: &[u8]) -> Result<Verdict>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	stream.write_all(b"zINSTREAM\0").await?;
+	for chunk in content.chunks(CHUNK_SIZE) {
+		let len = u32::try_from(chunk.len())?;
+		stream.write_all(&len.to_be_bytes()).await?;
+		stream.write_all(chunk).await?;
+	}
+
+	stream.write_all(&0_u32.to_be_bytes()).await?;
+	stream.flush().await?;
+
+	let mut reply = Vec::new();
+	stream.read_to_end(&mut reply).await?;
+
+	parse_reply(&reply)
+}
+
+/// Parses replies like `stream: OK` or `stream: Eicar-Signature FOUND`.
+fn parse_reply(reply: &[u8]) -> Result<Verdict> {
+	let reply = str::from_utf8(reply)?.trim_end_matches(['\0', '\n']);
+	let reply = reply.strip_prefix("stream: ").unwrap_or(reply);
+
+	if reply == "OK" {
+		return Ok(Verdict::Clean);
+	}
+
+	if let Some(threat) = reply.strip_suffix(" FOUND") {
+		return Ok(Verdict::Infected(threat.to_owned()));
+	}
+
+	Err!("clamd replied with an error: {reply}")
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+
+	use super::{ClamdScanner, Scanner, Verdict, parse_reply};
+
+	const EICAR: &[u8] = b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*";
+
+	/// Accepts a single `INSTREAM` session and replies like clamd, finding
+	/// the EICAR test signature.
+	async fn fake_clamd() -> String {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let address = listener.local_addr().unwrap().to_string();
+
+		tokio::spawn(async move {
+			let (mut socket, _) = listener.accept().await.unwrap();
+
+			let mut command = [0_u8; 10];
+			socket.read_exact(&mut command).await.unwrap();
+			assert_eq!(&command, b"zINSTREAM\0");
+
+			let mut content = Vec::new();
+			loop {
+				let len = socket.read_u32().await.unwrap();
+				if len == 0 {
+					break;
+				}
+
+				let mut chunk = vec![0_u8; len.try_into().unwrap()];
+				socket.read_exact(&mut chunk).await.unwrap();
+				content.extend(chunk);
+			}
+
+			let reply: &[u8] = if content
+				.windows(EICAR.len())
+				.any(|window| window == EICAR)
+			{
+				b"stream: Eicar-Test-Signature FOUND\0"
+			} else {
+				b"stream: OK\0"
+			};
+
+			socket.write_all(reply).await.unwrap();
+		});
+
+		address
+	}
+
+	#[tokio::test]
+	async fn clean_content() {
+		let scanner = ClamdScanner::new(&fake_clamd().await, Duration::from_secs(5));
+		let content = vec![7_u8; 200_000];
+
+		assert_eq!(scanner.scan(&content).await.unwrap(), Verdict::Clean);
+	}
+
+	#[tokio::test]
+	async fn infected_content() {
+		let scanner = ClamdScanner::new(&fake_clamd().await, Duration::from_secs(5));
+
+		assert_eq!(
+			scanner.scan(EICAR).await.unwrap(),
+			Verdict::Infected("Eicar-Test-Signature".to_owned())
+		);
+	}
+
+	#[tokio::test]
+	async fn unreachable() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let address = listener.local_addr().unwrap().to_string();
+		drop(listener);
+
+		let scanner = ClamdScanner::new(&address, Duration::from_secs(5));
+		assert!(scanner.scan(b"content").await.is_err());
+	}
+
+	#[test]
+	fn replies() {
+		assert_eq!(parse_reply(b"stream: OK\0").unwrap(), Verdict::Clean);
+		assert_eq!(
+			parse_reply(b"stream: Win.Test.EICAR_HDB-1 FOUND\n").unwrap(),
+			Verdict::Infected("Win.Test.EICAR_HDB-1".to_owned())
+		);
+		assert!(parse_reply(b"INSTREAM size limit exceeded. ERROR\0").is_err());
+	}
+}
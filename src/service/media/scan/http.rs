@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use http::header::CONTENT_TYPE;
+use serde::Deserialize;
+use tuwunel_core::Result;
+use url::Url;
+
+use super::{Scanner, Verdict};
+
+/// Scans content with a generic HTTP scanner: the content is POSTed to the
+/// URL and the scanner replies with its verdict as JSON.
+pub struct HttpScanner {
+	client: reqwest::Client,
+	url: Url,
+	token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Reply {
+	clean: bool,
+
+	#[serde(default)]
+	threat: Option<String>,
+}
+
+impl HttpScanner {
+	pub fn new(url: Url, token: Option<String>, timeout: Duration) -> Result<Self> {
+		let client = reqwest::Client::builder()
+			.timeout(timeout)
+			.redirect(reqwest::redirect::Policy::none())
+			.build()?;
+
+		Ok(Self { client, url, token })
+	}
+}
+
+#[async_trait]
+impl Scanner for HttpScanner {
+	async fn scan(&self, content: &[u8]) -> Result<Verdict> {
+		let mut request = self
+			.client
+			.post(self.url.clone())
+			.header(CONTENT_TYPE, "application/octet-stream")
+			.body(content.to_vec());
+
+		if let Some(token) = &self.token {
+			request = request.bearer_auth(token);
+		}
+
+		let reply: Reply = request
+			.send()
+			.await?
+			.error_for_status()?
+			.json()
+			.await?;
+
+		Ok(if reply.clean {
+			Verdict::Clean
+		} else {
+			Verdict::Infected(
+				reply
+					.threat
+					.unwrap_or_else(|| "unknown threat".to_owned()),
+			)
+		})
+	}
+}
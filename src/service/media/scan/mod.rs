@@ -0,0 +1,203 @@
+//! Malware scanning of media content
+//!
+//! Content is scanned before it is stored or served. The verdicts are cached
+//! by the SHA-256 hash of the content, so identical content is scanned once.
+
+mod clamd;
+mod http;
+
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use ruma::Mxc;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use tuwunel_core::{Err, Result, config::MediaScanningConfig, debug, implement, utils, warn};
+use tuwunel_database::{Deserialized, Json};
+
+pub use self::{clamd::ClamdScanner, http::HttpScanner};
+use super::{Dim, sha256_hex};
+
+/// Trait for malware scanner backends
+#[async_trait]
+pub trait Scanner: Send + Sync {
+	/// Scans the content, returning an error when no verdict could be made.
+	async fn scan(&self, content: &[u8]) -> Result<Verdict>;
+}
+
+/// Verdict of a scanner about some content.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+	Clean,
+
+	/// Infected with the named threat.
+	Infected(String),
+}
+
+/// Cached verdict about the content with a hash.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScanResult {
+	/// Time of the scan in milliseconds since the unix epoch.
+	pub scanned_at: u64,
+	pub verdict: Verdict,
+}
+
+/// Builds the configured scanner, if any.
+pub(super) fn build(config: &MediaScanningConfig) -> Result<Option<Arc<dyn Scanner>>> {
+	let timeout = Duration::from_secs(config.timeout);
+	let scanner: Arc<dyn Scanner> = match config.backend.as_deref() {
+		| None => return Ok(None),
+		| Some("clamd") => Arc::new(ClamdScanner::new(&config.clamd_address, timeout)),
+		| Some("http") => {
+			let Some(url) = &config.http_url else {
+				return Err!(Config(
+					"media_scanning.http_url",
+					"The URL of the HTTP scanner is required."
+				));
+			};
+
+			Arc::new(HttpScanner::new(url.clone(), config.http_token.clone(), timeout)?)
+		},
+		| Some(backend) => {
+			return Err!(Config(
+				"media_scanning.backend",
+				"Unknown media scanner {backend:?}; expected \"clamd\" or \"http\"."
+			));
+		},
+	};
+
+	Ok(Some(scanner))
+}
+
+/// Whether media is scanned for malware.
+#[implement(super::Service)]
+#[inline]
+pub fn is_scanning(&self) -> bool { self.scanner.is_some() }
+
+/// Refuses content the scanner considers infected, quarantining the media.
+/// Content which could not be scanned is refused as well unless the scanner
+/// is configured to fail open.
+#[implement(super::Service)]
+pub(super) async fn check_scan(&self, mxc: &Mxc<'_>, hash: &[u8; 32], content: &[u8]) -> Result {
+	if !self.is_scanning() {
+		return Ok(());
+	}
+
+	let verdict = match self.get_scan_result(hash).await {
+		| Ok(result) => result.verdict,
+		| Err(_) => match self.scan(hash, content).await {
+			| Ok(verdict) => verdict,
+			| Err(e)
+				if self
+					.services
+					.server
+					.config
+					.media_scanning
+					.fail_open =>
+			{
+				warn!(%mxc, "Serving media which could not be scanned: {e}");
+				return Ok(());
+			},
+			| Err(e) => {
+				warn!(%mxc, "Refusing media which could not be scanned: {e}");
+				return Err!(Request(Unknown(
+					"The media could not be scanned for malware. Try again later."
+				)));
+			},
+		},
+	};
+
+	let Verdict::Infected(threat) = verdict else {
+		return Ok(());
+	};
+
+	if !self.is_quarantined(mxc).await {
+		let reason = format!("Infected with {threat}");
+		self.quarantine(mxc, None, Some(&reason), false)
+			.await?;
+	}
+
+	Err!(Request(Forbidden(
+		warn!(%mxc, "Media was rejected by the malware scanner: {threat}")
+	)))
+}
+
+/// Scans the content regardless of a cached verdict and caches the new
+/// verdict.
+#[implement(super::Service)]
+pub async fn scan(&self, hash: &[u8; 32], content: &[u8]) -> Result<Verdict> {
+	let Some(scanner) = &self.scanner else {
+		return Err!(Config("media_scanning.backend", "Media scanning is not enabled."));
+	};
+
+	let verdict = scanner.scan(content).await?;
+	debug!(sha256 = %sha256_hex(hash), ?verdict, "Scanned media");
+
+	let result = ScanResult {
+		scanned_at: utils::millis_since_unix_epoch(),
+		verdict: verdict.clone(),
+	};
+
+	self.db
+		.media_sha256_scan
+		.raw_put(hash.as_slice(), Json(result));
+
+	Ok(verdict)
+}
+
+/// Rescans the content of the media, quarantining it when infected.
+#[implement(super::Service)]
+pub async fn rescan(&self, mxc: &Mxc<'_>) -> Result<Verdict> {
+	let Some((hash, content)) = self.content(mxc).await? else {
+		return Err!(Request(NotFound("No content of {mxc} to scan.")));
+	};
+
+	let verdict = self.scan(&hash, &content).await?;
+	if let Verdict::Infected(threat) = &verdict
+		&& !self.is_quarantined(mxc).await
+	{
+		let reason = format!("Infected with {threat}");
+		self.quarantine(mxc, None, Some(&reason), false)
+			.await?;
+	}
+
+	Ok(verdict)
+}
+
+/// The cached verdict about the content with the hash.
+#[implement(super::Service)]
+pub async fn get_scan_result(&self, hash: &[u8; 32]) -> Result<ScanResult> {
+	self.db
+		.media_sha256_scan
+		.get(hash.as_slice())
+		.await
+		.deserialized()
+}
+
+/// Drops all cached verdicts, e.g. after the signatures of the scanner were
+/// updated.
+#[implement(super::Service)]
+pub async fn clear_scan_results(&self) { self.db.media_sha256_scan.clear().await; }
+
+/// Reads the original content of the media along with its hash.
+#[implement(super::Service)]
+async fn content(&self, mxc: &Mxc<'_>) -> Result<Option<([u8; 32], Vec<u8>)>> {
+	let Ok(metadata) = self
+		.db
+		.search_file_metadata(mxc, &Dim::default())
+		.await
+	else {
+		return Ok(None);
+	};
+
+	let hash = self.db.get_media_hash(&metadata.key);
+	let storage_key = hash.map_or(metadata.key, |hash| hash.to_vec());
+	let Some(content) = self.get_storage().read(&storage_key).await? else {
+		return Ok(None);
+	};
+
+	let hash = hash.unwrap_or_else(|| sha2::Sha256::digest(&content).into());
+
+	Ok(Some((hash, content.to_vec())))
+}
@@ -0,0 +1,442 @@
+use std::{
+	collections::HashSet,
+	fmt::{self, Write},
+	time::Duration,
+};
+
+use futures::{Stream, StreamExt};
+use ruma::{Mxc, OwnedMxcUri, OwnedUserId, RoomId, UserId};
+use serde::{Deserialize, Serialize};
+use tuwunel_core::{
+	Result, debug_info, implement,
+	utils::{self, stream::TryIgnore, time},
+};
+use tuwunel_database::{Deserialized, Interfix, Json};
+
+use super::Dim;
+
+/// Size and origin of a media file, recorded for the statistics.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MediaInfo {
+	pub size: u64,
+	pub content_type: Option<String>,
+	pub uploader: Option<OwnedUserId>,
+
+	/// Time of the upload or first fetch in milliseconds since the unix epoch.
+	pub created_at: u64,
+}
+
+/// Number and total size of media files.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct Totals {
+	pub count: u64,
+	pub bytes: u64,
+}
+
+/// The groups media statistics are aggregated by.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StatsGroup {
+	/// "local" and "remote" media.
+	Origin,
+	Uploader,
+	Server,
+	ContentType,
+
+	/// Uploads and fetches by day (UTC, "YYYY-MM-DD"); deletions are not
+	/// subtracted.
+	Day,
+}
+
+impl StatsGroup {
+	fn as_str(self) -> &'static str {
+		match self {
+			| Self::Origin => "origin",
+			| Self::Uploader => "uploader",
+			| Self::Server => "server",
+			| Self::ContentType => "content_type",
+			| Self::Day => "day",
+		}
+	}
+}
+
+impl fmt::Display for Totals {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let bytes = usize::try_from(self.bytes).unwrap_or(usize::MAX);
+
+		write!(f, "{} files, {}", self.count, utils::bytes::pretty(bytes))
+	}
+}
+
+impl Totals {
+	fn add(&mut self, size: u64) {
+		self.count = self.count.saturating_add(1);
+		self.bytes = self.bytes.saturating_add(size);
+	}
+
+	fn sub(&mut self, size: u64) {
+		self.count = self.count.saturating_sub(1);
+		self.bytes = self.bytes.saturating_sub(size);
+	}
+}
+
+/// Periodically posts a summary of the media statistics to the admin room.
+#[implement(super::Service)]
+pub(super) async fn stats_worker(&self) -> Result {
+	let interval = self
+		.services
+		.server
+		.config
+		.media_stats_summary_interval;
+
+	if interval == 0 {
+		return Ok(());
+	}
+
+	let interval = Duration::from_secs(interval);
+	loop {
+		tokio::select! {
+			() = tokio::time::sleep(interval) => {},
+			() = self.services.server.until_shutdown() => return Ok(())
+		};
+
+		let summary = self.stats_summary().await;
+		self.services.admin.send_text(&summary).await;
+	}
+}
+
+/// A markdown summary of the media statistics.
+#[implement(super::Service)]
+pub async fn stats_summary(&self) -> String {
+	const TOP: usize = 5;
+
+	let mut summary = String::from("### Media statistics\n\n");
+	for (origin, totals) in self.stats(StatsGroup::Origin).await {
+		writeln!(summary, "- {origin}: {totals}").expect("writing to a String cannot fail");
+	}
+
+	let today = day(utils::millis_since_unix_epoch());
+	let uploaded_today = self
+		.stats_of(StatsGroup::Day, &today)
+		.await
+		.unwrap_or_default();
+
+	writeln!(summary, "- stored today: {uploaded_today}")
+		.expect("writing to a String cannot fail");
+
+	for (title, group) in [
+		("Top uploaders", StatsGroup::Uploader),
+		("Top content types", StatsGroup::ContentType),
+		("Top remote servers", StatsGroup::Server),
+	] {
+		let top = self.top_stats(group, TOP).await;
+		if top.is_empty() {
+			continue;
+		}
+
+		writeln!(summary, "\n#### {title}\n").expect("writing to a String cannot fail");
+		for (name, totals) in top {
+			writeln!(summary, "- {name}: {totals}").expect("writing to a String cannot fail");
+		}
+	}
+
+	summary
+}
+
+/// The statistics of the group, by name.
+#[implement(super::Service)]
+pub async fn stats(&self, group: StatsGroup) -> Vec<(String, Totals)> {
+	let prefix = (group.as_str(), Interfix);
+	self.db
+		.media_stats
+		.stream_prefix(&prefix)
+		.ignore_err()
+		.map(|((_, name), totals): ((&str, &str), Totals)| (name.to_owned(), totals))
+		.collect()
+		.await
+}
+
+/// The statistics of the group with the largest total sizes first.
+#[implement(super::Service)]
+pub async fn top_stats(&self, group: StatsGroup, limit: usize) -> Vec<(String, Totals)> {
+	let mut stats = self.stats(group).await;
+	stats.sort_unstable_by(|(_, a), (_, b)| b.bytes.cmp(&a.bytes));
+	stats.truncate(limit);
+	stats
+}
+
+#[implement(super::Service)]
+pub async fn stats_of(&self, group: StatsGroup, name: &str) -> Result<Totals> {
+	self.db
+		.media_stats
+		.qry(&(group.as_str(), name))
+		.await
+		.deserialized()
+}
+
+/// The largest media files, largest first.
+#[implement(super::Service)]
+pub async fn largest_media(&self, limit: usize) -> Vec<(OwnedMxcUri, MediaInfo)> {
+	let mut media: Vec<_> = self.media_info().collect().await;
+	media.sort_unstable_by(|(_, a), (_, b)| b.size.cmp(&a.size));
+	media.truncate(limit);
+	media
+}
+
+/// The recorded information of all media.
+#[implement(super::Service)]
+pub fn media_info(&self) -> impl Stream<Item = (OwnedMxcUri, MediaInfo)> + Send + '_ {
+	self.db
+		.mediaid_info
+		.stream()
+		.ignore_err()
+		.map(|(mxc, info): (&str, MediaInfo)| (mxc.into(), info))
+}
+
+/// The media referenced by the events of the room.
+#[implement(super::Service)]
+pub async fn room_stats(&self, room_id: &RoomId) -> Totals {
+	let mxcs = self.room_mxcs(room_id).await;
+
+	self.sum_stats(mxcs.iter()).await
+}
+
+/// The media referenced by the events of the rooms of the workspace.
+#[implement(super::Service)]
+pub async fn workspace_stats(&self, workspace_id: &str) -> Result<Totals> {
+	let space_room_id = self
+		.services
+		.workspace
+		.get_space_room_id(workspace_id)
+		.await?;
+
+	let mut mxcs: HashSet<OwnedMxcUri> = self
+		.room_mxcs(&space_room_id)
+		.await
+		.into_iter()
+		.collect();
+
+	let room_ids: Vec<_> = self
+		.services
+		.workspace
+		.rooms_by_workspace(workspace_id)
+		.collect()
+		.await;
+
+	for room_id in &room_ids {
+		mxcs.extend(self.room_mxcs(room_id).await);
+	}
+
+	Ok(self.sum_stats(mxcs.iter()).await)
+}
+
+#[implement(super::Service)]
+async fn sum_stats<'a, I>(&self, mxcs: I) -> Totals
+where
+	I: Iterator<Item = &'a OwnedMxcUri> + Send,
+{
+	let mut totals = Totals::default();
+	for mxc in mxcs {
+		if let Ok(info) = self.get_media_info(mxc.as_str()).await {
+			totals.add(info.size);
+		}
+	}
+
+	totals
+}
+
+/// Accounts a stored media file in the statistics.
+#[implement(super::Service)]
+pub(super) async fn add_stats(
+	&self,
+	mxc: &Mxc<'_>,
+	uploader: Option<&UserId>,
+	content_type: Option<&str>,
+	size: u64,
+) {
+	if self
+		.get_media_info(&mxc.to_string())
+		.await
+		.is_ok()
+	{
+		return;
+	}
+
+	// Remote media fetched on behalf of a local user is not their upload.
+	let local = self
+		.services
+		.globals
+		.server_is_ours(mxc.server_name);
+
+	let info = MediaInfo {
+		size,
+		content_type: content_type.map(ToOwned::to_owned),
+		uploader: uploader.filter(|_| local).map(ToOwned::to_owned),
+		created_at: utils::millis_since_unix_epoch(),
+	};
+
+	let today = day(info.created_at);
+	self.update_stats(StatsGroup::Day, &today, |totals| totals.add(size))
+		.await;
+
+	self.account(mxc, info).await;
+}
+
+#[implement(super::Service)]
+async fn account(&self, mxc: &Mxc<'_>, info: MediaInfo) {
+	for (group, name) in self.stats_keys(mxc, &info) {
+		self.update_stats(group, &name, |totals| totals.add(info.size))
+			.await;
+	}
+
+	self.db
+		.mediaid_info
+		.raw_put(mxc.to_string(), Json(info));
+}
+
+/// Removes a deleted media file from the statistics.
+#[implement(super::Service)]
+pub(super) async fn remove_stats(&self, mxc: &Mxc<'_>) {
+	let mxc_str = mxc.to_string();
+	let Ok(info) = self.get_media_info(&mxc_str).await else {
+		return;
+	};
+
+	for (group, name) in self.stats_keys(mxc, &info) {
+		self.update_stats(group, &name, |totals| totals.sub(info.size))
+			.await;
+	}
+
+	self.db.mediaid_info.remove(&mxc_str);
+}
+
+/// Rebuilds the statistics from the media in the database, taking the sizes
+/// from the storage backend. Returns the number of accounted files.
+#[implement(super::Service)]
+pub async fn rebuild_stats(&self) -> Result<usize> {
+	// The uploads by day are kept; they can not be recovered.
+	for group in [
+		StatsGroup::Origin,
+		StatsGroup::Uploader,
+		StatsGroup::Server,
+		StatsGroup::ContentType,
+	] {
+		for (name, _) in self.stats(group).await {
+			self.db
+				.media_stats
+				.del((group.as_str(), name.as_str()));
+		}
+	}
+
+	self.db.mediaid_info.clear().await;
+
+	let mut mxcs = self.get_all_mxcs().await?;
+	mxcs.sort_unstable();
+	mxcs.dedup();
+
+	let mut count: usize = 0;
+	for mxc in mxcs {
+		let Ok(mxc) = mxc.as_str().try_into() else {
+			continue;
+		};
+
+		let Ok(metadata) = self
+			.db
+			.search_file_metadata(&mxc, &Dim::default())
+			.await
+		else {
+			continue;
+		};
+
+		let storage_key = self
+			.db
+			.get_media_hash(&metadata.key)
+			.map_or(metadata.key, |hash| hash.to_vec());
+
+		let Ok(Some(stored)) = self.get_storage().metadata(&storage_key).await else {
+			continue;
+		};
+
+		let info = MediaInfo {
+			size: stored.size,
+			content_type: metadata.content_type,
+			uploader: self.db.get_uploader(&mxc).await.filter(|_| {
+				self.services
+					.globals
+					.server_is_ours(mxc.server_name)
+			}),
+			created_at: self.record_created_at(&mxc).await,
+		};
+
+		self.account(&mxc, info).await;
+
+		count = count.saturating_add(1);
+	}
+
+	debug_info!(?count, "Rebuilt media statistics");
+
+	Ok(count)
+}
+
+#[implement(super::Service)]
+async fn get_media_info(&self, mxc: &str) -> Result<MediaInfo> {
+	self.db.mediaid_info.get(mxc).await.deserialized()
+}
+
+#[implement(super::Service)]
+fn stats_keys(&self, mxc: &Mxc<'_>, info: &MediaInfo) -> Vec<(StatsGroup, String)> {
+	let local = self
+		.services
+		.globals
+		.server_is_ours(mxc.server_name);
+	let origin = if local { "local" } else { "remote" };
+	let content_type = info.content_type.as_deref().unwrap_or("unknown");
+
+	let mut keys = vec![
+		(StatsGroup::Origin, origin.to_owned()),
+		(StatsGroup::ContentType, content_type.to_owned()),
+	];
+
+	if local && let Some(uploader) = &info.uploader {
+		keys.push((StatsGroup::Uploader, uploader.to_string()));
+	}
+
+	if !local {
+		keys.push((StatsGroup::Server, mxc.server_name.to_string()));
+	}
+
+	keys
+}
+
+/// Updates the totals of a group, serialized per key so that concurrent
+/// uploads and deletions are not lost.
+#[implement(super::Service)]
+async fn update_stats<F>(&self, group: StatsGroup, name: &str, update: F)
+where
+	F: FnOnce(&mut Totals) + Send,
+{
+	let _lock = self
+		.stats_mutex
+		.lock(&(group, name.to_owned()))
+		.await;
+
+	let mut totals = self
+		.stats_of(group, name)
+		.await
+		.unwrap_or_default();
+
+	update(&mut totals);
+
+	let key = (group.as_str(), name);
+	if totals.count == 0 && group != StatsGroup::Day {
+		self.db.media_stats.del(key);
+	} else {
+		self.db.media_stats.put(key, Json(totals));
+	}
+}
+
+fn day(millis: u64) -> String {
+	let timepoint = time::timepoint_from_epoch(Duration::from_millis(millis))
+		.unwrap_or(std::time::UNIX_EPOCH);
+
+	time::format(timepoint, "%Y-%m-%d")
+}
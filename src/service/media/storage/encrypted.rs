@@ -0,0 +1,675 @@
+//! Encrypting storage backend
+//!
+//! Encrypts media at rest in another storage backend. Each file is encrypted
+//! with AES-256-GCM under a random data key, which is stored in the header of
+//! the file wrapped by the master key. The content is sealed in chunks, so
+//! `read_range` reads and decrypts a range of a file without the rest of it.
+//!
+//! Layout: `MAGIC | master key id | chunk size | nonce | wrapped data key`,
+//! followed by the chunks, each with its tag. The nonce of a chunk is its
+//! index; the storage key, the index and whether it is the final chunk are
+//! authenticated with it, so chunks can be neither moved nor truncated.
+
+use std::{iter, ops::Range, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use ring::{
+	aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+	rand::{SecureRandom, SystemRandom},
+};
+use sha2::Digest;
+use tuwunel_core::{Err, Result, config::MediaEncryptionConfig, err};
+
+use super::{MediaStorage, StorageMetadata};
+
+/// Marks files encrypted by this backend; other files are read as plaintext
+/// unless refused.
+const MAGIC: &[u8; 8] = b"TWMENC\x00\x01";
+
+const KEY_LEN: usize = 32;
+
+const TAG_LEN: usize = 16;
+
+const KEY_ID_LEN: usize = 8;
+
+/// Length of the header before the nonce, which the wrapped data key
+/// authenticates.
+const PREFIX_LEN: usize = MAGIC.len() + KEY_ID_LEN + size_of::<u32>();
+
+const HEADER_LEN: usize = PREFIX_LEN + NONCE_LEN + KEY_LEN + TAG_LEN;
+
+/// Storage encrypting files at rest in another storage backend
+pub struct EncryptedStorage {
+	inner: Arc<dyn MediaStorage>,
+	current: MasterKey,
+	previous: Vec<MasterKey>,
+	chunk_size: u32,
+	refuse_plaintext: bool,
+	rng: SystemRandom,
+}
+
+/// Key wrapping the data keys of files
+struct MasterKey {
+	/// Truncated SHA-256 of the key, naming it in the header of files.
+	id: [u8; KEY_ID_LEN],
+	key: LessSafeKey,
+}
+
+/// Header of an encrypted file
+struct Header {
+	key_id: [u8; KEY_ID_LEN],
+	chunk_size: u32,
+	nonce: [u8; NONCE_LEN],
+	wrapped: [u8; KEY_LEN + TAG_LEN],
+}
+
+impl EncryptedStorage {
+	/// Create a new encrypting storage backend
+	///
+	/// # Arguments
+	/// * `inner` - Storage backend the encrypted files are stored in
+	/// * `config` - Encryption configuration, naming the master keys
+	pub fn new(inner: Arc<dyn MediaStorage>, config: &MediaEncryptionConfig) -> Result<Self> {
+		let current = match (&config.key_file, &config.key_env) {
+			| (Some(path), _) => MasterKey::from_file(path)?,
+			| (None, Some(name)) => MasterKey::from_env(name)?,
+			| (None, None) => {
+				return Err!(Config(
+					"media_storage.encryption",
+					"Either key_file or key_env must be set."
+				));
+			},
+		};
+
+		let previous = config
+			.previous_key_files
+			.iter()
+			.map(|path| MasterKey::from_file(path))
+			.chain(
+				config
+					.previous_key_envs
+					.iter()
+					.map(|name| MasterKey::from_env(name)),
+			)
+			.collect::<Result<_>>()?;
+
+		if config.chunk_size == 0 {
+			return Err!(Config("media_storage.encryption.chunk_size", "Must not be zero."));
+		}
+
+		Ok(Self {
+			inner,
+			current,
+			previous,
+			chunk_size: config.chunk_size,
+			refuse_plaintext: config.refuse_plaintext,
+			rng: SystemRandom::new(),
+		})
+	}
+
+	/// Encrypt a file under a new data key
+	fn encrypt(&self, key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+		let mut data_key = [0_u8; KEY_LEN];
+		self.fill(&mut data_key)?;
+
+		let header = self.wrap(&data_key, self.chunk_size)?;
+		let cipher = data_cipher(&data_key)?;
+		let chunk_size = usize::try_from(self.chunk_size)?;
+
+		// Empty files consist of an empty final chunk.
+		let chunks: Vec<_> = data
+			.chunks(chunk_size)
+			.chain(data.is_empty().then_some(&[][..]))
+			.collect();
+
+		let mut sealed = Vec::with_capacity(
+			HEADER_LEN
+				.saturating_add(data.len())
+				.saturating_add(chunks.len().saturating_mul(TAG_LEN)),
+		);
+
+		sealed.extend_from_slice(&header.to_bytes());
+		let last = u64_len(chunks.len()).saturating_sub(1);
+		for (index, chunk) in (0_u64..).zip(&chunks) {
+			let start = sealed.len();
+			sealed.extend_from_slice(chunk);
+			let tag = cipher
+				.seal_in_place_separate_tag(
+					chunk_nonce(index),
+					chunk_aad(key, index, index == last),
+					sealed.get_mut(start..).unwrap_or_default(),
+				)
+				.map_err(|_| err!(Database("Encrypting media chunk {index} failed.")))?;
+
+			sealed.extend_from_slice(tag.as_ref());
+		}
+
+		Ok(sealed)
+	}
+
+	/// Wrap a data key by the current master key
+	fn wrap(&self, data_key: &[u8; KEY_LEN], chunk_size: u32) -> Result<Header> {
+		let mut nonce = [0_u8; NONCE_LEN];
+		self.fill(&mut nonce)?;
+
+		let mut header = Header {
+			key_id: self.current.id,
+			chunk_size,
+			nonce,
+			wrapped: [0_u8; KEY_LEN + TAG_LEN],
+		};
+
+		let mut wrapped = data_key.to_vec();
+		self.current
+			.key
+			.seal_in_place_append_tag(
+				Nonce::assume_unique_for_key(nonce),
+				Aad::from(header.prefix()),
+				&mut wrapped,
+			)
+			.map_err(|_| err!(Database("Wrapping media data key failed.")))?;
+
+		header.wrapped = wrapped
+			.try_into()
+			.map_err(|_| err!(Database("Wrapped media data key has an invalid length.")))?;
+
+		Ok(header)
+	}
+
+	/// Unwrap the data key of a file by the master key it was wrapped by
+	fn unwrap(&self, header: &Header) -> Result<[u8; KEY_LEN]> {
+		let master = iter::once(&self.current)
+			.chain(&self.previous)
+			.find(|master| master.id == header.key_id)
+			.ok_or_else(|| {
+				err!(Database("Media file was encrypted under an unknown master key."))
+			})?;
+
+		let mut wrapped = header.wrapped;
+		let data_key = master
+			.key
+			.open_in_place(
+				Nonce::assume_unique_for_key(header.nonce),
+				Aad::from(header.prefix()),
+				&mut wrapped,
+			)
+			.map_err(|_| err!(Database("Unwrapping media data key failed.")))?;
+
+		data_key
+			.try_into()
+			.map_err(|_| err!(Database("Media data key has an invalid length.")))
+	}
+
+	/// Decrypt consecutive chunks of a file, starting at chunk `first` of
+	/// `count` chunks
+	fn decrypt(
+		&self,
+		key: &[u8],
+		header: &Header,
+		first: u64,
+		count: u64,
+		sealed: &[u8],
+	) -> Result<Vec<u8>> {
+		let cipher = data_cipher(&self.unwrap(header)?)?;
+		let sealed_chunk = usize::try_from(header.chunk_size)?.saturating_add(TAG_LEN);
+
+		let mut data = Vec::with_capacity(sealed.len());
+		for (index, chunk) in (first..).zip(sealed.chunks(sealed_chunk)) {
+			let mut chunk = chunk.to_vec();
+			let opened = cipher
+				.open_in_place(
+					chunk_nonce(index),
+					chunk_aad(key, index, index.saturating_add(1) == count),
+					&mut chunk,
+				)
+				.map_err(|_| err!(Database("Decrypting media chunk {index} failed.")))?;
+
+			data.extend_from_slice(opened);
+		}
+
+		Ok(data)
+	}
+
+	/// Content stored before encryption was enabled, unless refused
+	fn plaintext<T>(&self, data: T) -> Result<T> {
+		if self.refuse_plaintext {
+			return Err!(Database("Media file is not encrypted."));
+		}
+
+		Ok(data)
+	}
+
+	/// Read the header of a file, if it is encrypted
+	async fn read_header(&self, key: &[u8]) -> Result<Option<Option<Header>>> {
+		let Some(data) = self
+			.inner
+			.read_range(key, 0..u64_len(HEADER_LEN))
+			.await?
+		else {
+			return Ok(None);
+		};
+
+		Ok(Some(Header::parse(&data)))
+	}
+
+	fn fill(&self, dest: &mut [u8]) -> Result {
+		self.rng
+			.fill(dest)
+			.map_err(|_| err!(Database("Generating random bytes failed.")))
+	}
+}
+
+impl MasterKey {
+	fn new(encoded: &str, source: &str) -> Result<Self> {
+		let secret = STANDARD.decode(encoded.trim()).map_err(|e| {
+			err!(Config("media_storage.encryption", "Master key of {source} is invalid: {e}"))
+		})?;
+
+		let key = UnboundKey::new(&AES_256_GCM, &secret).map_err(|_| {
+			err!(Config("media_storage.encryption", "Master key of {source} must be 32 bytes."))
+		})?;
+
+		let digest = sha2::Sha256::digest(&secret);
+		let id = digest
+			.first_chunk()
+			.copied()
+			.expect("digest is longer than the key id");
+
+		Ok(Self { id, key: LessSafeKey::new(key) })
+	}
+
+	fn from_file(path: &Path) -> Result<Self> {
+		let source = path.display().to_string();
+		let encoded = std::fs::read_to_string(path).map_err(|e| {
+			err!(Config("media_storage.encryption", "Reading master key {source} failed: {e}"))
+		})?;
+
+		Self::new(&encoded, &source)
+	}
+
+	fn from_env(name: &str) -> Result<Self> {
+		let encoded = std::env::var(name).map_err(|e| {
+			err!(Config("media_storage.encryption", "Reading master key ${name} failed: {e}"))
+		})?;
+
+		Self::new(&encoded, &format!("${name}"))
+	}
+}
+
+impl Header {
+	fn parse(data: &[u8]) -> Option<Self> {
+		let rest = data.strip_prefix(MAGIC)?;
+		let (key_id, rest) = rest.split_first_chunk()?;
+		let (chunk_size, rest) = rest.split_first_chunk()?;
+		let (nonce, rest) = rest.split_first_chunk()?;
+		let (wrapped, _) = rest.split_first_chunk()?;
+
+		Some(Self {
+			key_id: *key_id,
+			chunk_size: u32::from_be_bytes(*chunk_size),
+			nonce: *nonce,
+			wrapped: *wrapped,
+		})
+		.filter(|header| header.chunk_size > 0)
+	}
+
+	fn prefix(&self) -> Vec<u8> {
+		[MAGIC.as_slice(), &self.key_id, &self.chunk_size.to_be_bytes()].concat()
+	}
+
+	fn to_bytes(&self) -> Vec<u8> {
+		[self.prefix().as_slice(), &self.nonce, &self.wrapped].concat()
+	}
+
+	/// Length of the content and number of chunks of a file of `size` bytes
+	fn layout(&self, size: u64) -> Result<(u64, u64)> {
+		let body = size
+			.checked_sub(u64_len(HEADER_LEN))
+			.filter(|body| *body >= u64_len(TAG_LEN))
+			.ok_or_else(|| err!(Database("Encrypted media file is truncated.")))?;
+
+		let sealed_chunk = u64::from(self.chunk_size).saturating_add(u64_len(TAG_LEN));
+		let count = body.div_ceil(sealed_chunk);
+
+		Ok((body.saturating_sub(count.saturating_mul(u64_len(TAG_LEN))), count))
+	}
+}
+
+#[async_trait]
+impl MediaStorage for EncryptedStorage {
+	async fn create(&self, key: &[u8], data: &[u8]) -> Result<()> {
+		let sealed = self.encrypt(key, data)?;
+		self.inner.create(key, &sealed).await
+	}
+
+	async fn replace(&self, key: &[u8], data: &[u8]) -> Result<()> {
+		let sealed = self.encrypt(key, data)?;
+		self.inner.replace(key, &sealed).await
+	}
+
+	async fn read(&self, key: &[u8]) -> Result<Option<Bytes>> {
+		let Some(sealed) = self.inner.read(key).await? else {
+			return Ok(None);
+		};
+
+		let Some(header) = Header::parse(&sealed) else {
+			return self.plaintext(sealed).map(Some);
+		};
+
+		let (_, count) = header.layout(u64_len(sealed.len()))?;
+		let chunks = sealed.get(HEADER_LEN..).unwrap_or_default();
+		let data = self.decrypt(key, &header, 0, count, chunks)?;
+
+		Ok(Some(Bytes::from(data)))
+	}
+
+	async fn read_range(&self, key: &[u8], range: Range<u64>) -> Result<Option<Bytes>> {
+		let Some(header) = self.read_header(key).await? else {
+			return Ok(None);
+		};
+
+		let Some(header) = header else {
+			self.plaintext(())?;
+			return self.inner.read_range(key, range).await;
+		};
+
+		let Some(meta) = self.inner.metadata(key).await? else {
+			return Ok(None);
+		};
+
+		let (len, count) = header.layout(meta.size)?;
+		let start = range.start.min(len);
+		let end = range.end.clamp(start, len);
+		if start == end {
+			return Ok(Some(Bytes::new()));
+		}
+
+		// Read only the chunks the range falls into.
+		let chunk_size = u64::from(header.chunk_size);
+		let sealed_chunk = chunk_size.saturating_add(u64_len(TAG_LEN));
+		let first = start.checked_div(chunk_size).unwrap_or_default();
+		let last = end
+			.saturating_sub(1)
+			.checked_div(chunk_size)
+			.unwrap_or_default();
+		let sealed_start = first
+			.saturating_mul(sealed_chunk)
+			.saturating_add(u64_len(HEADER_LEN));
+		let sealed_end = last
+			.saturating_add(1)
+			.saturating_mul(sealed_chunk)
+			.saturating_add(u64_len(HEADER_LEN))
+			.min(meta.size);
+
+		let Some(sealed) = self
+			.inner
+			.read_range(key, sealed_start..sealed_end)
+			.await?
+		else {
+			return Ok(None);
+		};
+
+		let data = Bytes::from(self.decrypt(key, &header, first, count, &sealed)?);
+		let offset = usize::try_from(start.saturating_sub(first.saturating_mul(chunk_size)))?;
+		let offset = offset.min(data.len());
+		let end = offset
+			.saturating_add(usize::try_from(end.saturating_sub(start))?)
+			.min(data.len());
+
+		Ok(Some(data.slice(offset..end)))
+	}
+
+	async fn delete(&self, key: &[u8]) -> Result<()> { self.inner.delete(key).await }
+
+	async fn exists(&self, key: &[u8]) -> Result<bool> { self.inner.exists(key).await }
+
+	async fn metadata(&self, key: &[u8]) -> Result<Option<StorageMetadata>> {
+		let Some(mut meta) = self.inner.metadata(key).await? else {
+			return Ok(None);
+		};
+
+		// Report the size of the content rather than of the encrypted file.
+		if let Some(Some(header)) = self.read_header(key).await? {
+			(meta.size, _) = header.layout(meta.size)?;
+		}
+
+		Ok(Some(meta))
+	}
+
+	async fn list_keys(&self) -> Result<Vec<Vec<u8>>> { self.inner.list_keys().await }
+
+	async fn rewrap(&self, key: &[u8]) -> Result<bool> {
+		let Some(header) = self.read_header(key).await? else {
+			return Ok(false);
+		};
+
+		if header
+			.as_ref()
+			.is_some_and(|header| header.key_id == self.current.id)
+		{
+			return Ok(false);
+		}
+
+		let Some(sealed) = self.inner.read(key).await? else {
+			return Ok(false);
+		};
+
+		// The file is replaced rather than written over, so neither a crash nor a
+		// concurrent read sees it half-written.
+		let Some(header) = Header::parse(&sealed) else {
+			// Stored before encryption was enabled
+			self.replace(key, &sealed).await?;
+			return Ok(true);
+		};
+
+		// Only the data key is wrapped anew; the content is kept as is.
+		let data_key = self.unwrap(&header)?;
+		let header = self.wrap(&data_key, header.chunk_size)?;
+		let chunks = sealed.get(HEADER_LEN..).unwrap_or_default();
+		let rewrapped = [header.to_bytes().as_slice(), chunks].concat();
+		self.inner.replace(key, &rewrapped).await?;
+
+		Ok(true)
+	}
+}
+
+fn data_cipher(data_key: &[u8; KEY_LEN]) -> Result<LessSafeKey> {
+	UnboundKey::new(&AES_256_GCM, data_key)
+		.map(LessSafeKey::new)
+		.map_err(|_| err!(Database("Media data key is invalid.")))
+}
+
+/// Chunks are encrypted under their own data key, so the index of a chunk is
+/// a unique nonce.
+fn chunk_nonce(index: u64) -> Nonce {
+	let mut nonce = [0_u8; NONCE_LEN];
+	if let Some(counter) = nonce.last_chunk_mut() {
+		*counter = index.to_be_bytes();
+	}
+
+	Nonce::assume_unique_for_key(nonce)
+}
+
+fn chunk_aad(key: &[u8], index: u64, last: bool) -> Aad<Vec<u8>> {
+	Aad::from([key, &index.to_be_bytes(), &[u8::from(last)]].concat())
+}
+
+fn u64_len(len: usize) -> u64 { u64::try_from(len).expect("usize fits in u64") }
+
+#[cfg(test)]
+mod tests {
+	use std::path::PathBuf;
+
+	use super::{super::filesystem::FilesystemStorage, *};
+
+	const KEY: &[u8] = b"content-hash";
+
+	fn master_key(dir: &Path, name: &str, secret: u8) -> PathBuf {
+		let path = dir.join(name);
+		std::fs::write(&path, STANDARD.encode([secret; KEY_LEN])).unwrap();
+		path
+	}
+
+	fn storage(
+		inner: &Arc<FilesystemStorage>,
+		key_file: PathBuf,
+		previous_key_files: Vec<PathBuf>,
+	) -> EncryptedStorage {
+		let config = MediaEncryptionConfig {
+			key_file: Some(key_file),
+			key_env: None,
+			previous_key_files,
+			previous_key_envs: Vec::new(),
+			chunk_size: 4,
+			rewrap_on_startup: true,
+			refuse_plaintext: false,
+		};
+
+		EncryptedStorage::new(inner.clone(), &config).unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_encrypted_create_read() {
+		let files: [&[u8]; 4] = [b"", b"abc", b"abcd", b"0123456789"];
+		let temp_dir = tempfile::tempdir().unwrap();
+		let inner = Arc::new(FilesystemStorage::new(temp_dir.path().join("media")).unwrap());
+		let storage = storage(&inner, master_key(temp_dir.path(), "key", 1), Vec::new());
+
+		for data in files {
+			storage.create(KEY, data).await.unwrap();
+
+			let sealed = inner.read(KEY).await.unwrap().unwrap();
+			assert!(sealed.starts_with(MAGIC), "file is not encrypted");
+			assert!(!sealed.windows(3).any(|window| window == b"012"), "file contains plaintext");
+
+			let read_data = storage.read(KEY).await.unwrap().unwrap();
+			assert_eq!(read_data.as_ref(), data);
+
+			let meta = storage.metadata(KEY).await.unwrap().unwrap();
+			assert_eq!(meta.size, u64_len(data.len()));
+		}
+	}
+
+	#[tokio::test]
+	async fn test_encrypted_read_range() {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let inner = Arc::new(FilesystemStorage::new(temp_dir.path().join("media")).unwrap());
+		let storage = storage(&inner, master_key(temp_dir.path(), "key", 1), Vec::new());
+
+		storage.create(KEY, b"0123456789").await.unwrap();
+
+		let read = |range| storage.read_range(KEY, range);
+		assert_eq!(read(0..2).await.unwrap().unwrap().as_ref(), b"01");
+		assert_eq!(read(3..9).await.unwrap().unwrap().as_ref(), b"345678");
+		assert_eq!(read(8..20).await.unwrap().unwrap().as_ref(), b"89");
+		assert!(read(10..20).await.unwrap().unwrap().is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_encrypted_plaintext_and_rewrap() {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let inner = Arc::new(FilesystemStorage::new(temp_dir.path().join("media")).unwrap());
+		let old_key = master_key(temp_dir.path(), "old", 1);
+		let new_key = master_key(temp_dir.path(), "new", 2);
+
+		// Stored before encryption was enabled
+		inner
+			.create(b"plain", b"plaintext")
+			.await
+			.unwrap();
+
+		let old = storage(&inner, old_key.clone(), Vec::new());
+		old.create(KEY, b"0123456789").await.unwrap();
+		assert_eq!(
+			old.read(b"plain")
+				.await
+				.unwrap()
+				.unwrap()
+				.as_ref(),
+			b"plaintext"
+		);
+
+		let new = storage(&inner, new_key.clone(), vec![old_key]);
+		assert_eq!(new.read(KEY).await.unwrap().unwrap().as_ref(), b"0123456789");
+
+		assert!(new.rewrap(KEY).await.unwrap());
+		assert!(new.rewrap(b"plain").await.unwrap());
+		assert!(!new.rewrap(KEY).await.unwrap());
+		assert!(!new.rewrap(b"missing").await.unwrap());
+
+		// The previous key is no longer needed.
+		let rotated = storage(&inner, new_key, Vec::new());
+		assert_eq!(rotated.read(KEY).await.unwrap().unwrap().as_ref(), b"0123456789");
+		assert_eq!(
+			rotated
+				.read(b"plain")
+				.await
+				.unwrap()
+				.unwrap()
+				.as_ref(),
+			b"plaintext"
+		);
+		assert!(
+			inner
+				.read(b"plain")
+				.await
+				.unwrap()
+				.unwrap()
+				.starts_with(MAGIC)
+		);
+	}
+
+	#[tokio::test]
+	async fn test_encrypted_refuse_plaintext() {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let inner = Arc::new(FilesystemStorage::new(temp_dir.path().join("media")).unwrap());
+		let mut storage = storage(&inner, master_key(temp_dir.path(), "key", 1), Vec::new());
+		storage.refuse_plaintext = true;
+
+		inner
+			.create(b"plain", b"plaintext")
+			.await
+			.unwrap();
+		assert!(storage.read(b"plain").await.is_err());
+		assert!(storage.read_range(b"plain", 0..5).await.is_err());
+
+		// Re-wrapping still encrypts it.
+		assert!(storage.rewrap(b"plain").await.unwrap());
+		assert_eq!(
+			storage
+				.read(b"plain")
+				.await
+				.unwrap()
+				.unwrap()
+				.as_ref(),
+			b"plaintext"
+		);
+		assert!(storage.read(b"missing").await.unwrap().is_none());
+	}
+
+	#[tokio::test]
+	async fn test_encrypted_tampering() {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let inner = Arc::new(FilesystemStorage::new(temp_dir.path().join("media")).unwrap());
+		let storage = storage(&inner, master_key(temp_dir.path(), "key", 1), Vec::new());
+
+		storage.create(KEY, b"01234567").await.unwrap();
+		let sealed = inner.read(KEY).await.unwrap().unwrap();
+
+		// Moved to another key
+		inner.create(b"other", &sealed).await.unwrap();
+		assert!(storage.read(b"other").await.is_err());
+
+		// Truncated by the final chunk
+		let truncated = sealed.slice(..sealed.len().saturating_sub(4 + TAG_LEN));
+		inner.create(KEY, &truncated).await.unwrap();
+		assert!(storage.read(KEY).await.is_err());
+
+		// Unknown master key
+		inner.create(KEY, &sealed).await.unwrap();
+		let other = self::storage(&inner, master_key(temp_dir.path(), "other", 2), Vec::new());
+		assert!(other.read(KEY).await.is_err());
+	}
+}
@@ -2,11 +2,14 @@
 ///
 /// Stores media files on the local filesystem using a hash-based directory structure.
 
-use std::path::PathBuf;
+use std::{io::SeekFrom, ops::Range, path::PathBuf};
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use tokio::{fs, io::AsyncWriteExt};
+use tokio::{
+	fs,
+	io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
 
 use super::{MediaStorage, StorageMetadata};
 use tuwunel_core::Result;
@@ -58,6 +61,30 @@ async fn create(&self, key: &[u8], data: &[u8]) -> Result<()> {
 		Ok(())
 	}
 
+	async fn replace(&self, key: &[u8], data: &[u8]) -> Result<()> {
+		let path = self.get_path(key);
+		let mut temp = path.clone().into_os_string();
+		temp.push(".tmp");
+		let temp = PathBuf::from(temp);
+
+		// Write the new content aside, then rename it over the file
+		let written = async {
+			let mut file = fs::File::create(&temp).await?;
+			file.write_all(data).await?;
+			file.sync_all().await
+		}
+		.await;
+
+		if let Err(e) = written {
+			fs::remove_file(&temp).await.ok();
+			return Err(e.into());
+		}
+
+		fs::rename(&temp, &path).await?;
+
+		Ok(())
+	}
+
 	async fn read(&self, key: &[u8]) -> Result<Option<Bytes>> {
 		let path = self.get_path(key);
 
@@ -68,6 +95,25 @@ async fn read(&self, key: &[u8]) -> Result<Option<Bytes>> {
 		}
 	}
 
+	async fn read_range(&self, key: &[u8], range: Range<u64>) -> Result<Option<Bytes>> {
+		let path = self.get_path(key);
+
+		let mut file = match fs::File::open(&path).await {
+			Ok(file) => file,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+			Err(e) => return Err(e.into()),
+		};
+
+		// Reads stop at the end of the file
+		let mut data = Vec::new();
+		file.seek(SeekFrom::Start(range.start)).await?;
+		file.take(range.end.saturating_sub(range.start))
+			.read_to_end(&mut data)
+			.await?;
+
+		Ok(Some(Bytes::from(data)))
+	}
+
 	async fn delete(&self, key: &[u8]) -> Result<()> {
 		let path = self.get_path(key);
 
@@ -143,6 +189,37 @@ async fn test_filesystem_create_read_delete() {
 		assert!(storage.read(key).await.unwrap().is_none());
 	}
 
+	#[tokio::test]
+	async fn test_filesystem_read_range() {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let storage = FilesystemStorage::new(temp_dir.path().to_path_buf()).unwrap();
+
+		let key = b"test-key";
+		storage.create(key, b"0123456789").await.unwrap();
+
+		let read = |range| storage.read_range(key, range);
+		assert_eq!(read(2..5).await.unwrap().unwrap().as_ref(), b"234");
+		assert_eq!(read(8..20).await.unwrap().unwrap().as_ref(), b"89");
+		assert!(read(12..20).await.unwrap().unwrap().is_empty());
+		assert!(storage.read_range(b"missing", 0..1).await.unwrap().is_none());
+	}
+
+	#[tokio::test]
+	async fn test_filesystem_replace() {
+		let temp_dir = tempfile::tempdir().unwrap();
+		let storage = FilesystemStorage::new(temp_dir.path().to_path_buf()).unwrap();
+
+		let key = b"test-key";
+		storage.create(key, b"previous-data").await.unwrap();
+		storage.replace(key, b"new").await.unwrap();
+
+		assert_eq!(storage.read(key).await.unwrap().unwrap().as_ref(), b"new");
+
+		// Only the replaced file is left
+		let files = std::fs::read_dir(temp_dir.path()).unwrap().count();
+		assert_eq!(files, 1);
+	}
+
 	#[test]
 	fn test_encode_key() {
 		let key = b"hello world";
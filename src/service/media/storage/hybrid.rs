@@ -3,7 +3,7 @@
 /// Combines two storage backends (primary and secondary) with configurable behavior.
 
 #[cfg(feature = "s3_storage")]
-use std::{sync::Arc, time::{Duration, SystemTime}};
+use std::{ops::Range, sync::Arc, time::{Duration, SystemTime}};
 
 #[cfg(feature = "s3_storage")]
 use async_trait::async_trait;
@@ -92,6 +92,17 @@ async fn create(&self, key: &[u8], data: &[u8]) -> Result<()> {
 		Ok(())
 	}
 
+	async fn replace(&self, key: &[u8], data: &[u8]) -> Result<()> {
+		self.primary.replace(key, data).await?;
+
+		// Drop the copy of secondary storage, fetched again from primary on read
+		if let Err(e) = self.secondary.delete(key).await {
+			warn!("Failed to delete from secondary storage: {}", e);
+		}
+
+		Ok(())
+	}
+
 	async fn read(&self, key: &[u8]) -> Result<Option<Bytes>> {
 		// Try reading from secondary (cache) first
 		match self.secondary.read(key).await? {
@@ -138,6 +149,23 @@ async fn read(&self, key: &[u8]) -> Result<Option<Bytes>> {
 		Ok(None)
 	}
 
+	async fn read_range(&self, key: &[u8], range: Range<u64>) -> Result<Option<Bytes>> {
+		// Read from secondary (cache) unless expired; ranges are not cached
+		if self.secondary.exists(key).await.unwrap_or(false)
+			&& !self.is_cache_expired(key).await?
+		{
+			if let Some(data) = self.secondary.read_range(key, range.clone()).await? {
+				return Ok(Some(data));
+			}
+		}
+
+		if self.config.read_fallback {
+			return self.primary.read_range(key, range).await;
+		}
+
+		Ok(None)
+	}
+
 	async fn delete(&self, key: &[u8]) -> Result<()> {
 		// Delete from both storages
 		// We don't fail if one fails, just log a warning
@@ -12,7 +12,9 @@
 #[cfg(feature = "s3_storage")]
 pub mod hybrid;
 
-use std::time::SystemTime;
+pub mod encrypted;
+
+use std::{ops::Range, time::SystemTime};
 
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -36,6 +38,23 @@ pub trait MediaStorage: Send + Sync {
 	/// * `Err` if upload fails
 	async fn create(&self, key: &[u8], data: &[u8]) -> Result<()>;
 
+	/// Replace the content of an existing file
+	///
+	/// Readers see either the previous or the new content, and a failure
+	/// leaves the previous content in place. Backends whose writes replace
+	/// files atomically keep the default, which creates the file anew.
+	///
+	/// # Arguments
+	/// * `key` - Unique identifier for the file
+	/// * `data` - New file content as bytes
+	///
+	/// # Returns
+	/// * `Ok(())` if successful
+	/// * `Err` if the replacement fails
+	async fn replace(&self, key: &[u8], data: &[u8]) -> Result<()> {
+		self.create(key, data).await
+	}
+
 	/// Read a file
 	///
 	/// # Arguments
@@ -47,6 +66,31 @@ pub trait MediaStorage: Send + Sync {
 	/// * `Err` if read fails
 	async fn read(&self, key: &[u8]) -> Result<Option<Bytes>>;
 
+	/// Read a byte range of a file
+	///
+	/// The range is clamped to the size of the file. Backends able to read
+	/// part of a file override the default, which reads the whole file.
+	///
+	/// # Arguments
+	/// * `key` - Unique identifier for the file
+	/// * `range` - Offsets of the first and past the last byte to read
+	///
+	/// # Returns
+	/// * `Ok(Some(bytes))` if file exists
+	/// * `Ok(None)` if file not found
+	/// * `Err` if read fails
+	async fn read_range(&self, key: &[u8], range: Range<u64>) -> Result<Option<Bytes>> {
+		let Some(data) = self.read(key).await? else {
+			return Ok(None);
+		};
+
+		let len = data.len();
+		let start = usize::try_from(range.start).unwrap_or(len).min(len);
+		let end = usize::try_from(range.end).unwrap_or(len).clamp(start, len);
+
+		Ok(Some(data.slice(start..end)))
+	}
+
 	/// Delete a file
 	///
 	/// # Arguments
@@ -88,6 +132,19 @@ pub trait MediaStorage: Send + Sync {
 	/// * `Ok(Vec<Vec<u8>>)` - List of all keys
 	/// * `Err` if listing fails
 	async fn list_keys(&self) -> Result<Vec<Vec<u8>>>;
+
+	/// Re-wrap a file by the current master key
+	///
+	/// Backends without encryption at rest have nothing to do.
+	///
+	/// # Arguments
+	/// * `key` - Unique identifier for the file
+	///
+	/// # Returns
+	/// * `Ok(true)` if the file was rewritten
+	/// * `Ok(false)` if the file was already current or not found
+	/// * `Err` if re-wrapping fails
+	async fn rewrap(&self, _key: &[u8]) -> Result<bool> { Ok(false) }
 }
 
 /// Metadata about a stored file
@@ -3,7 +3,7 @@
 /// Stores media files on S3-compatible object storage.
 
 #[cfg(feature = "s3_storage")]
-use std::time::SystemTime;
+use std::{ops::Range, time::SystemTime};
 
 #[cfg(feature = "s3_storage")]
 use async_trait::async_trait;
@@ -161,6 +161,44 @@ async fn read(&self, key: &[u8]) -> Result<Option<Bytes>> {
 		}
 	}
 
+	async fn read_range(&self, key: &[u8], range: Range<u64>) -> Result<Option<Bytes>> {
+		if range.is_empty() {
+			return Ok(self.exists(key).await?.then(Bytes::new));
+		}
+
+		let s3_key = self.get_s3_key(key);
+
+		match self
+			.client
+			.get_object()
+			.bucket(&self.bucket)
+			.key(&s3_key)
+			.range(format!("bytes={}-{}", range.start, range.end.saturating_sub(1)))
+			.send()
+			.await
+		{
+			Ok(output) => {
+				let bytes = output
+					.body
+					.collect()
+					.await
+					.map_err(|e| err!(Database(error!("S3 body read failed: {}", e))))?
+					.into_bytes();
+				Ok(Some(bytes))
+			},
+			Err(e) => {
+				if is_not_found_error(&e) {
+					Ok(None)
+				} else if is_range_not_satisfiable_error(&e) {
+					// The range starts past the end of the object
+					Ok(Some(Bytes::new()))
+				} else {
+					Err(err!(Database(error!("S3 get_object failed: {}", e))))
+				}
+			},
+		}
+	}
+
 	async fn delete(&self, key: &[u8]) -> Result<()> {
 		let s3_key = self.get_s3_key(key);
 
@@ -243,6 +281,17 @@ fn is_not_found_error<E>(err: &aws_sdk_s3::error::SdkError<E>) -> bool
 	matches!(err, SdkError::ServiceError(e) if e.raw().status().as_u16() == 404)
 }
 
+/// Check if an S3 error is a "Range Not Satisfiable" error
+#[cfg(feature = "s3_storage")]
+fn is_range_not_satisfiable_error<E>(err: &aws_sdk_s3::error::SdkError<E>) -> bool
+where
+	E: std::error::Error + 'static,
+{
+	use aws_sdk_s3::error::SdkError;
+
+	matches!(err, SdkError::ServiceError(e) if e.raw().status().as_u16() == 416)
+}
+
 /// Encode a key (hash digest) to a string for use as S3 key
 #[cfg(feature = "s3_storage")]
 fn encode_key(key: &[u8]) -> String {
@@ -103,3 +103,258 @@ fn get_url_preview(&self, _url: &str) -> Option<UrlPreviewData> { todo!() }
 		r.to_str().unwrap().len()
 	);
 }
+
+#[test]
+fn sha256_hex_roundtrip() {
+	use super::{parse_sha256, sha256_hex};
+
+	let hash: [u8; 32] = std::array::from_fn(|i| u8::try_from(i).unwrap().wrapping_mul(17));
+	let hex = sha256_hex(&hash);
+
+	assert_eq!(hex.len(), 64);
+	assert_eq!(parse_sha256(&hex).unwrap(), hash);
+	assert!(parse_sha256("abc").is_err());
+	assert!(parse_sha256(&"zz".repeat(32)).is_err());
+}
+
+#[test]
+fn event_mxcs_of_inline_images_and_packs() {
+	use serde_json::json;
+
+	use super::quarantine::event_mxcs;
+
+	let message = json!({
+		"body": "look",
+		"formatted_body": "<p><img src=\"mxc://example.org/inline\" alt=\"a\"> and \
+			<img src='mxc://example.org/single'></p>",
+		"m.new_content": {
+			"formatted_body": "<img src=\"mxc://example.org/edited\">",
+		},
+	});
+
+	let pack = json!({
+		"pack": { "avatar_url": "mxc://example.org/avatar" },
+		"images": {
+			"smile": { "url": "mxc://example.org/smile" },
+			"wave": { "url": "mxc://example.org/wave", "usage": ["sticker"] },
+		},
+	});
+
+	let mut mxcs: Vec<_> = event_mxcs(&message)
+		.into_iter()
+		.chain(event_mxcs(&pack))
+		.map(|mxc| mxc.to_string())
+		.collect();
+
+	mxcs.sort_unstable();
+	assert_eq!(mxcs, [
+		"mxc://example.org/avatar",
+		"mxc://example.org/edited",
+		"mxc://example.org/inline",
+		"mxc://example.org/single",
+		"mxc://example.org/smile",
+		"mxc://example.org/wave",
+	]);
+}
+
+/// Services on a new database in a temporary directory, with the media storage
+/// initialized.
+async fn services() -> (tempfile::TempDir, std::sync::Arc<crate::Services>) {
+	use std::{iter, sync::Arc};
+
+	use tuwunel_core::{
+		Config, Server,
+		log::{LogLevelReloadHandles, Logging, capture},
+		tracing::subscriber::NoSubscriber,
+	};
+
+	let dir = tempfile::tempdir().unwrap();
+	let database_path = dir.path().join("database");
+	std::fs::create_dir(&database_path).unwrap();
+
+	let config_file = dir.path().join("tuwunel.toml");
+	std::fs::write(
+		&config_file,
+		format!("[global]\nserver_name = \"example.org\"\ndatabase_path = {database_path:?}\n"),
+	)
+	.unwrap();
+
+	let config = Config::load(iter::once(config_file.as_path()))
+		.and_then(|raw| Config::new(&raw))
+		.unwrap();
+
+	let logging = Logging {
+		subscriber: Arc::new(NoSubscriber::default()),
+		reload: LogLevelReloadHandles::default(),
+		capture: Arc::new(capture::State::new()),
+	};
+
+	let runtime = tokio::runtime::Handle::current();
+	let server = Arc::new(Server::new(config, Some(runtime), logging));
+	let services = crate::Services::build(server).await.unwrap();
+	services.media.init_storage().await.unwrap();
+	services.media.create_media_dir().await.unwrap();
+
+	(dir, services)
+}
+
+fn is_not_found(result: &tuwunel_core::Result<impl std::fmt::Debug>) -> bool {
+	use ruma::api::client::error::ErrorKind::NotFound;
+	use tuwunel_core::Error;
+
+	matches!(result, Err(Error::Request(NotFound, ..)))
+}
+
+fn is_forbidden(result: &tuwunel_core::Result<impl std::fmt::Debug>) -> bool {
+	use ruma::api::client::error::ErrorKind::Forbidden;
+	use tuwunel_core::Error;
+
+	matches!(result, Err(Error::Request(Forbidden { .. }, ..)))
+}
+
+#[tokio::test]
+async fn quarantine_hides_local_media() {
+	use ruma::Mxc;
+
+	use super::thumbnail::Dim;
+
+	let (_dir, services) = services().await;
+	let media = &services.media;
+	let mxc: Mxc<'_> = "mxc://example.org/quarantined"
+		.try_into()
+		.unwrap();
+	let dim = Dim::new(32, 32, None).normalized();
+
+	media
+		.create(&mxc, None, None, Some("text/plain"), b"original")
+		.await
+		.unwrap();
+	media
+		.upload_thumbnail(&mxc, None, None, Some("text/plain"), &dim, b"thumbnail")
+		.await
+		.unwrap();
+
+	assert!(media.get(&mxc).await.unwrap().is_some());
+	assert!(
+		media
+			.get_thumbnail(&mxc, &dim)
+			.await
+			.unwrap()
+			.is_some()
+	);
+	assert!(media.get_metadata(&mxc).await.is_some());
+
+	media
+		.quarantine(&mxc, None, Some("spam"), false)
+		.await
+		.unwrap();
+
+	assert!(media.get(&mxc).await.unwrap().is_none());
+	assert!(
+		media
+			.get_thumbnail(&mxc, &dim)
+			.await
+			.unwrap()
+			.is_none()
+	);
+	assert!(media.get_metadata(&mxc).await.is_none());
+
+	media.release(&mxc).await.unwrap();
+	let file = media.get(&mxc).await.unwrap().unwrap();
+	assert_eq!(file.content.as_deref(), Some(b"original".as_slice()));
+}
+
+#[tokio::test]
+async fn quarantine_hides_remote_media() {
+	use std::time::Duration;
+
+	use ruma::Mxc;
+
+	use super::thumbnail::Dim;
+
+	let (_dir, services) = services().await;
+	let media = &services.media;
+	let mxc: Mxc<'_> = "mxc://remote.example/quarantined"
+		.try_into()
+		.unwrap();
+
+	media
+		.quarantine(&mxc, None, None, false)
+		.await
+		.unwrap();
+
+	// Refused before contacting the remote server
+	let timeout = Duration::from_millis(1);
+	let content = media
+		.fetch_remote_content(&mxc, None, None, timeout)
+		.await;
+	assert!(is_not_found(&content));
+
+	let dim = Dim::new(32, 32, None);
+	let thumbnail = media
+		.fetch_remote_thumbnail(&mxc, None, None, timeout, &dim)
+		.await;
+	assert!(is_not_found(&thumbnail));
+}
+
+#[tokio::test]
+async fn blocked_content_refused_on_upload() {
+	use ruma::Mxc;
+
+	let (_dir, services) = services().await;
+	let media = &services.media;
+	let original: Mxc<'_> = "mxc://example.org/original".try_into().unwrap();
+	let reupload: Mxc<'_> = "mxc://example.org/reupload".try_into().unwrap();
+
+	media
+		.create(&original, None, None, None, b"blocked")
+		.await
+		.unwrap();
+	media
+		.quarantine(&original, None, None, true)
+		.await
+		.unwrap();
+
+	let result = media
+		.create(&reupload, None, None, None, b"blocked")
+		.await;
+	assert!(is_forbidden(&result));
+	assert!(media.get(&reupload).await.unwrap().is_none());
+
+	media
+		.create(&reupload, None, None, None, b"allowed")
+		.await
+		.unwrap();
+}
+
+#[tokio::test]
+async fn release_keeps_hash_blocked_by_other_media() {
+	use ruma::Mxc;
+	use sha2::Digest;
+
+	let (_dir, services) = services().await;
+	let media = &services.media;
+	let first: Mxc<'_> = "mxc://example.org/first".try_into().unwrap();
+	let second: Mxc<'_> = "mxc://example.org/second".try_into().unwrap();
+	let hash: [u8; 32] = sha2::Sha256::digest(b"shared").into();
+
+	for mxc in [&first, &second] {
+		media
+			.create(mxc, None, None, None, b"shared")
+			.await
+			.unwrap();
+		media
+			.quarantine(mxc, None, None, true)
+			.await
+			.unwrap();
+	}
+
+	media.release(&first).await.unwrap();
+	assert!(media.is_blocked_sha256(&hash).await);
+	assert!(media.get(&first).await.unwrap().is_some());
+	assert!(media.get(&second).await.unwrap().is_none());
+
+	media.release(&second).await.unwrap();
+	assert!(!media.is_blocked_sha256(&hash).await);
+	assert!(media.get(&second).await.unwrap().is_some());
+}
@@ -0,0 +1,180 @@
+use std::io::Cursor;
+
+use image::{
+	AnimationDecoder, DynamicImage, Frame, Frames, ImageDecoder, ImageError, ImageResult,
+	codecs::{
+		gif::{GifDecoder, GifEncoder, Repeat},
+		webp::WebPDecoder,
+	},
+	imageops::FilterType,
+};
+use tuwunel_core::{Error, Result, err};
+
+use super::Dim;
+
+/// Animations with more frames are thumbnailed statically.
+const MAX_FRAMES: usize = 300;
+
+/// Animations of which the decoded frames hold more pixels in total are
+/// thumbnailed statically; frames are decoded as RGBA, so 256 MiB.
+const MAX_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// Generates an animated GIF thumbnail of an animated GIF or WebP image.
+/// Returns None for images which are not animated or have too many frames,
+/// which are thumbnailed statically instead; as are images smaller than the
+/// requested dimensions, which are sent as they are.
+pub(super) fn thumbnail(
+	content: &[u8],
+	content_type: &str,
+	dim: &Dim,
+) -> Result<Option<Vec<u8>>> {
+	let Some(((width, height), frames)) = frames(content, content_type)? else {
+		return Ok(None);
+	};
+
+	if dim.width > width || dim.height > height {
+		return Ok(None);
+	}
+
+	// Bound the memory of the decoded frames before decoding them.
+	let max_frames = max_frames(width, height);
+	let frames: Vec<Frame> = frames
+		.take(max_frames.saturating_add(1))
+		.collect::<ImageResult<_>>()
+		.map_err(image_error)?;
+
+	if frames.len() < 2 || frames.len() > max_frames {
+		return Ok(None);
+	}
+
+	let size = if dim.crop() {
+		Dim::new(dim.width, dim.height, None)
+	} else {
+		dim.scaled(&Dim::new(width, height, None))?
+	};
+
+	let frames = frames.into_iter().map(|frame| {
+		let delay = frame.delay();
+		let image = DynamicImage::ImageRgba8(frame.into_buffer());
+		let image = if dim.crop() {
+			image.resize_to_fill(size.width, size.height, FilterType::CatmullRom)
+		} else {
+			image.thumbnail_exact(size.width, size.height)
+		};
+
+		Frame::from_parts(image.into_rgba8(), 0, 0, delay)
+	});
+
+	let mut out = Vec::new();
+	{
+		let mut encoder = GifEncoder::new(&mut out);
+		encoder
+			.set_repeat(Repeat::Infinite)
+			.map_err(image_error)?;
+		encoder
+			.encode_frames(frames)
+			.map_err(image_error)?;
+	}
+
+	Ok(Some(out))
+}
+
+/// The most frames of the dimensions decoded.
+fn max_frames(width: u32, height: u32) -> usize {
+	let frame_pixels = u64::from(width).saturating_mul(u64::from(height));
+
+	MAX_PIXELS
+		.checked_div(frame_pixels)
+		.and_then(|max_frames| usize::try_from(max_frames).ok())
+		.unwrap_or(MAX_FRAMES)
+		.min(MAX_FRAMES)
+}
+
+/// The dimensions and frames of an animation, which are decoded as they are
+/// iterated.
+fn frames<'a>(content: &'a [u8], content_type: &str) -> Result<Option<((u32, u32), Frames<'a>)>> {
+	let frames = match content_type {
+		| "image/gif" => {
+			let decoder = GifDecoder::new(Cursor::new(content)).map_err(image_error)?;
+			(decoder.dimensions(), decoder.into_frames())
+		},
+		| "image/webp" => {
+			let decoder = WebPDecoder::new(Cursor::new(content)).map_err(image_error)?;
+			if !decoder.has_animation() {
+				return Ok(None);
+			}
+
+			(decoder.dimensions(), decoder.into_frames())
+		},
+		| _ => return Ok(None),
+	};
+
+	Ok(Some(frames))
+}
+
+fn image_error(error: ImageError) -> Error { err!("{error}") }
+
+#[cfg(test)]
+mod tests {
+	use std::{io::Cursor, time::Duration};
+
+	use image::{
+		AnimationDecoder, Delay, Frame, RgbaImage,
+		codecs::gif::{GifDecoder, GifEncoder},
+	};
+
+	use super::{Dim, MAX_FRAMES, max_frames, thumbnail};
+
+	fn gif(width: u32, height: u32, frames: u32) -> Vec<u8> {
+		let mut out = Vec::new();
+		{
+			let mut encoder = GifEncoder::new(&mut out);
+			encoder
+				.encode_frames((0..frames).map(|i| {
+					let pixel = image::Rgba([u8::try_from(i).unwrap(), 0, 0, 255]);
+					let delay = Delay::from_saturating_duration(Duration::from_millis(100));
+
+					Frame::from_parts(RgbaImage::from_pixel(width, height, pixel), 0, 0, delay)
+				}))
+				.unwrap();
+		}
+
+		out
+	}
+
+	#[test]
+	fn keeps_animation() {
+		let dim = Dim::new(320, 240, None);
+		let out = thumbnail(&gif(640, 480, 3), "image/gif", &dim)
+			.unwrap()
+			.unwrap();
+
+		let frames = GifDecoder::new(Cursor::new(out))
+			.unwrap()
+			.into_frames()
+			.collect_frames()
+			.unwrap();
+
+		assert_eq!(frames.len(), 3);
+		assert_eq!(frames[0].buffer().dimensions(), (320, 240));
+	}
+
+	#[test]
+	fn frames_bounded_by_pixels() {
+		assert_eq!(max_frames(320, 240), MAX_FRAMES);
+		assert_eq!(max_frames(4096, 4096), 4);
+		assert_eq!(max_frames(65535, 65535), 0);
+		assert_eq!(max_frames(0, 0), MAX_FRAMES);
+	}
+
+	#[test]
+	fn static_image() {
+		let dim = Dim::new(32, 32, None);
+
+		assert!(
+			thumbnail(&gif(64, 64, 1), "image/gif", &dim)
+				.unwrap()
+				.is_none()
+		);
+	}
+}
@@ -4,6 +4,14 @@
 //! for historical and simplicity reasons. Instead the feature gates the
 //! inclusion of dependencies and nulls out results using the existing interface
 //! when not featured.
+//!
+//! Animated thumbnails and thumbnails of PDF documents and videos are gated by
+//! further features extending it.
+
+#[cfg(feature = "media_thumbnail_animated")]
+mod animated;
+#[cfg(feature = "media_thumbnail")]
+mod render;
 
 use std::{cmp, num::Saturating as Sat};
 
@@ -19,8 +27,16 @@ pub struct Dim {
 	pub width: u32,
 	pub height: u32,
 	pub method: Method,
+
+	/// Whether an animated thumbnail is requested for animated images
+	/// (MSC2705).
+	pub animated: bool,
 }
 
+/// Marks animated thumbnails in the width of their database key, keeping them
+/// apart from the static thumbnails of the same dimensions.
+const ANIMATED_KEY_FLAG: u32 = 1 << 31;
+
 impl super::Service {
 	/// Uploads or replaces a file thumbnail.
 	pub async fn upload_thumbnail(
@@ -33,19 +49,16 @@ pub async fn upload_thumbnail(
 		file: &[u8],
 	) -> Result {
 		let content_hash: [u8; 32] = sha2::Sha256::digest(file).into();
+		self.check_blocked(&content_hash).await?;
 
 		let key =
 			self.db
 				.create_file_metadata(mxc, user, dim, content_disposition, content_type)?;
 
-		self.db.set_media_hash(&key, &content_hash);
-		self.db.increment_sha256_ref(&content_hash)?;
-
-		let already_stored = self.get_storage().exists(&content_hash).await.unwrap_or(false);
+		self.store_content(&key, &content_hash, file)
+			.await?;
 
-		if !already_stored {
-			self.get_storage().create(&content_hash, file).await?;
-		}
+		self.check_scan(mxc, &content_hash, file).await?;
 
 		Ok(())
 	}
@@ -65,11 +78,15 @@ pub async fn upload_thumbnail(
 	/// which crops the image afterwards.
 	#[tracing::instrument(skip(self), name = "thumbnail", level = "debug")]
 	pub async fn get_thumbnail(&self, mxc: &Mxc<'_>, dim: &Dim) -> Result<Option<FileMeta>> {
+		if self.is_quarantined(mxc).await {
+			return Ok(None);
+		}
+
 		// 0, 0 because that's the original file
 		let dim = dim.normalized();
 
-		match self.db.search_file_metadata(mxc, &dim).await {
-			| Ok(metadata) => self.get_thumbnail_saved(metadata).await,
+		let thumbnail = match self.db.search_file_metadata(mxc, &dim).await {
+			| Ok(metadata) => self.get_thumbnail_saved(mxc, metadata).await,
 			| _ => match self
 				.db
 				.search_file_metadata(mxc, &Dim::default())
@@ -80,19 +97,33 @@ pub async fn get_thumbnail(&self, mxc: &Mxc<'_>, dim: &Dim) -> Result<Option<Fil
 						.await,
 				| _ => Ok(None),
 			},
+		};
+
+		if matches!(thumbnail, Ok(Some(_))) {
+			self.set_accessed(mxc).await;
 		}
+
+		thumbnail
 	}
 }
 
 /// Using saved thumbnail
 #[implement(super::Service)]
 #[tracing::instrument(name = "saved", level = "debug", skip(self, data))]
-async fn get_thumbnail_saved(&self, data: Metadata) -> Result<Option<FileMeta>> {
-	let storage_key = self.db.get_media_hash(&data.key).map(|h| h.to_vec()).unwrap_or(data.key.clone());
+async fn get_thumbnail_saved(&self, mxc: &Mxc<'_>, data: Metadata) -> Result<Option<FileMeta>> {
+	let hash = self.db.get_media_hash(&data.key);
+	let storage_key = hash.map(|h| h.to_vec()).unwrap_or(data.key.clone());
 
 	// Use storage trait to read thumbnail
 	match self.get_storage().read(&storage_key).await? {
-		Some(bytes) => Ok(Some(into_filemeta(data, bytes.to_vec()))),
+		Some(bytes) => {
+			if self.is_scanning() {
+				let hash = hash.unwrap_or_else(|| sha2::Sha256::digest(&bytes).into());
+				self.check_scan(mxc, &hash, &bytes).await?;
+			}
+
+			Ok(Some(into_filemeta(data, bytes.to_vec())))
+		},
 		None => Ok(None),
 	}
 }
@@ -107,7 +138,8 @@ async fn get_thumbnail_generate(
 	dim: &Dim,
 	data: Metadata,
 ) -> Result<Option<FileMeta>> {
-	let storage_key = self.db.get_media_hash(&data.key).map(|h| h.to_vec()).unwrap_or(data.key.clone());
+	let hash = self.db.get_media_hash(&data.key);
+	let storage_key = hash.map(|h| h.to_vec()).unwrap_or(data.key.clone());
 
 	// Use storage trait to read original file
 	let content = match self.get_storage().read(&storage_key).await? {
@@ -115,7 +147,42 @@ async fn get_thumbnail_generate(
 		None => return Ok(None),
 	};
 
+	// The original is sent when no thumbnail can be generated.
+	if self.is_scanning() {
+		let hash = hash.unwrap_or_else(|| sha2::Sha256::digest(&content).into());
+		self.check_scan(mxc, &hash, &content).await?;
+	}
+
+	let content_type = data.content_type.as_deref().unwrap_or_default();
+
+	#[cfg(feature = "media_thumbnail_animated")]
+	if dim.animated
+		&& let Some(thumbnail) = animated::thumbnail(&content, content_type, dim)
+			.inspect_err(|error| {
+				tuwunel_core::debug_warn!(?error, "Error generating animated thumbnail.")
+			})
+			.ok()
+			.flatten()
+	{
+		return self
+			.save_thumbnail(mxc, dim, data, thumbnail, Some("image/gif"))
+			.await;
+	}
+
 	let Ok(image) = image::load_from_memory(&content) else {
+		if let Some(image) = self.render(&content, content_type).await {
+			// The original is no image, so a thumbnail is sent at any size.
+			let thumbnail = if dim.width > image.width() || dim.height > image.height() {
+				image
+			} else {
+				thumbnail_generate(&image, dim)?
+			};
+
+			return self
+				.save_thumbnail(mxc, dim, data, encode_png(&thumbnail)?, Some("image/png"))
+				.await;
+		}
+
 		// Couldn't parse file to generate thumbnail, send original
 		return Ok(Some(into_filemeta(data, content)));
 	};
@@ -124,34 +191,43 @@ async fn get_thumbnail_generate(
 		return Ok(Some(into_filemeta(data, content)));
 	}
 
-	let mut thumbnail_bytes = Vec::new();
-	let thumbnail = thumbnail_generate(&image, dim)?;
-	let mut cursor = std::io::Cursor::new(&mut thumbnail_bytes);
-	thumbnail
-		.write_to(&mut cursor, image::ImageFormat::Png)
-		.map_err(|error| err!(error!(?error, "Error writing PNG thumbnail.")))?;
+	let thumbnail = encode_png(&thumbnail_generate(&image, dim)?)?;
+	let content_type = data.content_type.clone();
 
+	self.save_thumbnail(mxc, dim, data, thumbnail, content_type.as_deref())
+		.await
+}
+
+/// Saves a generated thumbnail in database so we don't have to generate it
+/// again next time
+#[cfg(feature = "media_thumbnail")]
+#[implement(super::Service)]
+async fn save_thumbnail(
+	&self,
+	mxc: &Mxc<'_>,
+	dim: &Dim,
+	data: Metadata,
+	thumbnail_bytes: Vec<u8>,
+	content_type: Option<&str>,
+) -> Result<Option<FileMeta>> {
 	let thumbnail_hash: [u8; 32] = sha2::Sha256::digest(&thumbnail_bytes).into();
 
-	// Save thumbnail in database so we don't have to generate it again next time
 	let thumbnail_key = self.db.create_file_metadata(
 		mxc,
 		None,
 		dim,
 		data.content_disposition.as_ref(),
-		data.content_type.as_deref(),
+		content_type,
 	)?;
 
-	self.db.set_media_hash(&thumbnail_key, &thumbnail_hash);
-	self.db.increment_sha256_ref(&thumbnail_hash)?;
+	self.store_content(&thumbnail_key, &thumbnail_hash, &thumbnail_bytes)
+		.await?;
 
-	let already_stored = self.get_storage().exists(&thumbnail_hash).await.unwrap_or(false);
-
-	if !already_stored {
-		self.get_storage().create(&thumbnail_hash, &thumbnail_bytes).await?;
-	}
-
-	Ok(Some(into_filemeta(data, thumbnail_bytes)))
+	Ok(Some(FileMeta {
+		content: Some(thumbnail_bytes),
+		content_type: content_type.map(ToOwned::to_owned),
+		content_disposition: data.content_disposition,
+	}))
 }
 
 #[cfg(not(feature = "media_thumbnail"))]
@@ -159,11 +235,21 @@ async fn get_thumbnail_generate(
 #[tracing::instrument(name = "fallback", level = "debug", skip_all)]
 async fn get_thumbnail_generate(
 	&self,
-	_mxc: &Mxc<'_>,
+	mxc: &Mxc<'_>,
 	_dim: &Dim,
 	data: Metadata,
 ) -> Result<Option<FileMeta>> {
-	self.get_thumbnail_saved(data).await
+	self.get_thumbnail_saved(mxc, data).await
+}
+
+#[cfg(feature = "media_thumbnail")]
+fn encode_png(image: &image::DynamicImage) -> Result<Vec<u8>> {
+	let mut bytes = Vec::new();
+	image
+		.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+		.map_err(|error| err!(error!(?error, "Error writing PNG thumbnail.")))?;
+
+	Ok(bytes)
 }
 
 #[cfg(feature = "media_thumbnail")]
@@ -216,9 +302,33 @@ pub fn new(width: u32, height: u32, method: Option<Method>) -> Self {
 			width,
 			height,
 			method: method.unwrap_or(Method::Scale),
+			animated: false,
 		}
 	}
 
+	/// Requests an animated thumbnail for animated images.
+	#[inline]
+	#[must_use]
+	pub fn with_animated(self, animated: Option<bool>) -> Self {
+		Self {
+			animated: animated.unwrap_or(false),
+			..self
+		}
+	}
+
+	/// Width and height in the database key of the thumbnail.
+	#[inline]
+	#[must_use]
+	pub(super) fn key(&self) -> [u32; 2] {
+		let width = if self.animated {
+			self.width | ANIMATED_KEY_FLAG
+		} else {
+			self.width
+		};
+
+		[width, self.height]
+	}
+
 	pub fn scaled(&self, image: &Self) -> Result<Self> {
 		let image_width = image.width;
 		let image_height = image.height;
@@ -246,6 +356,7 @@ pub fn scaled(&self, image: &Self) -> Result<Self> {
 			width: x,
 			height: y,
 			method: Method::Scale,
+			animated: self.animated,
 		})
 	}
 
@@ -254,12 +365,16 @@ pub fn scaled(&self, image: &Self) -> Result<Self> {
 	/// Ignores the input Method.
 	#[must_use]
 	pub fn normalized(&self) -> Self {
+		let animated = Some(self.animated);
 		match (self.width, self.height) {
-			| (0..=32, 0..=32) => Self::new(32, 32, Some(Method::Crop)),
-			| (0..=96, 0..=96) => Self::new(96, 96, Some(Method::Crop)),
-			| (0..=320, 0..=240) => Self::new(320, 240, Some(Method::Scale)),
-			| (0..=640, 0..=480) => Self::new(640, 480, Some(Method::Scale)),
-			| (0..=800, 0..=600) => Self::new(800, 600, Some(Method::Scale)),
+			| (0..=32, 0..=32) => Self::new(32, 32, Some(Method::Crop)).with_animated(animated),
+			| (0..=96, 0..=96) => Self::new(96, 96, Some(Method::Crop)).with_animated(animated),
+			| (0..=320, 0..=240) =>
+				Self::new(320, 240, Some(Method::Scale)).with_animated(animated),
+			| (0..=640, 0..=480) =>
+				Self::new(640, 480, Some(Method::Scale)).with_animated(animated),
+			| (0..=800, 0..=600) =>
+				Self::new(800, 600, Some(Method::Scale)).with_animated(animated),
 			| _ => Self::default(),
 		}
 	}
@@ -277,6 +392,7 @@ fn default() -> Self {
 			width: 0,
 			height: 0,
 			method: Method::Scale,
+			animated: false,
 		}
 	}
 }
@@ -0,0 +1,153 @@
+//! Rendering of documents and videos into images for their thumbnails using
+//! external programs; gated by the `media_thumbnail_pdf` and
+//! `media_thumbnail_video` features.
+
+use tuwunel_core::implement;
+
+/// Renders the first page of a PDF document or a poster frame of a video.
+/// Returns None for other content and when rendering fails.
+#[implement(super::super::Service)]
+#[cfg(not(any(
+	feature = "media_thumbnail_pdf",
+	feature = "media_thumbnail_video"
+)))]
+#[expect(clippy::unused_async)]
+pub(super) async fn render(
+	&self,
+	_content: &[u8],
+	_content_type: &str,
+) -> Option<image::DynamicImage> {
+	None
+}
+
+#[implement(super::super::Service)]
+#[cfg(any(
+	feature = "media_thumbnail_pdf",
+	feature = "media_thumbnail_video"
+))]
+#[tracing::instrument(
+	name = "render",
+	level = "debug",
+	skip(self, content),
+	fields(bytes = content.len()),
+)]
+pub(super) async fn render(
+	&self,
+	content: &[u8],
+	content_type: &str,
+) -> Option<image::DynamicImage> {
+	use std::{process::Stdio, time::Duration};
+
+	use tokio::{fs, io::AsyncWriteExt, process::Command};
+	use tuwunel_core::{debug_warn, utils};
+
+	let config = &self.services.server.config;
+	let dir = self.get_media_dir().join("render");
+	let path = dir.join(utils::random_string(16));
+	let input = path.to_str()?;
+
+	let mut command = match content_type {
+		#[cfg(feature = "media_thumbnail_pdf")]
+		| "application/pdf" => {
+			let mut command = Command::new(&config.thumbnail_pdftoppm_path);
+			command.args(["-png", "-f", "1", "-l", "1", "-singlefile", "-scale-to"]);
+			command
+				.arg(MAX_SIZE.to_string())
+				.args([input, "-"]);
+			command
+		},
+		#[cfg(feature = "media_thumbnail_video")]
+		| video if video.starts_with("video/") => {
+			// Uploads are untrusted: only the demuxer of the content type may read
+			// them, and only from the file, lest playlists fetch URLs or local
+			// files. The file is needed as MP4 files are not read from pipes.
+			let demuxer = video_demuxer(video)?;
+			let mut command = Command::new(&config.thumbnail_ffmpeg_path);
+			command.args(["-hide_banner", "-loglevel", "error", "-nostdin"]);
+			command.args(["-protocol_whitelist", "file,pipe", "-f", demuxer, "-i", input]);
+			command.args(["-an", "-sn", "-dn", "-vf", "thumbnail", "-frames:v", "1"]);
+			command.args(["-f", "image2pipe", "-c:v", "png", "pipe:1"]);
+			command
+		},
+		| _ => return None,
+	};
+
+	// The content of private uploads is readable by the server only.
+	let written = async {
+		let mut builder = fs::DirBuilder::new();
+		builder.recursive(true);
+		#[cfg(unix)]
+		builder.mode(0o700);
+		builder.create(&dir).await?;
+
+		let mut options = fs::OpenOptions::new();
+		options.write(true).create_new(true);
+		#[cfg(unix)]
+		options.mode(0o600);
+
+		let mut file = options.open(&path).await?;
+		file.write_all(content).await?;
+		file.flush().await
+	};
+
+	if let Err(error) = written.await {
+		debug_warn!(?error, "Failed to write content to render");
+		fs::remove_file(&path).await.ok();
+		return None;
+	}
+
+	let timeout = Duration::from_secs(config.thumbnail_render_timeout);
+	let output = command
+		.stdin(Stdio::null())
+		.stderr(Stdio::piped())
+		.kill_on_drop(true)
+		.output();
+
+	let output = tokio::time::timeout(timeout, output).await;
+	fs::remove_file(&path).await.ok();
+
+	let output = match output {
+		| Ok(Ok(output)) if output.status.success() => output,
+		| Ok(Ok(output)) => {
+			let stderr = String::from_utf8_lossy(&output.stderr);
+			debug_warn!(status = ?output.status, %stderr, "Failed to render {content_type}");
+			return None;
+		},
+		| Ok(Err(error)) => {
+			debug_warn!(?error, "Failed to run the renderer of {content_type}");
+			return None;
+		},
+		| Err(_) => {
+			debug_warn!(?timeout, "Rendering {content_type} timed out");
+			return None;
+		},
+	};
+
+	image::load_from_memory(&output.stdout)
+		.inspect_err(|error| debug_warn!(?error, "Rendered {content_type} is no image"))
+		.ok()
+}
+
+/// Size of the longest side of rendered PDF pages; the largest thumbnail.
+#[cfg(feature = "media_thumbnail_pdf")]
+const MAX_SIZE: u32 = 800;
+
+/// The ffmpeg demuxer of the video content type; videos of other types are
+/// not rendered.
+#[cfg(feature = "media_thumbnail_video")]
+fn video_demuxer(content_type: &str) -> Option<&'static str> {
+	let essence = content_type
+		.split(';')
+		.next()
+		.unwrap_or_default()
+		.trim();
+
+	match essence {
+		| "video/mp4" | "video/quicktime" | "video/3gpp" | "video/3gpp2" => Some("mov"),
+		| "video/webm" | "video/x-matroska" => Some("matroska"),
+		| "video/ogg" => Some("ogg"),
+		| "video/mpeg" => Some("mpeg"),
+		| "video/x-msvideo" => Some("avi"),
+		| _ => None,
+	}
+}
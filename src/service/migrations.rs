@@ -62,6 +62,7 @@ async fn fresh(services: &Services) -> Result {
 		.bump_database_version(DATABASE_VERSION);
 
 	db["global"].insert(b"feat_sha256_media", []);
+	db["global"].insert(b"feat_media_dedup", []);
 	db["global"].insert(b"fix_bad_double_separator_in_state_cache", []);
 	db["global"].insert(b"retroactively_fix_bad_data_from_roomuserid_joined", []);
 	db["global"].insert(b"fix_referencedevents_missing_sep", []);
@@ -111,6 +112,14 @@ async fn migrate(services: &Services) -> Result {
 		media::migrations::checkup_sha256_media(services).await?;
 	}
 
+	if db["global"]
+		.get(b"feat_media_dedup")
+		.await
+		.is_not_found()
+	{
+		media::migrations::dedup_media(services).await?;
+	}
+
 	if db["global"]
 		.get(b"fix_bad_double_separator_in_state_cache")
 		.await
@@ -12,13 +12,17 @@
 pub mod account_data;
 pub mod admin;
 pub mod appservice;
+pub mod audit;
 pub mod client;
 pub mod config;
 pub mod deactivate;
 pub mod emergency;
 pub mod federation;
 pub mod globals;
+pub mod jwks;
 pub mod key_backups;
+pub mod ldap;
+pub mod livekit;
 pub mod media;
 pub mod membership;
 pub mod oauth;
@@ -29,7 +33,9 @@
 pub mod rooms;
 pub mod sending;
 pub mod server_keys;
+pub mod server_notices;
 pub mod sync;
+pub mod terms;
 pub mod transaction_ids;
 pub mod uiaa;
 pub mod users;
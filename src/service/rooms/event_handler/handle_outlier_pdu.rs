@@ -42,11 +42,7 @@ pub(super) async fn handle_outlier_pdu(
 		| Ok(ruma::signatures::Verified::Signatures) => {
 			// Redact
 			debug_info!("Calculated hash does not match (redaction): {event_id}");
-			let Some(rules) = room_version.rules() else {
-				return Err!(Request(UnsupportedRoomVersion(
-					"Cannot redact event for unknown room version {room_version:?}."
-				)));
-			};
+			let rules = room_version::rules(room_version)?;
 
 			let Ok(obj) = ruma::canonical_json::redact(pdu_json, &rules.redaction, None) else {
 				return Err!(Request(InvalidParam("Redaction failed")));
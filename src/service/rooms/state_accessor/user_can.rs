@@ -1,3 +1,4 @@
+use futures::{TryFutureExt, future::try_join};
 use ruma::{
 	EventId, RoomId, UserId,
 	events::{
@@ -11,11 +12,11 @@
 };
 use tuwunel_core::{
 	Err, Result, implement,
-	matrix::{Event, StateKey},
+	matrix::{Event, PduCount, StateKey},
 	pdu::PduBuilder,
 };
 
-use crate::rooms::state::RoomMutexGuard;
+use crate::rooms::{short::ShortStateHash, state::RoomMutexGuard};
 
 /// Checks if a given user can redact a given event
 ///
@@ -80,6 +81,10 @@ pub async fn user_can_redact(
 
 /// Whether a user is allowed to see an event, based on
 /// the room's history_visibility at that event's state.
+///
+/// Events are visible when the room was world_readable at the event, when the
+/// user was joined at the event (or invited, for invited visibility), or for
+/// shared visibility, when the event precedes the end of the user's membership.
 #[implement(super::Service)]
 #[tracing::instrument(skip_all, level = "trace")]
 pub async fn user_can_see_event(
@@ -104,27 +109,102 @@ pub async fn user_can_see_event(
 			c.history_visibility
 		});
 
-	match history_visibility {
-		| HistoryVisibility::WorldReadable => true,
+	if history_visibility == HistoryVisibility::WorldReadable {
+		return true;
+	}
 
-		// Allow if any member on requesting server was AT LEAST invited, else deny
-		| HistoryVisibility::Invited =>
-			self.user_was_invited(shortstatehash, user_id)
-				.await,
+	let membership = self
+		.user_membership_at_event(shortstatehash, user_id, event_id)
+		.await;
 
-		// Allow if any member on requested server was joined, else deny
-		| HistoryVisibility::Joined =>
-			self.user_was_joined(shortstatehash, user_id)
-				.await,
+	// Allow if the user was joined at the event.
+	if membership == MembershipState::Join {
+		return true;
+	}
+
+	match history_visibility {
+		// Allow if the user was at least invited at the event, else deny.
+		| HistoryVisibility::Invited => membership == MembershipState::Invite,
 
+		// Deny unless the user was joined at the event.
+		| HistoryVisibility::Joined => false,
+
+		// Allow if the user joined the room and the event precedes the end of their
+		// membership, else deny.
 		| HistoryVisibility::Shared | _ =>
-			self.services
-				.state_cache
-				.is_joined(user_id, room_id)
+			self.user_shares_history(user_id, room_id, event_id)
 				.await,
 	}
 }
 
+/// The membership of the user at an event. The membership events of the user
+/// themselves take effect at the event rather than after it.
+#[implement(super::Service)]
+async fn user_membership_at_event(
+	&self,
+	shortstatehash: ShortStateHash,
+	user_id: &UserId,
+	event_id: &EventId,
+) -> MembershipState {
+	let membership = self
+		.user_membership(shortstatehash, user_id)
+		.await;
+
+	if membership == MembershipState::Join {
+		return membership;
+	}
+
+	self.services
+		.timeline
+		.get_pdu(event_id)
+		.await
+		.ok()
+		.filter(|pdu| *pdu.kind() == TimelineEventType::RoomMember)
+		.filter(|pdu| pdu.state_key() == Some(user_id.as_str()))
+		.and_then(|pdu| pdu.get_content::<RoomMemberEventContent>().ok())
+		.map_or(membership, |content| content.membership)
+}
+
+/// Whether the event falls within the shared history of the room for the user:
+/// any event while joined, or any event before the user left or was banned.
+#[implement(super::Service)]
+async fn user_shares_history(
+	&self,
+	user_id: &UserId,
+	room_id: &RoomId,
+	event_id: &EventId,
+) -> bool {
+	if self
+		.services
+		.state_cache
+		.is_joined(user_id, room_id)
+		.await
+	{
+		return true;
+	}
+
+	if !self
+		.services
+		.state_cache
+		.once_joined(user_id, room_id)
+		.await
+	{
+		return false;
+	}
+
+	let left_count = self
+		.services
+		.state_cache
+		.get_left_count(room_id, user_id)
+		.map_ok(PduCount::Normal);
+
+	let event_count = self.services.timeline.get_pdu_count(event_id);
+
+	try_join(left_count, event_count)
+		.await
+		.is_ok_and(|(left_count, event_count)| event_count <= left_count)
+}
+
 /// Whether a user is allowed to see an event, based on
 /// the room's history_visibility at that event's state.
 #[implement(super::Service)]
@@ -4,9 +4,7 @@
 use ruma::{
 	OwnedServerName, RoomId, UserId,
 	events::{
-		AnyStrippedStateEvent, AnySyncStateEvent, GlobalAccountDataEventType,
-		RoomAccountDataEventType, StateEventType,
-		direct::DirectEvent,
+		AnyStrippedStateEvent, AnySyncStateEvent, StateEventType,
 		room::{
 			create::RoomCreateEventContent,
 			member::{MembershipState, RoomMemberEventContent},
@@ -14,7 +12,12 @@
 	},
 	serde::Raw,
 };
-use tuwunel_core::{Result, implement, is_not_empty, matrix::PduCount, utils::ReadyExt, warn};
+use tuwunel_core::{
+	Result, implement, is_not_empty,
+	matrix::PduCount,
+	utils::{ReadyExt, result::LogErr},
+	warn,
+};
 use tuwunel_database::{Json, serialize_key};
 
 /// Update current membership data.
@@ -73,55 +76,13 @@ pub async fn update_membership(
 					.await
 					.map(|content: RoomCreateEventContent| content.predecessor)
 				{
-					// Copy old tags to new room
-					if let Ok(tag_event) = self
-						.services
-						.account_data
-						.get_room(&predecessor.room_id, user_id, RoomAccountDataEventType::Tag)
-						.await
-					{
-						self.services
-							.account_data
-							.update(
-								Some(room_id),
-								user_id,
-								RoomAccountDataEventType::Tag,
-								&tag_event,
-							)
-							.await
-							.ok();
-					}
-
-					// Copy direct chat flag
-					if let Ok(mut direct_event) = self
-						.services
+					// Carry the user's tags, direct chat flags and push rules over
+					self.services
 						.account_data
-						.get_global::<DirectEvent>(user_id, GlobalAccountDataEventType::Direct)
+						.copy_room_data(user_id, &predecessor.room_id, room_id)
 						.await
-					{
-						let mut room_ids_updated = false;
-						for room_ids in direct_event.content.0.values_mut() {
-							if room_ids.iter().any(|r| r == &predecessor.room_id) {
-								room_ids.push(room_id.to_owned());
-								room_ids_updated = true;
-							}
-						}
-
-						if room_ids_updated {
-							self.services
-								.account_data
-								.update(
-									None,
-									user_id,
-									GlobalAccountDataEventType::Direct
-										.to_string()
-										.into(),
-									&serde_json::to_value(&direct_event)
-										.expect("to json always works"),
-								)
-								.await?;
-						}
-					}
+						.log_err()
+						.ok();
 				}
 			}
 
@@ -159,6 +120,15 @@ pub async fn update_membership(
 		self.update_joined_count(room_id).await;
 	}
 
+	if matches!(membership, MembershipState::Leave | MembershipState::Ban) {
+		self.services
+			.appservice
+			.append_device_left(user_id, room_id)
+			.await
+			.log_err()
+			.ok();
+	}
+
 	Ok(())
 }
 
@@ -302,6 +302,12 @@ async fn append_pdu_effects(
 				}
 			}
 		},
+		| TimelineEventType::RoomEncrypted =>
+			if self.services.globals.user_is_local(pdu.sender()) {
+				self.services
+					.media
+					.set_encrypted_sent(pdu.sender());
+			},
 		| _ => {},
 	}
 
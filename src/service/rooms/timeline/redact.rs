@@ -2,7 +2,10 @@
 	EventId, RoomId,
 	canonical_json::{RedactedBecause, redact_in_place},
 };
-use tuwunel_core::{Result, err, implement, matrix::event::Event};
+use tuwunel_core::{
+	Result, err, implement,
+	matrix::{event::Event, room_version},
+};
 
 use crate::rooms::{short::ShortRoomId, timeline::RoomMutexGuard};
 
@@ -54,11 +57,7 @@ pub async fn redact_pdu<Pdu: Event + Send + Sync>(
 		.get_room_version(room_id)
 		.await?;
 
-	let room_version_rules = room_version_id.rules().ok_or_else(|| {
-		err!(Request(UnsupportedRoomVersion(
-			"Cannot redact event for unknown room version {room_version_id:?}."
-		)))
-	})?;
+	let room_version_rules = room_version::rules(&room_version_id)?;
 
 	redact_in_place(
 		&mut pdu,
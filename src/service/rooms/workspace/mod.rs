@@ -78,6 +78,23 @@ pub async fn get_space_room_id(&self, workspace_id: &str) -> Result<OwnedRoomId>
 		.map_err(|e| err!(Database(error!("Invalid RoomId for space room id: {e}"))))
 }
 
+/// Stream tất cả mapping workspaceId → spaceRoomId.
+#[implement(Service)]
+pub fn workspaces(&self) -> impl Stream<Item = (String, OwnedRoomId)> + Send + '_ {
+	self.db
+		.workspaceid_spaceroomid
+		.raw_stream()
+		.ignore_err()
+		.ready_filter_map(|(key, val): (&[u8], &[u8])| {
+			let workspace_id = std::str::from_utf8(key).ok()?;
+			let space_room_id = std::str::from_utf8(val)
+				.ok()
+				.and_then(|s| OwnedRoomId::parse(s).ok())?;
+
+			Some((workspace_id.to_owned(), space_room_id))
+		})
+}
+
 // ─── Room / Workspace mapping ─────────────────────────────────────────────────
 
 /// Lưu mapping roomId → workspaceId + ghi vào set index.
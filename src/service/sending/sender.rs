@@ -16,28 +16,31 @@
 	stream::FuturesUnordered,
 };
 use ruma::{
-	MilliSecondsSinceUnixEpoch, OwnedRoomId, OwnedServerName, OwnedUserId, RoomId, ServerName,
-	UserId,
+	MilliSecondsSinceUnixEpoch, OwnedDeviceId, OwnedRoomId, OwnedServerName, OwnedUserId, RoomId,
+	ServerName, UserId,
 	api::{
-		appservice::event::push_events::v1::EphemeralData,
+		appservice::event::push_events::{self, v1::EphemeralData},
+		client::sync::sync_events::DeviceLists,
 		federation::transactions::{
 			edu::{
-				DeviceListUpdateContent, Edu, PresenceContent, PresenceUpdate, ReceiptContent,
-				ReceiptData, ReceiptMap,
+				DeviceListUpdateContent, DirectDeviceContent, Edu, PresenceContent,
+				PresenceUpdate, ReceiptContent, ReceiptData, ReceiptMap,
 			},
 			send_transaction_message,
 		},
 	},
 	device_id,
 	events::{
-		AnySyncEphemeralRoomEvent, GlobalAccountDataEventType, push_rules::PushRulesEvent,
-		receipt::ReceiptType,
+		AnySyncEphemeralRoomEvent, AnyToDeviceEvent, GlobalAccountDataEventType,
+		push_rules::PushRulesEvent, receipt::ReceiptType,
 	},
 	presence::PresenceState,
 	push,
 	serde::Raw,
+	to_device::DeviceIdOrAllDevices,
 	uint,
 };
+use serde_json::{json, value::to_raw_value};
 use tuwunel_core::{
 	Error, Event, Result, debug, err, error, extract_variant,
 	result::LogErr,
@@ -51,7 +54,10 @@
 };
 
 use super::{Destination, EduBuf, EduVec, Msg, SendingEvent, Service, data::QueueItem};
-use crate::rooms::timeline::RawPduId;
+use crate::{
+	appservice::{DeviceListChanges, DeviceListUpdate},
+	rooms::timeline::RawPduId,
+};
 
 #[derive(Debug)]
 enum TransactionStatus {
@@ -702,7 +708,7 @@ async fn send_events_dest_appservice(
 		let Some(appservice) = self
 			.services
 			.appservice
-			.get_registration(&id)
+			.get_registration_info(&id)
 			.await
 		else {
 			return Err((
@@ -711,6 +717,9 @@ async fn send_events_dest_appservice(
 			));
 		};
 
+		let receive_ephemeral = appservice.registration.receive_ephemeral;
+		let receive_device_lists = appservice.receive_device_lists;
+
 		let mut pdu_jsons = Vec::with_capacity(
 			events
 				.iter()
@@ -723,6 +732,8 @@ async fn send_events_dest_appservice(
 				.filter(|event| matches!(event, SendingEvent::Edu(_)))
 				.count(),
 		);
+		let mut to_device: Vec<Raw<AnyToDeviceEvent>> = Vec::new();
+		let mut device_lists = DeviceListChanges::default();
 		for event in &events {
 			match event {
 				| SendingEvent::Pdu(pdu_id) => {
@@ -736,17 +747,53 @@ async fn send_events_dest_appservice(
 					}
 				},
 				| SendingEvent::Edu(edu) => {
-					if appservice.receive_ephemeral
-						&& let Ok(edu) =
-							serde_json::from_slice(edu).and_then(|edu| Raw::new(&edu))
-					{
-						edu_jsons.push(edu);
+					// Queued by the appservice service for appservices receiving device lists.
+					if let Ok(update) = serde_json::from_slice::<DeviceListUpdate>(edu) {
+						if receive_device_lists {
+							device_lists.add(update, |user_id| appservice.is_user_match(user_id));
+						}
+
+						continue;
+					}
+
+					match serde_json::from_slice(edu) {
+						// Queued by the appservice service for the devices of its users.
+						| Ok(Edu::DirectToDevice(content)) =>
+							if receive_ephemeral {
+								to_device.extend(appservice_to_device(&content));
+								if receive_device_lists {
+									device_lists
+										.key_counts
+										.extend(to_device_targets(&content));
+								}
+							},
+						| _ =>
+							if receive_ephemeral
+								&& let Ok(edu) = serde_json::from_slice::<EphemeralData>(edu)
+									.and_then(|edu| Raw::new(&edu))
+							{
+								edu_jsons.push(edu);
+							},
 					}
 				},
 				| SendingEvent::Flush => {}, // flush only; no new content
 			}
 		}
 
+		let mut device_one_time_keys_count: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+		for (user_id, device_id) in device_lists.key_counts {
+			let counts = self
+				.services
+				.users
+				.count_one_time_keys(&user_id, &device_id)
+				.await;
+
+			device_one_time_keys_count
+				.entry(user_id)
+				.or_default()
+				.insert(device_id, counts);
+		}
+
 		let txn_hash = calculate_hash(events.iter().filter_map(|e| match e {
 			| SendingEvent::Edu(b) => Some(b.as_ref()),
 			| SendingEvent::Pdu(b) => Some(b.as_ref()),
@@ -760,11 +807,18 @@ async fn send_events_dest_appservice(
 		match self
 			.services
 			.appservice
-			.send_request(appservice, ruma::api::appservice::event::push_events::v1::Request {
+			.send_request(appservice.registration, push_events::v1::Request {
 				txn_id: txn_id.into(),
 				events: pdu_jsons,
 				ephemeral: edu_jsons,
-				to_device: Vec::new(), // TODO
+				to_device,
+				device_lists: DeviceLists {
+					changed: device_lists.changed.into_iter().collect(),
+					left: device_lists.left.into_iter().collect(),
+				},
+				device_one_time_keys_count,
+				// Fallback keys are not stored.
+				device_unused_fallback_key_types: BTreeMap::new(),
 			})
 			.await
 		{
@@ -1223,3 +1277,50 @@ async fn send_events_dest_federation(
 		}
 	}
 }
+
+/// To-device events of the message in the format of MSC4203, which adds the
+/// recipient to each event.
+fn appservice_to_device(
+	content: &DirectDeviceContent,
+) -> impl Iterator<Item = Raw<AnyToDeviceEvent>> + '_ {
+	content
+		.messages
+		.iter()
+		.flat_map(|(user_id, devices)| {
+			devices
+				.iter()
+				.map(move |(device_id, event)| (user_id, device_id, event))
+		})
+		.filter_map(|(user_id, device_id, event)| {
+			let DeviceIdOrAllDevices::DeviceId(device_id) = device_id else {
+				return None;
+			};
+
+			let event = json!({
+				"type": content.ev_type,
+				"sender": content.sender,
+				"content": event,
+				"to_user_id": user_id,
+				"to_device_id": device_id,
+			});
+
+			to_raw_value(&event).map(Raw::from_json).ok()
+		})
+}
+
+fn to_device_targets(
+	content: &DirectDeviceContent,
+) -> impl Iterator<Item = (OwnedUserId, OwnedDeviceId)> + '_ {
+	content
+		.messages
+		.iter()
+		.flat_map(|(user_id, devices)| {
+			devices
+				.keys()
+				.filter_map(move |device_id| match device_id {
+					| DeviceIdOrAllDevices::DeviceId(device_id) =>
+						Some((user_id.clone(), device_id.clone())),
+					| DeviceIdOrAllDevices::AllDevices => None,
+				})
+		})
+}
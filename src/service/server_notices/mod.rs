@@ -0,0 +1,369 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use async_trait::async_trait;
+use futures::{FutureExt, StreamExt};
+use ruma::{
+	OwnedEventId, OwnedRoomId, OwnedUserId, RoomId, RoomVersionId, UserId,
+	events::{
+		RoomAccountDataEventType,
+		room::{
+			avatar::RoomAvatarEventContent,
+			create::RoomCreateEventContent,
+			guest_access::{GuestAccess, RoomGuestAccessEventContent},
+			history_visibility::{HistoryVisibility, RoomHistoryVisibilityEventContent},
+			join_rules::{JoinRule, RoomJoinRulesEventContent},
+			member::{MembershipState, RoomMemberEventContent},
+			message::RoomMessageEventContent,
+			name::RoomNameEventContent,
+			power_levels::RoomPowerLevelsEventContent,
+		},
+		tag::{TagEvent, TagEventContent, TagInfo},
+	},
+	int,
+};
+use serde_json::json;
+use tuwunel_core::{Err, Result, debug, implement, pdu::PduBuilder, utils::MutexMap, warn};
+use tuwunel_database::{Deserialized, Map};
+
+/// Sends notices from a system user to individual users, each in a room of
+/// their own, as configured in `[global.server_notices]`.
+pub struct Service {
+	db: Data,
+	room_mutex: MutexMap<OwnedUserId, ()>,
+	services: Arc<crate::services::OnceServices>,
+}
+
+struct Data {
+	userid_servernoticeroomid: Arc<Map>,
+}
+
+/// Room tag of the notices rooms.
+const SERVER_NOTICE_TAG: &str = "m.server_notice";
+
+/// Origin of the system user account.
+const SYSTEM_USER_ORIGIN: &str = "server_notices";
+
+#[async_trait]
+impl crate::Service for Service {
+	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
+		Ok(Arc::new(Self {
+			db: Data {
+				userid_servernoticeroomid: args.db["userid_servernoticeroomid"].clone(),
+			},
+			room_mutex: MutexMap::new(),
+			services: args.services.clone(),
+		}))
+	}
+
+	async fn worker(self: Arc<Self>) -> Result {
+		let Some(system_user) = self.system_user() else {
+			return Ok(());
+		};
+
+		if self.is_human_account(&system_user).await {
+			return Err!(Config(
+				"server_notices.system_localpart",
+				"{system_user} is an existing account of a person; choose another localpart."
+			));
+		}
+
+		Ok(())
+	}
+
+	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
+}
+
+/// The system user sending server notices, when configured.
+#[implement(Service)]
+pub fn system_user(&self) -> Option<OwnedUserId> {
+	let localpart = self
+		.services
+		.server
+		.config
+		.server_notices
+		.system_localpart
+		.as_deref()?;
+
+	UserId::parse_with_server_name(localpart, self.services.globals.server_name()).ok()
+}
+
+/// Whether the user is the system user; its localpart is reserved against
+/// registration.
+#[implement(Service)]
+pub fn is_system_user(&self, user_id: &UserId) -> bool {
+	self.system_user()
+		.is_some_and(|system_user| *system_user == *user_id)
+}
+
+/// Whether server notices are configured.
+#[implement(Service)]
+#[inline]
+pub fn is_enabled(&self) -> bool { self.system_user().is_some() }
+
+/// Sends a markdown notice to the user.
+#[implement(Service)]
+pub async fn send_text(&self, user_id: &UserId, body: &str) -> Result<OwnedEventId> {
+	self.send_message(user_id, RoomMessageEventContent::notice_markdown(body))
+		.await
+}
+
+/// Notifies the user that a limit of the server was reached
+/// (`m.server_notice.usage_limit_reached`). Clients show the notice as a
+/// banner; the admin contact defaults to the support address of the server.
+#[implement(Service)]
+pub async fn send_usage_limit_reached(
+	&self,
+	user_id: &UserId,
+	body: &str,
+	limit_type: &str,
+	admin_contact: Option<&str>,
+) -> Result<OwnedEventId> {
+	let config = &self.services.server.config;
+	let admin_contact = admin_contact.map(ToOwned::to_owned).or_else(|| {
+		config
+			.well_known
+			.support_email
+			.as_ref()
+			.map(|email| format!("mailto:{email}"))
+			.or_else(|| {
+				config
+					.well_known
+					.support_page
+					.as_ref()
+					.map(ToString::to_string)
+			})
+	});
+
+	let content = serde_json::from_value(json!({
+		"msgtype": "m.server_notice",
+		"body": body,
+		"server_notice_type": "m.server_notice.usage_limit_reached",
+		"admin_contact": admin_contact,
+		"limit_type": limit_type,
+	}))?;
+
+	self.send_message(user_id, content).await
+}
+
+/// Sends a message to the user in their notices room, which is created on
+/// first use.
+#[implement(Service)]
+#[tracing::instrument(level = "debug", skip(self, content))]
+pub async fn send_message(
+	&self,
+	user_id: &UserId,
+	content: RoomMessageEventContent,
+) -> Result<OwnedEventId> {
+	let Some(system_user) = self.system_user() else {
+		return Err!(Config(
+			"server_notices.system_localpart",
+			"Server notices are not enabled."
+		));
+	};
+
+	if !self.services.globals.user_is_local(user_id) {
+		return Err!(Request(InvalidParam("Server notices can only be sent to local users.")));
+	}
+
+	if *user_id == *system_user {
+		return Err!(Request(InvalidParam("The system user does not receive server notices.")));
+	}
+
+	let room_id = self.notices_room(user_id, &system_user).await?;
+	let state_lock = self.services.state.mutex.lock(&room_id).await;
+
+	self.services
+		.timeline
+		.build_and_append_pdu(PduBuilder::timeline(&content), &system_user, &room_id, &state_lock)
+		.boxed()
+		.await
+}
+
+/// The notices room of the user, if one was created.
+#[implement(Service)]
+pub async fn get_notices_room(&self, user_id: &UserId) -> Result<OwnedRoomId> {
+	self.db
+		.userid_servernoticeroomid
+		.get(user_id)
+		.await
+		.deserialized()
+}
+
+/// Returns the notices room of the user, creating a new room when there is
+/// none or the user has left it.
+#[implement(Service)]
+async fn notices_room(&self, user_id: &UserId, system_user: &UserId) -> Result<OwnedRoomId> {
+	// Concurrent notices to the user must not each create a room.
+	let _lock = self.room_mutex.lock(user_id).await;
+
+	if let Ok(room_id) = self.get_notices_room(user_id).await {
+		let state_cache = &self.services.state_cache;
+		let usable = state_cache.is_joined(system_user, &room_id).await
+			&& (state_cache.is_joined(user_id, &room_id).await
+				|| state_cache.is_invited(user_id, &room_id).await);
+
+		if usable {
+			return Ok(room_id);
+		}
+
+		debug!(%user_id, %room_id, "server notices room is no longer usable");
+	}
+
+	self.ensure_system_user(system_user).await?;
+
+	let room_id = self.create_room(user_id, system_user).await?;
+	self.db
+		.userid_servernoticeroomid
+		.insert(user_id, room_id.as_bytes());
+
+	Ok(room_id)
+}
+
+#[implement(Service)]
+async fn ensure_system_user(&self, system_user: &UserId) -> Result {
+	let config = &self.services.server.config.server_notices;
+	let users = &self.services.users;
+
+	if self.is_human_account(system_user).await {
+		return Err!(Config(
+			"server_notices.system_localpart",
+			"{system_user} is an existing account of a person."
+		));
+	}
+
+	if !users.exists(system_user).await {
+		users
+			.create(system_user, None, Some(SYSTEM_USER_ORIGIN))
+			.await?;
+	}
+
+	users.set_displayname(system_user, Some(&config.system_display_name));
+	users.set_avatar_url(system_user, config.system_avatar_url.as_deref());
+
+	Ok(())
+}
+
+/// Whether the account exists and was not created for server notices: it can
+/// log in with a password or has devices.
+#[implement(Service)]
+async fn is_human_account(&self, user_id: &UserId) -> bool {
+	let users = &self.services.users;
+	if !users.exists(user_id).await
+		|| users
+			.origin(user_id)
+			.await
+			.is_ok_and(|origin| origin == SYSTEM_USER_ORIGIN)
+	{
+		return false;
+	}
+
+	users
+		.password_hash(user_id)
+		.await
+		.is_ok_and(|hash| !hash.is_empty())
+		|| users
+			.all_device_ids(user_id)
+			.boxed()
+			.next()
+			.await
+			.is_some()
+}
+
+#[implement(Service)]
+async fn create_room(&self, user_id: &UserId, system_user: &UserId) -> Result<OwnedRoomId> {
+	let config = &self.services.server.config.server_notices;
+	let room_id = RoomId::new_v1(self.services.globals.server_name());
+
+	let _short_id = self
+		.services
+		.short
+		.get_or_create_shortroomid(&room_id)
+		.await;
+
+	let state_lock = self.services.state.mutex.lock(&room_id).await;
+
+	let mut events = vec![
+		PduBuilder::state(String::new(), &RoomCreateEventContent {
+			federate: false,
+			room_version: RoomVersionId::V11,
+			..RoomCreateEventContent::new_v11()
+		}),
+		PduBuilder::state(system_user.to_string(), &RoomMemberEventContent {
+			displayname: Some(config.system_display_name.clone()),
+			avatar_url: config.system_avatar_url.clone(),
+			..RoomMemberEventContent::new(MembershipState::Join)
+		}),
+		// The user can read the notices but not reply to them.
+		PduBuilder::state(String::new(), &RoomPowerLevelsEventContent {
+			users: BTreeMap::from([(system_user.to_owned(), int!(100))]),
+			users_default: int!(-10),
+			..Default::default()
+		}),
+		PduBuilder::state(String::new(), &RoomJoinRulesEventContent::new(JoinRule::Invite)),
+		PduBuilder::state(
+			String::new(),
+			&RoomHistoryVisibilityEventContent::new(HistoryVisibility::Shared),
+		),
+		PduBuilder::state(
+			String::new(),
+			&RoomGuestAccessEventContent::new(GuestAccess::Forbidden),
+		),
+		PduBuilder::state(String::new(), &RoomNameEventContent::new(config.room_name.clone())),
+	];
+
+	if let Some(avatar_url) = &config.system_avatar_url {
+		events.push(PduBuilder::state(String::new(), &RoomAvatarEventContent {
+			url: Some(avatar_url.clone()),
+			..Default::default()
+		}));
+	}
+
+	for event in events {
+		self.services
+			.timeline
+			.build_and_append_pdu(event, system_user, &room_id, &state_lock)
+			.boxed()
+			.await?;
+	}
+
+	drop(state_lock);
+
+	self.services
+		.membership
+		.invite(system_user, user_id, &room_id, None, false, None)
+		.boxed()
+		.await?;
+
+	if let Err(e) = self.set_tag(user_id, &room_id).await {
+		warn!(%user_id, %room_id, "Failed to tag server notices room: {e}");
+	}
+
+	Ok(room_id)
+}
+
+#[implement(Service)]
+async fn set_tag(&self, user_id: &UserId, room_id: &RoomId) -> Result {
+	let mut tags_event = self
+		.services
+		.account_data
+		.get_room(room_id, user_id, RoomAccountDataEventType::Tag)
+		.await
+		.unwrap_or(TagEvent {
+			content: TagEventContent { tags: BTreeMap::new() },
+		});
+
+	tags_event
+		.content
+		.tags
+		.insert(SERVER_NOTICE_TAG.into(), TagInfo::new());
+
+	self.services
+		.account_data
+		.update(
+			Some(room_id),
+			user_id,
+			RoomAccountDataEventType::Tag,
+			&serde_json::to_value(tags_event)?,
+		)
+		.await
+}
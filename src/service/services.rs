@@ -9,25 +9,29 @@
 
 pub(crate) use crate::OnceServices;
 use crate::{
-	account_data, admin, appservice, client, config, deactivate, emergency, federation, globals,
-	key_backups,
+	account_data, admin, appservice, audit, client, config, deactivate, emergency, federation,
+	globals, jwks, key_backups, ldap, livekit,
 	manager::Manager,
 	media, membership, oauth, presence, pusher, registration_tokens, resolver,
 	rooms::{self, retention},
-	sending, server_keys,
+	sending, server_keys, server_notices,
 	service::{Args, Service},
-	sync, transaction_ids, uiaa, users,
+	sync, terms, transaction_ids, uiaa, users,
 };
 
 pub struct Services {
 	pub account_data: Arc<account_data::Service>,
 	pub admin: Arc<admin::Service>,
 	pub appservice: Arc<appservice::Service>,
+	pub audit: Arc<audit::Service>,
 	pub config: Arc<config::Service>,
 	pub client: Arc<client::Service>,
 	pub emergency: Arc<emergency::Service>,
 	pub globals: Arc<globals::Service>,
+	pub jwks: Arc<jwks::Service>,
 	pub key_backups: Arc<key_backups::Service>,
+	pub ldap: Arc<ldap::Service>,
+	pub livekit: Arc<livekit::Service>,
 	pub media: Arc<media::Service>,
 	pub presence: Arc<presence::Service>,
 	pub pusher: Arc<pusher::Service>,
@@ -56,7 +60,9 @@ pub struct Services {
 	pub federation: Arc<federation::Service>,
 	pub sending: Arc<sending::Service>,
 	pub server_keys: Arc<server_keys::Service>,
+	pub server_notices: Arc<server_notices::Service>,
 	pub sync: Arc<sync::Service>,
+	pub terms: Arc<terms::Service>,
 	pub transaction_ids: Arc<transaction_ids::Service>,
 	pub uiaa: Arc<uiaa::Service>,
 	pub users: Arc<users::Service>,
@@ -85,12 +91,16 @@ pub async fn build(server: Arc<Server>) -> Result<Arc<Self>> {
 		account_data: account_data::Service::build(&args)?,
 		admin: admin::Service::build(&args)?,
 		appservice: appservice::Service::build(&args)?,
+		audit: audit::Service::build(&args)?,
 		resolver: resolver::Service::build(&args)?,
 		client: client::Service::build(&args)?,
 		config: config::Service::build(&args)?,
 		emergency: emergency::Service::build(&args)?,
 		globals: globals::Service::build(&args)?,
+		jwks: jwks::Service::build(&args)?,
 		key_backups: key_backups::Service::build(&args)?,
+		ldap: ldap::Service::build(&args)?,
+		livekit: livekit::Service::build(&args)?,
 		media: media::Service::build(&args)?,
 		presence: presence::Service::build(&args)?,
 		pusher: pusher::Service::build(&args)?,
@@ -118,7 +128,9 @@ pub async fn build(server: Arc<Server>) -> Result<Arc<Self>> {
 		federation: federation::Service::build(&args)?,
 		sending: sending::Service::build(&args)?,
 		server_keys: server_keys::Service::build(&args)?,
+		server_notices: server_notices::Service::build(&args)?,
 		sync: sync::Service::build(&args)?,
+		terms: terms::Service::build(&args)?,
 		transaction_ids: transaction_ids::Service::build(&args)?,
 		uiaa: uiaa::Service::build(&args)?,
 		users: users::Service::build(&args)?,
@@ -148,12 +160,16 @@ macro_rules! cast {
 		cast!(self.account_data),
 		cast!(self.admin),
 		cast!(self.appservice),
+		cast!(self.audit),
 		cast!(self.resolver),
 		cast!(self.client),
 		cast!(self.config),
 		cast!(self.emergency),
 		cast!(self.globals),
+		cast!(self.jwks),
 		cast!(self.key_backups),
+		cast!(self.ldap),
+		cast!(self.livekit),
 		cast!(self.media),
 		cast!(self.presence),
 		cast!(self.pusher),
@@ -181,7 +197,9 @@ macro_rules! cast {
 		cast!(self.federation),
 		cast!(self.sending),
 		cast!(self.server_keys),
+		cast!(self.server_notices),
 		cast!(self.sync),
+		cast!(self.terms),
 		cast!(self.transaction_ids),
 		cast!(self.uiaa),
 		cast!(self.users),
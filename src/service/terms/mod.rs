@@ -0,0 +1,269 @@
+use std::{
+	collections::{BTreeMap, HashMap},
+	sync::{Arc, Mutex},
+};
+
+use futures::{Stream, StreamExt};
+use ruma::{OwnedUserId, UserId};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value as JsonValue, json};
+use tuwunel_core::{
+	Result,
+	config::{TermsConfig, TermsPolicy},
+	implement,
+	utils::{self, ReadyExt},
+};
+use tuwunel_database::{Deserialized, Json, Map};
+
+/// Tracks the acceptance of the policy documents configured in
+/// `[global.terms]`.
+pub struct Service {
+	db: Data,
+
+	/// Version of the policies each user accepted, if any, to spare a database
+	/// read on every request.
+	accepted: Mutex<HashMap<OwnedUserId, Option<String>>>,
+
+	services: Arc<crate::services::OnceServices>,
+}
+
+struct Data {
+	userid_consent: Arc<Map>,
+}
+
+/// The policies a user accepted.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Consent {
+	/// Version of every policy, as returned by [`Service::version`].
+	pub version: String,
+
+	/// Time of acceptance in milliseconds since the unix epoch.
+	pub accepted_at: u64,
+}
+
+impl crate::Service for Service {
+	fn build(args: &crate::Args<'_>) -> Result<Arc<Self>> {
+		Ok(Arc::new(Self {
+			db: Data {
+				userid_consent: args.db["userid_consent"].clone(),
+			},
+			accepted: Mutex::default(),
+			services: args.services.clone(),
+		}))
+	}
+
+	fn name(&self) -> &str { crate::service::make_name(std::module_path!()) }
+}
+
+/// Whether policies are configured.
+#[implement(Service)]
+#[inline]
+pub fn is_enabled(&self) -> bool {
+	!self
+		.services
+		.server
+		.config
+		.terms
+		.policies
+		.is_empty()
+}
+
+/// The version of the set of policies; it changes with the version of any
+/// policy.
+#[implement(Service)]
+pub fn version(&self) -> String { version(&self.services.server.config.terms.policies) }
+
+/// Where users review and accept the policies.
+#[implement(Service)]
+pub fn consent_uri(&self) -> Option<String> { consent_uri(&self.services.server.config.terms) }
+
+/// Parameters of the `m.login.terms` authentication stage.
+#[implement(Service)]
+pub fn params(&self) -> JsonValue {
+	let policies: serde_json::Map<_, _> = self
+		.services
+		.server
+		.config
+		.terms
+		.policies
+		.iter()
+		.map(|(name, policy)| {
+			let mut params = json!({ "version": policy.version });
+			for (lang, document) in &policy.documents {
+				params[lang] = json!(document);
+			}
+
+			(name.clone(), params)
+		})
+		.collect();
+
+	json!({ "policies": policies })
+}
+
+/// Records that the user accepted the current policies.
+#[implement(Service)]
+pub fn accept(&self, user_id: &UserId) {
+	let consent = Consent {
+		version: self.version(),
+		accepted_at: utils::millis_since_unix_epoch(),
+	};
+
+	self.accepted
+		.lock()
+		.expect("locked")
+		.insert(user_id.to_owned(), Some(consent.version.clone()));
+
+	self.db.userid_consent.put(user_id, Json(consent));
+}
+
+/// The policies the user accepted last.
+#[implement(Service)]
+pub async fn consent(&self, user_id: &UserId) -> Result<Consent> {
+	self.db
+		.userid_consent
+		.get(user_id)
+		.await
+		.deserialized()
+}
+
+/// Whether the user accepted the current policies; always true when no
+/// policies are configured.
+#[implement(Service)]
+pub async fn has_consented(&self, user_id: &UserId) -> bool {
+	if !self.is_enabled() {
+		return true;
+	}
+
+	let cached = self
+		.accepted
+		.lock()
+		.expect("locked")
+		.get(user_id)
+		.cloned();
+
+	let accepted = match cached {
+		| Some(accepted) => accepted,
+		| None => {
+			let accepted = self
+				.consent(user_id)
+				.await
+				.ok()
+				.map(|consent| consent.version);
+
+			self.accepted
+				.lock()
+				.expect("locked")
+				.insert(user_id.to_owned(), accepted.clone());
+
+			accepted
+		},
+	};
+
+	accepted.is_some_and(|accepted| accepted == self.version())
+}
+
+/// Whether requests of the user must be refused until they accept the
+/// current policies.
+#[implement(Service)]
+pub async fn is_blocked(&self, user_id: &UserId) -> bool {
+	self.services
+		.server
+		.config
+		.terms
+		.block_without_consent
+		&& user_id != self.services.globals.server_user
+		&& !self.has_consented(user_id).await
+}
+
+/// Local active users who have not accepted the current policies.
+#[implement(Service)]
+pub fn users_without_consent(&self) -> impl Stream<Item = &UserId> + Send + '_ {
+	self.services
+		.users
+		.list_local_users()
+		.ready_filter(|user_id| *user_id != self.services.globals.server_user)
+		.filter_map(async |user_id| (!self.has_consented(user_id).await).then_some(user_id))
+}
+
+fn version(policies: &BTreeMap<String, TermsPolicy>) -> String {
+	policies
+		.iter()
+		.map(|(name, policy)| format!("{name}={}", policy.version))
+		.collect::<Vec<_>>()
+		.join(",")
+}
+
+/// The configured consent page, or else the English document of the first
+/// policy, or else its first document.
+fn consent_uri(config: &TermsConfig) -> Option<String> {
+	if let Some(url) = &config.consent_url {
+		return Some(url.to_string());
+	}
+
+	let documents = &config.policies.values().next()?.documents;
+	documents
+		.get("en")
+		.or_else(|| documents.values().next())
+		.map(|document| document.url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+
+	use tuwunel_core::config::{TermsConfig, TermsDocument, TermsPolicy};
+
+	use super::{consent_uri, version};
+
+	fn policy(version: &str, langs: &[&str]) -> TermsPolicy {
+		TermsPolicy {
+			version: version.to_owned(),
+			documents: langs
+				.iter()
+				.map(|lang| {
+					let document = TermsDocument {
+						name: "Terms".to_owned(),
+						url: format!("https://example.org/{lang}.html")
+							.parse()
+							.expect("valid url"),
+					};
+
+					((*lang).to_owned(), document)
+				})
+				.collect(),
+		}
+	}
+
+	#[test]
+	fn version_of_every_policy() {
+		let policies = BTreeMap::from([
+			("terms".to_owned(), policy("2", &["en"])),
+			("privacy".to_owned(), policy("1.0", &["en"])),
+		]);
+
+		assert_eq!(version(&policies), "privacy=1.0,terms=2");
+		assert_eq!(version(&BTreeMap::new()), "");
+	}
+
+	#[test]
+	fn consent_uri_prefers_configured_then_english() {
+		let mut config = TermsConfig {
+			policies: BTreeMap::from([("terms".to_owned(), policy("1", &["de", "en"]))]),
+			..Default::default()
+		};
+
+		assert_eq!(consent_uri(&config).as_deref(), Some("https://example.org/en.html"));
+
+		config.policies = BTreeMap::from([("terms".to_owned(), policy("1", &["de", "fr"]))]);
+		assert_eq!(consent_uri(&config).as_deref(), Some("https://example.org/de.html"));
+
+		config.consent_url = Some(
+			"https://example.org/consent"
+				.parse()
+				.expect("valid url"),
+		);
+		assert_eq!(consent_uri(&config).as_deref(), Some("https://example.org/consent"));
+
+		assert_eq!(consent_uri(&TermsConfig::default()), None);
+	}
+}
@@ -169,6 +169,9 @@ pub async fn try_auth(
 		| AuthData::Dummy(_) => {
 			uiaainfo.completed.push(AuthType::Dummy);
 		},
+		| AuthData::Terms(_) => {
+			uiaainfo.completed.push(AuthType::Terms);
+		},
 		| auth => error!("AuthData type not supported: {auth:?}"),
 	}
 
@@ -11,6 +11,7 @@
 use serde_json::json;
 use tuwunel_core::{
 	Err, Result, implement,
+	result::LogErr,
 	utils::{
 		self, ReadyExt,
 		stream::{IterStream, TryIgnore},
@@ -99,6 +100,15 @@ pub async fn remove_device(&self, user_id: &UserId, device_id: &DeviceId) {
 		.await
 		.ok();
 
+	// Remove per-device notification settings (MSC3890)
+	let notification_settings =
+		format!("org.matrix.msc3890.local_notification_settings.{device_id}");
+	self.services
+		.account_data
+		.delete(None, user_id, notification_settings.into())
+		.await
+		.ok();
+
 	// TODO: Remove onetimekeys
 
 	let userdeviceid = (user_id, device_id);
@@ -300,7 +310,7 @@ pub fn generate_refresh_token() -> String {
 }
 
 #[implement(super::Service)]
-pub fn add_to_device_event(
+pub async fn add_to_device_event(
 	&self,
 	sender: &UserId,
 	target_user_id: &UserId,
@@ -319,6 +329,13 @@ pub fn add_to_device_event(
 			"content": content,
 		})),
 	);
+
+	self.services
+		.appservice
+		.append_to_device(sender, target_user_id, target_device_id, event_type, content)
+		.await
+		.log_err()
+		.ok();
 }
 
 #[implement(super::Service)]
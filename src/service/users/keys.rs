@@ -10,6 +10,7 @@
 };
 use tuwunel_core::{
 	Err, Error, Result, debug_error, err, implement,
+	result::LogErr,
 	utils::{ReadyExt, stream::TryIgnore, string::Unquoted},
 };
 use tuwunel_database::{Deserialized, Ignore, Json};
@@ -139,10 +140,19 @@ pub async fn take_one_time_key(
 		});
 
 	pin_mut!(one_time_keys);
-	one_time_keys
+	let one_time_key = one_time_keys
 		.next()
 		.await
-		.ok_or_else(|| err!(Request(NotFound("No one-time-key found"))))
+		.ok_or_else(|| err!(Request(NotFound("No one-time-key found"))))?;
+
+	self.services
+		.appservice
+		.append_device_update(user_id, Some(device_id))
+		.await
+		.log_err()
+		.ok();
+
+	Ok(one_time_key)
 }
 
 #[implement(super::Service)]
@@ -403,6 +413,13 @@ pub async fn mark_device_key_update(&self, user_id: &UserId) {
 
 	let key = (user_id, *count);
 	self.db.keychangeid_userid.put_raw(key, user_id);
+
+	self.services
+		.appservice
+		.append_device_update(user_id, None)
+		.await
+		.log_err()
+		.ok();
 }
 
 #[implement(super::Service)]
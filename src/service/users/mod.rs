@@ -209,6 +209,11 @@ pub async fn origin(&self, user_id: &UserId) -> Result<String> {
 			.deserialized()
 	}
 
+	/// Changes the origin of an existing user.
+	pub fn set_origin(&self, user_id: &UserId, origin: &str) {
+		self.db.userid_origin.insert(user_id, origin);
+	}
+
 	/// Returns the password hash for the given user.
 	pub async fn password_hash(&self, user_id: &UserId) -> Result<String> {
 		self.db
@@ -226,8 +231,9 @@ pub async fn set_password(&self, user_id: &UserId, password: Option<&str>) -> Re
 		//
 		// The above now applies to all non-password origin users by default unless an
 		// exception is made for that origin in the condition below. Note that users
-		// with no origin are also password-origin users.
-		let allowed_origins = ["password", "sso"];
+		// with no origin are also password-origin users. Users provisioned through
+		// SCIM without a password may be given one later.
+		let allowed_origins = ["password", "sso", "scim"];
 
 		if let Some(password) = password
 			&& password != "*"
@@ -248,7 +254,11 @@ pub async fn set_password(&self, user_id: &UserId, password: Option<&str>) -> Re
 			},
 			| Some(Ok(hash)) => {
 				self.db.userid_password.insert(user_id, hash);
-				self.db.userid_origin.insert(user_id, "password");
+
+				// The placeholder password keeps the origin the user was created with.
+				if password != Some("*") {
+					self.db.userid_origin.insert(user_id, "password");
+				}
 			},
 			| Some(Err(e)) => {
 				return Err!(Request(InvalidParam(